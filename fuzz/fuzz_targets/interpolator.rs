@@ -0,0 +1,36 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+#![no_main]
+
+use elasticsearch_core_mcp_server::fuzzing::interpolate;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &str| {
+    // Variables starting with a lowercase letter are defined, others are not
+    let lookup = |name: &str| {
+        name.starts_with(|c: char| c.is_ascii_lowercase())
+            .then(|| format!("<{name}>"))
+    };
+
+    let result = interpolate(data.to_string(), lookup);
+
+    // Templates without placeholders are returned unchanged
+    if !data.contains("${") {
+        assert_eq!(result.unwrap(), data);
+    }
+});
@@ -0,0 +1,404 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Human approval of tool calls before they are executed.
+
+use crate::authorization::Identity;
+use crate::protocol::http::TenantName;
+use crate::servers::matches_tool;
+use crate::utils::rmcp_ext::{random_id, session_id};
+use crate::utils::{string_or_vec_schema, vec_from_string_or_vec};
+use axum::extract::{Path, Request};
+use axum::http::StatusCode;
+use axum::middleware::{self, Next};
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use rmcp::model::{
+    CallToolRequestParam, ClientNotification, ClientRequest, ErrorCode, JsonObject, ServerInfo, ServerResult,
+};
+use rmcp::service::{NotificationContext, RequestContext};
+use rmcp::{RoleServer, Service};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// Tool calls that need a human approval before they are executed.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalConfig {
+    /// Tool name patterns, possibly with `*` wildcards, of the calls that need approval
+    #[serde(deserialize_with = "vec_from_string_or_vec")]
+    #[schemars(schema_with = "string_or_vec_schema")]
+    pub tools: Vec<String>,
+    /// How calls are approved
+    #[serde(default)]
+    pub method: ApprovalMethod,
+    /// Named clients of the http server that can list, approve and deny pending calls. Nobody can if empty.
+    #[serde(default)]
+    pub operators: Vec<String>,
+    /// Time to wait for a decision, in seconds. Calls are denied when it expires.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_timeout_secs() -> u64 {
+    300
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalMethod {
+    /// An operator approves or denies pending calls with the `/approvals` HTTP endpoints
+    #[default]
+    Http,
+}
+
+/// A tool call waiting for approval.
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingApproval {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    pub tool: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<JsonObject>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub requested_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Tool calls waiting for an operator decision.
+#[derive(Default)]
+pub struct Approvals {
+    pending: Mutex<HashMap<String, (PendingApproval, oneshot::Sender<bool>)>>,
+}
+
+impl Approvals {
+    /// The approvals of this process, shared by all servers to survive configuration reloads.
+    pub fn global() -> &'static Approvals {
+        static GLOBAL: OnceLock<Approvals> = OnceLock::new();
+        GLOBAL.get_or_init(Approvals::default)
+    }
+
+    /// Add a pending approval. The receiver gets the decision, and fails if the approval is removed.
+    ///
+    /// Ids are random, so that they can't be guessed by the clients that are waiting for a decision.
+    fn request(&self, mut approval: PendingApproval) -> (String, oneshot::Receiver<bool>) {
+        let id = random_id();
+        approval.id = id.clone();
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id.clone(), (approval, sender));
+        (id, receiver)
+    }
+
+    fn remove(&self, id: &str) {
+        self.pending.lock().unwrap().remove(id);
+    }
+
+    /// Pending approvals of a tenant, or of the default server if `tenant` is `None`, oldest first.
+    pub fn list(&self, tenant: Option<&str>) -> Vec<PendingApproval> {
+        let mut approvals = self
+            .pending
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|(approval, _)| approval.tenant.as_deref() == tenant)
+            .map(|(approval, _)| approval.clone())
+            .collect::<Vec<_>>();
+        approvals.sort_by_key(|approval| approval.requested_at);
+        approvals
+    }
+
+    /// Approve or deny a pending call of a tenant. Returns `false` if the tenant has no such pending call.
+    pub fn decide(&self, tenant: Option<&str>, id: &str, approved: bool) -> bool {
+        let mut pending = self.pending.lock().unwrap();
+        if !pending
+            .get(id)
+            .is_some_and(|(approval, _)| approval.tenant.as_deref() == tenant)
+        {
+            return false;
+        }
+        match pending.remove(id) {
+            Some((_, sender)) => sender.send(approved).is_ok(),
+            None => false,
+        }
+    }
+}
+
+/// HTTP endpoints to list the pending approvals of a tenant, or of the default server if `tenant` is `None`, and
+/// approve or deny them. They're only available to the `operators` clients, whose identity is set by the
+/// authentication middleware.
+pub fn router(operators: Vec<String>, tenant: Option<String>) -> Router {
+    let tenant = Arc::new(tenant);
+    let operators = Arc::new(operators);
+
+    let decide = |approved: bool| {
+        let tenant = tenant.clone();
+        move |Path(id): Path<String>| {
+            let tenant = tenant.clone();
+            async move {
+                if Approvals::global().decide(tenant.as_deref(), &id, approved) {
+                    let decision = if approved { "approved" } else { "denied" };
+                    tracing::info!(approval_id = %id, "Tool call {decision} by an operator");
+                    (StatusCode::OK, format!("Call {id} {decision}\n"))
+                } else {
+                    (StatusCode::NOT_FOUND, format!("No pending call {id}\n"))
+                }
+            }
+        }
+    };
+    let list = {
+        let tenant = tenant.clone();
+        move || {
+            let tenant = tenant.clone();
+            async move { Json(Approvals::global().list(tenant.as_deref())) }
+        }
+    };
+    let check_operator = move |request: Request, next: Next| {
+        let operators = operators.clone();
+        async move {
+            let identity = request.extensions().get::<Identity>();
+            if !identity.is_some_and(|identity| operators.contains(&identity.0)) {
+                return (StatusCode::FORBIDDEN, "Not an approval operator\n").into_response();
+            }
+            next.run(request).await
+        }
+    };
+
+    Router::new()
+        .route("/", get(list))
+        .route("/{id}/approve", post(decide(true)))
+        .route("/{id}/deny", post(decide(false)))
+        .layer(middleware::from_fn(check_operator))
+}
+
+/// A server that waits for a human approval before executing some tool calls.
+#[derive(Clone)]
+pub struct ApprovalServer<S> {
+    inner: S,
+    config: Option<Arc<ApprovalConfig>>,
+}
+
+impl<S: Service<RoleServer>> ApprovalServer<S> {
+    pub fn new(inner: S, config: Option<ApprovalConfig>) -> Self {
+        ApprovalServer {
+            inner,
+            config: config.map(Arc::new),
+        }
+    }
+
+    fn needs_approval(&self, tool: &str) -> Option<&ApprovalConfig> {
+        self.config
+            .as_deref()
+            .filter(|config| config.tools.iter().any(|pattern| matches_tool(pattern, tool)))
+    }
+
+    /// Wait for an operator decision on the pending approvals endpoints.
+    async fn approve_with_operator(
+        &self,
+        call: &CallToolRequestParam,
+        context: &RequestContext<RoleServer>,
+        timeout: Duration,
+    ) -> Result<(), String> {
+        let now = Utc::now();
+        let approvals = Approvals::global();
+        let (id, decision) = approvals.request(PendingApproval {
+            id: String::new(),
+            tenant: TenantName::of(context).map(str::to_string),
+            tool: call.name.to_string(),
+            arguments: call.arguments.clone(),
+            session_id: session_id(context).map(str::to_string),
+            requested_at: now,
+            expires_at: now + chrono::TimeDelta::seconds(timeout.as_secs() as i64),
+        });
+        tracing::info!(approval_id = %id, tool = %call.name, "Tool call waiting for approval");
+
+        let result = tokio::select! {
+            decision = tokio::time::timeout(timeout, decision) => match decision {
+                Ok(Ok(true)) => Ok(()),
+                Ok(_) => Err("it was denied by an operator".to_string()),
+                Err(_) => Err(format!("it wasn't approved within {} seconds", timeout.as_secs())),
+            },
+            _ = context.ct.cancelled() => Err("the request was cancelled".to_string()),
+        };
+        approvals.remove(&id);
+        result
+    }
+}
+
+impl<S: Service<RoleServer>> Service<RoleServer> for ApprovalServer<S> {
+    async fn handle_request(
+        &self,
+        request: ClientRequest,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ServerResult, rmcp::Error> {
+        if let ClientRequest::CallToolRequest(call) = &request
+            && let Some(config) = self.needs_approval(&call.params.name)
+        {
+            let timeout = Duration::from_secs(config.timeout_secs);
+            let approval = match config.method {
+                ApprovalMethod::Http => self.approve_with_operator(&call.params, &context, timeout).await,
+            };
+            if let Err(reason) = approval {
+                return Err(rmcp::Error::new(
+                    ErrorCode::INVALID_REQUEST,
+                    format!("Tool '{}' was not executed: {reason}", call.params.name),
+                    None,
+                ));
+            }
+        }
+
+        self.inner.handle_request(request, context).await
+    }
+
+    async fn handle_notification(
+        &self,
+        notification: ClientNotification,
+        context: NotificationContext<RoleServer>,
+    ) -> Result<(), rmcp::Error> {
+        self.inner.handle_notification(notification, context).await
+    }
+
+    fn get_info(&self) -> ServerInfo {
+        self.inner.get_info()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::ServiceExt;
+    use rmcp::model::{CallToolResult, Content, ServerCapabilities};
+
+    #[derive(Clone)]
+    struct EchoServer;
+
+    impl rmcp::ServerHandler for EchoServer {
+        async fn call_tool(
+            &self,
+            request: CallToolRequestParam,
+            _context: RequestContext<RoleServer>,
+        ) -> Result<CallToolResult, rmcp::Error> {
+            Ok(CallToolResult::success(vec![Content::text(request.name.to_string())]))
+        }
+
+        fn get_info(&self) -> ServerInfo {
+            ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_tools().build(),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn operator_approval() -> anyhow::Result<()> {
+        let config = ApprovalConfig {
+            tools: vec!["approval_test_*".to_string()],
+            method: ApprovalMethod::Http,
+            operators: Vec::new(),
+            timeout_secs: 1,
+        };
+        let server = ApprovalServer::new(EchoServer, Some(config));
+
+        let (server_io, client_io) = tokio::io::duplex(65536);
+        tokio::spawn(async move {
+            if let Ok(service) = server.serve(server_io).await {
+                let _ = service.waiting().await;
+            }
+        });
+        let client = ().serve(client_io).await?;
+        let call = |name: &'static str| CallToolRequestParam {
+            name: name.into(),
+            arguments: None,
+        };
+
+        // Tools that don't need approval
+        let result = client.call_tool(call("search")).await?;
+        assert_eq!(result.content[0].as_text().unwrap().text, "search");
+
+        // Approve the pending call, as an operator would
+        let decide = |tool: &'static str, approved: bool| async move {
+            loop {
+                let pending = Approvals::global().list(None);
+                if let Some(approval) = pending.iter().find(|a| a.tool == tool) {
+                    // Calls can only be decided by the operators of their tenant
+                    assert!(!Approvals::global().decide(Some("other"), &approval.id, approved));
+                    return Approvals::global().decide(None, &approval.id, approved);
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        let (result, decided) = tokio::join!(
+            client.call_tool(call("approval_test_approved")),
+            decide("approval_test_approved", true)
+        );
+        assert!(decided);
+        assert_eq!(result?.content[0].as_text().unwrap().text, "approval_test_approved");
+
+        let (result, _) = tokio::join!(
+            client.call_tool(call("approval_test_denied")),
+            decide("approval_test_denied", false)
+        );
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("denied by an operator"), "{err}");
+
+        // Timeout
+        let err = client
+            .call_tool(call("approval_test_expired"))
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("within 1 seconds"), "{err}");
+        assert!(
+            Approvals::global()
+                .list(None)
+                .iter()
+                .all(|a| a.tool != "approval_test_expired")
+        );
+
+        client.cancel().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn operator_endpoints() -> anyhow::Result<()> {
+        use tower::ServiceExt as _;
+
+        let router = router(vec!["alice".to_string()], Some("team-a".to_string()));
+        let get = |identity: Option<&str>| {
+            let mut request = http::Request::builder()
+                .uri("/")
+                .body(axum::body::Body::empty())
+                .unwrap();
+            if let Some(identity) = identity {
+                request.extensions_mut().insert(Identity(identity.to_string()));
+            }
+            router.clone().oneshot(request)
+        };
+
+        assert_eq!(get(Some("alice")).await?.status(), StatusCode::OK);
+        assert_eq!(get(Some("bob")).await?.status(), StatusCode::FORBIDDEN);
+        assert_eq!(get(None).await?.status(), StatusCode::FORBIDDEN);
+        Ok(())
+    }
+}
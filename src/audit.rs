@@ -0,0 +1,260 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Audit log of tool invocations.
+
+use crate::authorization::Identity;
+use crate::utils::rmcp_ext::session_id;
+use chrono::{DateTime, Utc};
+use elasticsearch::{Elasticsearch, IndexParts};
+use rmcp::model::{ClientNotification, ClientRequest, JsonObject, ServerInfo, ServerResult};
+use rmcp::service::{NotificationContext, RequestContext};
+use rmcp::{RoleServer, Service};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tokio::sync::mpsc;
+
+/// Where audit records are written.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditConfig {
+    /// Append records to a JSON Lines file
+    File(PathBuf),
+    /// Add records to an index of the default Elasticsearch cluster
+    Index(String),
+}
+
+/// A tool invocation.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditRecord {
+    #[serde(rename = "@timestamp")]
+    pub timestamp: DateTime<Utc>,
+    pub tool: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<JsonObject>,
+    /// The MCP session, for stateful HTTP servers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// The authenticated client, for http servers with named credentials
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity: Option<String>,
+    pub duration_ms: u64,
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The audit log. Records are written by a background task so that tool calls aren't slowed down by
+/// the audit sink, and the task ends when all clones of the log have been dropped.
+#[derive(Clone)]
+pub struct AuditLog {
+    sender: mpsc::UnboundedSender<AuditRecord>,
+}
+
+impl AuditLog {
+    /// Append records to a JSON Lines file. The file is opened immediately to report errors at startup.
+    pub fn to_file(path: &Path) -> anyhow::Result<Self> {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|err| anyhow::anyhow!("Cannot open audit file {}: {err}", path.display()))?;
+
+        let (sender, mut receiver) = mpsc::unbounded_channel::<AuditRecord>();
+        tokio::task::spawn_blocking(move || {
+            while let Some(record) = receiver.blocking_recv() {
+                let mut line = serde_json::to_vec(&record).unwrap_or_default();
+                line.push(b'\n');
+                if let Err(err) = file.write_all(&line).and_then(|_| file.flush()) {
+                    tracing::error!("Failed to write audit record: {err}");
+                }
+            }
+        });
+
+        Ok(AuditLog { sender })
+    }
+
+    /// Add records to an Elasticsearch index.
+    pub fn to_index(es_client: Elasticsearch, index: String) -> Self {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<AuditRecord>();
+        tokio::spawn(async move {
+            while let Some(record) = receiver.recv().await {
+                let response = es_client.index(IndexParts::Index(&index)).body(record).send().await;
+                let error = match response {
+                    Ok(response) => response.error_for_status_code().err(),
+                    Err(err) => Some(err),
+                };
+                if let Some(err) = error {
+                    tracing::error!("Failed to index audit record: {err}");
+                }
+            }
+        });
+
+        AuditLog { sender }
+    }
+
+    pub fn record(&self, record: AuditRecord) {
+        // Can only fail if the writer task has panicked
+        let _ = self.sender.send(record);
+    }
+}
+
+/// A server that records tool invocations in an audit log, if one is configured.
+#[derive(Clone)]
+pub struct AuditedServer<S> {
+    inner: S,
+    audit: Option<AuditLog>,
+}
+
+impl<S: Service<RoleServer>> AuditedServer<S> {
+    pub fn new(inner: S, audit: Option<AuditLog>) -> Self {
+        AuditedServer { inner, audit }
+    }
+}
+
+impl<S: Service<RoleServer>> Service<RoleServer> for AuditedServer<S> {
+    async fn handle_request(
+        &self,
+        request: ClientRequest,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ServerResult, rmcp::Error> {
+        let (Some(audit), ClientRequest::CallToolRequest(call)) = (&self.audit, &request) else {
+            return self.inner.handle_request(request, context).await;
+        };
+
+        let tool = call.params.name.to_string();
+        let arguments = call.params.arguments.clone();
+        let session_id = session_id(&context).map(str::to_string);
+        let identity = Identity::of(&context).map(str::to_string);
+
+        let timestamp = Utc::now();
+        let start = Instant::now();
+        let result = self.inner.handle_request(request, context).await;
+
+        let error = match &result {
+            Ok(ServerResult::CallToolResult(result)) if result.is_error == Some(true) => Some(
+                result
+                    .content
+                    .iter()
+                    .filter_map(|c| c.as_text().map(|t| t.text.as_str()))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            Ok(_) => None,
+            Err(err) => Some(err.message.to_string()),
+        };
+
+        audit.record(AuditRecord {
+            timestamp,
+            tool,
+            arguments,
+            session_id,
+            identity,
+            duration_ms: start.elapsed().as_millis() as u64,
+            success: error.is_none(),
+            error,
+        });
+
+        result
+    }
+
+    async fn handle_notification(
+        &self,
+        notification: ClientNotification,
+        context: NotificationContext<RoleServer>,
+    ) -> Result<(), rmcp::Error> {
+        self.inner.handle_notification(notification, context).await
+    }
+
+    fn get_info(&self) -> ServerInfo {
+        self.inner.get_info()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::ServiceExt;
+    use rmcp::model::{CallToolRequestParam, CallToolResult, Content, ServerCapabilities};
+    use serde_json::json;
+
+    #[derive(Clone)]
+    struct FailingServer;
+
+    impl rmcp::ServerHandler for FailingServer {
+        async fn call_tool(
+            &self,
+            _request: CallToolRequestParam,
+            _context: RequestContext<RoleServer>,
+        ) -> Result<CallToolResult, rmcp::Error> {
+            Ok(CallToolResult::error(vec![Content::text("index not found")]))
+        }
+
+        fn get_info(&self) -> ServerInfo {
+            ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_tools().build(),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn tool_calls_are_recorded() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join(format!("mcp-audit-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let server = AuditedServer::new(FailingServer, Some(AuditLog::to_file(&path)?));
+        let (server_io, client_io) = tokio::io::duplex(4096);
+        tokio::spawn(async move {
+            if let Ok(service) = server.serve(server_io).await {
+                let _ = service.waiting().await;
+            }
+        });
+
+        let client = ().serve(client_io).await?;
+        let result = client
+            .call_tool(CallToolRequestParam {
+                name: "search".into(),
+                arguments: json!({"index": "logs"}).as_object().cloned(),
+            })
+            .await?;
+        assert_eq!(result.is_error, Some(true));
+        client.cancel().await?;
+
+        // Wait for the writer task
+        let mut content = String::new();
+        for _ in 0..50 {
+            content = std::fs::read_to_string(&path)?;
+            if !content.is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        std::fs::remove_file(&path)?;
+
+        let record: AuditRecord = serde_json::from_str(content.trim())?;
+        assert_eq!(record.tool, "search");
+        assert_eq!(record.arguments, json!({"index": "logs"}).as_object().cloned());
+        assert!(!record.success);
+        assert_eq!(record.error.as_deref(), Some("index not found"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,220 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Role-based authorization of the tools that clients of the http server can call.
+
+use crate::protocol::oidc;
+use crate::servers::matches_tool;
+use http::request::Parts;
+use rmcp::model::{ClientNotification, ClientRequest, ErrorCode, ServerInfo, ServerResult};
+use rmcp::service::{NotificationContext, RequestContext};
+use rmcp::{RoleServer, Service};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The identity of an authenticated http client, added to the request extensions by the http server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identity(pub String);
+
+impl Identity {
+    /// The identity of the client of a request, if it was sent to the http server with named credentials.
+    pub fn of(context: &RequestContext<RoleServer>) -> Option<&str> {
+        context
+            .extensions
+            .get::<Parts>()
+            .and_then(|p| p.extensions.get::<Identity>())
+            .map(|identity| identity.0.as_str())
+    }
+}
+
+/// Tools that clients of the http server can call, depending on their roles.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorizationConfig {
+    /// Tool name patterns, possibly with `*` wildcards, that each role can call
+    #[serde(default)]
+    pub roles: HashMap<String, Vec<String>>,
+    /// Clients, keyed by identity: a name for clients with an API key or bearer token, `oidc:<subject>` for
+    /// clients with OIDC tokens
+    #[serde(default)]
+    pub clients: HashMap<String, ClientConfig>,
+    /// Roles of the clients that aren't listed in `clients`, including anonymous ones
+    #[serde(default)]
+    pub default_roles: Vec<String>,
+}
+
+/// A client of the http server.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientConfig {
+    /// API key of the client, accepted in addition to the `--auth-api-key` keys
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Bearer token of the client, accepted in addition to the `--auth-bearer-token` tokens
+    #[serde(default)]
+    pub bearer_token: Option<String>,
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+impl AuthorizationConfig {
+    /// Check that all roles given to clients are defined, and that OIDC subjects have no credentials.
+    pub fn check(&self) -> anyhow::Result<()> {
+        for (name, client) in &self.clients {
+            if name.starts_with(oidc::IDENTITY_PREFIX) && (client.api_key.is_some() || client.bearer_token.is_some()) {
+                anyhow::bail!(
+                    "Authorization: client '{name}' is an OIDC subject, and can't have an API key or bearer token"
+                );
+            }
+        }
+        let clients = self.clients.iter().map(|(name, client)| (name.as_str(), &client.roles));
+        for (client, roles) in clients.chain([("default", &self.default_roles)]) {
+            if let Some(role) = roles.iter().find(|role| !self.roles.contains_key(*role)) {
+                anyhow::bail!("Authorization: client '{client}' has an unknown role '{role}'");
+            }
+        }
+        Ok(())
+    }
+
+    /// Roles of a client: those of a named client, or the default roles. `identity` is `None` for anonymous
+    /// clients.
+    pub fn roles_of(&self, identity: Option<&str>) -> &[String] {
+        identity
+            .and_then(|identity| self.clients.get(identity))
+            .map_or(&self.default_roles, |client| &client.roles)
+    }
+
+    /// Can a client call a tool? `identity` is `None` for anonymous clients.
+    pub fn is_allowed(&self, identity: Option<&str>, tool: &str) -> bool {
+        self.roles_of(identity)
+            .iter()
+            .filter_map(|role| self.roles.get(role))
+            .flatten()
+            .any(|pattern| matches_tool(pattern, tool))
+    }
+}
+
+/// A server that only lists and executes the tools that the client of an http request is allowed to call.
+/// Requests that don't come from the http server, e.g. with the stdio protocol, aren't restricted.
+#[derive(Clone)]
+pub struct AuthorizedServer<S> {
+    inner: S,
+    config: Option<AuthorizationConfig>,
+}
+
+impl<S: Service<RoleServer>> AuthorizedServer<S> {
+    pub fn new(inner: S, config: Option<AuthorizationConfig>) -> Self {
+        AuthorizedServer { inner, config }
+    }
+}
+
+impl<S: Service<RoleServer>> Service<RoleServer> for AuthorizedServer<S> {
+    async fn handle_request(
+        &self,
+        request: ClientRequest,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ServerResult, rmcp::Error> {
+        let Some(config) = self
+            .config
+            .as_ref()
+            .filter(|_| context.extensions.get::<Parts>().is_some())
+        else {
+            return self.inner.handle_request(request, context).await;
+        };
+        let identity = Identity::of(&context).map(str::to_string);
+
+        match &request {
+            ClientRequest::CallToolRequest(call) if !config.is_allowed(identity.as_deref(), &call.params.name) => {
+                let tool = &call.params.name;
+                let identity = identity.as_deref().unwrap_or("anonymous");
+                tracing::warn!(identity, tool = %tool, "Tool call denied");
+                Err(rmcp::Error::new(
+                    ErrorCode::INVALID_REQUEST,
+                    format!("Tool '{tool}' is not allowed for client '{identity}'"),
+                    None,
+                ))
+            }
+            ClientRequest::ListToolsRequest(_) => {
+                let mut result = self.inner.handle_request(request, context).await?;
+                if let ServerResult::ListToolsResult(list) = &mut result {
+                    list.tools
+                        .retain(|tool| config.is_allowed(identity.as_deref(), &tool.name));
+                }
+                Ok(result)
+            }
+            _ => self.inner.handle_request(request, context).await,
+        }
+    }
+
+    async fn handle_notification(
+        &self,
+        notification: ClientNotification,
+        context: NotificationContext<RoleServer>,
+    ) -> Result<(), rmcp::Error> {
+        self.inner.handle_notification(notification, context).await
+    }
+
+    fn get_info(&self) -> ServerInfo {
+        self.inner.get_info()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn role_based_access() -> anyhow::Result<()> {
+        let config: AuthorizationConfig = serde_json::from_value(json!({
+            "roles": {
+                "reader": ["search", "list_*", "get_*"],
+                "admin": ["*"]
+            },
+            "clients": {
+                "alice": { "apiKey": "key-a", "roles": ["admin"] },
+                "bob": { "roles": ["reader"] },
+                "eve": {}
+            },
+            "defaultRoles": ["reader"]
+        }))?;
+        config.check()?;
+
+        assert!(config.is_allowed(Some("alice"), "reindex"));
+        assert!(config.is_allowed(Some("bob"), "list_indices"));
+        assert!(!config.is_allowed(Some("bob"), "reindex"));
+        assert!(!config.is_allowed(Some("eve"), "search"));
+        assert!(config.is_allowed(Some("mallory"), "search"));
+        assert!(config.is_allowed(None, "get_mappings"));
+        assert!(!config.is_allowed(None, "esql"));
+
+        let unknown_role = AuthorizationConfig {
+            default_roles: vec!["writer".to_string()],
+            ..config
+        };
+        let err = unknown_role.check().unwrap_err().to_string();
+        assert!(err.contains("unknown role 'writer'"), "{err}");
+
+        let oidc_with_key: AuthorizationConfig = serde_json::from_value(json!({
+            "clients": { "oidc:alice": { "apiKey": "key-a" } }
+        }))?;
+        let err = oidc_with_key.check().unwrap_err().to_string();
+        assert!(err.contains("is an OIDC subject"), "{err}");
+        Ok(())
+    }
+}
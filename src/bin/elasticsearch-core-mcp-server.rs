@@ -19,6 +19,8 @@ use std::io::ErrorKind;
 use clap::Parser;
 use elasticsearch_core_mcp_server::cli::Cli;
 use tracing_subscriber::EnvFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
 // To test with stdio, use npx @modelcontextprotocol/inspector cargo run -p elastic-mcp
 
 #[tokio::main]
@@ -45,14 +47,22 @@ async fn main() -> anyhow::Result<()> {
         Cli::parse()
     };
 
-    // Initialize the tracing subscriber with file and stdout logging
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env().add_directive(tracing::Level::INFO.into()))
-        .with_writer(std::io::stderr)
-        .with_ansi(false)
-        .init();
+    // Initialize the tracing subscriber with stderr logging, and span export if enabled
+    let registry = tracing_subscriber::registry()
+        .with(EnvFilter::from_default_env().add_directive(tracing::Level::INFO.into()))
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_ansi(false),
+        );
+    #[cfg(feature = "otel")]
+    let registry = registry.with(elasticsearch_core_mcp_server::telemetry::otlp_layer()?);
+    registry.init();
 
     tracing::info!("Elasticsearch MCP server, version {}", env!("CARGO_PKG_VERSION"));
 
-    cli.run().await
+    let result = cli.run().await;
+    #[cfg(feature = "otel")]
+    elasticsearch_core_mcp_server::telemetry::shutdown();
+    result
 }
@@ -26,8 +26,15 @@ pub async fn main() -> anyhow::Result<()> {
 
     run_http(HttpCommand {
         config: Some("elastic-mcp.json5".parse()?),
+        reload: false,
+        check: false,
+        dry_run: false,
         address: None,
         sse: true,
+        systemd: false,
+        session: Default::default(),
+        tls: Default::default(),
+        auth: Default::default(),
     },
     false)
     .await?;
@@ -0,0 +1,282 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Programmatic construction of the MCP server, to embed it in other Rust services without a configuration
+//! file or the CLI.
+
+use crate::approval::{ApprovalConfig, ApprovalServer};
+use crate::audit::{AuditConfig, AuditLog, AuditedServer};
+use crate::authorization::{AuthorizationConfig, AuthorizedServer};
+use crate::cli::{Configuration, McpServer};
+use crate::large_results::{LargeResultServer, LargeResultsConfig};
+use crate::metrics::MeteredServer;
+use crate::rate_limit::{RateLimit, RateLimitedServer, RateLimits};
+use crate::redaction::{RedactingServer, RedactionRule};
+use crate::servers::elasticsearch::{ElasticsearchMcp, ElasticsearchMcpConfig};
+use crate::timeout::{TimeoutServer, ToolTimeouts};
+use rmcp::{RoleServer, Service};
+use std::time::Duration;
+
+/// Builds an MCP server that exposes tools for one or more Elasticsearch clusters.
+///
+/// The server can then be served with any rmcp transport:
+///
+/// ```no_run
+/// use elasticsearch_core_mcp_server::{ElasticsearchMcpConfig, RateLimit, ServerBuilder};
+/// use rmcp::ServiceExt;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let server = ServerBuilder::new()
+///     .elasticsearch(ElasticsearchMcpConfig {
+///         url: "https://localhost:9200".to_string(),
+///         api_key: Some("<api key>".to_string()),
+///         ..Default::default()
+///     })
+///     .rate_limit("*", "10/min".parse::<RateLimit>().map_err(anyhow::Error::msg)?)
+///     .build()?;
+///
+/// server.serve(rmcp::transport::stdio()).await?.waiting().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct ServerBuilder {
+    elasticsearch: Option<ElasticsearchMcpConfig>,
+    clusters: Vec<(String, ElasticsearchMcpConfig)>,
+    audit: Option<AuditConfig>,
+    rate_limits: RateLimits,
+    tool_timeouts: ToolTimeouts,
+    large_results: Option<LargeResultsConfig>,
+    redactions: Vec<RedactionRule>,
+    approvals: Option<ApprovalConfig>,
+    authorization: Option<AuthorizationConfig>,
+    approval_endpoints: bool,
+    container_mode: bool,
+    dry_run: bool,
+}
+
+impl ServerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A builder initialized from the content of a configuration file. MCP servers that aren't Elasticsearch
+    /// clusters are ignored, as well as tenants: use [`Configuration::into_tenant`] to build the server of a
+    /// tenant.
+    pub fn from_config(config: Configuration) -> Self {
+        if !config.tenants.is_empty() {
+            tracing::warn!(
+                "Tenants are ignored, only the default clusters are served. Build the server of a tenant from its \
+                configuration, see Configuration::into_tenant"
+            );
+        }
+        let mut builder = ServerBuilder {
+            elasticsearch: config.elasticsearch,
+            audit: config.audit,
+            rate_limits: config.rate_limits,
+            tool_timeouts: config.tool_timeouts,
+            large_results: config.large_results,
+            redactions: config.redactions,
+            approvals: config.approvals,
+            authorization: config.authorization,
+            ..Default::default()
+        };
+        for (name, server) in config.mcp_servers {
+            match server {
+                McpServer::Elasticsearch(es_config) => builder = builder.cluster(name, *es_config),
+                _ => tracing::warn!("MCP server '{name}': only Elasticsearch servers are supported, ignoring it"),
+            }
+        }
+        builder
+    }
+
+    /// The default cluster, whose tools aren't prefixed.
+    pub fn elasticsearch(mut self, config: ElasticsearchMcpConfig) -> Self {
+        self.elasticsearch = Some(config);
+        self
+    }
+
+    /// An additional cluster, whose tool names are prefixed with `<name>_`.
+    pub fn cluster(mut self, name: impl Into<String>, config: ElasticsearchMcpConfig) -> Self {
+        self.clusters.push((name.into(), config));
+        self
+    }
+
+    /// Record tool invocations in an audit log.
+    pub fn audit(mut self, audit: AuditConfig) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Limit the calls of the tools matching a name pattern, possibly with `*` wildcards. The first pattern
+    /// matching a tool name defines its limit.
+    pub fn rate_limit(mut self, pattern: impl Into<String>, limit: RateLimit) -> Self {
+        self.rate_limits.insert(pattern.into(), limit);
+        self
+    }
+
+    /// Timeout of the calls of the tools matching a name pattern, possibly with `*` wildcards. The first
+    /// pattern matching a tool name defines its timeout. Timeouts are rounded up to the second.
+    pub fn tool_timeout(mut self, pattern: impl Into<String>, timeout: Duration) -> Self {
+        let secs = timeout.as_secs() + (timeout.subsec_nanos() > 0) as u64;
+        self.tool_timeouts.insert(pattern.into(), secs);
+        self
+    }
+
+    /// Store tool results larger than a maximum size as resources, and return their URI instead.
+    pub fn large_results(mut self, config: LargeResultsConfig) -> Self {
+        self.large_results = Some(config);
+        self
+    }
+
+    /// Remove or mask fields of tool results. Rules are applied in order.
+    pub fn redaction(mut self, rule: RedactionRule) -> Self {
+        self.redactions.push(rule);
+        self
+    }
+
+    /// Wait for a human approval before executing some tool calls. Operators decide on the `/approvals` endpoints
+    /// of the http server: building a server with approvals fails if they aren't served.
+    pub fn approvals(mut self, config: ApprovalConfig) -> Self {
+        self.approvals = Some(config);
+        self
+    }
+
+    /// The `/approvals` endpoints are served along with the server, by the `http` command.
+    pub(crate) fn approval_endpoints(mut self, served: bool) -> Self {
+        self.approval_endpoints = served;
+        self
+    }
+
+    /// Restrict the tools that clients of the http server can call to those of their roles.
+    pub fn authorization(mut self, config: AuthorizationConfig) -> Self {
+        self.authorization = Some(config);
+        self
+    }
+
+    /// Dry runs of all write tools, whatever the cluster configurations: they describe the API calls they would
+    /// make instead of making them.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Running in a container: `localhost` in cluster URLs is rewritten to reach the host.
+    pub fn container_mode(mut self, container_mode: bool) -> Self {
+        self.container_mode = container_mode;
+        self
+    }
+
+    /// Build the server. Must be called within a Tokio runtime.
+    pub fn build(mut self) -> anyhow::Result<impl Service<RoleServer> + Clone + use<>> {
+        // Sorted to have a stable tool list
+        self.clusters.sort_by(|a, b| a.0.cmp(&b.0));
+        if self.dry_run {
+            let configs = self
+                .elasticsearch
+                .iter_mut()
+                .chain(self.clusters.iter_mut().map(|(_, c)| c));
+            configs.for_each(|config| config.dry_run = true);
+        }
+
+        if let Some(authorization) = &self.authorization {
+            authorization.check()?;
+        }
+        // Calls would wait for a decision that can't be made, and be denied when it times out
+        if self.approvals.is_some() && !self.approval_endpoints {
+            anyhow::bail!(
+                "Tool call approvals need the /approvals endpoints of the http server: use the 'http' command, or \
+                remove 'approvals' from the configuration"
+            );
+        }
+
+        let audit = match &self.audit {
+            None => None,
+            Some(AuditConfig::File(path)) => Some(AuditLog::to_file(path)?),
+            Some(AuditConfig::Index(index)) => {
+                let Some(es_config) = &self.elasticsearch else {
+                    anyhow::bail!("Audit index '{index}' requires a default Elasticsearch cluster");
+                };
+                let es_client = ElasticsearchMcp::new_client(es_config, self.container_mode)?;
+                Some(AuditLog::to_index(es_client, index.clone()))
+            }
+        };
+
+        let handler = ElasticsearchMcp::new_with_clusters(self.elasticsearch, self.clusters, self.container_mode)?;
+        let tool_names = handler.tool_names().map(str::to_string).collect();
+        // Redact before large results are stored
+        let handler = RedactingServer::new(handler, self.redactions)?;
+        let handler = LargeResultServer::new(handler, self.large_results);
+        // Audit and measure after rate limiting and timeouts, to also record rejected calls
+        let handler = TimeoutServer::new(handler, self.tool_timeouts);
+        // Waiting for approval isn't limited by tool timeouts
+        let handler = ApprovalServer::new(handler, self.approvals);
+        let handler = RateLimitedServer::new(handler, self.rate_limits);
+        // Denied calls don't consume rate limits, and are audited
+        let handler = AuthorizedServer::new(handler, self.authorization);
+        let handler = AuditedServer::new(handler, audit);
+        Ok(MeteredServer::new(handler, tool_names))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn build_without_config_file() -> anyhow::Result<()> {
+        let es_config = |url: &str| ElasticsearchMcpConfig {
+            url: url.to_string(),
+            ..Default::default()
+        };
+        let server = ServerBuilder::new()
+            .elasticsearch(es_config("http://localhost:9200"))
+            .cluster("prod", es_config("https://prod:9200"))
+            .rate_limit("*", "10/min".parse().map_err(anyhow::Error::msg)?)
+            .tool_timeout("search", Duration::from_millis(1500))
+            .build()?;
+
+        let instructions = server.get_info().instructions.unwrap_or_default();
+        assert!(instructions.contains("Elasticsearch cluster 'prod'"), "{instructions}");
+        Ok(())
+    }
+
+    #[test]
+    fn no_cluster() {
+        assert!(ServerBuilder::new().build().is_err());
+    }
+
+    #[tokio::test]
+    async fn approvals_need_http_endpoints() {
+        let builder = || {
+            ServerBuilder::new()
+                .elasticsearch(ElasticsearchMcpConfig {
+                    url: "http://localhost:9200".to_string(),
+                    ..Default::default()
+                })
+                .approvals(ApprovalConfig {
+                    tools: vec!["reindex".to_string()],
+                    method: Default::default(),
+                    operators: vec!["alice".to_string()],
+                    timeout_secs: 60,
+                })
+        };
+        let err = builder().build().err().unwrap();
+        assert!(err.to_string().contains("need the /approvals endpoints"), "{err}");
+        assert!(builder().approval_endpoints(true).build().is_ok());
+    }
+}
@@ -15,11 +15,22 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use crate::approval::ApprovalConfig;
+use crate::audit::AuditConfig;
+use crate::authorization::AuthorizationConfig;
+use crate::client_config::Client;
+use crate::large_results::LargeResultsConfig;
+use crate::protocol::oidc::OidcConfig;
+use crate::rate_limit::{RateLimit, RateLimits};
+use crate::redaction::RedactionRule;
 use crate::servers::elasticsearch;
+use crate::timeout::ToolTimeouts;
 use clap::Parser;
 use clap::{Args, Subcommand};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::ffi::OsString;
 use std::path::PathBuf;
 
 /// Elastic MCP server
@@ -27,7 +38,7 @@ use std::path::PathBuf;
 #[command(version)]
 pub struct Cli {
     /// Container mode: change default http address, rewrite localhost to the host's address
-    #[clap(global=true, long, env = "CONTAINER_MODE")]
+    #[clap(global = true, long, env = "CONTAINER_MODE")]
     pub container_mode: bool,
 
     #[clap(subcommand)]
@@ -38,30 +49,262 @@ pub struct Cli {
 pub enum Command {
     Stdio(StdioCommand),
     Http(HttpCommand),
+    MigrateConfig(MigrateConfigCommand),
+    Init(InitCommand),
+    ClientConfig(ClientConfigCommand),
+    Validate(ValidateCommand),
+    ConfigSchema(ConfigSchemaCommand),
 }
 
 /// Start a streamable-HTTP server with optional SSE support
 #[derive(Debug, Args)]
 pub struct HttpCommand {
-    /// Config file
+    /// Config file [default: ~/.config/elastic-mcp/config.json5 if it exists]
     #[clap(short, long)]
     pub config: Option<PathBuf>,
 
+    /// Reload the config file when it changes. Needs a config file
+    #[clap(long)]
+    pub reload: bool,
+
+    /// Validate the config file and exit, as the 'validate' command. Needs a config file
+    #[clap(long)]
+    pub check: bool,
+
+    /// Write tools describe the API calls they would make instead of making them
+    #[clap(long, env = "DRY_RUN")]
+    pub dry_run: bool,
+
     /// Address to listen to [default: 127.0.0.1:8080]
     #[clap(long, value_name = "IP_ADDRESS:PORT", env = "HTTP_ADDRESS")]
     pub address: Option<std::net::SocketAddr>,
 
-    /// Also start an SSE server on '/sse'
-    #[clap(long)]
+    /// Unused: the legacy SSE transport is always served on '/mcp/sse'
+    #[clap(long, hide = true)]
     pub sse: bool,
+
+    /// Run as a systemd service: use the socket passed by socket activation, if any, notify systemd when ready,
+    /// and stop on SIGTERM
+    #[clap(long, env = "HTTP_SYSTEMD")]
+    pub systemd: bool,
+
+    #[command(flatten)]
+    pub session: HttpSessionArgs,
+
+    #[command(flatten)]
+    pub tls: HttpTlsArgs,
+
+    #[command(flatten)]
+    pub auth: HttpAuthArgs,
+}
+
+/// Streamable-HTTP sessions.
+#[derive(Debug, Args, Default)]
+#[command(next_help_heading = "Sessions")]
+pub struct HttpSessionArgs {
+    /// Keep a session for each client, identified with the 'Mcp-Session-Id' header. This allows
+    /// server-to-client notifications, at the cost of keeping state in the server.
+    #[clap(long, env = "HTTP_STATEFUL")]
+    pub stateful: bool,
+
+    /// Interval between keep-alive messages on SSE response streams
+    #[clap(long, value_name = "SECONDS", env = "HTTP_SESSION_KEEP_ALIVE")]
+    pub session_keep_alive: Option<u64>,
+
+    /// Close sessions after this duration of inactivity
+    #[clap(long, value_name = "SECONDS", env = "HTTP_SESSION_TTL", requires = "stateful")]
+    pub session_ttl: Option<u64>,
+}
+
+/// Serve HTTPS instead of HTTP.
+#[derive(Debug, Args, Default)]
+#[command(next_help_heading = "TLS")]
+pub struct HttpTlsArgs {
+    /// TLS certificate chain file, in PEM format
+    #[clap(long, value_name = "PEM_FILE", env = "HTTP_TLS_CERT", requires = "tls_key")]
+    pub tls_cert: Option<PathBuf>,
+
+    /// TLS private key file, in PEM format
+    #[clap(long, value_name = "PEM_FILE", env = "HTTP_TLS_KEY", requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+}
+
+/// Authentication of MCP clients. If no API key or bearer token is set, and no OIDC provider is configured in the
+/// configuration file, authentication is disabled.
+///
+/// When enabled, the `Authorization` header authenticates to the MCP server and isn't forwarded
+/// to Elasticsearch.
+#[derive(Debug, Args, Default)]
+#[command(next_help_heading = "Authentication")]
+pub struct HttpAuthArgs {
+    /// API key accepted in 'X-Api-Key' or 'Authorization: ApiKey' headers (can be repeated)
+    #[clap(
+        long = "auth-api-key",
+        value_name = "KEY",
+        env = "MCP_AUTH_API_KEYS",
+        value_delimiter = ','
+    )]
+    pub api_keys: Vec<String>,
+
+    /// Token accepted in 'Authorization: Bearer' headers (can be repeated)
+    #[clap(
+        long = "auth-bearer-token",
+        value_name = "TOKEN",
+        env = "MCP_AUTH_BEARER_TOKENS",
+        value_delimiter = ','
+    )]
+    pub bearer_tokens: Vec<String>,
+
+    /// Origin of the web pages that can open WebSocket sessions, e.g. 'https://app.example.com' (can be
+    /// repeated). Other cross-origin upgrade requests from browsers are rejected.
+    #[clap(
+        long = "allowed-origin",
+        value_name = "ORIGIN",
+        env = "HTTP_ALLOWED_ORIGINS",
+        value_delimiter = ','
+    )]
+    pub allowed_origins: Vec<String>,
 }
 
 /// Start an stdio server
 #[derive(Debug, Args)]
 pub struct StdioCommand {
+    /// Config file [default: ~/.config/elastic-mcp/config.json5 if it exists]
+    #[clap(short, long)]
+    pub config: Option<PathBuf>,
+
+    /// Reload the config file when it changes. Needs a config file
+    #[clap(long)]
+    pub reload: bool,
+
+    /// Validate the config file and exit, as the 'validate' command. Needs a config file
+    #[clap(long)]
+    pub check: bool,
+
+    /// Write tools describe the API calls they would make instead of making them
+    #[clap(long, env = "DRY_RUN")]
+    pub dry_run: bool,
+}
+
+/// Upgrade a configuration file written for an older version of the server. The original file is saved
+/// with a '.bak' extension.
+#[derive(Debug, Args)]
+pub struct MigrateConfigCommand {
+    /// Config file
+    #[clap(short, long)]
+    pub config: PathBuf,
+
+    /// Print the upgraded configuration instead of writing it
+    #[clap(long)]
+    pub dry_run: bool,
+}
+
+/// Validate a configuration file: report unknown fields, invalid values and cluster settings, and exit with an
+/// error status if there are problems
+#[derive(Debug, Args)]
+pub struct ValidateCommand {
     /// Config file
     #[clap(short, long)]
+    pub config: PathBuf,
+
+    /// Also check that the Elasticsearch clusters can be reached with their credentials
+    #[clap(long)]
+    pub ping: bool,
+}
+
+/// Print the JSON schema of configuration files, for editors to validate and complete them
+#[derive(Debug, Args)]
+pub struct ConfigSchemaCommand {}
+
+/// Create a configuration file by answering questions, and print the configuration of MCP clients to use it
+#[derive(Debug, Args)]
+pub struct InitCommand {
+    /// Config file to create (default: `~/.config/elastic-mcp/config.json5`, that the stdio and http commands
+    /// use when `--config` isn't set)
+    #[clap(short, long)]
+    pub config: Option<PathBuf>,
+
+    /// Overwrite the config file if it already exists
+    #[clap(long)]
+    pub force: bool,
+}
+
+/// Print the configuration that adds this server to an MCP client. Secrets are replaced by placeholders.
+#[derive(Debug, Args)]
+pub struct ClientConfigCommand {
+    /// MCP client
+    #[clap(long, value_enum)]
+    pub client: Client,
+
+    /// How the client starts or connects to the server
+    #[clap(long, value_enum, default_value = "stdio")]
+    pub transport: ClientTransport,
+
+    /// Server binary, for the stdio transport [default: this binary]
+    #[clap(long)]
+    pub command: Option<PathBuf>,
+
+    /// Config file of the server, for the stdio and docker transports. Without it, the server is configured with
+    /// environment variables
+    #[clap(short, long)]
     pub config: Option<PathBuf>,
+
+    /// URL of the server, for the http transport
+    #[clap(long, default_value = "http://localhost:8080/mcp")]
+    pub url: String,
+
+    /// Credentials sent by the client, for the http transport
+    #[clap(long, value_enum, default_value = "none")]
+    pub auth: ClientAuth,
+
+    /// Name of the server in the client configuration
+    #[clap(long, default_value = "elasticsearch")]
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum ClientTransport {
+    /// Run this binary
+    Stdio,
+    /// Run the Docker image
+    Docker,
+    /// Connect to a streamable-HTTP server
+    Http,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum ClientAuth {
+    None,
+    /// An API key of the MCP server, in the 'X-Api-Key' header
+    Mcp,
+    /// An Elasticsearch API key, in the 'X-Elastic-Api-Key' header
+    Elasticsearch,
+}
+
+/// Configuration file of the stdio and http commands when `--config` isn't set:
+/// `$XDG_CONFIG_HOME/elastic-mcp/config.json5` or `~/.config/elastic-mcp/config.json5`, if it exists.
+pub fn default_config_file() -> Option<PathBuf> {
+    find_config_file(|name| std::env::var_os(name))
+}
+
+/// Location of [`default_config_file`], whether it exists or not. `None` if there's no home directory.
+pub fn default_config_location() -> Option<PathBuf> {
+    config_location(|name| std::env::var_os(name))
+}
+
+fn find_config_file(env: impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    config_location(env).filter(|path| path.is_file())
+}
+
+fn config_location(env: impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    let config_home = env("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            let home = env("HOME").or_else(|| env("USERPROFILE"))?;
+            Some(PathBuf::from(home).join(".config"))
+        })?;
+    Some(config_home.join("elastic-mcp").join("config.json5"))
 }
 
 //---------------------------------------------------------------
@@ -72,7 +315,7 @@ pub struct StdioCommand {
 // https://docs.aws.amazon.com/amazonq/latest/qdeveloper-ug/command-line-mcp-configuration.html
 // https://github.com/landicefu/mcp-client-configuration-server
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Stdio {
     /// Command to run (e.g. "npx", "docker")
@@ -86,7 +329,7 @@ pub struct Stdio {
     pub env: HashMap<String, String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Http {
     /// URL of the server
@@ -97,20 +340,187 @@ pub struct Http {
     pub headers: HashMap<String, String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 #[serde(tag = "type")]
 pub enum McpServer {
     //Builtin(BuiltinConfig),
+    /// An additional Elasticsearch cluster
+    Elasticsearch(Box<elasticsearch::ElasticsearchMcpConfig>),
     Sse(Http),
     StreamableHttp(Http),
     Stdio(Stdio),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Configuration {
-    pub elasticsearch: elasticsearch::ElasticsearchMcpConfig,
+    /// Version of the configuration layout, see `migration::CONFIG_VERSION`
+    #[serde(default)]
+    pub version: u64,
+    /// The default Elasticsearch cluster
+    #[serde(default)]
+    pub elasticsearch: Option<elasticsearch::ElasticsearchMcpConfig>,
+    #[serde(default)]
+    pub mcp_servers: HashMap<String, McpServer>,
+    /// Audit log of tool invocations
+    #[serde(default)]
+    pub audit: Option<AuditConfig>,
+    /// Tool call rate limits, by tool name pattern
+    #[serde(default)]
+    #[schemars(with = "HashMap<String, RateLimit>")]
+    pub rate_limits: RateLimits,
+    /// Tool call timeouts in seconds, by tool name pattern
+    #[serde(default)]
+    #[schemars(with = "HashMap<String, u64>")]
+    pub tool_timeouts: ToolTimeouts,
+    /// Storage of large tool results as resources
+    #[serde(default)]
+    pub large_results: Option<LargeResultsConfig>,
+    /// Fields of tool results that are removed or masked
+    #[serde(default)]
+    pub redactions: Vec<RedactionRule>,
+    /// Tool calls that need a human approval
+    #[serde(default)]
+    pub approvals: Option<ApprovalConfig>,
+    /// Authentication of the clients of the http server, in addition to the command line credentials
+    #[serde(default)]
+    pub auth: Option<AuthConfig>,
+    /// Tools that clients of the http server can call, depending on their roles
+    #[serde(default)]
+    pub authorization: Option<AuthorizationConfig>,
+    /// Tenants of the http server, each with its own clusters, served on `/tenants/<name>/mcp`
+    #[serde(default)]
+    pub tenants: HashMap<String, Tenant>,
+}
+
+impl Configuration {
+    /// The configuration of a tenant: its clusters, with the other settings of this configuration.
+    pub fn into_tenant(mut self, name: &str) -> anyhow::Result<Configuration> {
+        let Some(tenant) = self.tenants.remove(name) else {
+            anyhow::bail!("Tenant '{name}' not found in the configuration");
+        };
+        self.elasticsearch = tenant.elasticsearch;
+        self.mcp_servers = tenant.mcp_servers;
+        self.tenants.clear();
+        Ok(self)
+    }
+}
+
+/// Authentication of the clients of the http server.
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthConfig {
+    /// OpenID Connect provider whose bearer tokens are accepted
+    #[serde(default)]
+    pub oidc: Option<OidcConfig>,
+}
+
+/// A tenant of the http server. Settings other than clusters, such as rate limits and audit, are shared by all
+/// tenants.
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Tenant {
+    /// The default Elasticsearch cluster of the tenant
+    #[serde(default)]
+    pub elasticsearch: Option<elasticsearch::ElasticsearchMcpConfig>,
     #[serde(default)]
     pub mcp_servers: HashMap<String, McpServer>,
+    /// Named clients that can use the endpoints of the tenant
+    #[serde(default)]
+    pub clients: Vec<String>,
+    /// Roles of the clients that can use the endpoints of the tenant, see `authorization`
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+impl Tenant {
+    /// Can a client use the endpoints of this tenant? All clients can if no clients or roles are set. `identity`
+    /// is `None` for anonymous clients.
+    pub fn is_allowed(&self, authorization: &AuthorizationConfig, identity: Option<&str>) -> bool {
+        if self.clients.is_empty() && self.roles.is_empty() {
+            return true;
+        }
+        identity.is_some_and(|identity| self.clients.iter().any(|client| client == identity))
+            || authorization
+                .roles_of(identity)
+                .iter()
+                .any(|role| self.roles.contains(role))
+    }
+}
+
+/// Check that a tenant name can be used in URL paths and headers.
+pub fn check_tenant_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!(
+            "Invalid tenant name '{name}', it must only contain letters, digits, '-' and '_'"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_schema() {
+        let schema = serde_json::to_value(schemars::schema_for!(Configuration)).unwrap();
+        assert!(schema["properties"]["mcpServers"].is_object());
+        assert_eq!(schema["definitions"]["RateLimit"]["type"], "string");
+
+        let es_config = &schema["definitions"]["ElasticsearchMcpConfig"]["properties"];
+        assert_eq!(es_config["url"]["description"], "Cluster URL");
+        assert!(es_config["ssl_skip_verify"]["anyOf"].is_array());
+    }
+
+    #[test]
+    fn tenant_config() -> anyhow::Result<()> {
+        let config: Configuration = serde_json::from_value(serde_json::json!({
+            "elasticsearch": { "url": "http://localhost:9200" },
+            "rateLimits": { "*": "10/min" },
+            "tenants": {
+                "team-a": { "elasticsearch": { "url": "https://team-a:9200" } },
+                "team-b": { "mcpServers": { "logs": { "type": "elasticsearch", "url": "https://logs:9200" } } },
+            }
+        }))?;
+        let tenant = config.into_tenant("team-b")?;
+        assert!(tenant.elasticsearch.is_none());
+        assert!(tenant.mcp_servers.contains_key("logs"));
+        assert_eq!(tenant.rate_limits.len(), 1);
+        assert!(tenant.tenants.is_empty());
+
+        let tenant: Tenant = serde_json::from_value(serde_json::json!({ "clients": ["alice"], "roles": ["search"] }))?;
+        let authorization: AuthorizationConfig = serde_json::from_value(serde_json::json!({
+            "roles": { "search": ["search"], "ops": ["*"] },
+            "clients": { "bob": { "roles": ["search"] }, "carol": { "roles": ["ops"] } },
+            "defaultRoles": ["ops"]
+        }))?;
+        assert!(tenant.is_allowed(&authorization, Some("alice")));
+        assert!(tenant.is_allowed(&authorization, Some("bob")));
+        assert!(!tenant.is_allowed(&authorization, Some("carol")));
+        assert!(!tenant.is_allowed(&authorization, None));
+        assert!(Tenant::default().is_allowed(&authorization, None));
+
+        assert!(check_tenant_name("team-a").is_ok());
+        assert!(check_tenant_name("team/a").is_err());
+        assert!(check_tenant_name("").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn find_default_config_file() -> anyhow::Result<()> {
+        let home = std::env::temp_dir().join(format!("elastic-mcp-home-{}", std::process::id()));
+        let path = home.join(".config/elastic-mcp/config.json5");
+        let env = |name: &str| (name == "HOME").then(|| home.clone().into_os_string());
+        assert_eq!(find_config_file(env), None);
+        assert_eq!(config_location(env), Some(path.clone()));
+
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(&path, "{}")?;
+        let found = find_config_file(env);
+        std::fs::remove_dir_all(&home)?;
+        assert_eq!(found, Some(path));
+        Ok(())
+    }
 }
@@ -0,0 +1,315 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Configuration snippets that register this server in MCP clients (`client-config` command).
+//!
+//! Secrets and values that depend on the user's environment are never written in snippets: they're replaced by
+//! a placeholder in the syntax of each client, e.g. `${env:ES_API_KEY}` for Cursor.
+
+use crate::cli::{ClientAuth, ClientConfigCommand, ClientTransport};
+use serde_json::{Map, Value, json};
+
+/// Docker image of the server.
+const DOCKER_IMAGE: &str = "docker.elastic.co/mcp/elasticsearch";
+
+/// Path of the configuration file in the container.
+const DOCKER_CONFIG: &str = "/config/elastic-mcp.json5";
+
+/// An MCP client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Client {
+    ClaudeDesktop,
+    Cursor,
+    VsCode,
+    /// Clients using the common `mcpServers` layout with a `type`, including this server's configuration file
+    Generic,
+}
+
+/// A value that the user provides, like an API key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Variable {
+    /// Name of the environment variable
+    pub name: &'static str,
+    pub description: &'static str,
+    pub secret: bool,
+}
+
+const ES_URL: Variable = Variable {
+    name: "ES_URL",
+    description: "Elasticsearch URL",
+    secret: false,
+};
+
+const ES_API_KEY: Variable = Variable {
+    name: "ES_API_KEY",
+    description: "Elasticsearch API key",
+    secret: true,
+};
+
+const MCP_API_KEY: Variable = Variable {
+    name: "MCP_API_KEY",
+    description: "API key of the MCP server",
+    secret: true,
+};
+
+/// How the client runs or connects to the server.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transport {
+    /// Start a command and talk to it with stdio
+    Stdio {
+        command: String,
+        args: Vec<String>,
+        env: Vec<Variable>,
+    },
+    /// Connect to a streamable-HTTP server
+    Http {
+        url: String,
+        headers: Vec<(String, Variable)>,
+    },
+}
+
+impl Transport {
+    pub fn from_command(cmd: &ClientConfigCommand) -> anyhow::Result<Transport> {
+        let config = cmd.config.as_ref().map(std::path::absolute).transpose()?;
+        // Without a configuration file, the server is configured with environment variables
+        let env = if config.is_some() {
+            vec![]
+        } else {
+            vec![ES_URL, ES_API_KEY]
+        };
+
+        Ok(match cmd.transport {
+            ClientTransport::Stdio => {
+                let command = match &cmd.command {
+                    Some(command) => command.clone(),
+                    None => std::env::current_exe()?,
+                };
+                let mut args = vec!["stdio".to_string()];
+                if let Some(config) = config {
+                    args.extend(["--config".to_string(), config.display().to_string()]);
+                }
+                Transport::Stdio {
+                    command: command.display().to_string(),
+                    args,
+                    env,
+                }
+            }
+            ClientTransport::Docker => {
+                let mut args = ["run", "-i", "--rm"].map(String::from).to_vec();
+                for var in &env {
+                    args.extend(["-e".to_string(), var.name.to_string()]);
+                }
+                if let Some(config) = &config {
+                    args.extend(["-v".to_string(), format!("{}:{DOCKER_CONFIG}:ro", config.display())]);
+                }
+                args.extend([DOCKER_IMAGE.to_string(), "stdio".to_string()]);
+                if config.is_some() {
+                    args.extend(["--config".to_string(), DOCKER_CONFIG.to_string()]);
+                }
+                Transport::Stdio {
+                    command: "docker".to_string(),
+                    args,
+                    env,
+                }
+            }
+            ClientTransport::Http => Transport::Http {
+                url: cmd.url.clone(),
+                headers: match cmd.auth {
+                    ClientAuth::None => vec![],
+                    ClientAuth::Mcp => vec![("X-Api-Key".to_string(), MCP_API_KEY)],
+                    ClientAuth::Elasticsearch => vec![("X-Elastic-Api-Key".to_string(), ES_API_KEY)],
+                },
+            },
+        })
+    }
+}
+
+impl Client {
+    pub fn name(self) -> &'static str {
+        match self {
+            Client::ClaudeDesktop => "Claude Desktop",
+            Client::Cursor => "Cursor",
+            Client::VsCode => "VS Code",
+            Client::Generic => "Other clients",
+        }
+    }
+
+    /// Where the snippet is added.
+    pub fn location(self) -> &'static str {
+        match self {
+            Client::ClaudeDesktop => "claude_desktop_config.json (Settings > Developer > Edit Config)",
+            Client::Cursor => "~/.cursor/mcp.json, or .cursor/mcp.json in your project",
+            Client::VsCode => ".vscode/mcp.json in your workspace",
+            Client::Generic => "the configuration of your MCP client",
+        }
+    }
+
+    /// Placeholder for a variable, in the syntax of the client.
+    fn placeholder(self, var: &Variable) -> String {
+        match self {
+            // No variable expansion: the value has to be edited
+            Client::ClaudeDesktop => format!("<{}>", var.name),
+            Client::Cursor => format!("${{env:{}}}", var.name),
+            // Values are asked when the server starts
+            Client::VsCode => format!("${{input:{}}}", var.name.to_lowercase().replace('_', "-")),
+            Client::Generic => format!("${{{}}}", var.name),
+        }
+    }
+
+    /// Snippet that registers the server under `server_name`.
+    pub fn snippet(self, server_name: &str, transport: &Transport) -> Value {
+        let (mut server, variables) = match transport {
+            Transport::Stdio { command, args, env } => {
+                let mut server = json!({ "command": command, "args": args });
+                if !env.is_empty() {
+                    let env = env
+                        .iter()
+                        .map(|var| (var.name.to_string(), Value::String(self.placeholder(var))))
+                        .collect::<Map<_, _>>();
+                    server["env"] = Value::Object(env);
+                }
+                (server, env.clone())
+            }
+            // No http support: use a proxy that bridges stdio to streamable-HTTP
+            Transport::Http { url, headers } if self == Client::ClaudeDesktop => {
+                let mut args = vec!["--transport=streamablehttp".to_string()];
+                for (name, var) in headers {
+                    args.extend(["--header".to_string(), name.clone(), self.placeholder(var)]);
+                }
+                args.push(url.clone());
+                let server = json!({ "command": "mcp-proxy", "args": args });
+                (server, headers.iter().map(|(_, var)| *var).collect())
+            }
+            Transport::Http { url, headers } => {
+                let mut server = json!({ "url": url });
+                if !headers.is_empty() {
+                    let headers = headers
+                        .iter()
+                        .map(|(name, var)| (name.clone(), Value::String(self.placeholder(var))))
+                        .collect::<Map<_, _>>();
+                    server["headers"] = Value::Object(headers);
+                }
+                (server, headers.iter().map(|(_, var)| *var).collect())
+            }
+        };
+
+        let server_type = match transport {
+            Transport::Stdio { .. } => "stdio",
+            Transport::Http { .. } if self == Client::VsCode => "http",
+            Transport::Http { .. } => "streamable-http",
+        };
+        match self {
+            Client::ClaudeDesktop | Client::Cursor => json!({ "mcpServers": { server_name: server } }),
+            Client::Generic => {
+                server["type"] = server_type.into();
+                json!({ "mcpServers": { server_name: server } })
+            }
+            Client::VsCode => {
+                server["type"] = server_type.into();
+                let mut snippet = json!({ "servers": { server_name: server } });
+                if !variables.is_empty() {
+                    let inputs = variables
+                        .iter()
+                        .map(|var| {
+                            json!({
+                                "type": "promptString",
+                                "id": var.name.to_lowercase().replace('_', "-"),
+                                "description": var.description,
+                                "password": var.secret,
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    snippet["inputs"] = inputs.into();
+                }
+                snippet
+            }
+        }
+    }
+}
+
+/// Print the configuration snippet of an MCP client.
+pub fn client_config(cmd: ClientConfigCommand) -> anyhow::Result<()> {
+    let transport = Transport::from_command(&cmd)?;
+    let snippet = cmd.client.snippet(&cmd.name, &transport);
+    tracing::info!("{}: add this to {}", cmd.client.name(), cmd.client.location());
+    println!("{}", serde_json::to_string_pretty(&snippet)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stdio() -> Transport {
+        Transport::Stdio {
+            command: "/usr/bin/mcp".to_string(),
+            args: vec!["stdio".to_string()],
+            env: vec![ES_URL, ES_API_KEY],
+        }
+    }
+
+    #[test]
+    fn stdio_snippets() {
+        assert_eq!(
+            Client::ClaudeDesktop.snippet("elasticsearch", &stdio()),
+            json!({ "mcpServers": { "elasticsearch": {
+                "command": "/usr/bin/mcp",
+                "args": ["stdio"],
+                "env": { "ES_URL": "<ES_URL>", "ES_API_KEY": "<ES_API_KEY>" },
+            }}})
+        );
+        assert_eq!(
+            Client::Cursor.snippet("es", &stdio())["mcpServers"]["es"]["env"]["ES_API_KEY"],
+            "${env:ES_API_KEY}"
+        );
+
+        let snippet = Client::VsCode.snippet("es", &stdio());
+        assert_eq!(snippet["servers"]["es"]["type"], "stdio");
+        assert_eq!(snippet["servers"]["es"]["env"]["ES_API_KEY"], "${input:es-api-key}");
+        assert_eq!(
+            snippet["inputs"][1],
+            json!({ "type": "promptString", "id": "es-api-key", "description": "Elasticsearch API key", "password": true })
+        );
+    }
+
+    #[test]
+    fn http_snippets() {
+        let http = Transport::Http {
+            url: "http://localhost:8080/mcp".to_string(),
+            headers: vec![("X-Elastic-Api-Key".to_string(), ES_API_KEY)],
+        };
+        assert_eq!(
+            Client::Generic.snippet("es", &http),
+            json!({ "mcpServers": { "es": {
+                "type": "streamable-http",
+                "url": "http://localhost:8080/mcp",
+                "headers": { "X-Elastic-Api-Key": "${ES_API_KEY}" },
+            }}})
+        );
+        assert_eq!(
+            Client::ClaudeDesktop.snippet("es", &http)["mcpServers"]["es"]["args"],
+            json!([
+                "--transport=streamablehttp",
+                "--header",
+                "X-Elastic-Api-Key",
+                "<ES_API_KEY>",
+                "http://localhost:8080/mcp"
+            ])
+        );
+        assert_eq!(Client::VsCode.snippet("es", &http)["servers"]["es"]["type"], "http");
+    }
+}
@@ -0,0 +1,364 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The `init` command: create a configuration file by answering questions, check that the cluster can be
+//! reached, and print the configuration of MCP clients that run the server with this file.
+
+use crate::cli::{self, InitCommand};
+use crate::client_config::{Client, Transport};
+use crate::migration::CONFIG_VERSION;
+use crate::servers::elasticsearch::{ElasticsearchMcp, ElasticsearchMcpConfig};
+use serde_json::{Map, Value, json};
+use std::io::{BufRead, IsTerminal, Write};
+
+/// Example configuration, with all settings.
+const EXAMPLE_CONFIG: &str = "https://github.com/elastic/mcp-server-elasticsearch/blob/main/elastic-mcp.json5";
+
+/// Name of the server in client configurations.
+const SERVER_NAME: &str = "elasticsearch";
+
+pub async fn init(cmd: InitCommand, container_mode: bool) -> anyhow::Result<()> {
+    let Some(config) = cmd.config.or_else(cli::default_config_location) else {
+        anyhow::bail!("Cannot find the home directory, use --config to choose the configuration file");
+    };
+    if config.exists() && !cmd.force {
+        anyhow::bail!(
+            "Configuration file {} already exists, use --force to overwrite it",
+            config.display()
+        );
+    }
+
+    let mut prompt = Prompt {
+        input: std::io::BufReader::new(std::io::stdin()),
+        output: std::io::stdout(),
+        terminal: std::io::stdin().is_terminal(),
+    };
+    let es_config = ask_cluster(&mut prompt)?;
+
+    prompt.say("\nConnecting to Elasticsearch...")?;
+    match check_connection(&es_config, container_mode).await {
+        Ok(cluster) => prompt.say(&format!("Connected to {cluster}"))?,
+        Err(err) => {
+            prompt.say(&format!("Could not connect to Elasticsearch: {err:#}"))?;
+            if !prompt.confirm("Save the configuration anyway?", false)? {
+                anyhow::bail!("Configuration not saved");
+            }
+        }
+    }
+
+    if let Some(dir) = config.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    write_private(&config, &config_text(es_config), cmd.force)?;
+    let path = std::path::absolute(&config)?;
+    prompt.say(&format!("\nConfiguration saved to {}", path.display()))?;
+
+    let transport = Transport::Stdio {
+        command: std::env::current_exe()?.display().to_string(),
+        args: vec!["stdio".to_string(), "--config".to_string(), path.display().to_string()],
+        env: vec![],
+    };
+    for client in [Client::ClaudeDesktop, Client::Cursor, Client::VsCode] {
+        let snippet = client.snippet(SERVER_NAME, &transport);
+        prompt.say(&format!(
+            "\nFor {}, add this to {}:\n{}",
+            client.name(),
+            client.location(),
+            serde_json::to_string_pretty(&snippet)?
+        ))?;
+    }
+    prompt.say("\nThe 'client-config' command prints the configuration of other clients.")?;
+    Ok(())
+}
+
+/// Ask how to connect to the cluster and which tools to provide, and return its configuration.
+fn ask_cluster(prompt: &mut Prompt<impl BufRead, impl Write>) -> anyhow::Result<Map<String, Value>> {
+    let mut es = Map::new();
+
+    let connection = prompt.choose(
+        "How do you connect to Elasticsearch?",
+        &["With the URL of the cluster", "With an Elastic Cloud id"],
+    )?;
+    match connection {
+        0 => es.insert(
+            "url".into(),
+            prompt.ask("Elasticsearch URL", Some("http://localhost:9200"))?.into(),
+        ),
+        _ => es.insert("cloud_id".into(), prompt.ask("Elastic Cloud id", None)?.into()),
+    };
+
+    let auth = prompt.choose(
+        "How do you authenticate?",
+        &["With an API key", "With a login and password", "No authentication"],
+    )?;
+    match auth {
+        0 => {
+            es.insert("api_key".into(), prompt.ask_secret("API key")?.into());
+        }
+        1 => {
+            es.insert("login".into(), prompt.ask("Login", Some("elastic"))?.into());
+            es.insert("password".into(), prompt.ask_secret("Password")?.into());
+        }
+        _ => {}
+    }
+
+    let tools = prompt.choose(
+        "Which tools should be available?",
+        &[
+            "Read-only tools",
+            "All tools, including those that modify data",
+            "A list of tools",
+        ],
+    )?;
+    let allow_writes = match tools {
+        0 => false,
+        1 => true,
+        _ => {
+            let names = prompt.ask("Tool names, separated by commas", None)?;
+            let names = names
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .collect::<Vec<_>>();
+            es.insert("tools".into(), json!({ "include": names }));
+            prompt.confirm("Do they include tools that modify data?", false)?
+        }
+    };
+    es.insert("allow_writes".into(), allow_writes.into());
+
+    Ok(es)
+}
+
+/// Connect to the cluster, and return its name and version.
+async fn check_connection(es: &Map<String, Value>, container_mode: bool) -> anyhow::Result<String> {
+    let config: ElasticsearchMcpConfig = serde_json::from_value(Value::Object(es.clone()))?;
+    let es_client = ElasticsearchMcp::new_client(&config, container_mode)?;
+    let info: Value = es_client.info().send().await?.error_for_status_code()?.json().await?;
+    Ok(format!(
+        "cluster '{}', version {}",
+        info["cluster_name"].as_str().unwrap_or("unknown"),
+        info["version"]["number"].as_str().unwrap_or("unknown")
+    ))
+}
+
+/// Commented examples of other MCP servers, to be proxied with the tools of the cluster.
+const MCP_SERVERS_EXAMPLE: &str = r#"
+  // Other MCP servers whose tools are also provided, prefixed with their name
+  /* "mcpServers": {
+    // Another Elasticsearch cluster
+    "logs": {
+      "type": "elasticsearch",
+      "url": "https://logs.example.com:9200",
+      "api_key": "${LOGS_API_KEY:}"
+    },
+    // A server started as a child process, using stdio
+    "files": {
+      "type": "stdio",
+      "command": "npx",
+      "args": ["-y", "@modelcontextprotocol/server-filesystem", "/data"]
+    },
+    // A server reached with the streamable http transport ("sse" for the older SSE transport)
+    "docs": {
+      "type": "streamable-http",
+      "url": "https://docs.example.com/mcp",
+      "headers": { "Authorization": "Bearer ${DOCS_TOKEN:}" }
+    }
+  } */
+"#;
+
+fn config_text(es: Map<String, Value>) -> String {
+    // Written by hand to have the version first, and comments
+    let es = serde_json::to_string_pretty(&es)
+        .unwrap_or_default()
+        .replace('\n', "\n  ");
+    format!(
+        "// Created with the 'init' command. All settings are described in {EXAMPLE_CONFIG}\n\
+        {{\n  \"version\": {CONFIG_VERSION},\n  \"elasticsearch\": {es},\n{MCP_SERVERS_EXAMPLE}}}\n"
+    )
+}
+
+/// Write a file that only the current user can read, as it may contain credentials.
+fn write_private(path: &std::path::Path, text: &str, overwrite: bool) -> anyhow::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true);
+    if overwrite {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut file = options.open(path)?;
+    // The mode only applies to new files, not to those that are overwritten
+    #[cfg(unix)]
+    file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+    file.write_all(text.as_bytes())?;
+    Ok(())
+}
+
+/// Questions and answers on a terminal.
+struct Prompt<R, W> {
+    input: R,
+    output: W,
+    /// Whether the input is a terminal, where secrets are read without echoing them.
+    terminal: bool,
+}
+
+impl<R: BufRead, W: Write> Prompt<R, W> {
+    fn say(&mut self, text: &str) -> anyhow::Result<()> {
+        writeln!(self.output, "{text}")?;
+        Ok(())
+    }
+
+    /// Write a question and read the answer, failing if the input is closed.
+    fn read_answer(&mut self, question: &str) -> anyhow::Result<String> {
+        write!(self.output, "{question} ")?;
+        self.output.flush()?;
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            anyhow::bail!("Setup aborted");
+        }
+        Ok(line.trim().to_string())
+    }
+
+    /// Ask for a value, until a non-empty one is provided if there's no default.
+    fn ask(&mut self, question: &str, default: Option<&str>) -> anyhow::Result<String> {
+        loop {
+            let answer = match default {
+                Some(default) => self.read_answer(&format!("{question} [{default}]:"))?,
+                None => self.read_answer(&format!("{question}:"))?,
+            };
+            match (answer.is_empty(), default) {
+                (false, _) => return Ok(answer),
+                (true, Some(default)) => return Ok(default.to_string()),
+                (true, None) => {}
+            }
+        }
+    }
+
+    /// Ask for a non-empty secret, that isn't displayed while it's typed on a terminal.
+    fn ask_secret(&mut self, question: &str) -> anyhow::Result<String> {
+        if !self.terminal {
+            return self.ask(question, None);
+        }
+        loop {
+            let answer = rpassword::prompt_password(format!("{question}: "))?;
+            if !answer.trim().is_empty() {
+                return Ok(answer.trim().to_string());
+            }
+        }
+    }
+
+    /// Ask to choose in a list, the first choice being the default. Returns the index of the choice.
+    fn choose(&mut self, question: &str, choices: &[&str]) -> anyhow::Result<usize> {
+        self.say(&format!("\n{question}"))?;
+        for (i, choice) in choices.iter().enumerate() {
+            self.say(&format!("  {}) {choice}", i + 1))?;
+        }
+        loop {
+            let answer = self.ask("Choice", Some("1"))?;
+            if let Ok(n) = answer.parse::<usize>()
+                && (1..=choices.len()).contains(&n)
+            {
+                return Ok(n - 1);
+            }
+            self.say(&format!("Please enter a number between 1 and {}", choices.len()))?;
+        }
+    }
+
+    fn confirm(&mut self, question: &str, default: bool) -> anyhow::Result<bool> {
+        let options = if default { "[Y/n]" } else { "[y/N]" };
+        loop {
+            let answer = self.read_answer(&format!("{question} {options}"))?.to_lowercase();
+            match answer.as_str() {
+                "" => return Ok(default),
+                "y" | "yes" => return Ok(true),
+                "n" | "no" => return Ok(false),
+                _ => self.say("Please answer 'y' or 'n'")?,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answers(input: &str) -> anyhow::Result<Map<String, Value>> {
+        let mut prompt = Prompt {
+            input: input.as_bytes(),
+            output: Vec::new(),
+            terminal: false,
+        };
+        ask_cluster(&mut prompt)
+    }
+
+    #[test]
+    fn ask_questions() -> anyhow::Result<()> {
+        // Defaults: local cluster, then login and password, then an invalid choice and a list of tools
+        let es = answers("\n\n2\n\nchangeme\n4\n3\nsearch, esql,\ny\n")?;
+        assert_eq!(
+            Value::Object(es.clone()),
+            json!({
+                "url": "http://localhost:9200",
+                "login": "elastic",
+                "password": "changeme",
+                "tools": { "include": ["search", "esql"] },
+                "allow_writes": true,
+            })
+        );
+
+        // The generated file is a valid configuration, also when the examples of other servers are uncommented
+        let config = crate::parse_config(config_text(es.clone()), None)?;
+        assert!(config.mcp_servers.is_empty());
+        let es_config = config.elasticsearch.unwrap();
+        assert!(es_config.tools.incl_excl.unwrap().is_included("esql"));
+
+        let uncommented = config_text(es)
+            .replace("/* \"mcpServers\"", "\"mcpServers\"")
+            .replace("} */", "}");
+        let config = crate::parse_config(uncommented, None)?;
+        assert_eq!(config.mcp_servers.len(), 3);
+
+        let es = answers("2\nmy-deployment:ZXUtd2VzdC0xLmF3cy5mb3VuZC5pbyRhYmMkZGVm\n1\nabc\n\n")?;
+        assert_eq!(es["cloud_id"], "my-deployment:ZXUtd2VzdC0xLmF3cy5mb3VuZC5pbyRhYmMkZGVm");
+        assert_eq!(es["api_key"], "abc");
+        assert_eq!(es["allow_writes"], false);
+
+        // Closed input
+        assert!(answers("1\n").is_err());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn overwritten_files_are_private() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("elastic-mcp-init-{}.json5", std::process::id()));
+        std::fs::write(&path, "{}")?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644))?;
+
+        assert!(write_private(&path, "{}", false).is_err());
+        write_private(&path, "{ \"version\": 1 }", true)?;
+        let mode = std::fs::metadata(&path)?.permissions().mode();
+        std::fs::remove_file(&path)?;
+        assert_eq!(mode & 0o777, 0o600);
+        Ok(())
+    }
+}
@@ -0,0 +1,328 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Storage of large tool results as resources, to keep them out of the model context.
+
+use crate::utils::resource_store::{Owner, ResourceStore, URI_SCHEME};
+use rmcp::model::{
+    CallToolResult, ClientNotification, ClientRequest, Content, ReadResourceResult, ResourcesCapability, ServerInfo,
+    ServerResult,
+};
+use rmcp::service::{NotificationContext, RequestContext};
+use rmcp::{RoleServer, Service};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Maximum number of stored results. The oldest ones are removed first.
+const MAX_STORED_RESULTS: usize = 100;
+
+/// Length of the beginning of a result that is returned in place of the result.
+const PREVIEW_LENGTH: usize = 1000;
+
+/// Storage of large tool results as resources.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+pub struct LargeResultsConfig {
+    /// Tool results larger than this size in bytes are stored as resources
+    pub max_size: usize,
+    /// Time to live of stored results, in seconds
+    pub ttl_secs: u64,
+}
+
+impl Default for LargeResultsConfig {
+    fn default() -> Self {
+        LargeResultsConfig {
+            max_size: 100_000,
+            ttl_secs: 600,
+        }
+    }
+}
+
+/// A server that stores the text of tool results larger than a maximum size as resources, and returns
+/// their URI and beginning instead. Clients can then read the full result if they need it.
+///
+/// The store is shared by all the sessions of the server, and results can only be listed and read by the client
+/// that called the tool (see [`Owner`]).
+#[derive(Clone)]
+pub struct LargeResultServer<S> {
+    inner: S,
+    config: Option<LargeResultsConfig>,
+    store: ResourceStore,
+}
+
+impl<S: Service<RoleServer>> LargeResultServer<S> {
+    pub fn new(inner: S, config: Option<LargeResultsConfig>) -> Self {
+        let ttl = Duration::from_secs(config.as_ref().map_or(0, |c| c.ttl_secs));
+        LargeResultServer {
+            inner,
+            config,
+            store: ResourceStore::new(ttl, MAX_STORED_RESULTS),
+        }
+    }
+
+    /// Store a successful result if it's too large. Results that have non-text content are kept as is.
+    fn store_if_large(&self, owner: &Owner, tool: &str, result: CallToolResult) -> CallToolResult {
+        let Some(config) = &self.config else {
+            return result;
+        };
+        if result.is_error == Some(true) {
+            return result;
+        }
+        let Some(texts) = result
+            .content
+            .iter()
+            .map(|c| c.as_text().map(|t| t.text.as_str()))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return result;
+        };
+        let size = texts.iter().map(|t| t.len()).sum::<usize>();
+        if size <= config.max_size {
+            return result;
+        }
+
+        let text = texts.join("\n");
+        let mime_type = match serde_json::from_str::<serde_json::Value>(&text) {
+            Ok(_) => "application/json",
+            Err(_) => "text/plain",
+        };
+        let preview = match text.char_indices().nth(PREVIEW_LENGTH) {
+            Some((end, _)) => text[..end].to_string(),
+            None => text.clone(),
+        };
+        let uri = self
+            .store
+            .add(owner, "results", format!("Result of {tool}"), mime_type, text);
+
+        CallToolResult::success(vec![Content::text(format!(
+            "The result of '{tool}' is {size} bytes long, more than the limit of {} bytes. It was stored as \
+            resource {uri} ({mime_type}) that expires in {} seconds, read it for the full result. \
+            It starts with:\n{preview}",
+            config.max_size, config.ttl_secs,
+        ))])
+    }
+}
+
+impl<S: Service<RoleServer>> Service<RoleServer> for LargeResultServer<S> {
+    async fn handle_request(
+        &self,
+        request: ClientRequest,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ServerResult, rmcp::Error> {
+        if self.config.is_none() {
+            return self.inner.handle_request(request, context).await;
+        }
+
+        let owner = Owner::of(&context);
+        match request {
+            ClientRequest::CallToolRequest(call) => {
+                let tool = call.params.name.to_string();
+                let result = self
+                    .inner
+                    .handle_request(ClientRequest::CallToolRequest(call), context)
+                    .await?;
+                match result {
+                    ServerResult::CallToolResult(result) => {
+                        Ok(ServerResult::CallToolResult(self.store_if_large(&owner, &tool, result)))
+                    }
+                    result => Ok(result),
+                }
+            }
+            ClientRequest::ReadResourceRequest(read) if read.params.uri.starts_with(&results_uri_prefix()) => {
+                let contents = self
+                    .store
+                    .read(&owner, &read.params.uri)
+                    .ok_or_else(|| rmcp::Error::resource_not_found("resource not found or expired", None))?;
+                Ok(ServerResult::ReadResourceResult(ReadResourceResult {
+                    contents: vec![contents],
+                }))
+            }
+            ClientRequest::ListResourcesRequest(list) => {
+                let result = self
+                    .inner
+                    .handle_request(ClientRequest::ListResourcesRequest(list), context)
+                    .await?;
+                match result {
+                    ServerResult::ListResourcesResult(mut result) => {
+                        result.resources.extend(self.store.list(&owner));
+                        Ok(ServerResult::ListResourcesResult(result))
+                    }
+                    result => Ok(result),
+                }
+            }
+            request => self.inner.handle_request(request, context).await,
+        }
+    }
+
+    async fn handle_notification(
+        &self,
+        notification: ClientNotification,
+        context: NotificationContext<RoleServer>,
+    ) -> Result<(), rmcp::Error> {
+        self.inner.handle_notification(notification, context).await
+    }
+
+    fn get_info(&self) -> ServerInfo {
+        let mut info = self.inner.get_info();
+        if self.config.is_some() && info.capabilities.resources.is_none() {
+            info.capabilities.resources = Some(ResourcesCapability::default());
+        }
+        info
+    }
+}
+
+fn results_uri_prefix() -> String {
+    format!("{URI_SCHEME}://results/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::rmcp_ext::TransportSession;
+    use rmcp::model::{CallToolRequestParam, ReadResourceRequestParam, ResourceContents, ServerCapabilities};
+    use rmcp::service::RunningService;
+    use rmcp::{RoleClient, ServiceExt};
+
+    #[derive(Clone)]
+    struct VerboseServer;
+
+    impl rmcp::ServerHandler for VerboseServer {
+        async fn call_tool(
+            &self,
+            request: CallToolRequestParam,
+            _context: RequestContext<RoleServer>,
+        ) -> Result<CallToolResult, rmcp::Error> {
+            let len = if request.name == "verbose" { 5000 } else { 10 };
+            Ok(CallToolResult::success(vec![Content::text("x".repeat(len))]))
+        }
+
+        fn get_info(&self) -> ServerInfo {
+            ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_tools().build(),
+                ..Default::default()
+            }
+        }
+    }
+
+    /// A server whose requests come from a session of the http server.
+    #[derive(Clone)]
+    struct InSession<S>(S, &'static str);
+
+    impl<S: Service<RoleServer>> Service<RoleServer> for InSession<S> {
+        async fn handle_request(
+            &self,
+            request: ClientRequest,
+            mut context: RequestContext<RoleServer>,
+        ) -> Result<ServerResult, rmcp::Error> {
+            let (mut parts, _) = http::Request::new(()).into_parts();
+            parts.extensions.insert(TransportSession(self.1.to_string()));
+            context.extensions.insert(parts);
+            self.0.handle_request(request, context).await
+        }
+
+        async fn handle_notification(
+            &self,
+            notification: ClientNotification,
+            context: NotificationContext<RoleServer>,
+        ) -> Result<(), rmcp::Error> {
+            self.0.handle_notification(notification, context).await
+        }
+
+        fn get_info(&self) -> ServerInfo {
+            self.0.get_info()
+        }
+    }
+
+    async fn connect(server: impl Service<RoleServer>) -> anyhow::Result<RunningService<RoleClient, ()>> {
+        let (server_io, client_io) = tokio::io::duplex(65536);
+        tokio::spawn(async move {
+            if let Ok(service) = server.serve(server_io).await {
+                let _ = service.waiting().await;
+            }
+        });
+        Ok(().serve(client_io).await?)
+    }
+
+    fn call(name: &'static str) -> CallToolRequestParam {
+        CallToolRequestParam {
+            name: name.into(),
+            arguments: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn large_result_as_resource() -> anyhow::Result<()> {
+        let config = LargeResultsConfig {
+            max_size: 1000,
+            ttl_secs: 60,
+        };
+        let server = LargeResultServer::new(VerboseServer, Some(config));
+        assert!(server.get_info().capabilities.resources.is_some());
+        let client = connect(server).await?;
+
+        let result = client.call_tool(call("terse")).await?;
+        assert_eq!(result.content[0].as_text().unwrap().text, "x".repeat(10));
+
+        let result = client.call_tool(call("verbose")).await?;
+        let summary = &result.content[0].as_text().unwrap().text;
+        assert!(summary.contains("is 5000 bytes long"), "{summary}");
+        assert!(summary.len() < 2000, "{summary}");
+
+        let resources = client.list_all_resources().await?;
+        assert_eq!(resources.len(), 1);
+        let uri = resources[0].uri.clone();
+        assert!(summary.contains(&uri));
+
+        let read = client.read_resource(ReadResourceRequestParam { uri }).await?;
+        assert!(matches!(
+            &read.contents[0],
+            ResourceContents::TextResourceContents { text, .. } if text.len() == 5000
+        ));
+
+        client.cancel().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn results_of_other_sessions() -> anyhow::Result<()> {
+        let config = LargeResultsConfig {
+            max_size: 1000,
+            ttl_secs: 60,
+        };
+        let server = LargeResultServer::new(VerboseServer, Some(config));
+        let alice = connect(InSession(server.clone(), "session-a")).await?;
+        let bob = connect(InSession(server, "session-b")).await?;
+
+        alice.call_tool(call("verbose")).await?;
+        let resources = alice.list_all_resources().await?;
+        assert_eq!(resources.len(), 1);
+        let uri = resources[0].uri.clone();
+
+        assert!(bob.list_all_resources().await?.is_empty());
+        assert!(
+            bob.read_resource(ReadResourceRequestParam { uri: uri.clone() })
+                .await
+                .is_err()
+        );
+        assert!(alice.read_resource(ReadResourceRequestParam { uri }).await.is_ok());
+
+        alice.cancel().await?;
+        bob.cancel().await?;
+        Ok(())
+    }
+}
@@ -15,51 +15,178 @@
 // specific language governing permissions and limitations
 // under the License.
 
+mod approval;
+mod audit;
+mod authorization;
+mod builder;
 pub mod cli;
+mod client_config;
+mod init;
+mod large_results;
+mod metrics;
+mod migration;
 mod protocol;
+mod rate_limit;
+mod redaction;
 mod servers;
+#[cfg(feature = "otel")]
+pub mod telemetry;
+mod timeout;
 mod utils;
+mod validate;
 
-use crate::cli::{Cli, Command, Configuration, HttpCommand, StdioCommand};
-use crate::protocol::http::{HttpProtocol, HttpServerConfig};
-use crate::servers::elasticsearch;
+use crate::cli::{
+    Cli, Command, ConfigSchemaCommand, Configuration, HttpCommand, MigrateConfigCommand, StdioCommand, ValidateCommand,
+};
+use crate::protocol::http::{HttpAuth, HttpProtocol, HttpServerConfig, TenantAccess, TlsConfig};
 use crate::utils::interpolator;
+use crate::utils::maintenance::Scheduler;
+use crate::utils::rmcp_ext::{ReloadableServer, ServerProvider};
+use crate::utils::systemd;
+use anyhow::Context;
 use rmcp::transport::stdio;
+use rmcp::transport::streamable_http_server::session::local::LocalSessionManager;
 use rmcp::transport::streamable_http_server::session::never::NeverSessionManager;
 use rmcp::{RoleServer, Service, ServiceExt};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-use std::path::PathBuf;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 use tokio::select;
 use tokio_util::sync::CancellationToken;
 
+// Embedding API
+pub use crate::approval::{ApprovalConfig, ApprovalMethod};
+pub use crate::audit::AuditConfig;
+pub use crate::authorization::{AuthorizationConfig, ClientConfig};
+pub use crate::builder::ServerBuilder;
+pub use crate::large_results::LargeResultsConfig;
+pub use crate::rate_limit::RateLimit;
+pub use crate::redaction::RedactionRule;
+pub use crate::servers::elasticsearch::ElasticsearchMcpConfig;
+
 impl Cli {
     pub async fn run(self) -> anyhow::Result<()> {
         match self.command {
-            Command::Stdio(cmd) => run_stdio(cmd, self.container_mode).await,
-            Command::Http(cmd) => run_http(cmd, self.container_mode).await,
+            Command::Stdio(mut cmd) => {
+                cmd.config = cmd.config.or_else(discover_config_file);
+                run_stdio(cmd, self.container_mode).await
+            }
+            Command::Http(mut cmd) => {
+                cmd.config = cmd.config.or_else(discover_config_file);
+                run_http(cmd, self.container_mode).await
+            }
+            Command::MigrateConfig(cmd) => migrate_config(cmd),
+            Command::Init(cmd) => init::init(cmd, self.container_mode).await,
+            Command::ClientConfig(cmd) => client_config::client_config(cmd),
+            Command::Validate(cmd) => validate_config(cmd, self.container_mode).await,
+            Command::ConfigSchema(cmd) => config_schema(cmd),
         }
     }
 }
 
+/// Wait for Ctrl-C or SIGTERM, that container runtimes and service managers send to stop services.
+#[cfg(unix)]
+async fn shutdown_signal() -> std::io::Result<()> {
+    let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+    select! {
+        result = tokio::signal::ctrl_c() => result,
+        _ = sigterm.recv() => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+async fn shutdown_signal() -> std::io::Result<()> {
+    tokio::signal::ctrl_c().await
+}
+
+/// Fail if `--reload` or `--check` is set without a configuration file, once the default one has been looked up.
+fn check_config_options(config: &Option<PathBuf>, reload: bool, check: bool) -> anyhow::Result<()> {
+    if config.is_none() && (reload || check) {
+        let option = if check { "--check" } else { "--reload" };
+        anyhow::bail!(
+            "{option} needs a configuration file: set it with --config, or create the default one with 'init'"
+        );
+    }
+    Ok(())
+}
+
+/// Find a configuration file in well-known locations, see [`cli::default_config_file`].
+fn discover_config_file() -> Option<PathBuf> {
+    let path = cli::default_config_file()?;
+    tracing::info!("Using configuration file {}", path.display());
+    Some(path)
+}
+
 pub async fn run_stdio(cmd: StdioCommand, container_mode: bool) -> anyhow::Result<()> {
+    check_config_options(&cmd.config, cmd.reload, cmd.check)?;
+    if let (true, Some(config)) = (cmd.check, &cmd.config) {
+        return validate::validate(config, container_mode, false).await;
+    }
     tracing::info!("Starting stdio server");
-    let handler = setup_services(&cmd.config, container_mode).await?;
+    let handler = setup_reloadable_services(&cmd.config, container_mode, cmd.dry_run, cmd.reload, false, None).await?;
     let service = handler.serve(stdio()).await.inspect_err(|e| {
         tracing::error!("serving error: {:?}", e);
     })?;
 
     select! {
         _ = service.waiting() => {},
-        _ = tokio::signal::ctrl_c() => {},
+        _ = shutdown_signal() => {},
     }
 
+    Scheduler::global().shutdown();
     Ok(())
 }
 
 pub async fn run_http(cmd: HttpCommand, container_mode: bool) -> anyhow::Result<()> {
-    let handler = setup_services(&cmd.config, container_mode).await?;
-    let server_provider = move || handler.clone();
+    check_config_options(&cmd.config, cmd.reload, cmd.check)?;
+    if let (true, Some(config)) = (cmd.check, &cmd.config) {
+        return validate::validate(config, container_mode, false).await;
+    }
+    // The default servers, and those of each tenant. Tenants are only read at startup.
+    let config = read_config(&cmd.config)?;
+    let mut tenant_names = config.tenants.keys().cloned().collect::<Vec<_>>();
+    tenant_names.sort();
+    for name in &tenant_names {
+        cli::check_tenant_name(name).map_err(anyhow::Error::msg)?;
+    }
+    // Credentials of named clients, the OIDC provider, approval operators and the clients of tenants are only read
+    // at startup
+    let authorization = Arc::new(config.authorization.unwrap_or_default());
+    let oidc = config.auth.and_then(|a| a.oidc);
+    let approval_operators = config.approvals.map(|a| a.operators).unwrap_or_default();
+    let mut tenant_access = HashMap::new();
+    for (name, tenant) in config.tenants {
+        let authorization = authorization.clone();
+        let access: TenantAccess = Arc::new(move |identity: Option<&str>| tenant.is_allowed(&authorization, identity));
+        tenant_access.insert(name, access);
+    }
+    let has_default = config.elasticsearch.is_some() || !config.mcp_servers.is_empty() || tenant_names.is_empty();
+
+    let provider = |handler: ReloadableServer<_>| ServerProvider::from(move || handler.clone());
+    let server_provider = if has_default {
+        let handler =
+            setup_reloadable_services(&cmd.config, container_mode, cmd.dry_run, cmd.reload, true, None).await?;
+        Some(provider(handler))
+    } else {
+        None
+    };
+    let mut tenants = Vec::new();
+    for name in tenant_names {
+        let handler = setup_reloadable_services(
+            &cmd.config,
+            container_mode,
+            cmd.dry_run,
+            cmd.reload,
+            true,
+            Some(name.clone()),
+        )
+        .await?;
+        tenants.push((name, provider(handler)));
+    }
     let address: SocketAddr = if let Some(addr) = cmd.address {
         addr
     } else if container_mode {
@@ -68,60 +195,248 @@ pub async fn run_http(cmd: HttpCommand, container_mode: bool) -> anyhow::Result<
         SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 8080)
     };
 
-    let ct = HttpProtocol::serve_with_config(
-        server_provider,
-        HttpServerConfig {
-            bind: address,
-            ct: CancellationToken::new(),
-            // streaming http:
-            keep_alive: None,
-            stateful_mode: false,
-            session_manager: Arc::new(NeverSessionManager::default()),
-        },
-    )
-    .await?;
-
-    tracing::info!("Starting http server at address {}", address);
-
-    tokio::signal::ctrl_c().await?;
+    let listener = if cmd.systemd { systemd::listener()? } else { None };
+    let address = match &listener {
+        Some(listener) => listener.local_addr()?,
+        None => address,
+    };
+
+    let protocol = if cmd.tls.tls_cert.is_some() { "https" } else { "http" };
+    let config = HttpServerConfig {
+        bind: address,
+        listener,
+        ct: CancellationToken::new(),
+        // streaming http:
+        keep_alive: cmd.session.session_keep_alive.map(Duration::from_secs),
+        stateful_mode: cmd.session.stateful,
+        session_manager: Arc::new(NeverSessionManager::default()),
+        session_idle_timeout: cmd.session.session_ttl.map(Duration::from_secs),
+        auth: HttpAuth::from_config(cmd.auth.api_keys, cmd.auth.bearer_tokens, &authorization.clients, oidc),
+        allowed_origins: cmd.auth.allowed_origins,
+        approval_operators,
+        tenant_access,
+        tls: cmd
+            .tls
+            .tls_cert
+            .zip(cmd.tls.tls_key)
+            .map(|(cert, key)| TlsConfig { cert, key }),
+    };
+
+    let ct = if cmd.session.stateful {
+        // Sessions are kept in memory
+        let config = config.with_session_manager(Arc::new(LocalSessionManager::default()));
+        HttpProtocol::serve_with_tenants(server_provider, tenants, config).await?
+    } else {
+        HttpProtocol::serve_with_tenants(server_provider, tenants, config).await?
+    };
+
+    tracing::info!("Starting {protocol} server at address {address}");
+
+    if cmd.systemd {
+        systemd::notify("READY=1")?;
+    }
+    shutdown_signal().await?;
+    if cmd.systemd {
+        systemd::notify("STOPPING=1")?;
+    }
     ct.cancel();
+    Scheduler::global().shutdown();
     Ok(())
 }
 
-pub async fn setup_services(config: &Option<PathBuf>, container_mode: bool) -> anyhow::Result<impl Service<RoleServer> + Clone> {
-    // Read config file and expand variables
+pub async fn setup_services(
+    config: &Option<PathBuf>,
+    container_mode: bool,
+    dry_run: bool,
+) -> anyhow::Result<impl Service<RoleServer> + Clone + use<>> {
+    setup_tenant_services(config, container_mode, dry_run, false, None).await
+}
+
+/// Setup the services of a tenant, or the default services if `tenant` is `None`. `approval_endpoints` is true if
+/// the `/approvals` endpoints are served, which tool call approvals require.
+async fn setup_tenant_services(
+    config: &Option<PathBuf>,
+    container_mode: bool,
+    dry_run: bool,
+    approval_endpoints: bool,
+    tenant: Option<&str>,
+) -> anyhow::Result<impl Service<RoleServer> + Clone + use<>> {
+    let mut config = read_config(config)?;
+    match tenant {
+        Some(tenant) => config = config.into_tenant(tenant)?,
+        // Tenants have their own services, that only the http command serves
+        None => config.tenants.clear(),
+    }
+    ServerBuilder::from_config(config)
+        .container_mode(container_mode)
+        .dry_run(dry_run)
+        .approval_endpoints(approval_endpoints)
+        .build()
+}
 
-    let config = if let Some(path) = config {
+/// Read a configuration file, or the built-in configuration based on environment variables if there's none.
+fn read_config(path: &Option<PathBuf>) -> anyhow::Result<Configuration> {
+    // Read config file and expand variables
+    let config = if let Some(path) = path {
         std::fs::read_to_string(path)?
     } else {
         // Built-in default configuration, based on env variables.
         r#"{
+            "version": 1,
             "elasticsearch": {
                 "url": "${ES_URL}",
                 "api_key": "${ES_API_KEY:}",
-                "username": "${ES_USERNAME:}",
+                "login": "${ES_USERNAME:}",
                 "password": "${ES_PASSWORD:}",
-                "ssl_skip_verify": "${ES_SSL_SKIP_VERIFY:false}"
+                "ssl_skip_verify": "${ES_SSL_SKIP_VERIFY:false}",
+                "allow_writes": "${ES_ALLOW_WRITES:false}"
             }
         }"#
         .to_string()
     };
 
+    parse_config(config, path.as_deref().and_then(Path::parent))
+}
+
+/// Interval between two checks for changes in the configuration file.
+///
+/// Polling is used rather than file system notifications, as these are unreliable with container volume
+/// mounts and Kubernetes config maps that are updated by swapping symbolic links.
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Setup the services of a tenant, or the default services, and, if `reload` is true, rebuild them when the
+/// configuration file changes.
+async fn setup_reloadable_services(
+    config: &Option<PathBuf>,
+    container_mode: bool,
+    dry_run: bool,
+    reload: bool,
+    approval_endpoints: bool,
+    tenant: Option<String>,
+) -> anyhow::Result<ReloadableServer<impl Service<RoleServer> + Clone + use<>>> {
+    let server = ReloadableServer::new(
+        setup_tenant_services(config, container_mode, dry_run, approval_endpoints, tenant.as_deref()).await?,
+    );
+
+    if let Some(path) = config.clone().filter(|_| reload) {
+        let server = server.clone();
+        let modified = |path: &PathBuf| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        let mut last_modified: Option<SystemTime> = modified(&path);
+
+        Scheduler::global().spawn("config_reload", CONFIG_POLL_INTERVAL, move || {
+            let modified = modified(&path);
+            let changed = modified != last_modified;
+            last_modified = modified;
+
+            let server = server.clone();
+            let path = path.clone();
+            let tenant = tenant.clone();
+            async move {
+                if changed {
+                    let new_server = setup_tenant_services(
+                        &Some(path),
+                        container_mode,
+                        dry_run,
+                        approval_endpoints,
+                        tenant.as_deref(),
+                    )
+                    .await
+                    .context("Failed to reload the configuration file, keeping the current one")?;
+                    tracing::info!("Configuration file changed, services reloaded");
+                    server.replace(new_server).await;
+                }
+                Ok(ControlFlow::Continue(()))
+            }
+        });
+    }
+
+    Ok(server)
+}
+
+/// Parse a configuration file: expand environment variables, deserialize its JSON5 content and migrate it
+/// if it was written for an older version of the configuration layout. `dir` is the directory of the file, that
+/// relative paths of secret files are resolved from.
+pub fn parse_config(config: String, dir: Option<&Path>) -> anyhow::Result<Configuration> {
     // Expand environment variables in the config file
-    let config = interpolator::interpolate_from_env(config)?;
+    let config = interpolator::interpolate_from_env(config, dir)?;
+
+    let mut value: serde_json::Value = from_json5(&config)?;
+    let changes = migration::migrate(&mut value)?;
+    if changes.is_empty() {
+        // Deserialize the text rather than the value to report error locations
+        return from_json5(&config);
+    }
+
+    for change in &changes {
+        tracing::warn!("Outdated configuration: {change}");
+    }
+    tracing::warn!("Use the 'migrate-config' command to upgrade the configuration file");
+    Ok(serde_json::from_value(value)?)
+}
 
+fn from_json5<T: DeserializeOwned>(text: &str) -> anyhow::Result<T> {
     // JSON5 adds comments and multiline strings (useful for ES|QL) to JSON
-    let config: Configuration = match serde_json5::from_str(&config) {
-        Ok(c) => c,
+    match serde_json5::from_str(text) {
+        Ok(c) => Ok(c),
         Err(serde_json5::Error::Message { msg, location }) if location.is_some() => {
             let location = location.unwrap();
             let line = location.line;
             let column = location.column;
             anyhow::bail!("Failed to parse config: {msg}, at line {line} column {column}");
         }
-        Err(err) => return Err(err)?,
-    };
+        Err(err) => Err(err)?,
+    }
+}
+
+/// Validate a configuration file, reporting all the problems found.
+pub async fn validate_config(cmd: ValidateCommand, container_mode: bool) -> anyhow::Result<()> {
+    validate::validate(&cmd.config, container_mode, cmd.ping).await
+}
+
+/// Print the JSON schema of configuration files.
+pub fn config_schema(_cmd: ConfigSchemaCommand) -> anyhow::Result<()> {
+    let schema = schemars::schema_for!(Configuration);
+    println!("{}", serde_json::to_string_pretty(&schema)?);
+    Ok(())
+}
+
+/// Upgrade a configuration file to the current version of the configuration layout.
+pub fn migrate_config(cmd: MigrateConfigCommand) -> anyhow::Result<()> {
+    // Environment variables aren't expanded, so that they're kept in the upgraded file
+    let text = std::fs::read_to_string(&cmd.config)?;
+    let mut config: serde_json::Value = from_json5(&text)?;
+    let original = config.clone();
+
+    let changes = migration::migrate(&mut config)?;
+    for change in &changes {
+        tracing::info!("{change}");
+    }
+
+    let upgraded = serde_json::to_string_pretty(&config)? + "\n";
+    if cmd.dry_run {
+        print!("{upgraded}");
+        return Ok(());
+    }
+    if config == original {
+        tracing::info!("Configuration file is up to date");
+        return Ok(());
+    }
+
+    let mut backup = cmd.config.clone().into_os_string();
+    backup.push(".bak");
+    std::fs::copy(&cmd.config, &backup)?;
+    std::fs::write(&cmd.config, upgraded)?;
+    tracing::info!(
+        "Configuration file upgraded to version {}, the original file was saved to {}. Comments were not preserved.",
+        migration::CONFIG_VERSION,
+        backup.display()
+    );
+    Ok(())
+}
 
-    let handler = elasticsearch::ElasticsearchMcp::new_with_config(config.elasticsearch, container_mode)?;
-    Ok(handler)
+/// Internal entry points for the fuzz targets in `fuzz/` (`cargo fuzz` builds with `--cfg fuzzing`).
+#[cfg(fuzzing)]
+#[doc(hidden)]
+pub mod fuzzing {
+    pub use crate::utils::interpolator::interpolate;
 }
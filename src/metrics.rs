@@ -0,0 +1,398 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Metrics of tool calls, exported in the Prometheus text format, and tracing spans of tool calls.
+
+use crate::servers::elasticsearch::retry::is_transient;
+use crate::utils::maintenance::Scheduler;
+use crate::utils::rmcp_ext::session_id;
+use rmcp::model::{ClientNotification, ClientRequest, ServerInfo, ServerResult};
+use rmcp::service::{NotificationContext, RequestContext};
+use rmcp::{RoleServer, Service};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::Instrument;
+
+/// Label of the calls to tools that don't exist, so that clients can't create any number of series.
+const UNKNOWN_TOOL: &str = "unknown";
+
+/// Upper bounds of the latency histogram buckets, in seconds.
+const LATENCY_BUCKETS: [f64; 10] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+/// Metrics of the calls to a tool.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct ToolMetrics {
+    calls: u64,
+    /// Calls that failed or returned an error result
+    errors: u64,
+    /// Number of calls in each latency bucket (not cumulative)
+    latency_buckets: [u64; LATENCY_BUCKETS.len()],
+    latency_sum: f64,
+}
+
+#[derive(Debug, Default)]
+struct MetricsInner {
+    tools: BTreeMap<String, ToolMetrics>,
+    /// Was Elasticsearch reachable and not overloaded at the last tool call? Unknown before the first call.
+    upstream_up: Option<bool>,
+    /// Calls to the deprecated names of renamed tools
+    deprecated_calls: BTreeMap<String, u64>,
+    /// Sessions of the http server closed for being idle
+    expired_sessions: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    inner: Mutex<MetricsInner>,
+}
+
+impl Metrics {
+    /// The metrics of the process.
+    pub fn global() -> &'static Metrics {
+        static GLOBAL: OnceLock<Metrics> = OnceLock::new();
+        GLOBAL.get_or_init(Metrics::default)
+    }
+
+    fn record(&self, tool: &str, duration: Duration, result: &Result<ServerResult, rmcp::Error>) {
+        let mut inner = self.inner.lock().unwrap();
+
+        let is_error = match result {
+            Ok(ServerResult::CallToolResult(result)) => result.is_error == Some(true),
+            Ok(_) => false,
+            Err(_) => true,
+        };
+        inner.upstream_up = Some(!matches!(result, Err(err) if is_transient(err)));
+
+        let metrics = inner.tools.entry(tool.to_string()).or_default();
+        metrics.calls += 1;
+        metrics.errors += is_error as u64;
+        let secs = duration.as_secs_f64();
+        metrics.latency_sum += secs;
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|bound| secs <= *bound) {
+            metrics.latency_buckets[bucket] += 1;
+        }
+    }
+
+    /// Count a call to the deprecated name of a renamed tool.
+    pub fn record_deprecated_call(&self, tool: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        *inner.deprecated_calls.entry(tool.to_string()).or_default() += 1;
+    }
+
+    /// Count a session closed for being idle, and return the number of sessions closed so far.
+    pub fn record_expired_session(&self) -> u64 {
+        let mut inner = self.inner.lock().unwrap();
+        inner.expired_sessions += 1;
+        inner.expired_sessions
+    }
+
+    /// Tool call, session and maintenance task metrics, in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let inner = self.inner.lock().unwrap();
+        let mut out = String::new();
+
+        header(&mut out, "mcp_tool_calls_total", "counter", "Tool calls");
+        for (tool, metrics) in &inner.tools {
+            let _ = writeln!(
+                out,
+                "mcp_tool_calls_total{{tool=\"{}\"}} {}",
+                escape(tool),
+                metrics.calls
+            );
+        }
+
+        header(
+            &mut out,
+            "mcp_tool_errors_total",
+            "counter",
+            "Tool calls that failed or returned an error",
+        );
+        for (tool, metrics) in &inner.tools {
+            let _ = writeln!(
+                out,
+                "mcp_tool_errors_total{{tool=\"{}\"}} {}",
+                escape(tool),
+                metrics.errors
+            );
+        }
+
+        header(
+            &mut out,
+            "mcp_tool_call_duration_seconds",
+            "histogram",
+            "Duration of tool calls",
+        );
+        for (tool, metrics) in &inner.tools {
+            let tool = escape(tool);
+            let mut count = 0;
+            for (bound, calls) in LATENCY_BUCKETS.iter().zip(metrics.latency_buckets) {
+                count += calls;
+                let _ = writeln!(
+                    out,
+                    "mcp_tool_call_duration_seconds_bucket{{tool=\"{tool}\",le=\"{bound}\"}} {count}"
+                );
+            }
+            let _ = writeln!(
+                out,
+                "mcp_tool_call_duration_seconds_bucket{{tool=\"{tool}\",le=\"+Inf\"}} {}",
+                metrics.calls
+            );
+            let _ = writeln!(
+                out,
+                "mcp_tool_call_duration_seconds_sum{{tool=\"{tool}\"}} {}",
+                metrics.latency_sum
+            );
+            let _ = writeln!(
+                out,
+                "mcp_tool_call_duration_seconds_count{{tool=\"{tool}\"}} {}",
+                metrics.calls
+            );
+        }
+
+        header(
+            &mut out,
+            "mcp_deprecated_tool_calls_total",
+            "counter",
+            "Calls to the deprecated names of renamed tools",
+        );
+        for (tool, calls) in &inner.deprecated_calls {
+            let _ = writeln!(
+                out,
+                "mcp_deprecated_tool_calls_total{{tool=\"{}\"}} {calls}",
+                escape(tool)
+            );
+        }
+
+        if let Some(up) = inner.upstream_up {
+            header(
+                &mut out,
+                "mcp_upstream_up",
+                "gauge",
+                "Was Elasticsearch reachable and not overloaded at the last tool call",
+            );
+            let _ = writeln!(out, "mcp_upstream_up {}", up as u8);
+        }
+
+        header(
+            &mut out,
+            "mcp_sessions_expired_total",
+            "counter",
+            "Sessions of the http server closed for being idle",
+        );
+        let _ = writeln!(out, "mcp_sessions_expired_total {}", inner.expired_sessions);
+
+        let tasks = Scheduler::global().metrics();
+        header(
+            &mut out,
+            "mcp_maintenance_task_runs_total",
+            "counter",
+            "Runs of background maintenance tasks",
+        );
+        for (task, metrics) in &tasks {
+            let _ = writeln!(
+                out,
+                "mcp_maintenance_task_runs_total{{task=\"{task}\"}} {}",
+                metrics.runs
+            );
+        }
+        header(
+            &mut out,
+            "mcp_maintenance_task_failures_total",
+            "counter",
+            "Failed runs of background maintenance tasks",
+        );
+        for (task, metrics) in &tasks {
+            let _ = writeln!(
+                out,
+                "mcp_maintenance_task_failures_total{{task=\"{task}\"}} {}",
+                metrics.failures
+            );
+        }
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+/// Escape a label value.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// A server that records metrics of tool calls in the [global metrics](Metrics::global).
+///
+/// Calls are recorded with the name of their tool if it's one of `tools`, and as `unknown` otherwise.
+#[derive(Clone)]
+pub struct MeteredServer<S> {
+    inner: S,
+    tools: Arc<HashSet<String>>,
+}
+
+impl<S: Service<RoleServer>> MeteredServer<S> {
+    pub fn new(inner: S, tools: HashSet<String>) -> Self {
+        MeteredServer {
+            inner,
+            tools: Arc::new(tools),
+        }
+    }
+}
+
+impl<S: Service<RoleServer>> Service<RoleServer> for MeteredServer<S> {
+    async fn handle_request(
+        &self,
+        request: ClientRequest,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ServerResult, rmcp::Error> {
+        let ClientRequest::CallToolRequest(call) = &request else {
+            return self.inner.handle_request(request, context).await;
+        };
+
+        let tool = call.params.name.to_string();
+        // Exported with OpenTelemetry if enabled
+        let span =
+            tracing::info_span!("tool_call", tool = %tool, session_id = session_id(&context), otel.kind = "server");
+        let label = if self.tools.contains(&tool) {
+            tool.as_str()
+        } else {
+            UNKNOWN_TOOL
+        };
+        let start = Instant::now();
+        let result = self.inner.handle_request(request, context).instrument(span).await;
+        Metrics::global().record(label, start.elapsed(), &result);
+
+        result
+    }
+
+    async fn handle_notification(
+        &self,
+        notification: ClientNotification,
+        context: NotificationContext<RoleServer>,
+    ) -> Result<(), rmcp::Error> {
+        self.inner.handle_notification(notification, context).await
+    }
+
+    fn get_info(&self) -> ServerInfo {
+        self.inner.get_info()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::servers::elasticsearch::retry::transient_error;
+    use rmcp::model::{CallToolResult, Content};
+
+    #[test]
+    fn render_metrics() {
+        let metrics = Metrics::default();
+        let success = Ok(ServerResult::CallToolResult(CallToolResult::success(vec![])));
+        let error = Ok(ServerResult::CallToolResult(CallToolResult::error(vec![
+            Content::text("no index"),
+        ])));
+
+        metrics.record("search", Duration::from_millis(30), &success);
+        metrics.record("search", Duration::from_millis(700), &error);
+        metrics.record("list_indices", Duration::from_secs(120), &success);
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE mcp_tool_calls_total counter\n"));
+        assert!(text.contains("mcp_tool_calls_total{tool=\"search\"} 2\n"));
+        assert!(text.contains("mcp_tool_errors_total{tool=\"search\"} 1\n"));
+        assert!(text.contains("mcp_tool_errors_total{tool=\"list_indices\"} 0\n"));
+        assert!(text.contains("mcp_tool_call_duration_seconds_bucket{tool=\"search\",le=\"0.05\"} 1\n"));
+        assert!(text.contains("mcp_tool_call_duration_seconds_bucket{tool=\"search\",le=\"1\"} 2\n"));
+        assert!(text.contains("mcp_tool_call_duration_seconds_bucket{tool=\"list_indices\",le=\"60\"} 0\n"));
+        assert!(text.contains("mcp_tool_call_duration_seconds_bucket{tool=\"list_indices\",le=\"+Inf\"} 1\n"));
+        assert!(text.contains("mcp_tool_call_duration_seconds_count{tool=\"search\"} 2\n"));
+        assert!(text.contains("mcp_upstream_up 1\n"));
+        assert!(!text.contains("mcp_deprecated_tool_calls_total{"));
+        assert!(text.contains("# TYPE mcp_sessions_expired_total counter\nmcp_sessions_expired_total 0\n"));
+
+        metrics.record_expired_session();
+        assert_eq!(metrics.record_expired_session(), 2);
+        assert!(metrics.render().contains("mcp_sessions_expired_total 2\n"));
+
+        metrics.record_deprecated_call("esql");
+        metrics.record_deprecated_call("esql");
+        assert!(
+            metrics
+                .render()
+                .contains("mcp_deprecated_tool_calls_total{tool=\"esql\"} 2\n")
+        );
+
+        metrics.record(
+            "search",
+            Duration::from_secs(1),
+            &Err(transient_error(std::io::Error::other("refused"))),
+        );
+        let text = metrics.render();
+        assert!(text.contains("mcp_tool_errors_total{tool=\"search\"} 2\n"));
+        assert!(text.contains("mcp_upstream_up 0\n"));
+    }
+
+    #[derive(Clone)]
+    struct EchoServer;
+
+    impl rmcp::ServerHandler for EchoServer {
+        async fn call_tool(
+            &self,
+            request: rmcp::model::CallToolRequestParam,
+            _context: RequestContext<RoleServer>,
+        ) -> Result<CallToolResult, rmcp::Error> {
+            Ok(CallToolResult::success(vec![Content::text(request.name.to_string())]))
+        }
+    }
+
+    #[tokio::test]
+    async fn unknown_tools() -> anyhow::Result<()> {
+        use rmcp::ServiceExt;
+
+        let server = MeteredServer::new(EchoServer, HashSet::from(["metrics_test_known".to_string()]));
+        let (server_io, client_io) = tokio::io::duplex(65536);
+        tokio::spawn(async move {
+            if let Ok(service) = server.serve(server_io).await {
+                let _ = service.waiting().await;
+            }
+        });
+        let client = ().serve(client_io).await?;
+        for name in ["metrics_test_known", "metrics_test_random_1", "metrics_test_random_2"] {
+            let call = rmcp::model::CallToolRequestParam {
+                name: name.into(),
+                arguments: None,
+            };
+            client.call_tool(call).await?;
+        }
+        client.cancel().await?;
+
+        let text = Metrics::global().render();
+        assert!(text.contains("mcp_tool_calls_total{tool=\"metrics_test_known\"} 1\n"));
+        assert!(text.contains("mcp_tool_calls_total{tool=\"unknown\"}"));
+        assert!(!text.contains("metrics_test_random"));
+        Ok(())
+    }
+
+    #[test]
+    fn escape_labels() {
+        assert_eq!(escape(r#"a"b\c"#), r#"a\"b\\c"#);
+    }
+}
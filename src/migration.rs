@@ -0,0 +1,149 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Migration of configuration files written for older versions of the configuration layout.
+
+use serde_json::{Map, Value};
+
+/// Current version of the configuration layout. Files without a `version` field are version 0.
+pub const CONFIG_VERSION: u64 = 1;
+
+/// A migration from a version to the next one, that adds a description of each change to the list of changes.
+type Migration = fn(&mut Map<String, Value>, &mut Vec<String>);
+
+/// Migrations, indexed by the version they migrate from.
+const MIGRATIONS: &[Migration] = &[migrate_v0];
+
+/// Upgrade a configuration to the current version, and return a description of the changes.
+///
+/// Configurations newer than the current version are rejected, as they may use settings that this
+/// server would silently ignore.
+pub fn migrate(config: &mut Value) -> anyhow::Result<Vec<String>> {
+    let Some(config) = config.as_object_mut() else {
+        anyhow::bail!("The configuration must be an object");
+    };
+
+    let version = match config.get("version") {
+        None => 0,
+        Some(v) => v
+            .as_u64()
+            .ok_or_else(|| anyhow::anyhow!("Invalid configuration version '{v}', expecting a number"))?,
+    };
+    if version > CONFIG_VERSION {
+        anyhow::bail!(
+            "Configuration version {version} is not supported by this server, which supports versions up \
+            to {CONFIG_VERSION}. Please upgrade the server."
+        );
+    }
+
+    let mut changes = Vec::new();
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(config, &mut changes);
+    }
+    config.insert("version".to_string(), CONFIG_VERSION.into());
+
+    Ok(changes)
+}
+
+/// Rename a property of an object, unless the new name is already used.
+fn rename(obj: &mut Map<String, Value>, path: &str, from: &str, to: &str, changes: &mut Vec<String>) {
+    if obj.contains_key(to) {
+        return;
+    }
+    if let Some(value) = obj.remove(from) {
+        obj.insert(to.to_string(), value);
+        changes.push(format!("'{path}{from}' was renamed to '{path}{to}'"));
+    }
+}
+
+/// Version 0: unversioned configurations.
+fn migrate_v0(config: &mut Map<String, Value>, changes: &mut Vec<String>) {
+    // Top-level properties are camelCase, like the MCP client configurations they're modelled after
+    rename(config, "", "mcp_servers", "mcpServers", changes);
+    rename(config, "", "rate_limits", "rateLimits", changes);
+    rename(config, "", "tool_timeouts", "toolTimeouts", changes);
+
+    // Elasticsearch clusters used `username` instead of `login`, which was then ignored
+    if let Some(Value::Object(es)) = config.get_mut("elasticsearch") {
+        rename(es, "elasticsearch.", "username", "login", changes);
+    }
+    if let Some(Value::Object(servers)) = config.get_mut("mcpServers") {
+        for (name, server) in servers {
+            if let Value::Object(server) = server
+                && server.get("type").and_then(Value::as_str) == Some("elasticsearch")
+            {
+                rename(server, &format!("mcpServers.{name}."), "username", "login", changes);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn migrate_unversioned() -> anyhow::Result<()> {
+        let mut config = json!({
+            "elasticsearch": { "url": "http://localhost:9200", "username": "elastic" },
+            "mcp_servers": {
+                "prod": { "type": "elasticsearch", "url": "http://prod:9200", "username": "admin" },
+                "other": { "type": "stdio", "command": "other", "args": [], "username": "foo" },
+            },
+            "rate_limits": { "*": "10/min" },
+        });
+        let changes = migrate(&mut config)?;
+        assert_eq!(
+            changes,
+            vec![
+                "'mcp_servers' was renamed to 'mcpServers'",
+                "'rate_limits' was renamed to 'rateLimits'",
+                "'elasticsearch.username' was renamed to 'elasticsearch.login'",
+                "'mcpServers.prod.username' was renamed to 'mcpServers.prod.login'",
+            ]
+        );
+        assert_eq!(
+            config,
+            json!({
+                "version": 1,
+                "elasticsearch": { "url": "http://localhost:9200", "login": "elastic" },
+                "mcpServers": {
+                    "prod": { "type": "elasticsearch", "url": "http://prod:9200", "login": "admin" },
+                    "other": { "type": "stdio", "command": "other", "args": [], "username": "foo" },
+                },
+                "rateLimits": { "*": "10/min" },
+            })
+        );
+
+        // Already migrated
+        assert!(migrate(&mut config)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn check_version() {
+        assert!(
+            migrate(&mut json!({ "version": 1, "username": "foo" }))
+                .unwrap()
+                .is_empty()
+        );
+        assert!(migrate(&mut json!({ "version": 2 })).is_err());
+        assert!(migrate(&mut json!({ "version": "1" })).is_err());
+        assert!(migrate(&mut json!([])).is_err());
+    }
+}
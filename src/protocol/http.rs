@@ -17,19 +17,43 @@
 
 //! Implementation of HTTP protocols
 
-use crate::utils::rmcp_ext::ServerProvider;
-use axum::Router;
-use axum::http::StatusCode;
+use crate::approval;
+use crate::authorization::{ClientConfig, Identity};
+use crate::cli;
+use crate::metrics::Metrics;
+use crate::protocol::oidc::{OidcConfig, OidcValidator};
+use crate::protocol::ws;
+use crate::utils::maintenance::Scheduler;
+use crate::utils::rmcp_ext::{self, IdleSessionManager, ServerProvider};
+use axum::extract::{Request, State};
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE, WWW_AUTHENTICATE};
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
 use axum::routing::get;
+use axum::serve::Listener;
+use axum::{Extension, Json, Router, ServiceExt};
+use rmcp::service::RequestContext;
 use rmcp::transport::sse_server::SseServerConfig;
 use rmcp::transport::streamable_http_server::session::local::LocalSessionManager;
 use rmcp::transport::streamable_http_server::{SessionManager, StreamableHttpServerConfig};
 use rmcp::transport::{SseServer, StreamableHttpService};
 use rmcp::{RoleServer, Service};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::server::TlsStream;
 use tokio_util::sync::CancellationToken;
+use tower::Layer;
 use tracing::Instrument;
 
 /// Configuration for an HTTP MCP server
@@ -37,6 +61,10 @@ pub struct HttpServerConfig<M: SessionManager = LocalSessionManager> {
     /// TCP address to bind to
     pub bind: SocketAddr,
 
+    /// Already bound listener to use instead of binding to `bind`, e.g. from systemd socket activation.
+    /// Must be in non-blocking mode.
+    pub listener: Option<std::net::TcpListener>,
+
     /// Parent cancellation token. `serve_with_config` will return a child token
     pub ct: CancellationToken,
 
@@ -48,51 +76,342 @@ pub struct HttpServerConfig<M: SessionManager = LocalSessionManager> {
 
     /// Streamable http server option
     pub session_manager: Arc<M>,
+
+    /// Close stateful sessions after this duration of inactivity
+    pub session_idle_timeout: Option<Duration>,
+
+    /// Client authentication on MCP endpoints. No authentication if `None`.
+    pub auth: Option<HttpAuth>,
+
+    /// Origins of the web pages that can open WebSocket sessions, in addition to the server's own origin
+    pub allowed_origins: Vec<String>,
+
+    /// Named clients that can list, approve and deny pending tool calls on the `/approvals` endpoints
+    pub approval_operators: Vec<String>,
+
+    /// Clients allowed on the endpoints of each tenant. All clients are allowed on tenants that aren't listed.
+    pub tenant_access: HashMap<String, TenantAccess>,
+
+    /// Serve HTTPS with this certificate. Plain HTTP if `None`.
+    pub tls: Option<TlsConfig>,
+}
+
+impl<M: SessionManager> HttpServerConfig<M> {
+    /// Replace the session manager, e.g. to use a custom session store.
+    pub fn with_session_manager<M2: SessionManager>(self, session_manager: Arc<M2>) -> HttpServerConfig<M2> {
+        HttpServerConfig {
+            bind: self.bind,
+            listener: self.listener,
+            ct: self.ct,
+            keep_alive: self.keep_alive,
+            stateful_mode: self.stateful_mode,
+            session_manager,
+            session_idle_timeout: self.session_idle_timeout,
+            auth: self.auth,
+            allowed_origins: self.allowed_origins,
+            approval_operators: self.approval_operators,
+            tenant_access: self.tenant_access,
+            tls: self.tls,
+        }
+    }
+}
+
+/// TLS certificate chain and private key files, in PEM format.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+impl TlsConfig {
+    fn acceptor(&self) -> std::io::Result<TlsAcceptor> {
+        let read_error =
+            |path: &PathBuf, err| std::io::Error::other(format!("Failed to read {}: {err}", path.display()));
+
+        let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(&self.cert)?))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| read_error(&self.cert, err))?;
+        let key = rustls_pemfile::private_key(&mut BufReader::new(File::open(&self.key)?))
+            .map_err(|err| read_error(&self.key, err))?
+            .ok_or_else(|| std::io::Error::other(format!("No private key found in {}", self.key.display())))?;
+
+        let mut config = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .map_err(std::io::Error::other)?;
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+}
+
+/// Maximum duration of a TLS handshake.
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A listener that accepts TLS connections. Handshakes are performed in separate tasks so that slow
+/// clients don't block accepting new connections.
+struct TlsListener {
+    local_addr: SocketAddr,
+    connections: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+}
+
+impl TlsListener {
+    fn new(listener: TcpListener, acceptor: TlsAcceptor) -> std::io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let (tx, connections) = mpsc::channel(64);
+
+        tokio::spawn(async move {
+            loop {
+                let (stream, addr) = tokio::select! {
+                    // Listener was dropped
+                    _ = tx.closed() => break,
+                    accepted = listener.accept() => match accepted {
+                        Ok(accepted) => accepted,
+                        Err(err) => {
+                            // Same as axum's TcpListener: errors are usually related to the connection
+                            // or a lack of file descriptors, retry after a short pause.
+                            tracing::warn!("Failed to accept connection: {err}");
+                            tokio::time::sleep(Duration::from_millis(100)).await;
+                            continue;
+                        }
+                    }
+                };
+
+                let acceptor = acceptor.clone();
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(stream)) => {
+                            let _ = tx.send((stream, addr)).await;
+                        }
+                        Ok(Err(err)) => tracing::debug!("TLS handshake with {addr} failed: {err}"),
+                        Err(_) => tracing::debug!("TLS handshake with {addr} timed out"),
+                    }
+                });
+            }
+        });
+
+        Ok(TlsListener {
+            local_addr,
+            connections,
+        })
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.connections.recv().await {
+            Some(connection) => connection,
+            // The accept loop never stops while this listener exists
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+/// Credentials accepted from MCP clients.
+#[derive(Debug, Default, Clone)]
+pub struct HttpAuth {
+    /// Accepted in `X-Api-Key: <key>` or `Authorization: ApiKey <key>` headers
+    pub api_keys: Vec<String>,
+    /// Accepted in `Authorization: Bearer <token>` headers
+    pub bearer_tokens: Vec<String>,
+    /// Identities of the clients, keyed by API key or bearer token. Other credentials are anonymous.
+    pub identities: HashMap<String, String>,
+    /// Validation of OIDC tokens in `Authorization: Bearer <token>` headers. The client identity is their subject,
+    /// prefixed with `oidc:`.
+    pub oidc: Option<Arc<OidcValidator>>,
+}
+
+const X_API_KEY: &str = "x-api-key";
+
+impl HttpAuth {
+    /// Returns `None` if there are no credentials, i.e. authentication is disabled.
+    pub fn new(api_keys: Vec<String>, bearer_tokens: Vec<String>) -> Option<Self> {
+        if api_keys.is_empty() && bearer_tokens.is_empty() {
+            None
+        } else {
+            Some(HttpAuth {
+                api_keys,
+                bearer_tokens,
+                ..Default::default()
+            })
+        }
+    }
+
+    /// Credentials of named clients and OIDC tokens, in addition to anonymous API keys and bearer tokens.
+    pub fn from_config(
+        mut api_keys: Vec<String>,
+        mut bearer_tokens: Vec<String>,
+        clients: &HashMap<String, ClientConfig>,
+        oidc: Option<OidcConfig>,
+    ) -> Option<Self> {
+        let mut identities = HashMap::new();
+        for (name, client) in clients {
+            for (secret, secrets) in [
+                (&client.api_key, &mut api_keys),
+                (&client.bearer_token, &mut bearer_tokens),
+            ] {
+                if let Some(secret) = secret {
+                    secrets.push(secret.clone());
+                    identities.insert(secret.clone(), name.clone());
+                }
+            }
+        }
+        let oidc = oidc.map(|config| Arc::new(OidcValidator::new(config)));
+        if api_keys.is_empty() && bearer_tokens.is_empty() && oidc.is_none() {
+            return None;
+        }
+        Some(HttpAuth {
+            api_keys,
+            bearer_tokens,
+            identities,
+            oidc,
+        })
+    }
+
+    /// The accepted credential of a request, if any.
+    fn find_credential(&self, headers: &HeaderMap) -> Option<&String> {
+        let header = |name| headers.get(name).and_then(|v| v.to_str().ok());
+
+        if let Some(key) = header(X_API_KEY) {
+            return find_secret(&self.api_keys, key.trim());
+        }
+
+        match header(AUTHORIZATION.as_str()).and_then(|h| h.split_once(' ')) {
+            Some((scheme, key)) if scheme.eq_ignore_ascii_case("ApiKey") => find_secret(&self.api_keys, key.trim()),
+            _ => find_secret(&self.bearer_tokens, bearer_token(headers)?),
+        }
+    }
+
+    /// The identity of a request with valid credentials: `Some` for named clients and OIDC tokens, `None` for
+    /// anonymous credentials.
+    async fn authenticate(&self, headers: &HeaderMap) -> Result<Option<String>, ()> {
+        if let Some(credential) = self.find_credential(headers) {
+            return Ok(self.identities.get(credential).cloned());
+        }
+        let (Some(oidc), Some(token)) = (&self.oidc, bearer_token(headers)) else {
+            return Err(());
+        };
+        match oidc.validate(token).await {
+            Ok(subject) => Ok(Some(subject)),
+            Err(err) => {
+                tracing::debug!("Rejected bearer token: {err}");
+                Err(())
+            }
+        }
+    }
+}
+
+/// The token of an `Authorization: Bearer <token>` header.
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let (scheme, token) = headers.get(AUTHORIZATION)?.to_str().ok()?.split_once(' ')?;
+    scheme.eq_ignore_ascii_case("Bearer").then(|| token.trim())
+}
+
+/// Find `value` in `secrets`, in constant time for secrets that have the same length.
+fn find_secret<'a>(secrets: &'a [String], value: &str) -> Option<&'a String> {
+    let eq = |secret: &String| {
+        secret.len() == value.len() && secret.bytes().zip(value.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+    };
+    // Don't short-circuit
+    secrets
+        .iter()
+        .fold(None, |found, secret| if eq(secret) { Some(secret) } else { found })
+}
+
+/// Axum middleware that rejects unauthenticated requests, and adds the [`Identity`] of named clients to the
+/// request extensions.
+async fn authenticate(State(auth): State<Arc<HttpAuth>>, mut request: Request, next: Next) -> Response {
+    let Ok(identity) = auth.authenticate(request.headers()).await else {
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(WWW_AUTHENTICATE, "Bearer")],
+            "Unauthorized\n",
+        )
+            .into_response();
+    };
+    if let Some(identity) = identity {
+        request.extensions_mut().insert(Identity(identity));
+    }
+
+    // Credentials are for the MCP server, and must not be forwarded to Elasticsearch
+    request.headers_mut().remove(AUTHORIZATION);
+    request.headers_mut().remove(X_API_KEY);
+
+    next.run(request).await
 }
 
-/// An HTTP MCP server that supports both SSE and streamable HTTP.
+/// Header that sends requests to the `/mcp` endpoints to the endpoints of a tenant.
+pub const HEADER_TENANT: &str = "x-mcp-tenant";
+
+/// Is a client allowed on the endpoints of a tenant? The argument is the client [`Identity`], `None` for anonymous
+/// clients.
+pub type TenantAccess = Arc<dyn Fn(Option<&str>) -> bool + Send + Sync>;
+
+/// The tenant of a request to the endpoints of a tenant, added to the request extensions by the http server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TenantName(pub String);
+
+impl TenantName {
+    /// The tenant of a request, if it was sent to the endpoints of a tenant.
+    pub fn of(context: &RequestContext<RoleServer>) -> Option<&str> {
+        context
+            .extensions
+            .get::<Parts>()
+            .and_then(|p| p.extensions.get::<TenantName>())
+            .map(|tenant| tenant.0.as_str())
+    }
+}
+
+/// Axum middleware that rewrites requests to `/mcp` endpoints with a tenant header to the endpoints of that tenant.
+/// It must wrap the router, as routes are selected before the router's middlewares are called.
+async fn select_tenant(mut request: Request, next: Next) -> Response {
+    let Some(tenant) = request.headers().get(HEADER_TENANT) else {
+        return next.run(request).await;
+    };
+    let path = request.uri().path();
+    if path == "/mcp" || path.starts_with("/mcp/") {
+        let path_and_query = request.uri().path_and_query().map_or(path, |p| p.as_str());
+        let uri = tenant
+            .to_str()
+            .ok()
+            .filter(|tenant| cli::check_tenant_name(tenant).is_ok())
+            .and_then(|tenant| format!("/tenants/{tenant}{path_and_query}").parse().ok());
+        let Some(uri) = uri else {
+            return (StatusCode::BAD_REQUEST, "Invalid tenant\n").into_response();
+        };
+        *request.uri_mut() = uri;
+    }
+    next.run(request).await
+}
+
+/// An HTTP MCP server that supports streamable HTTP, SSE and WebSocket.
 pub struct HttpProtocol {}
 
 impl HttpProtocol {
-    pub async fn serve_with_config<S: Service<RoleServer>, M: SessionManager>(
+    pub async fn serve_with_config<S: Service<RoleServer>, M: SessionManager + Default>(
         server_provider: impl Into<ServerProvider<S>>,
         config: HttpServerConfig<M>,
     ) -> std::io::Result<CancellationToken> {
-        let server_provider = server_provider.into().0;
+        Self::serve_with_tenants(Some(server_provider.into()), Vec::new(), config).await
+    }
 
+    /// Serve the default MCP server, if any, on `/mcp` and the server of each tenant on `/tenants/<name>/mcp`.
+    /// Requests to `/mcp` that have an `X-Mcp-Tenant` header are sent to that tenant.
+    pub async fn serve_with_tenants<S: Service<RoleServer>, M: SessionManager + Default>(
+        server_provider: Option<ServerProvider<S>>,
+        tenants: Vec<(String, ServerProvider<S>)>,
+        config: HttpServerConfig<M>,
+    ) -> std::io::Result<CancellationToken> {
         let ct = config.ct.child_token();
-
-        // Create a streamable http router
-        let sh_router = {
-            let sh_config = StreamableHttpServerConfig {
-                sse_keep_alive: config.keep_alive,
-                stateful_mode: config.stateful_mode,
-            };
-
-            let server_provider = server_provider.clone();
-            // TODO: internally, new() wraps the server provider closure with an Arc. We can avoid
-            // "double-Arc" by having
-            let sh_service =
-                StreamableHttpService::new(move || Ok(server_provider()), config.session_manager, sh_config);
-            Router::new().route_service("/", sh_service)
-        };
-
-        // Create an SSE router
-        let sse_router = {
-            let sse_config = SseServerConfig {
-                bind: config.bind,
-                // SSE server will create a child cancellation token for every transport that is created
-                // (see with_service() below)
-                ct: ct.clone(),
-                sse_keep_alive: config.keep_alive,
-                sse_path: "/".to_string(),
-                post_path: "/message".to_string(),
-            };
-            let (sse_server, sse_router) = SseServer::new(sse_config);
-            let _sse_ct = sse_server.with_service(move || server_provider());
-
-            sse_router
-        };
+        let auth = config.auth.clone().map(Arc::new);
 
         // Health and readiness
         // See https://kubernetes.io/docs/concepts/configuration/liveness-readiness-startup-probes/
@@ -104,39 +423,189 @@ impl HttpProtocol {
                 .route("/ready", get(async || (StatusCode::OK, "Ready\n")))
                 // Live: are we alive?
                 .route("/live", get(async || "Alive\n"))
+                // Metrics of background maintenance tasks, for authenticated clients
+                .merge(with_auth(
+                    Router::new().route("/maintenance", get(async || Json(Scheduler::global().metrics()))),
+                    &auth,
+                    &None,
+                ))
         };
 
+        // Prometheus metrics, for authenticated clients
+        let metrics_router = with_auth(
+            Router::new().route(
+                "/metrics",
+                get(async || {
+                    (
+                        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+                        Metrics::global().render(),
+                    )
+                }),
+            ),
+            &auth,
+            &None,
+        );
+
+        // Pending tool call approvals, for authenticated operators
+        let approvals_router = with_auth(approval::router(config.approval_operators.clone(), None), &auth, &None);
+
         // Put all things together
-        let main_router = Router::new()
+        let mut main_router = Router::new()
             .route("/", get(hello))
             .route("/ping", get(async || (StatusCode::OK, "Ready\n")))
-            .nest("/mcp/sse", sse_router)
-            .nest("/mcp", sh_router)
             .nest("/_health", health_router)
-            .with_state(());
+            .nest("/approvals", approvals_router)
+            .merge(metrics_router);
+        if let Some(server_provider) = server_provider {
+            let session_manager = config.session_manager.clone();
+            let mcp_router = mcp_router(server_provider.0, session_manager, &config, &auth, &None, &ct);
+            main_router = main_router.nest("/mcp", mcp_router);
+        }
+        for (name, server_provider) in tenants {
+            // Tenants have their own sessions, so that a session id can't be used to reach another tenant
+            let session_manager = Arc::new(M::default());
+            let access = config.tenant_access.get(&name).cloned();
+            let mcp_router = mcp_router(server_provider.0, session_manager, &config, &auth, &access, &ct)
+                .layer(Extension(TenantName(name.clone())));
+            main_router = main_router.nest(&format!("/tenants/{name}/mcp"), mcp_router);
+
+            let approvals_router = approval::router(config.approval_operators.clone(), Some(name.clone()));
+            main_router = main_router.nest(
+                &format!("/tenants/{name}/approvals"),
+                with_auth(approvals_router, &auth, &access),
+            );
+        }
+        let main_router = middleware::from_fn(select_tenant).layer(main_router.with_state(()));
 
         // Start the http server
-        let listener = tokio::net::TcpListener::bind(config.bind).await?;
-        let server = axum::serve(listener, main_router).with_graceful_shutdown({
+        let tls_acceptor = config.tls.as_ref().map(TlsConfig::acceptor).transpose()?;
+        let listener = match config.listener {
+            Some(listener) => TcpListener::from_std(listener)?,
+            None => TcpListener::bind(config.bind).await?,
+        };
+        let bind_address = listener.local_addr()?;
+        let shutdown = {
             let ct = ct.clone();
             async move {
                 ct.cancelled().await;
                 tracing::info!("http server cancelled");
             }
-        });
+        };
+        let server = async move {
+            match tls_acceptor {
+                Some(acceptor) => {
+                    let listener = TlsListener::new(listener, acceptor)?;
+                    axum::serve(listener, main_router.into_make_service())
+                        .with_graceful_shutdown(shutdown)
+                        .await
+                }
+                None => {
+                    axum::serve(listener, main_router.into_make_service())
+                        .with_graceful_shutdown(shutdown)
+                        .await
+                }
+            }
+        };
 
         // Await the server, or it will do nothing :-)
         tokio::spawn(
             async {
                 let _ = server.await;
             }
-            .instrument(tracing::info_span!("http-server", bind_address = %config.bind)),
+            .instrument(tracing::info_span!("http-server", %bind_address)),
         );
 
         Ok(ct)
     }
 }
 
+/// Routes of the streamable HTTP, SSE and WebSocket transports of an MCP server.
+fn mcp_router<S: Service<RoleServer>, M: SessionManager>(
+    server_provider: Arc<dyn Fn() -> S + Send + Sync>,
+    session_manager: Arc<M>,
+    config: &HttpServerConfig<M>,
+    auth: &Option<Arc<HttpAuth>>,
+    access: &Option<TenantAccess>,
+    ct: &CancellationToken,
+) -> Router {
+    // Create a streamable http router
+    let sh_router = {
+        let sh_config = StreamableHttpServerConfig {
+            sse_keep_alive: config.keep_alive,
+            stateful_mode: config.stateful_mode,
+        };
+
+        let session_manager = Arc::new(IdleSessionManager::new(
+            session_manager,
+            config.session_idle_timeout.filter(|_| config.stateful_mode),
+        ));
+        session_manager.spawn_reaper(ct.clone());
+
+        let server_provider = server_provider.clone();
+        // TODO: internally, new() wraps the server provider closure with an Arc. We can avoid
+        // "double-Arc" by having
+        let sh_service = StreamableHttpService::new(move || Ok(server_provider()), session_manager, sh_config);
+        let router = Router::new().route_service("/", sh_service);
+        if config.stateful_mode {
+            // Session ids are only checked in stateful mode
+            router.layer(middleware::from_fn(rmcp_ext::streamable_http_session))
+        } else {
+            router
+        }
+    };
+
+    // Create a WebSocket router
+    let ws_router = ws::router(server_provider.clone(), config.allowed_origins.clone(), ct.clone());
+
+    // Create an SSE router
+    let sse_router = {
+        let sse_config = SseServerConfig {
+            bind: config.bind,
+            // SSE server will create a child cancellation token for every transport that is created
+            // (see with_service() below)
+            ct: ct.clone(),
+            sse_keep_alive: config.keep_alive,
+            sse_path: "/".to_string(),
+            post_path: "/message".to_string(),
+        };
+        let (sse_server, sse_router) = SseServer::new(sse_config);
+        let _sse_ct = sse_server.with_service(move || server_provider());
+
+        sse_router.layer(middleware::from_fn(rmcp_ext::sse_session))
+    };
+
+    // Authenticate clients on MCP endpoints
+    Router::new()
+        .nest("/sse", with_auth(sse_router, auth, access))
+        .nest("/ws", with_auth(ws_router, auth, access))
+        .merge(with_auth(sh_router, auth, access))
+}
+
+/// Authenticate clients, and reject those that `access` doesn't allow.
+fn with_auth(router: Router, auth: &Option<Arc<HttpAuth>>, access: &Option<TenantAccess>) -> Router {
+    // Layers added last are called first: clients are authenticated before their access is checked
+    let router = match access.clone() {
+        Some(access) => router.layer(middleware::from_fn(move |request: Request, next: Next| {
+            let access = access.clone();
+            async move {
+                let identity = request
+                    .extensions()
+                    .get::<Identity>()
+                    .map(|identity| identity.0.as_str());
+                if !access(identity) {
+                    return (StatusCode::FORBIDDEN, "Client not allowed on this tenant\n").into_response();
+                }
+                next.run(request).await
+            }
+        })),
+        None => router,
+    };
+    match auth {
+        Some(auth) => router.layer(middleware::from_fn_with_state(auth.clone(), authenticate)),
+        None => router,
+    }
+}
+
 async fn hello() -> String {
     let version = env!("CARGO_PKG_VERSION");
     format!(
@@ -145,12 +614,105 @@ async fn hello() -> String {
 Endpoints:
 - streamable-http: /mcp
 - sse: /mcp/sse
+- websocket: /mcp/ws
+- metrics: /metrics
+- pending approvals: /approvals, or /tenants/<name>/approvals
+- tenants: /tenants/<name>/mcp, or /mcp with an 'X-Mcp-Tenant' header
 "#
     )
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     pub fn test_parts_in_extensions() {}
+
+    #[test]
+    fn test_auth_headers() {
+        let auth = HttpAuth::new(vec!["key1".to_string()], vec!["token1".to_string()]).unwrap();
+        let check = |name: &'static str, value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(name, value.parse().unwrap());
+            auth.find_credential(&headers).is_some()
+        };
+
+        assert!(check("x-api-key", "key1"));
+        assert!(check("authorization", "ApiKey key1"));
+        assert!(check("authorization", "bearer token1"));
+
+        assert!(!check("x-api-key", "token1"));
+        assert!(!check("authorization", "ApiKey token1"));
+        assert!(!check("authorization", "Bearer key1"));
+        assert!(!check("authorization", "Basic a2V5MQ=="));
+        assert!(!check("authorization", "Bearer token"));
+        assert!(auth.find_credential(&HeaderMap::new()).is_none());
+
+        assert!(HttpAuth::new(vec![], vec![]).is_none());
+    }
+
+    #[test]
+    fn test_client_identities() {
+        let client = ClientConfig {
+            bearer_token: Some("token2".to_string()),
+            ..Default::default()
+        };
+        let clients = HashMap::from([("ops".to_string(), client)]);
+        let auth = HttpAuth::from_config(vec!["key1".to_string()], vec![], &clients, None).unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer token2".parse().unwrap());
+        let credential = auth.find_credential(&headers).unwrap();
+        assert_eq!(auth.identities.get(credential).map(String::as_str), Some("ops"));
+
+        headers.insert("authorization", "ApiKey key1".parse().unwrap());
+        let credential = auth.find_credential(&headers).unwrap();
+        assert!(!auth.identities.contains_key(credential));
+
+        assert!(HttpAuth::from_config(vec![], vec![], &HashMap::new(), None).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tenant_access() -> anyhow::Result<()> {
+        use tower::ServiceExt as _;
+
+        let auth = HttpAuth::from_config(
+            vec![],
+            vec![],
+            &HashMap::from([
+                (
+                    "alice".to_string(),
+                    ClientConfig {
+                        api_key: Some("key-a".to_string()),
+                        ..Default::default()
+                    },
+                ),
+                (
+                    "bob".to_string(),
+                    ClientConfig {
+                        api_key: Some("key-b".to_string()),
+                        ..Default::default()
+                    },
+                ),
+            ]),
+            None,
+        )
+        .map(Arc::new);
+        let access: TenantAccess = Arc::new(|identity: Option<&str>| identity == Some("alice"));
+        let router = with_auth(Router::new().route("/", get(async || "Ok")), &auth, &Some(access));
+
+        let get = |key: &str| {
+            let request = http::Request::builder()
+                .uri("/")
+                .header(X_API_KEY, key)
+                .body(axum::body::Body::empty())
+                .unwrap();
+            router.clone().oneshot(request)
+        };
+        assert_eq!(get("key-a").await?.status(), StatusCode::OK);
+        assert_eq!(get("key-b").await?.status(), StatusCode::FORBIDDEN);
+        assert_eq!(get("key-c").await?.status(), StatusCode::UNAUTHORIZED);
+        Ok(())
+    }
 }
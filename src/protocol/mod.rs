@@ -16,4 +16,6 @@
 // under the License.
 
 pub mod http;
+pub mod oidc;
 pub mod stdio;
+pub mod ws;
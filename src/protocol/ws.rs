@@ -0,0 +1,176 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! MCP over WebSocket: each text frame is a JSON-RPC message, in both directions. A socket is an MCP session
+//! that lasts until either side closes it.
+
+use crate::utils::rmcp_ext::{TransportSession, random_id};
+use axum::Router;
+use axum::extract::State;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use futures::{SinkExt, StreamExt};
+use http::header::{HOST, ORIGIN};
+use http::request::Parts;
+use http::{HeaderMap, StatusCode};
+use rmcp::model::{ClientJsonRpcMessage, GetExtensions, ServerJsonRpcMessage};
+use rmcp::{RoleServer, Service, ServiceExt};
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+struct WsState<S> {
+    server_provider: Arc<dyn Fn() -> S + Send + Sync>,
+    allowed_origins: Vec<String>,
+    ct: CancellationToken,
+}
+
+/// A router that serves MCP on WebSocket upgrades of `GET /`. Upgrades from web pages are only accepted from
+/// the server's own origin and `allowed_origins`. Sessions are closed when `ct` is cancelled.
+pub fn router<S: Service<RoleServer>>(
+    server_provider: Arc<dyn Fn() -> S + Send + Sync>,
+    allowed_origins: Vec<String>,
+    ct: CancellationToken,
+) -> Router {
+    let state = Arc::new(WsState {
+        server_provider,
+        allowed_origins,
+        ct,
+    });
+    Router::new().route("/", get(upgrade::<S>)).with_state(state)
+}
+
+async fn upgrade<S: Service<RoleServer>>(
+    State(state): State<Arc<WsState<S>>>,
+    parts: Parts,
+    ws: WebSocketUpgrade,
+) -> Response {
+    if !is_allowed_origin(&parts.headers, &state.allowed_origins) {
+        tracing::warn!(origin = ?parts.headers.get(ORIGIN), "Rejected cross-origin WebSocket upgrade");
+        return (StatusCode::FORBIDDEN, "Origin not allowed\n").into_response();
+    }
+    ws.on_upgrade(move |socket| serve(socket, parts, state))
+}
+
+/// Can a WebSocket be opened from the `Origin` of an upgrade request? Browsers don't apply CORS to WebSocket,
+/// so without this check any web page could use a server running on localhost. Requests without an origin
+/// don't come from browsers.
+fn is_allowed_origin(headers: &HeaderMap, allowed_origins: &[String]) -> bool {
+    let Some(origin) = headers.get(ORIGIN) else {
+        return true;
+    };
+    let Ok(origin) = origin.to_str() else {
+        return false;
+    };
+    let same_origin = origin
+        .strip_prefix("http://")
+        .or_else(|| origin.strip_prefix("https://"))
+        .zip(headers.get(HOST).and_then(|host| host.to_str().ok()))
+        .is_some_and(|(authority, host)| authority.eq_ignore_ascii_case(host));
+
+    same_origin
+        || allowed_origins
+            .iter()
+            .any(|allowed| allowed.trim_end_matches('/').eq_ignore_ascii_case(origin))
+}
+
+async fn serve<S: Service<RoleServer>>(socket: WebSocket, mut parts: Parts, state: Arc<WsState<S>>) {
+    // Identify the socket like a stateful http session, so that tools keep per-session state. The id is random
+    // so that it can't be guessed.
+    let session_id = random_id();
+    parts.extensions.insert(TransportSession(session_id.clone()));
+
+    let (sink, stream) = socket.split();
+
+    let sink = sink.with(|message: ServerJsonRpcMessage| async move {
+        let text = serde_json::to_string(&message).map_err(axum::Error::new)?;
+        Ok::<_, axum::Error>(Message::Text(text.into()))
+    });
+
+    let stream = stream.filter_map(move |message| {
+        // Requests carry the upgrade request, like http requests do, to forward Elasticsearch credentials
+        let parts = parts.clone();
+        async move {
+            let text = match message {
+                Ok(Message::Text(text)) => text,
+                // Pings are answered by axum, and closing ends the stream
+                Ok(_) => return None,
+                Err(err) => {
+                    tracing::debug!("WebSocket error: {err}");
+                    return None;
+                }
+            };
+            match serde_json::from_str::<ClientJsonRpcMessage>(&text) {
+                Ok(mut message) => {
+                    if let ClientJsonRpcMessage::Request(request) = &mut message {
+                        request.request.extensions_mut().insert(parts);
+                    }
+                    Some(message)
+                }
+                Err(err) => {
+                    tracing::warn!("Invalid JSON-RPC message on WebSocket: {err}");
+                    None
+                }
+            }
+        }
+    });
+
+    let server = match (state.server_provider)()
+        .serve((Box::pin(sink), Box::pin(stream)))
+        .await
+    {
+        Ok(server) => server,
+        Err(err) => {
+            tracing::warn!("WebSocket session {session_id} failed to initialize: {err}");
+            return;
+        }
+    };
+    tracing::debug!("WebSocket session {session_id} started");
+
+    let ct = server.cancellation_token();
+    tokio::select! {
+        _ = server.waiting() => {}
+        _ = state.ct.cancelled() => ct.cancel(),
+    }
+    tracing::debug!("WebSocket session {session_id} closed");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cross_origin_upgrades() {
+        let headers = |origin: Option<&'static str>| {
+            let mut headers = HeaderMap::new();
+            headers.insert(HOST, "localhost:8080".parse().unwrap());
+            if let Some(origin) = origin {
+                headers.insert(ORIGIN, origin.parse().unwrap());
+            }
+            headers
+        };
+        let allowed = vec!["https://app.example.com/".to_string()];
+
+        assert!(is_allowed_origin(&headers(None), &[]));
+        assert!(is_allowed_origin(&headers(Some("http://localhost:8080")), &[]));
+        assert!(is_allowed_origin(&headers(Some("https://app.example.com")), &allowed));
+
+        assert!(!is_allowed_origin(&headers(Some("https://evil.example.com")), &allowed));
+        assert!(!is_allowed_origin(&headers(Some("http://localhost:3000")), &allowed));
+        assert!(!is_allowed_origin(&headers(Some("null")), &[]));
+    }
+}
@@ -0,0 +1,319 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Rate limiting of tool calls.
+
+use crate::authorization::Identity;
+use crate::servers::matches_tool;
+use crate::utils::rmcp_ext::session_id;
+use indexmap::IndexMap;
+use rmcp::model::{ClientNotification, ClientRequest, ErrorCode, ServerInfo, ServerResult};
+use rmcp::service::{NotificationContext, RequestContext};
+use rmcp::{RoleServer, Service};
+use schemars::JsonSchema;
+use schemars::r#gen::SchemaGenerator;
+use schemars::schema::Schema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// A number of calls per period, written as `<count>/<unit>`, e.g. `10/min`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RateLimit {
+    pub count: u32,
+    pub period: Duration,
+}
+
+impl FromStr for RateLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("invalid rate limit '{s}', expecting <count>/<sec|min|hour>");
+        let (count, unit) = s.split_once('/').ok_or_else(err)?;
+        let count = count.trim().parse::<u32>().map_err(|_| err())?;
+        let period = match unit.trim() {
+            "s" | "sec" | "second" => Duration::from_secs(1),
+            "m" | "min" | "minute" => Duration::from_secs(60),
+            "h" | "hour" => Duration::from_secs(3600),
+            _ => return Err(err()),
+        };
+        if count == 0 {
+            return Err(err());
+        }
+        Ok(RateLimit { count, period })
+    }
+}
+
+impl TryFrom<String> for RateLimit {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl Display for RateLimit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let unit = match self.period.as_secs() {
+            1 => "sec",
+            60 => "min",
+            _ => "hour",
+        };
+        write!(f, "{}/{unit}", self.count)
+    }
+}
+
+impl From<RateLimit> for String {
+    fn from(value: RateLimit) -> Self {
+        value.to_string()
+    }
+}
+
+impl JsonSchema for RateLimit {
+    fn schema_name() -> String {
+        "RateLimit".to_string()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        let mut schema = String::json_schema(generator).into_object();
+        schema.metadata().description = Some("A number of calls per period, e.g. `10/min`".to_string());
+        schema.string().pattern = Some(r"^\s*[0-9]+\s*/\s*(s|sec|second|m|min|minute|h|hour)\s*$".to_string());
+        schema.into()
+    }
+}
+
+/// Rate limits, keyed by tool name pattern. A pattern is a tool name, possibly with `*` wildcards. The
+/// first pattern matching a tool name defines its limit.
+pub type RateLimits = IndexMap<String, RateLimit>;
+
+/// A token bucket, that holds up to `count` calls and is refilled over `period`.
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// Period of the last limit applied, after which the bucket is full again
+    period: Duration,
+}
+
+impl Bucket {
+    /// Take a token, or return the delay until one is available.
+    fn take(&mut self, limit: &RateLimit, now: Instant) -> Result<(), Duration> {
+        let refill_rate = limit.count as f64 / limit.period.as_secs_f64();
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * refill_rate).min(limit.count as f64);
+        self.updated = now;
+        self.period = limit.period;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / refill_rate))
+        }
+    }
+}
+
+/// Buckets keyed by pattern and caller, see [`caller`]
+#[derive(Default)]
+struct Buckets(Mutex<HashMap<(String, Option<String>), Bucket>>);
+
+impl Buckets {
+    /// The buckets of this process, shared by all servers so that configuration reloads don't reset the limits.
+    fn global() -> &'static Buckets {
+        static GLOBAL: OnceLock<Buckets> = OnceLock::new();
+        GLOBAL.get_or_init(Buckets::default)
+    }
+}
+
+struct Limiter {
+    limits: RateLimits,
+    buckets: &'static Buckets,
+}
+
+impl Limiter {
+    /// Check that a tool call is allowed, or return the applicable limit and the delay to wait for.
+    fn check(&self, tool: &str, caller: Option<&str>, now: Instant) -> Result<(), (&RateLimit, Duration)> {
+        let Some((pattern, limit)) = self.limits.iter().find(|(pattern, _)| matches_tool(pattern, tool)) else {
+            return Ok(());
+        };
+
+        let mut buckets = self.buckets.0.lock().unwrap();
+        let key = (pattern.clone(), caller.map(str::to_string));
+        if !buckets.contains_key(&key) {
+            // Forget buckets that are full again, such as those of closed sessions.
+            buckets.retain(|_, bucket| now.saturating_duration_since(bucket.updated) < bucket.period);
+        }
+
+        buckets
+            .entry(key)
+            .or_insert_with(|| Bucket {
+                tokens: limit.count as f64,
+                updated: now,
+                period: limit.period,
+            })
+            .take(limit, now)
+            .map_err(|delay| (limit, delay))
+    }
+}
+
+/// The caller of a request, whose calls share the same buckets: the identity of its client, so that opening new
+/// sessions doesn't reset the limits, or its session for anonymous clients. Other requests, i.e. stdio and anonymous
+/// stateless HTTP, have no caller.
+///
+/// The `Mcp-Session-Id` header of stateless requests isn't used, as clients could send a new one for each call.
+fn caller(context: &RequestContext<RoleServer>) -> Option<String> {
+    match Identity::of(context) {
+        Some(identity) => Some(format!("client:{identity}")),
+        None => session_id(context).map(|session| format!("session:{session}")),
+    }
+}
+
+/// A server that limits the rate of tool calls per tool and per client.
+///
+/// Calls that exceed the limit are rejected with an error that tells the client when to retry. Clients
+/// are limited per identity, anonymous ones per session, and those without a session (stdio or stateless HTTP)
+/// share the same limits. Limits are kept across configuration reloads.
+#[derive(Clone)]
+pub struct RateLimitedServer<S> {
+    inner: S,
+    limiter: Arc<Limiter>,
+}
+
+impl<S: Service<RoleServer>> RateLimitedServer<S> {
+    pub fn new(inner: S, limits: RateLimits) -> Self {
+        RateLimitedServer {
+            inner,
+            limiter: Arc::new(Limiter {
+                limits,
+                buckets: Buckets::global(),
+            }),
+        }
+    }
+}
+
+impl<S: Service<RoleServer>> Service<RoleServer> for RateLimitedServer<S> {
+    async fn handle_request(
+        &self,
+        request: ClientRequest,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ServerResult, rmcp::Error> {
+        if let ClientRequest::CallToolRequest(call) = &request {
+            let caller = caller(&context);
+
+            let tool = &call.params.name;
+            if let Err((limit, delay)) = self.limiter.check(tool, caller.as_deref(), Instant::now()) {
+                let retry_after = delay.as_secs() + 1;
+                return Err(rmcp::Error::new(
+                    ErrorCode::INVALID_REQUEST,
+                    format!(
+                        "Rate limit of {limit} exceeded for tool '{tool}'. \
+                        Back off and retry in {retry_after} seconds, or try fewer calls."
+                    ),
+                    Some(json!({ "retry_after_seconds": retry_after })),
+                ));
+            }
+        }
+
+        self.inner.handle_request(request, context).await
+    }
+
+    async fn handle_notification(
+        &self,
+        notification: ClientNotification,
+        context: NotificationContext<RoleServer>,
+    ) -> Result<(), rmcp::Error> {
+        self.inner.handle_notification(notification, context).await
+    }
+
+    fn get_info(&self) -> ServerInfo {
+        self.inner.get_info()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_limits() {
+        let limits: RateLimits = serde_json::from_str(r#"{"esql": "10/min", "*": "2/s"}"#).unwrap();
+        assert_eq!(
+            limits["esql"],
+            RateLimit {
+                count: 10,
+                period: Duration::from_secs(60)
+            }
+        );
+        assert_eq!(limits["*"].to_string(), "2/sec");
+
+        assert!("10".parse::<RateLimit>().is_err());
+        assert!("0/min".parse::<RateLimit>().is_err());
+        assert!("10/day".parse::<RateLimit>().is_err());
+    }
+
+    fn new_limiter(limits: &str, buckets: &'static Buckets) -> Limiter {
+        Limiter {
+            limits: serde_json::from_str(limits).unwrap(),
+            buckets,
+        }
+    }
+
+    #[test]
+    fn limit_per_session() {
+        let limiter = new_limiter(r#"{"esql": "2/min"}"#, Box::leak(Box::<Buckets>::default()));
+        let now = Instant::now();
+
+        assert!(limiter.check("esql", Some("a"), now).is_ok());
+        assert!(limiter.check("esql", Some("a"), now).is_ok());
+        let (_, delay) = limiter.check("esql", Some("a"), now).unwrap_err();
+        assert_eq!(delay, Duration::from_secs(30));
+
+        // Other sessions and tools aren't limited
+        assert!(limiter.check("esql", Some("b"), now).is_ok());
+        assert!(limiter.check("search", Some("a"), now).is_ok());
+
+        // A token is available after a refill
+        assert!(limiter.check("esql", Some("a"), now + Duration::from_secs(30)).is_ok());
+        assert!(limiter.check("esql", Some("a"), now + Duration::from_secs(30)).is_err());
+    }
+
+    #[test]
+    fn limits_survive_reloads() {
+        let buckets: &'static Buckets = Box::leak(Box::default());
+        let now = Instant::now();
+
+        let limiter = new_limiter(r#"{"esql": "2/min"}"#, buckets);
+        assert!(limiter.check("esql", Some("a"), now).is_ok());
+        assert!(limiter.check("esql", Some("a"), now).is_ok());
+
+        // A reloaded configuration doesn't refill the bucket
+        let limiter = new_limiter(r#"{"esql": "2/min", "search": "1/min"}"#, buckets);
+        assert!(limiter.check("esql", Some("a"), now).is_err());
+
+        // Full buckets are forgotten when a new one is created
+        assert!(
+            limiter
+                .check("search", Some("a"), now + Duration::from_secs(60))
+                .is_ok()
+        );
+        assert_eq!(buckets.0.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,325 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Redaction of fields in tool results and resources, before they are returned to the model.
+
+use crate::servers::matches_tool;
+use crate::utils::{matches_pattern, string_or_vec_schema, vec_from_string_or_vec};
+use regex::Regex;
+use rmcp::model::{
+    CallToolResult, ClientNotification, ClientRequest, Content, JsonObject, ReadResourceResult, ResourceContents,
+    ServerInfo, ServerResult,
+};
+use rmcp::service::{NotificationContext, RequestContext};
+use rmcp::{RoleServer, Service};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::sync::Arc;
+
+/// Replacement of the masked parts of values.
+const MASK: &str = "***";
+
+/// A redaction rule: fields of the results of some tools, that are removed or masked.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RedactionRule {
+    /// Tool name patterns, possibly with `*` wildcards. Applies to all tools if empty.
+    #[serde(default, deserialize_with = "vec_from_string_or_vec")]
+    #[schemars(schema_with = "string_or_vec_schema")]
+    pub tools: Vec<String>,
+    /// Index name patterns, possibly with `*` wildcards, matched against the `index` argument of tool calls.
+    /// Applies to all indices if empty, and to the calls without an `index` argument such as ES|QL queries,
+    /// whose indices aren't known.
+    #[serde(default, deserialize_with = "vec_from_string_or_vec")]
+    #[schemars(schema_with = "string_or_vec_schema")]
+    pub indices: Vec<String>,
+    /// Field path patterns, possibly with `*` wildcards, e.g. `email` or `*.password`. Paths are the keys
+    /// from the top of the result objects, separated with a dot.
+    #[serde(deserialize_with = "vec_from_string_or_vec")]
+    #[schemars(schema_with = "string_or_vec_schema")]
+    pub fields: Vec<String>,
+    /// Regular expression of the parts of field values to replace with `***`. Fields are removed if not set.
+    #[serde(default)]
+    pub mask: Option<String>,
+}
+
+/// A rule, with its mask compiled.
+struct Rule {
+    config: RedactionRule,
+    mask: Option<Regex>,
+}
+
+impl Rule {
+    fn applies_to(&self, tool: &str, arguments: Option<&JsonObject>) -> bool {
+        let matches_any = |patterns: &[String], name: &str| patterns.iter().any(|p| matches_pattern(p, name));
+
+        if !self.config.tools.is_empty() && !self.config.tools.iter().any(|p| matches_tool(p, tool)) {
+            return false;
+        }
+        match arguments.and_then(|args| args.get("index")).and_then(Value::as_str) {
+            Some(index) if !self.config.indices.is_empty() => {
+                // Comma-separated index names
+                index
+                    .split(',')
+                    .any(|index| matches_any(&self.config.indices, index.trim()))
+            }
+            _ => true,
+        }
+    }
+
+    fn matches_field(&self, path: &str) -> bool {
+        self.config.fields.iter().any(|p| matches_pattern(p, path))
+    }
+}
+
+/// A server that redacts the JSON content of tool results according to rules.
+///
+/// Resources, such as exports and error details, are redacted with all rules when they're read, since the tool
+/// call that created them isn't known.
+#[derive(Clone)]
+pub struct RedactingServer<S> {
+    inner: S,
+    rules: Arc<Vec<Rule>>,
+}
+
+impl<S: Service<RoleServer>> RedactingServer<S> {
+    pub fn new(inner: S, rules: Vec<RedactionRule>) -> anyhow::Result<Self> {
+        let rules = rules
+            .into_iter()
+            .map(|config| {
+                let mask = config
+                    .mask
+                    .as_deref()
+                    .map(Regex::new)
+                    .transpose()
+                    .map_err(|err| anyhow::anyhow!("Invalid redaction mask: {err}"))?;
+                Ok(Rule { config, mask })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(RedactingServer {
+            inner,
+            rules: Arc::new(rules),
+        })
+    }
+
+    fn redact_result(&self, rules: &[&Rule], mut result: CallToolResult) -> CallToolResult {
+        for content in &mut result.content {
+            let Some(text) = content.as_text() else {
+                continue;
+            };
+            // Only JSON content has fields
+            let Ok(mut value) = serde_json::from_str::<Value>(&text.text) else {
+                continue;
+            };
+            redact(rules, "", &mut value);
+            if let Ok(redacted) = Content::json(value) {
+                *content = redacted;
+            }
+        }
+        result
+    }
+
+    fn redact_resource(&self, rules: &[&Rule], mut result: ReadResourceResult) -> ReadResourceResult {
+        for contents in &mut result.contents {
+            if let ResourceContents::TextResourceContents { text, .. } = contents {
+                *text = redact_text(rules, text);
+            }
+        }
+        result
+    }
+}
+
+/// Redact a JSON or NDJSON text. Lines that aren't JSON are kept as is.
+fn redact_text(rules: &[&Rule], text: &str) -> String {
+    if let Ok(mut value) = serde_json::from_str::<Value>(text) {
+        redact(rules, "", &mut value);
+        return value.to_string();
+    }
+    text.split('\n')
+        .map(|line| match serde_json::from_str::<Value>(line) {
+            Ok(mut value) => {
+                redact(rules, "", &mut value);
+                value.to_string()
+            }
+            Err(_) => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Redact a value whose path is `path`.
+fn redact(rules: &[&Rule], path: &str, value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            object.retain(|key, value| {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                let mut keep = true;
+                for rule in rules.iter().filter(|rule| rule.matches_field(&path)) {
+                    match &rule.mask {
+                        Some(mask) => mask_value(mask, value),
+                        None => keep = false,
+                    }
+                }
+                if keep {
+                    redact(rules, &path, value);
+                }
+                keep
+            });
+        }
+        // Array items have the path of the array
+        Value::Array(values) => values.iter_mut().for_each(|value| redact(rules, path, value)),
+        _ => {}
+    }
+}
+
+/// Mask the scalar values of a field, including in nested objects and arrays.
+fn mask_value(mask: &Regex, value: &mut Value) {
+    match value {
+        Value::Object(object) => object.values_mut().for_each(|value| mask_value(mask, value)),
+        Value::Array(values) => values.iter_mut().for_each(|value| mask_value(mask, value)),
+        Value::Null => {}
+        Value::String(text) => {
+            if let Cow::Owned(masked) = mask.replace_all(text, MASK) {
+                *text = masked;
+            }
+        }
+        // Numbers and booleans become strings if masked
+        scalar => {
+            let text = scalar.to_string();
+            if let Cow::Owned(masked) = mask.replace_all(&text, MASK) {
+                *scalar = Value::String(masked);
+            }
+        }
+    }
+}
+
+impl<S: Service<RoleServer>> Service<RoleServer> for RedactingServer<S> {
+    async fn handle_request(
+        &self,
+        request: ClientRequest,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ServerResult, rmcp::Error> {
+        let rules = match &request {
+            ClientRequest::CallToolRequest(call) => self
+                .rules
+                .iter()
+                .filter(|rule| rule.applies_to(&call.params.name, call.params.arguments.as_ref()))
+                .collect::<Vec<_>>(),
+            ClientRequest::ReadResourceRequest(_) => self.rules.iter().collect::<Vec<_>>(),
+            _ => Vec::new(),
+        };
+        if rules.is_empty() {
+            return self.inner.handle_request(request, context).await;
+        }
+
+        match self.inner.handle_request(request, context).await? {
+            ServerResult::CallToolResult(result) => {
+                Ok(ServerResult::CallToolResult(self.redact_result(&rules, result)))
+            }
+            ServerResult::ReadResourceResult(result) => {
+                Ok(ServerResult::ReadResourceResult(self.redact_resource(&rules, result)))
+            }
+            result => Ok(result),
+        }
+    }
+
+    async fn handle_notification(
+        &self,
+        notification: ClientNotification,
+        context: NotificationContext<RoleServer>,
+    ) -> Result<(), rmcp::Error> {
+        self.inner.handle_notification(notification, context).await
+    }
+
+    fn get_info(&self) -> ServerInfo {
+        self.inner.get_info()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rules(rules: Value) -> Vec<Rule> {
+        serde_json::from_value::<Vec<RedactionRule>>(rules)
+            .unwrap()
+            .into_iter()
+            .map(|config| Rule {
+                mask: config.mask.as_deref().map(|m| Regex::new(m).unwrap()),
+                config,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn redact_fields() {
+        let rules = rules(json!([
+            { "fields": ["password", "*.password"] },
+            { "fields": "email", "mask": "^[^@]+" },
+            { "fields": "user.phone", "mask": "\\d{4}$" },
+        ]));
+        let rules = rules.iter().collect::<Vec<_>>();
+
+        let mut value = json!([
+            { "password": "a", "email": "jane@example.com", "user": { "password": "b", "phone": 5551234, "name": "Jane" } },
+            { "user.phone": "555-1234", "user.name": "John" },
+        ]);
+        redact(&rules, "", &mut value);
+        assert_eq!(
+            value,
+            json!([
+                { "email": "***@example.com", "user": { "phone": "555***", "name": "Jane" } },
+                { "user.phone": "555-***", "user.name": "John" },
+            ])
+        );
+    }
+
+    #[test]
+    fn redact_ndjson() {
+        let rules = rules(json!([{ "tools": "search", "fields": "password" }]));
+        let rules = rules.iter().collect::<Vec<_>>();
+
+        let text = "{\"name\":\"a\",\"password\":\"x\"}\nnot json\n{\"name\":\"b\",\"password\":\"y\"}\n";
+        assert_eq!(
+            redact_text(&rules, text),
+            "{\"name\":\"a\"}\nnot json\n{\"name\":\"b\"}\n"
+        );
+        assert_eq!(redact_text(&rules, "{\"password\":1}"), "{}");
+    }
+
+    #[test]
+    fn rule_scope() {
+        let rules = rules(json!([{ "tools": "search", "indices": "customers*", "fields": "email" }]));
+        let rule = &rules[0];
+        let args = |index: &str| json!({ "index": index }).as_object().cloned();
+
+        assert!(rule.applies_to("search", args("customers-eu").as_ref()));
+        assert!(rule.applies_to("search", args("logs,customers-eu").as_ref()));
+        assert!(!rule.applies_to("search", args("logs").as_ref()));
+        assert!(!rule.applies_to("esql", None));
+
+        let rules = self::rules(json!([{ "indices": "customers*", "fields": "email" }]));
+        // Indices of ES|QL queries aren't known
+        assert!(rules[0].applies_to("esql", None));
+    }
+}
@@ -0,0 +1,117 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Helpers of the sub-servers that summarize recent data: time ranges back from now, and aggregation results.
+
+use crate::servers::elasticsearch::guardrails::parse_span;
+use serde_json::{Value, json};
+use std::time::Duration;
+
+/// Number of buckets of timelines.
+const TIMELINE_BUCKETS: u64 = 30;
+
+/// A time range back from now.
+pub struct TimeRange {
+    /// Length as date math, like `1h`
+    pub span: String,
+    pub duration: Duration,
+}
+
+impl TimeRange {
+    /// Parse a time range like `15m`, `1h` or `7d`.
+    pub fn parse(span: Option<&str>, default: &str) -> Result<Self, rmcp::Error> {
+        let span = span.unwrap_or(default).trim();
+        match parse_span(span) {
+            Some(duration) if !duration.is_zero() => Ok(TimeRange {
+                span: span.to_string(),
+                duration,
+            }),
+            _ => Err(rmcp::Error::invalid_params(
+                format!("Invalid time range '{span}', expected a number and a unit like '15m', '1h' or '7d'"),
+                None,
+            )),
+        }
+    }
+
+    pub fn start(&self) -> String {
+        format!("now-{}", self.span)
+    }
+
+    /// Filter on the `@timestamp` field.
+    pub fn filter(&self) -> Value {
+        json!({ "range": { "@timestamp": { "gte": self.start() } } })
+    }
+
+    /// Interval of timeline buckets, in whole minutes.
+    pub fn interval(&self) -> Duration {
+        let minutes = (self.duration.as_secs() / TIMELINE_BUCKETS).div_ceil(60).max(1);
+        Duration::from_secs(minutes * 60)
+    }
+
+    /// Date histogram of a timeline over the time range, including empty buckets.
+    pub fn timeline(&self) -> Value {
+        json!({
+            "field": "@timestamp",
+            "fixed_interval": format!("{}s", self.interval().as_secs()),
+            "min_doc_count": 0,
+            "extended_bounds": { "min": self.start(), "max": "now" }
+        })
+    }
+}
+
+/// Buckets of a multi-bucket aggregation.
+pub fn buckets<'a>(value: &'a Value, pointer: &str) -> impl Iterator<Item = &'a Value> {
+    value.pointer(pointer).and_then(Value::as_array).into_iter().flatten()
+}
+
+/// A field of a document source, either as nested objects or as a dotted name.
+pub fn source_field<'a>(source: &'a Value, name: &str) -> Option<&'a Value> {
+    source
+        .get(name)
+        .or_else(|| source.pointer(&format!("/{}", name.replace('.', "/"))))
+}
+
+/// Round to one decimal.
+pub fn round(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+pub fn per_minute(count: u64, duration: Duration) -> f64 {
+    round(count as f64 * 60.0 / duration.as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_ranges() -> anyhow::Result<()> {
+        let range = TimeRange::parse(None, "1h")?;
+        assert_eq!(range.start(), "now-1h");
+        assert_eq!(range.interval(), Duration::from_secs(120));
+        assert_eq!(TimeRange::parse(Some("15m"), "1h")?.interval(), Duration::from_secs(60));
+        assert_eq!(
+            TimeRange::parse(Some("7d"), "1h")?.interval(),
+            Duration::from_secs(336 * 60)
+        );
+        assert!(TimeRange::parse(Some("now-1h"), "1h").is_err());
+        assert!(TimeRange::parse(Some("0m"), "1h").is_err());
+
+        assert_eq!(per_minute(300, range.duration), 5.0);
+        Ok(())
+    }
+}
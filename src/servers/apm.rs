@@ -0,0 +1,536 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Tools on the data of Elastic APM: services, their throughput, transactions and errors.
+//!
+//! They run the aggregations of the APM UI on the `traces-apm*`, `metrics-apm*` and `logs-apm.error*` data streams
+//! and return compact summaries, rather than having the model rediscover the APM data model.
+
+use crate::servers::SubServer;
+use crate::servers::analytics::{TimeRange, buckets, per_minute, round};
+use crate::servers::elasticsearch::index_policy::IndexPolicy;
+use crate::servers::elasticsearch::{EsClientProvider, read_json, send};
+use elasticsearch::{Elasticsearch, SearchParts};
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::{Parameters, ToolRouter};
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+/// Maximum number of services listed.
+const MAX_SERVICES: usize = 500;
+
+/// Maximum number of transactions or error groups returned.
+const MAX_GROUPS: usize = 100;
+
+/// Data streams of the APM data of a cluster.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ApmConfig {
+    /// Transactions and spans (default: `traces-apm*`)
+    #[serde(default = "default_traces")]
+    pub traces: String,
+
+    /// Metrics aggregated by the APM server (default: `metrics-apm*`)
+    #[serde(default = "default_metrics")]
+    pub metrics: String,
+
+    /// Errors (default: `logs-apm.error*`)
+    #[serde(default = "default_errors")]
+    pub errors: String,
+}
+
+fn default_traces() -> String {
+    "traces-apm*".to_string()
+}
+
+fn default_metrics() -> String {
+    "metrics-apm*".to_string()
+}
+
+fn default_errors() -> String {
+    "logs-apm.error*".to_string()
+}
+
+impl Default for ApmConfig {
+    fn default() -> Self {
+        ApmConfig {
+            traces: default_traces(),
+            metrics: default_metrics(),
+            errors: default_errors(),
+        }
+    }
+}
+
+impl ApmConfig {
+    /// Check that the APM data streams can be accessed by tools.
+    pub fn check(&self, index_policy: &IndexPolicy) -> anyhow::Result<()> {
+        for indices in [&self.traces, &self.metrics, &self.errors] {
+            index_policy
+                .check_expression(indices)
+                .map_err(|reason| anyhow::anyhow!("APM data is denied by the index policy: {reason}"))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct ApmTools {
+    es_client: EsClientProvider,
+    config: ApmConfig,
+    tool_router: ToolRouter<ApmTools>,
+}
+
+impl ApmTools {
+    pub fn new(es_client: Elasticsearch, config: ApmConfig) -> Self {
+        ApmTools {
+            es_client: EsClientProvider::new(es_client),
+            config,
+            tool_router: Self::tool_router(),
+        }
+    }
+
+    async fn search(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        indices: &[&str],
+        body: Value,
+    ) -> Result<Value, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+        let response = send!(es_client.search(SearchParts::Index(indices)).body(body)).await;
+        read_json(response).await
+    }
+}
+
+impl SubServer for ApmTools {
+    fn router(&self) -> &ToolRouter<Self> {
+        &self.tool_router
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ListServicesParams {
+    /// Only list the services of this environment, e.g. `production`
+    environment: Option<String>,
+
+    /// Time range to look at, back from now, like `15m`, `1h` or `7d` (default: `1h`)
+    time_range: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ServiceThroughputParams {
+    /// Service name, as returned by list_services
+    service: String,
+
+    /// Only count the transactions of this environment, e.g. `production`
+    environment: Option<String>,
+
+    /// Time range to look at, back from now, like `15m`, `1h` or `7d` (default: `1h`)
+    time_range: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct TopTransactionsParams {
+    /// Service name, as returned by list_services
+    service: String,
+
+    /// Only count the transactions of this environment, e.g. `production`
+    environment: Option<String>,
+
+    /// Time range to look at, back from now, like `15m`, `1h` or `7d` (default: `1h`)
+    time_range: Option<String>,
+
+    /// How transactions are ranked (default: `impact`)
+    #[serde(default)]
+    sort_by: TransactionOrder,
+
+    /// Maximum number of transactions, at most 100 (default: 10)
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum TransactionOrder {
+    /// Share of the total time spent in the service: throughput times latency
+    #[default]
+    Impact,
+    /// Average latency
+    Latency,
+    /// Number of transactions
+    Throughput,
+    /// Number of failed transactions
+    Failures,
+}
+
+impl TransactionOrder {
+    fn order(self) -> Value {
+        match self {
+            TransactionOrder::Impact => json!({ "total_duration": "desc" }),
+            TransactionOrder::Latency => json!({ "latency": "desc" }),
+            TransactionOrder::Throughput => json!({ "_count": "desc" }),
+            TransactionOrder::Failures => json!({ "failures": "desc" }),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ErrorGroupsParams {
+    /// Service name, as returned by list_services. If omitted, errors of all services are grouped
+    service: Option<String>,
+
+    /// Only count the errors of this environment, e.g. `production`
+    environment: Option<String>,
+
+    /// Time range to look at, back from now, like `15m`, `1h` or `7d` (default: `1h`)
+    time_range: Option<String>,
+
+    /// Maximum number of error groups, at most 100 (default: 10)
+    limit: Option<usize>,
+}
+
+#[tool_router]
+impl ApmTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: list APM services
+    #[tool(
+        description = "List the services instrumented with Elastic APM that sent data in a time range, with their \
+            environments, agent and when they were last seen",
+        annotations(title = "List APM services", read_only_hint = true)
+    )]
+    async fn list_services(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(ListServicesParams {
+            environment,
+            time_range,
+        }): Parameters<ListServicesParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let range = TimeRange::parse(time_range.as_deref(), "1h")?;
+        let mut filters = filters(&range, None, environment.as_deref());
+        filters.push(json!({ "terms": { "processor.event": ["transaction", "metric"] } }));
+
+        let body = json!({
+            "size": 0,
+            "query": { "bool": { "filter": filters } },
+            "aggs": {
+                "services": {
+                    "terms": { "field": "service.name", "size": MAX_SERVICES },
+                    "aggs": {
+                        "environments": { "terms": { "field": "service.environment", "size": 10 } },
+                        "agent": { "terms": { "field": "agent.name", "size": 1 } },
+                        "last_seen": { "max": { "field": "@timestamp" } }
+                    }
+                }
+            }
+        });
+        let response = self
+            .search(
+                req_ctx,
+                &[self.config.traces.as_str(), self.config.metrics.as_str()],
+                body,
+            )
+            .await?;
+
+        let services = buckets(&response, "/aggregations/services/buckets")
+            .map(|bucket| {
+                json!({
+                    "service": bucket["key"],
+                    "environments": buckets(bucket, "/environments/buckets").map(|b| &b["key"]).collect::<Vec<_>>(),
+                    "agent": buckets(bucket, "/agent/buckets").next().map(|b| &b["key"]),
+                    "last_seen": bucket.pointer("/last_seen/value_as_string"),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!(
+                "{} services sent data in the last {}:",
+                services.len(),
+                range.span
+            )),
+            Content::json(services)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: service throughput
+    #[tool(
+        description = "Get the throughput (transactions per minute), failure rate and latency of an APM service \
+            over a time range, overall and as a timeline, to spot traffic drops, spikes and slowdowns",
+        annotations(title = "Get the throughput of an APM service", read_only_hint = true)
+    )]
+    async fn service_throughput(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(ServiceThroughputParams {
+            service,
+            environment,
+            time_range,
+        }): Parameters<ServiceThroughputParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let range = TimeRange::parse(time_range.as_deref(), "1h")?;
+        let interval = range.interval();
+        let body = json!({
+            "size": 0,
+            "track_total_hits": true,
+            "query": { "bool": { "filter": transaction_filters(&range, &service, environment.as_deref()) } },
+            "aggs": {
+                "failures": { "filter": { "term": { "event.outcome": "failure" } } },
+                "latency": { "avg": { "field": "transaction.duration.us" } },
+                "p95": { "percentiles": { "field": "transaction.duration.us", "percents": [95] } },
+                "timeline": {
+                    "date_histogram": range.timeline(),
+                    "aggs": {
+                        "failures": { "filter": { "term": { "event.outcome": "failure" } } },
+                        "latency": { "avg": { "field": "transaction.duration.us" } }
+                    }
+                }
+            }
+        });
+        let response = self.search(req_ctx, &[self.config.traces.as_str()], body).await?;
+
+        let count = response
+            .pointer("/hits/total/value")
+            .and_then(Value::as_u64)
+            .unwrap_or_default();
+        let aggs = &response["aggregations"];
+        let timeline = buckets(aggs, "/timeline/buckets")
+            .map(|bucket| {
+                let count = bucket["doc_count"].as_u64().unwrap_or_default();
+                json!({
+                    "time": bucket["key_as_string"],
+                    "per_minute": per_minute(count, interval),
+                    "failure_rate": failure_rate(bucket, count),
+                    "avg_latency_ms": millis(&bucket["latency"]["value"]),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let summary = json!({
+            "service": service,
+            "transactions": count,
+            "per_minute": per_minute(count, range.duration),
+            "failure_rate": failure_rate(aggs, count),
+            "avg_latency_ms": millis(&aggs["latency"]["value"]),
+            "p95_latency_ms": millis(&aggs["p95"]["values"]["95.0"]),
+            "interval": format!("{}s", interval.as_secs()),
+            "timeline": timeline,
+        });
+        Ok(CallToolResult::success(vec![
+            Content::text(format!(
+                "Service '{service}' had {count} transactions in the last {}:",
+                range.span
+            )),
+            Content::json(summary)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: top transactions
+    #[tool(
+        description = "List the top transactions of an APM service, e.g. endpoints or background jobs, with their \
+            throughput, latency, failure rate and impact (share of the time spent in the service). Ranked by \
+            impact by default",
+        annotations(title = "List the top transactions of an APM service", read_only_hint = true)
+    )]
+    async fn top_transactions(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(TopTransactionsParams {
+            service,
+            environment,
+            time_range,
+            sort_by,
+            limit,
+        }): Parameters<TopTransactionsParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let range = TimeRange::parse(time_range.as_deref(), "1h")?;
+        let body = json!({
+            "size": 0,
+            "query": { "bool": { "filter": transaction_filters(&range, &service, environment.as_deref()) } },
+            "aggs": {
+                "total_duration": { "sum": { "field": "transaction.duration.us" } },
+                "transactions": {
+                    "terms": {
+                        "field": "transaction.name",
+                        "size": limit.unwrap_or(10).min(MAX_GROUPS),
+                        "order": sort_by.order()
+                    },
+                    "aggs": {
+                        "type": { "terms": { "field": "transaction.type", "size": 1 } },
+                        "total_duration": { "sum": { "field": "transaction.duration.us" } },
+                        "latency": { "avg": { "field": "transaction.duration.us" } },
+                        "p95": { "percentiles": { "field": "transaction.duration.us", "percents": [95] } },
+                        "failures": { "filter": { "term": { "event.outcome": "failure" } } }
+                    }
+                }
+            }
+        });
+        let response = self.search(req_ctx, &[self.config.traces.as_str()], body).await?;
+
+        let aggs = &response["aggregations"];
+        let total_duration = aggs["total_duration"]["value"].as_f64().unwrap_or_default();
+        let transactions = buckets(aggs, "/transactions/buckets")
+            .map(|bucket| {
+                let count = bucket["doc_count"].as_u64().unwrap_or_default();
+                let duration = bucket["total_duration"]["value"].as_f64().unwrap_or_default();
+                json!({
+                    "name": bucket["key"],
+                    "type": buckets(bucket, "/type/buckets").next().map(|b| &b["key"]),
+                    "count": count,
+                    "per_minute": per_minute(count, range.duration),
+                    "avg_latency_ms": millis(&bucket["latency"]["value"]),
+                    "p95_latency_ms": millis(&bucket["p95"]["values"]["95.0"]),
+                    "failure_rate": failure_rate(bucket, count),
+                    "impact_percent": (total_duration > 0.0).then(|| round(duration / total_duration * 100.0)),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!(
+                "Top {} transactions of service '{service}' in the last {}:",
+                transactions.len(),
+                range.span
+            )),
+            Content::json(transactions)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: error groups
+    #[tool(
+        description = "List the most frequent APM error groups (errors with the same grouping key), with a sample \
+            message, exception type, culprit, service, number of occurrences and when they were last seen",
+        annotations(title = "List APM error groups", read_only_hint = true)
+    )]
+    async fn error_groups(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(ErrorGroupsParams {
+            service,
+            environment,
+            time_range,
+            limit,
+        }): Parameters<ErrorGroupsParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let range = TimeRange::parse(time_range.as_deref(), "1h")?;
+        let body = json!({
+            "size": 0,
+            "track_total_hits": true,
+            "query": { "bool": { "filter": filters(&range, service.as_deref(), environment.as_deref()) } },
+            "aggs": {
+                "groups": {
+                    "terms": { "field": "error.grouping_key", "size": limit.unwrap_or(10).min(MAX_GROUPS) },
+                    "aggs": {
+                        "last_seen": { "max": { "field": "@timestamp" } },
+                        "sample": {
+                            "top_hits": {
+                                "size": 1,
+                                "sort": [{ "@timestamp": "desc" }],
+                                "_source": [
+                                    "service.name",
+                                    "error.grouping_name",
+                                    "error.culprit",
+                                    "error.exception.type",
+                                    "error.exception.message",
+                                    "error.log.message"
+                                ]
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        let response = self.search(req_ctx, &[self.config.errors.as_str()], body).await?;
+
+        let count = response
+            .pointer("/hits/total/value")
+            .and_then(Value::as_u64)
+            .unwrap_or_default();
+        let groups = buckets(&response, "/aggregations/groups/buckets")
+            .map(|bucket| {
+                let error = bucket.pointer("/sample/hits/hits/0/_source").unwrap_or(&Value::Null);
+                let message = error
+                    .pointer("/error/grouping_name")
+                    .or_else(|| error.pointer("/error/exception/0/message"))
+                    .or_else(|| error.pointer("/error/log/message"));
+                json!({
+                    "grouping_key": bucket["key"],
+                    "message": message,
+                    "type": error.pointer("/error/exception/0/type"),
+                    "culprit": error.pointer("/error/culprit"),
+                    "service": error.pointer("/service/name"),
+                    "count": bucket["doc_count"],
+                    "last_seen": bucket.pointer("/last_seen/value_as_string"),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!(
+                "{count} errors in the last {}, top {} groups:",
+                range.span,
+                groups.len()
+            )),
+            Content::json(groups)?,
+        ]))
+    }
+}
+
+/// Filters on the time range, and optionally on a service and environment.
+fn filters(range: &TimeRange, service: Option<&str>, environment: Option<&str>) -> Vec<Value> {
+    let mut filters = vec![range.filter()];
+    if let Some(service) = service {
+        filters.push(json!({ "term": { "service.name": service } }));
+    }
+    if let Some(environment) = environment {
+        filters.push(json!({ "term": { "service.environment": environment } }));
+    }
+    filters
+}
+
+fn transaction_filters(range: &TimeRange, service: &str, environment: Option<&str>) -> Vec<Value> {
+    let mut filters = filters(range, Some(service), environment);
+    filters.push(json!({ "term": { "processor.event": "transaction" } }));
+    filters
+}
+
+/// Microseconds to milliseconds.
+fn millis(micros: &Value) -> Option<f64> {
+    micros.as_f64().map(|us| round(us / 1000.0))
+}
+
+/// Failure rate of the transactions of a bucket with a `failures` filter aggregation, in percent.
+fn failure_rate(bucket: &Value, count: u64) -> Option<f64> {
+    let failures = bucket["failures"]["doc_count"].as_u64()?;
+    (count > 0).then(|| round(failures as f64 * 100.0 / count as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates() {
+        assert_eq!(millis(&json!(1234567.0)), Some(1234.6));
+        assert_eq!(failure_rate(&json!({ "failures": { "doc_count": 1 } }), 3), Some(33.3));
+        assert_eq!(failure_rate(&json!({ "failures": { "doc_count": 0 } }), 0), None);
+    }
+}
@@ -0,0 +1,294 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Aggregations built from a flat description of groups and metrics, with results as a list of rows.
+
+use indexmap::IndexMap;
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+
+/// Maximum number of groups (rows) an aggregation can return.
+pub const MAX_BUCKETS: u64 = 1000;
+
+/// Default number of groups per group-by field.
+pub const DEFAULT_GROUP_SIZE: u32 = 10;
+
+/// Calendar intervals of date histograms. Other intervals, such as `30m` or `7d`, are fixed intervals.
+const CALENDAR_INTERVALS: &[&str] = &[
+    "minute", "1m", "hour", "1h", "day", "1d", "week", "1w", "month", "1M", "quarter", "1q", "year", "1y",
+];
+
+#[derive(Debug, Clone, Copy, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricFunction {
+    Avg,
+    Sum,
+    Min,
+    Max,
+    /// Approximate count of distinct values
+    Cardinality,
+    /// Count of values
+    ValueCount,
+}
+
+impl MetricFunction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MetricFunction::Avg => "avg",
+            MetricFunction::Sum => "sum",
+            MetricFunction::Min => "min",
+            MetricFunction::Max => "max",
+            MetricFunction::Cardinality => "cardinality",
+            MetricFunction::ValueCount => "value_count",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct Metric {
+    /// Metric function
+    pub function: MetricFunction,
+
+    /// Field to compute the metric on
+    pub field: String,
+}
+
+impl Metric {
+    /// Name of the metric in the aggregation and in the result rows, e.g. `avg_price`.
+    fn name(&self) -> String {
+        format!("{}_{}", self.function.as_str(), self.field)
+    }
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct DateHistogram {
+    /// Date field
+    pub field: String,
+
+    /// Interval of the histogram: a calendar interval (`minute`, `hour`, `day`, `week`, `month`, `quarter`,
+    /// `year`) or a fixed interval such as `30m`, `12h` or `7d`.
+    pub interval: String,
+}
+
+/// A level of bucket aggregations, and the row key of its buckets.
+struct Level {
+    name: String,
+    key: String,
+}
+
+/// An aggregation request: a date histogram, then terms groups from outermost to innermost, and the
+/// metrics computed in the innermost groups.
+pub struct Aggregation<'a> {
+    pub date_histogram: Option<&'a DateHistogram>,
+    pub group_by: &'a [String],
+    pub group_size: u32,
+    pub metrics: &'a [Metric],
+}
+
+impl Aggregation<'_> {
+    /// Maximum number of groups of the terms aggregations. The number of date histogram buckets is only
+    /// known from the results.
+    pub fn max_groups(&self) -> u64 {
+        (self.group_size as u64).saturating_pow(self.group_by.len() as u32)
+    }
+
+    fn levels(&self) -> Vec<Level> {
+        let histogram = self.date_histogram.map(|h| Level {
+            name: "date_histogram".to_string(),
+            key: h.field.clone(),
+        });
+        let groups = self.group_by.iter().enumerate().map(|(i, field)| Level {
+            name: format!("group_{i}"),
+            key: field.clone(),
+        });
+        histogram.into_iter().chain(groups).collect()
+    }
+
+    /// The `aggs` of the search request.
+    pub fn body(&self) -> Map<String, Value> {
+        let metrics = self
+            .metrics
+            .iter()
+            .map(|m| (m.name(), json!({ m.function.as_str(): { "field": m.field } })))
+            .collect::<Map<_, _>>();
+
+        let histogram = self.date_histogram.map(|h| {
+            let interval_kind = if CALENDAR_INTERVALS.contains(&h.interval.as_str()) {
+                "calendar_interval"
+            } else {
+                "fixed_interval"
+            };
+            json!({ "date_histogram": { "field": h.field, interval_kind: h.interval } })
+        });
+        let groups = self
+            .group_by
+            .iter()
+            .map(|field| json!({ "terms": { "field": field, "size": self.group_size } }));
+        let bucket_aggs = histogram.into_iter().chain(groups).collect::<Vec<_>>();
+
+        // Nest bucket aggregations, starting from the innermost one
+        self.levels()
+            .iter()
+            .zip(bucket_aggs)
+            .rev()
+            .fold(metrics, |inner, (level, agg)| nest(&level.name, agg, inner))
+    }
+
+    /// Flatten the `aggregations` of a search response into rows with the group keys, the document
+    /// count and the metrics. `total_docs` is the document count when there are no groups.
+    pub fn rows(&self, aggregations: &Value, total_docs: u64) -> Vec<IndexMap<String, Value>> {
+        let mut rows = Vec::new();
+        self.add_rows(&self.levels(), aggregations, IndexMap::new(), total_docs, &mut rows);
+        rows
+    }
+
+    fn add_rows(
+        &self,
+        levels: &[Level],
+        aggs: &Value,
+        mut row: IndexMap<String, Value>,
+        doc_count: u64,
+        rows: &mut Vec<IndexMap<String, Value>>,
+    ) {
+        let Some((level, inner_levels)) = levels.split_first() else {
+            row.insert("doc_count".to_string(), doc_count.into());
+            for metric in self.metrics {
+                let name = metric.name();
+                let value = aggs[&name]["value"].clone();
+                row.insert(name, value);
+            }
+            rows.push(row);
+            return;
+        };
+
+        let buckets = aggs[&level.name]["buckets"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        for bucket in buckets {
+            let mut row = row.clone();
+            let key = bucket.get("key_as_string").unwrap_or(&bucket["key"]);
+            row.insert(level.key.clone(), key.clone());
+            let doc_count = bucket["doc_count"].as_u64().unwrap_or_default();
+            self.add_rows(inner_levels, bucket, row, doc_count, rows);
+        }
+    }
+}
+
+fn nest(name: &str, mut agg: Value, inner: Map<String, Value>) -> Map<String, Value> {
+    if !inner.is_empty() {
+        agg["aggs"] = Value::Object(inner);
+    }
+    Map::from_iter([(name.to_string(), agg)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics() -> Vec<Metric> {
+        vec![Metric {
+            function: MetricFunction::Avg,
+            field: "price".to_string(),
+        }]
+    }
+
+    #[test]
+    fn build_body() {
+        let histogram = DateHistogram {
+            field: "@timestamp".to_string(),
+            interval: "day".to_string(),
+        };
+        let group_by = vec!["status".to_string(), "host".to_string()];
+        let metrics = metrics();
+        let agg = Aggregation {
+            date_histogram: Some(&histogram),
+            group_by: &group_by,
+            group_size: 5,
+            metrics: &metrics,
+        };
+        assert_eq!(agg.max_groups(), 25);
+        assert_eq!(
+            Value::Object(agg.body()),
+            json!({ "date_histogram": {
+                "date_histogram": { "field": "@timestamp", "calendar_interval": "day" },
+                "aggs": { "group_0": {
+                    "terms": { "field": "status", "size": 5 },
+                    "aggs": { "group_1": {
+                        "terms": { "field": "host", "size": 5 },
+                        "aggs": { "avg_price": { "avg": { "field": "price" } } }
+                    }}
+                }}
+            }})
+        );
+
+        let histogram = DateHistogram {
+            field: "@timestamp".to_string(),
+            interval: "30m".to_string(),
+        };
+        let agg = Aggregation {
+            date_histogram: Some(&histogram),
+            group_by: &[],
+            group_size: 5,
+            metrics: &[],
+        };
+        assert_eq!(agg.max_groups(), 1);
+        assert_eq!(
+            Value::Object(agg.body()),
+            json!({ "date_histogram": { "date_histogram": { "field": "@timestamp", "fixed_interval": "30m" } } })
+        );
+    }
+
+    #[test]
+    fn flatten_rows() {
+        let group_by = vec!["status".to_string()];
+        let metrics = metrics();
+        let agg = Aggregation {
+            date_histogram: None,
+            group_by: &group_by,
+            group_size: 10,
+            metrics: &metrics,
+        };
+        let rows = agg.rows(
+            &json!({ "group_0": { "buckets": [
+                { "key": "ok", "doc_count": 10, "avg_price": { "value": 1.5 } },
+                { "key": "ko", "doc_count": 2, "avg_price": { "value": null } },
+            ]}}),
+            12,
+        );
+        assert_eq!(
+            serde_json::to_value(rows).unwrap(),
+            json!([
+                { "status": "ok", "doc_count": 10, "avg_price": 1.5 },
+                { "status": "ko", "doc_count": 2, "avg_price": null },
+            ])
+        );
+
+        // No groups: a single row
+        let agg = Aggregation {
+            date_histogram: None,
+            group_by: &[],
+            group_size: 10,
+            metrics: &metrics,
+        };
+        let rows = agg.rows(&json!({ "avg_price": { "value": 3.0 } }), 12);
+        assert_eq!(
+            serde_json::to_value(rows).unwrap(),
+            json!([{ "doc_count": 12, "avg_price": 3.0 }])
+        );
+    }
+}
@@ -0,0 +1,602 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Advice on the text analysis configuration of a field, from its mapping, the analysis settings of its index,
+//! and sample values.
+//!
+//! Languages are detected with frequent words and writing scripts. This is rough, but enough to spot an
+//! analyzer that doesn't match the data.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Built-in language analyzers, named after their language.
+const LANGUAGE_ANALYZERS: &[&str] = &[
+    "arabic",
+    "armenian",
+    "basque",
+    "bengali",
+    "brazilian",
+    "bulgarian",
+    "catalan",
+    "cjk",
+    "czech",
+    "danish",
+    "dutch",
+    "english",
+    "estonian",
+    "finnish",
+    "french",
+    "galician",
+    "german",
+    "greek",
+    "hindi",
+    "hungarian",
+    "indonesian",
+    "irish",
+    "italian",
+    "latvian",
+    "lithuanian",
+    "norwegian",
+    "persian",
+    "portuguese",
+    "romanian",
+    "russian",
+    "serbian",
+    "sorani",
+    "spanish",
+    "swedish",
+    "thai",
+    "turkish",
+];
+
+/// Built-in analyzers that don't stem words, and that a language analyzer can replace.
+const GENERIC_ANALYZERS: &[&str] = &["standard", "simple", "whitespace", "stop"];
+
+/// Frequent words of the languages detected from words.
+const FREQUENT_WORDS: &[(&str, &[&str])] = &[
+    (
+        "english",
+        &[
+            "the", "and", "of", "to", "is", "with", "for", "this", "that", "are", "it", "was",
+        ],
+    ),
+    (
+        "french",
+        &[
+            "le", "la", "les", "et", "des", "du", "est", "une", "pour", "dans", "avec", "sur",
+        ],
+    ),
+    (
+        "german",
+        &[
+            "der", "die", "das", "und", "ist", "mit", "für", "ein", "eine", "nicht", "auf", "den",
+        ],
+    ),
+    (
+        "spanish",
+        &[
+            "el", "los", "las", "y", "es", "una", "por", "con", "para", "del", "al", "como",
+        ],
+    ),
+    (
+        "italian",
+        &[
+            "il", "gli", "di", "che", "per", "una", "sono", "della", "non", "è", "nel", "anche",
+        ],
+    ),
+    (
+        "portuguese",
+        &[
+            "o", "os", "do", "da", "uma", "com", "não", "em", "ao", "dos", "mais", "é",
+        ],
+    ),
+    (
+        "dutch",
+        &[
+            "de", "het", "een", "en", "van", "niet", "met", "voor", "op", "zijn", "dat", "ook",
+        ],
+    ),
+];
+
+/// A writing script, with the language or group of languages whose analyzer handles it, and its character ranges.
+type Script = (&'static str, &'static [(char, char)]);
+
+const SCRIPTS: &[Script] = &[
+    (
+        "cjk",
+        &[
+            ('\u{3040}', '\u{30FF}'),
+            ('\u{4E00}', '\u{9FFF}'),
+            ('\u{AC00}', '\u{D7AF}'),
+        ],
+    ),
+    ("russian", &[('\u{0400}', '\u{04FF}')]),
+    ("arabic", &[('\u{0600}', '\u{06FF}')]),
+    ("greek", &[('\u{0370}', '\u{03FF}')]),
+    ("hindi", &[('\u{0900}', '\u{097F}')]),
+    ("thai", &[('\u{0E00}', '\u{0E7F}')]),
+];
+
+/// Keyword values longer than this may exceed the maximum term size of 32766 bytes in UTF-8.
+const MAX_SAFE_IGNORE_ABOVE: u64 = 8191;
+
+/// Maximum length of values that are likely to be used for exact matches, sorting or aggregations.
+const MAX_KEYWORD_LENGTH: usize = 256;
+
+#[derive(Debug, Serialize)]
+pub struct Advice {
+    field: String,
+    #[serde(rename = "type")]
+    field_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    analyzer: Option<String>,
+    /// Definition of a custom analyzer: tokenizer and filters
+    #[serde(skip_serializing_if = "Option::is_none")]
+    analyzer_chain: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    search_analyzer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ignore_above: Option<u64>,
+    /// Multi-fields and their type
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    subfields: BTreeMap<String, String>,
+    /// Number of sample values
+    samples: usize,
+    /// Number of sample values per detected language
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    languages: BTreeMap<&'static str, usize>,
+    pub issues: Vec<Issue>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Issue {
+    problem: String,
+    recommendation: String,
+}
+
+impl Issue {
+    fn new(problem: String, recommendation: String) -> Self {
+        Issue {
+            problem,
+            recommendation,
+        }
+    }
+}
+
+/// Check the configuration of a field, given its mapping (e.g. `{ "type": "text", "analyzer": "english" }`), the
+/// `analysis` settings of its index, and sample values.
+pub fn advise(field: &str, mapping: &Value, analysis: &Value, samples: &[String]) -> Advice {
+    let field_type = mapping["type"].as_str().unwrap_or("object");
+    let subfields = mapping["fields"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(name, sub)| (name.clone(), sub["type"].as_str().unwrap_or("object").to_string()))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut languages = BTreeMap::new();
+    for sample in samples {
+        if let Some(language) = detect_language(sample) {
+            *languages.entry(language).or_insert(0) += 1;
+        }
+    }
+
+    let mut advice = Advice {
+        field: field.to_string(),
+        field_type: field_type.to_string(),
+        analyzer: None,
+        analyzer_chain: None,
+        search_analyzer: mapping["search_analyzer"].as_str().map(str::to_string),
+        ignore_above: mapping["ignore_above"].as_u64(),
+        subfields,
+        samples: samples.len(),
+        languages,
+        issues: Vec::new(),
+    };
+
+    match field_type {
+        "text" | "match_only_text" => check_text(&mut advice, mapping, analysis, samples),
+        "keyword" => check_long_keywords(&mut advice, samples),
+        _ => {}
+    }
+
+    // Ignored values of the field and its keyword subfields
+    let keyword_fields = std::iter::once((field.to_string(), mapping))
+        .chain(
+            mapping["fields"]
+                .as_object()
+                .into_iter()
+                .flatten()
+                .map(|(name, sub)| (format!("{field}.{name}"), sub)),
+        )
+        .filter(|(_, mapping)| mapping["type"] == "keyword");
+    for (name, mapping) in keyword_fields {
+        if let Some(ignore_above) = mapping["ignore_above"].as_u64() {
+            check_ignore_above(&mut advice.issues, &name, ignore_above, samples);
+        }
+    }
+
+    advice
+}
+
+fn check_text(advice: &mut Advice, mapping: &Value, analysis: &Value, samples: &[String]) {
+    let field = &advice.field;
+    // Without an explicit analyzer, the index default analyzer is used
+    let analyzer = match mapping["analyzer"].as_str() {
+        Some(analyzer) => analyzer,
+        None if analysis["analyzer"]["default"].is_object() => "default",
+        None => "standard",
+    };
+    let index_language = analyzer_language(analyzer, analysis);
+    advice.analyzer = Some(analyzer.to_string());
+    advice.analyzer_chain = analysis["analyzer"].get(analyzer).cloned();
+
+    // Languages of the samples, most frequent first
+    let mut languages = advice.languages.iter().map(|(l, n)| (*l, *n)).collect::<Vec<_>>();
+    languages.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    let detected = languages.iter().map(|(_, count)| count).sum::<usize>();
+
+    match languages.as_slice() {
+        [_, (_, second), ..] if detected >= 5 && second * 5 >= detected => {
+            let list = languages
+                .iter()
+                .map(|(language, count)| format!("{language} ({count})"))
+                .collect::<Vec<_>>();
+            advice.issues.push(Issue::new(
+                format!("Sample values are in several languages: {}", list.join(", ")),
+                format!(
+                    "A single analyzer can't handle all languages. Index each language in its own subfield with \
+                    its language analyzer (e.g. `{field}.{}`) and search them with a multi_match query, or detect \
+                    the language in an ingest pipeline and copy values to per-language fields",
+                    languages[0].0
+                ),
+            ));
+        }
+        [(language, count), ..] if *count >= 2 && count * 2 > detected => {
+            let language = *language;
+            match &index_language {
+                Some(index_language) if index_language != language => {
+                    advice.issues.push(Issue::new(
+                        format!(
+                            "The '{analyzer}' analyzer is for {index_language}, but sample values are in {language}"
+                        ),
+                        format!(
+                            "Use the {language} analyzer. The analyzer of an existing field can't be changed: add a \
+                            `{field}.{language}` subfield with this analyzer and run update_by_query, or reindex \
+                            into a new index"
+                        ),
+                    ));
+                }
+                None if language == "cjk" => advice.issues.push(Issue::new(
+                    format!("The '{analyzer}' analyzer doesn't segment Chinese, Japanese or Korean words"),
+                    "Use the cjk analyzer, or the analyzers of the analysis-smartcn (Chinese), analysis-kuromoji \
+                    (Japanese) or analysis-nori (Korean) plugins"
+                        .to_string(),
+                )),
+                None if GENERIC_ANALYZERS.contains(&analyzer) => advice.issues.push(Issue::new(
+                    format!(
+                        "The '{analyzer}' analyzer doesn't stem {language} words: singular and plural forms, or \
+                        verb tenses, don't match each other"
+                    ),
+                    format!(
+                        "Add a `{field}.{language}` subfield with the {language} analyzer, and search both fields \
+                        with a multi_match query"
+                    ),
+                )),
+                _ => {}
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(search_analyzer) = &advice.search_analyzer {
+        let search_language = analyzer_language(search_analyzer, analysis);
+        if search_language != index_language {
+            let describe = |language: &Option<String>| language.clone().unwrap_or_else(|| "no language".to_string());
+            advice.issues.push(Issue::new(
+                format!(
+                    "The search analyzer '{search_analyzer}' ({}) and the index analyzer '{analyzer}' ({}) process \
+                    languages differently: query terms may not match indexed terms",
+                    describe(&search_language),
+                    describe(&index_language)
+                ),
+                "Use analyzers for the same language at index and search time".to_string(),
+            ));
+        }
+    }
+
+    // Short values are often used for exact matches, sorting and aggregations
+    let has_keyword = advice.subfields.values().any(|t| t == "keyword");
+    if !has_keyword && !samples.is_empty() && samples.iter().all(|s| s.chars().count() <= MAX_KEYWORD_LENGTH) {
+        advice.issues.push(Issue::new(
+            "No keyword subfield: the field can't be used for exact matches, sorting or aggregations".to_string(),
+            format!(
+                "Add a keyword subfield to the mapping, `\"fields\": {{ \"keyword\": {{ \"type\": \"keyword\", \
+                \"ignore_above\": {MAX_KEYWORD_LENGTH} }} }}`, then run update_by_query to index existing documents"
+            ),
+        ));
+    }
+}
+
+fn check_long_keywords(advice: &mut Advice, samples: &[String]) {
+    if samples.is_empty() {
+        return;
+    }
+    let words = samples.iter().map(|s| s.split_whitespace().count()).sum::<usize>() / samples.len();
+    if words > 8 {
+        advice.issues.push(Issue::new(
+            format!(
+                "Values are texts of {words} words on average, but '{}' is a keyword field: only exact matches of \
+                whole values work",
+                advice.field
+            ),
+            "Map it as a text field with a language analyzer, and a keyword subfield if exact matches or \
+            aggregations are needed"
+                .to_string(),
+        ));
+    }
+}
+
+fn check_ignore_above(issues: &mut Vec<Issue>, field: &str, ignore_above: u64, samples: &[String]) {
+    if ignore_above > MAX_SAFE_IGNORE_ABOVE {
+        issues.push(Issue::new(
+            format!(
+                "ignore_above of '{field}' is {ignore_above}: values with more than 32766 bytes in UTF-8 make \
+                documents fail to index, and long values create large terms"
+            ),
+            format!(
+                "Set ignore_above to at most {MAX_SAFE_IGNORE_ABOVE}, or lower (e.g. {MAX_KEYWORD_LENGTH}) if \
+                long values don't need exact matches"
+            ),
+        ));
+    }
+
+    let ignored = samples
+        .iter()
+        .filter(|s| s.chars().count() as u64 > ignore_above)
+        .count();
+    if ignored > 0 {
+        issues.push(Issue::new(
+            format!(
+                "{ignored} of {} sample values are longer than the ignore_above of '{field}' ({ignore_above}): \
+                they aren't indexed in this field and can't be matched, sorted or aggregated on it",
+                samples.len()
+            ),
+            format!(
+                "Increase ignore_above (at most {MAX_SAFE_IGNORE_ABOVE}) if these values need exact matches, or \
+                use a text field to search them"
+            ),
+        ));
+    }
+}
+
+/// Language of an analyzer: built-in language analyzers, and custom analyzers with language filters (stemmers,
+/// stop words, normalization).
+fn analyzer_language(analyzer: &str, analysis: &Value) -> Option<String> {
+    if let Some(language) = language_name(analyzer) {
+        return Some(language);
+    }
+
+    let definition = analysis["analyzer"].get(analyzer)?;
+    if definition["type"] != "custom" && definition["type"].is_string() {
+        // Configured built-in analyzer, e.g. `{ "type": "french", "stopwords": [...] }`
+        return definition["type"].as_str().and_then(language_name);
+    }
+
+    let filters = definition["filter"].as_array()?;
+    filters.iter().filter_map(Value::as_str).find_map(|filter| {
+        // Custom filters define the language in a parameter
+        match analysis["filter"].get(filter) {
+            Some(def) => ["language", "name", "stopwords", "locale"]
+                .iter()
+                .filter_map(|param| def[*param].as_str())
+                .find_map(language_name),
+            None => language_name(filter),
+        }
+    })
+}
+
+/// Language named by an analyzer, filter, or filter parameter, e.g. `light_french`, `_german_` or `porter_stem`.
+fn language_name(name: &str) -> Option<String> {
+    let name = name.to_lowercase();
+    if name.starts_with("porter") || name == "kstem" || name.starts_with("possessive_english") {
+        return Some("english".to_string());
+    }
+    let language = LANGUAGE_ANALYZERS.iter().find(|language| name.contains(*language))?;
+    // Brazilian is a variant of Portuguese
+    Some(
+        if *language == "brazilian" {
+            "portuguese"
+        } else {
+            language
+        }
+        .to_string(),
+    )
+}
+
+/// Language of a text, if it can be detected with confidence.
+pub fn detect_language(text: &str) -> Option<&'static str> {
+    let letters = text.chars().filter(|c| c.is_alphabetic()).collect::<Vec<_>>();
+    if letters.is_empty() {
+        return None;
+    }
+    for (language, ranges) in SCRIPTS {
+        let in_script = |c: &&char| ranges.iter().any(|(first, last)| (first..=last).contains(c));
+        if letters.iter().filter(in_script).count() * 2 > letters.len() {
+            return Some(language);
+        }
+    }
+
+    let words = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>();
+    let mut scores = FREQUENT_WORDS
+        .iter()
+        .map(|(language, frequent)| {
+            let score = words.iter().filter(|w| frequent.contains(&w.as_str())).count();
+            (*language, score)
+        })
+        .collect::<Vec<_>>();
+    scores.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+
+    match scores.as_slice() {
+        [(language, best), (_, second), ..] if *best >= 2 && best > second => Some(language),
+        _ => None,
+    }
+}
+
+/// String values of a field in a document source, with the field path in object or dotted notation.
+pub fn source_values(source: &Value, path: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    collect_values(source, path, &mut values);
+    values
+}
+
+fn collect_values(value: &Value, path: &str, values: &mut Vec<String>) {
+    match value {
+        Value::Array(items) => items.iter().for_each(|item| collect_values(item, path, values)),
+        Value::Object(obj) if !path.is_empty() => {
+            for (key, value) in obj {
+                if key == path {
+                    collect_values(value, "", values);
+                } else if let Some(rest) = path.strip_prefix(key.as_str()).and_then(|rest| rest.strip_prefix('.')) {
+                    collect_values(value, rest, values);
+                }
+            }
+        }
+        Value::String(s) if path.is_empty() => values.push(s.clone()),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn samples(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn detect_languages() {
+        assert_eq!(detect_language("The cat is sleeping on the sofa"), Some("english"));
+        assert_eq!(detect_language("Le chat dort sur le canapé"), Some("french"));
+        assert_eq!(
+            detect_language("Die Katze schläft auf dem Sofa und das ist gut"),
+            Some("german")
+        );
+        assert_eq!(detect_language("猫がソファで寝ている"), Some("cjk"));
+        assert_eq!(detect_language("Кошка спит на диване"), Some("russian"));
+        assert_eq!(detect_language("Sofa"), None);
+        assert_eq!(detect_language("42"), None);
+    }
+
+    #[test]
+    fn resolve_analyzer_language() {
+        let analysis = json!({
+            "analyzer": {
+                "fr_text": { "type": "custom", "tokenizer": "standard", "filter": ["lowercase", "fr_stemmer"] },
+                "de_text": { "type": "custom", "tokenizer": "standard", "filter": ["lowercase", "german_normalization"] },
+                "en_stop": { "type": "english", "stopwords": ["a"] },
+                "plain": { "type": "custom", "tokenizer": "standard", "filter": ["lowercase"] },
+            },
+            "filter": { "fr_stemmer": { "type": "stemmer", "language": "light_french" } },
+        });
+        assert_eq!(analyzer_language("fr_text", &analysis).as_deref(), Some("french"));
+        assert_eq!(analyzer_language("de_text", &analysis).as_deref(), Some("german"));
+        assert_eq!(analyzer_language("en_stop", &analysis).as_deref(), Some("english"));
+        assert_eq!(analyzer_language("brazilian", &analysis).as_deref(), Some("portuguese"));
+        assert_eq!(analyzer_language("plain", &analysis), None);
+        assert_eq!(analyzer_language("standard", &analysis), None);
+    }
+
+    #[test]
+    fn wrong_language_analyzer() {
+        let mapping = json!({ "type": "text", "analyzer": "english", "fields": { "raw": { "type": "keyword" } } });
+        let values = samples(&[
+            "La robe est en coton et la coupe est ajustée",
+            "Une veste pour les jours de pluie",
+            "Le pantalon est disponible dans les tailles S et M",
+        ]);
+        let advice = advise("description", &mapping, &json!({}), &values);
+        assert_eq!(advice.issues.len(), 1);
+        assert_eq!(
+            advice.issues[0].problem,
+            "The 'english' analyzer is for english, but sample values are in french"
+        );
+    }
+
+    #[test]
+    fn mixed_languages() {
+        let mapping = json!({ "type": "text", "fields": { "keyword": { "type": "keyword", "ignore_above": 256 } } });
+        let values = samples(&[
+            "The shirt is made of cotton",
+            "This jacket is for the rain",
+            "The trousers are blue and the belt is brown",
+            "La robe est en coton et la coupe est ajustée",
+            "Une veste pour les jours de pluie",
+        ]);
+        let advice = advise("description", &mapping, &json!({}), &values);
+        assert_eq!(advice.issues.len(), 1);
+        assert_eq!(
+            advice.issues[0].problem,
+            "Sample values are in several languages: english (3), french (2)"
+        );
+    }
+
+    #[test]
+    fn missing_keyword_and_huge_ignore_above() {
+        let mapping = json!({ "type": "text" });
+        let advice = advise("city", &mapping, &json!({}), &samples(&["Paris", "Berlin"]));
+        assert_eq!(advice.analyzer.as_deref(), Some("standard"));
+        assert_eq!(advice.issues.len(), 1);
+        assert!(advice.issues[0].problem.starts_with("No keyword subfield"));
+
+        let mapping = json!({ "type": "text", "fields": { "keyword": { "type": "keyword", "ignore_above": 10 } } });
+        let advice = advise(
+            "city",
+            &mapping,
+            &json!({}),
+            &samples(&["Paris", "Llanfairpwllgwyngyll"]),
+        );
+        assert_eq!(advice.issues.len(), 1);
+        assert!(
+            advice.issues[0]
+                .problem
+                .starts_with("1 of 2 sample values are longer than the ignore_above")
+        );
+
+        let mapping = json!({ "type": "keyword", "ignore_above": 100000 });
+        let advice = advise("url", &mapping, &json!({}), &samples(&["https://example.com"]));
+        assert_eq!(advice.issues.len(), 1);
+        assert!(advice.issues[0].problem.starts_with("ignore_above of 'url' is 100000"));
+    }
+
+    #[test]
+    fn values_of_source() {
+        let source = json!({ "product": { "name": "Shirt" }, "tags.name": ["a", "b"], "items": [{ "name": "x" }] });
+        assert_eq!(source_values(&source, "product.name"), vec!["Shirt"]);
+        assert_eq!(source_values(&source, "tags.name"), vec!["a", "b"]);
+        assert_eq!(source_values(&source, "items.name"), vec!["x"]);
+        assert!(source_values(&source, "product").is_empty());
+    }
+}
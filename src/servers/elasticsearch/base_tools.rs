@@ -15,14 +15,49 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::servers::elasticsearch::{EsClientProvider, read_json};
-use elasticsearch::cat::{CatIndicesParts, CatShardsParts};
-use elasticsearch::indices::IndicesGetMappingParts;
-use elasticsearch::{Elasticsearch, SearchParts};
+use crate::servers::elasticsearch::aggregate::{self, Aggregation};
+use crate::servers::elasticsearch::analysis;
+use crate::servers::elasticsearch::custom_tools;
+use crate::servers::elasticsearch::eql::EqlSearchResponse;
+use crate::servers::elasticsearch::error_hints;
+use crate::servers::elasticsearch::esql;
+use crate::servers::elasticsearch::folding;
+use crate::servers::elasticsearch::guardrails::Guardrails;
+use crate::servers::elasticsearch::ilm::{self, StoredPolicy};
+use crate::servers::elasticsearch::index_policy::IndexPolicy;
+use crate::servers::elasticsearch::index_templates::{ComponentTemplatesResponse, IndexTemplatesResponse, Templates};
+use crate::servers::elasticsearch::inference::{self, InferenceResult};
+use crate::servers::elasticsearch::ingest;
+use crate::servers::elasticsearch::mappings::MappingDiff;
+use crate::servers::elasticsearch::ndjson::{self, DocumentValidator, LineFailure};
+use crate::servers::elasticsearch::pit;
+use crate::servers::elasticsearch::profile::Profile;
+use crate::servers::elasticsearch::retry::RetryConfig;
+use crate::servers::elasticsearch::semantic;
+use crate::servers::elasticsearch::stats::{IndicesStatsResponse, latency_report};
+use crate::servers::elasticsearch::writes::{self, PlannedCall, RefreshPolicy, UpdateToken};
+use crate::servers::elasticsearch::{CustomTool, EsClient, EsClientProvider, internal_error, read_json, send};
+use crate::servers::{IncludeExclude, SubServer};
+use crate::utils::resource_store::{Owner, ResourceStore};
+use elasticsearch::cat::{CatAliasesParts, CatAllocationParts, CatIndicesParts, CatShardsParts, CatThreadPoolParts};
+use elasticsearch::cluster::ClusterGetComponentTemplateParts;
+use elasticsearch::eql::EqlSearchParts;
+use elasticsearch::http::request::JsonBody;
+use elasticsearch::http::{Method, StatusCode};
+use elasticsearch::ilm::{IlmExplainLifecycleParts, IlmGetLifecycleParts};
+use elasticsearch::indices::{
+    IndicesDiskUsageParts, IndicesGetFieldMappingParts, IndicesGetIndexTemplateParts, IndicesGetMappingParts,
+    IndicesGetSettingsParts, IndicesSimulateIndexTemplateParts, IndicesSimulateTemplateParts, IndicesStatsParts,
+    IndicesValidateQueryParts,
+};
+use elasticsearch::ingest::{IngestGetPipelineParts, IngestPutPipelineParts, IngestSimulateParts};
+use elasticsearch::{BulkParts, Elasticsearch, MsearchParts, SearchParts};
 use indexmap::IndexMap;
 use rmcp::handler::server::tool::{Parameters, ToolRouter};
 use rmcp::model::{
-    CallToolResult, Content, Implementation, JsonObject, ProtocolVersion, ServerCapabilities, ServerInfo,
+    CallToolResult, CompleteRequestParam, CompleteResult, CompletionInfo, Content, Implementation, JsonObject,
+    ListResourcesResult, PaginatedRequestParam, ProgressNotificationParam, ProtocolVersion, ReadResourceRequestParam,
+    ReadResourceResult, Resource, ResourceContents, ServerCapabilities, ServerInfo, Tool,
 };
 use rmcp::service::RequestContext;
 use rmcp::{RoleServer, ServerHandler};
@@ -30,12 +65,47 @@ use rmcp_macros::{tool, tool_handler, tool_router};
 use serde::{Deserialize, Serialize};
 use serde_aux::prelude::*;
 use serde_json::{Map, Value, json};
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::Instrument;
+
+/// How long the details of failures are kept as resources.
+const ERROR_RESOURCE_TTL: Duration = Duration::from_secs(30 * 60);
+const MAX_ERROR_RESOURCES: usize = 100;
+
+/// Number of documents read by each search of an export, and maximum number of exported documents.
+const EXPORT_BATCH_SIZE: usize = 1000;
+const MAX_EXPORTED_DOCS: usize = 100_000;
+
+/// Exports are kept in their own store, so that a few large exports don't push out the details of failures.
+/// Their size is bounded so that they can be imported again.
+const EXPORT_TTL: Duration = Duration::from_secs(30 * 60);
+const MAX_EXPORTS: usize = 10;
+const MAX_EXPORT_SIZE: usize = ndjson::MAX_IMPORT_SIZE as usize;
+
+/// Maximum number of searches in a multi-search request.
+const MAX_MSEARCH_SEARCHES: usize = 20;
+
+/// Tools that create server-side state, like point in times, without modifying data. They aren't read-only, so
+/// that their calls aren't retried, but they're available on read-only clusters.
+const SESSION_STATE_TOOLS: &[&str] = &["open_consistency_token"];
+
+/// Number of failures listed in the result of imports and tasks. All import failures are available as a resource.
+const MAX_REPORTED_FAILURES: usize = 10;
 
 #[derive(Clone)]
 pub struct EsBaseTools {
     es_client: EsClientProvider,
     tool_router: ToolRouter<EsBaseTools>,
+    resources: ResourceStore,
+    exports: ResourceStore,
+    import_root: Option<PathBuf>,
+    retry: RetryConfig,
+    index_policy: IndexPolicy,
+    guardrails: Guardrails,
+    dry_run: bool,
 }
 
 impl EsBaseTools {
@@ -43,7 +113,138 @@ impl EsBaseTools {
         Self {
             es_client: EsClientProvider::new(es_client),
             tool_router: Self::tool_router(),
+            resources: ResourceStore::new(ERROR_RESOURCE_TTL, MAX_ERROR_RESOURCES),
+            exports: ResourceStore::new(EXPORT_TTL, MAX_EXPORTS),
+            import_root: None,
+            retry: RetryConfig::default(),
+            index_policy: IndexPolicy::default(),
+            guardrails: Guardrails::default(),
+            dry_run: false,
+        }
+    }
+
+    /// Set the directory of the files that can be imported.
+    pub fn with_import_root(mut self, import_root: Option<PathBuf>) -> Self {
+        self.import_root = import_root;
+        self
+    }
+
+    /// Set the retries of the calls to read-only tools.
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Set the indices that tools can access.
+    pub fn with_index_policy(mut self, index_policy: IndexPolicy) -> Self {
+        self.index_policy = index_policy;
+        self
+    }
+
+    /// Set the guardrails on the cost of searches.
+    pub fn with_guardrails(mut self, guardrails: Guardrails) -> Self {
+        self.guardrails = guardrails;
+        self
+    }
+
+    /// Describe the API calls of write tools instead of making them, for all calls.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Use the resource stores of another server, so that their resources can be passed to the tools.
+    pub fn with_resources_of(mut self, other: &EsBaseTools) -> Self {
+        self.resources = other.resources.clone();
+        self.exports = other.exports.clone();
+        self
+    }
+
+    /// Definitions of the tools provided by this server.
+    pub fn tools(&self) -> Vec<Tool> {
+        self.tool_router.list_all()
+    }
+
+    /// Resources created by the tools, other than exports.
+    pub fn resources(&self) -> &ResourceStore {
+        &self.resources
+    }
+
+    /// Resources of a client, including exports.
+    pub fn list_stored_resources(&self, owner: &Owner) -> Vec<Resource> {
+        let mut resources = self.resources.list(owner);
+        resources.extend(self.exports.list(owner));
+        resources
+    }
+
+    /// Read a resource of a client, including exports.
+    pub fn read_stored_resource(&self, owner: &Owner, uri: &str) -> Option<ResourceContents> {
+        self.resources
+            .read(owner, uri)
+            .or_else(|| self.exports.read(owner, uri))
+    }
+
+    /// Retries of the calls to read-only tools.
+    pub fn retry(&self) -> &RetryConfig {
+        &self.retry
+    }
+
+    /// Indices that tools can access.
+    pub fn index_policy(&self) -> &IndexPolicy {
+        &self.index_policy
+    }
+
+    /// Guardrails on the cost of searches.
+    pub fn guardrails(&self) -> &Guardrails {
+        &self.guardrails
+    }
+
+    /// Does a tool only read data? Calls to other tools are never retried, as they may have been partially applied.
+    pub fn is_read_only(&self, name: &str) -> bool {
+        self.tool_router
+            .map
+            .get(name)
+            .and_then(|route| route.attr.annotations.as_ref())
+            .and_then(|a| a.read_only_hint)
+            == Some(true)
+    }
+
+    /// Remove the tools that modify data, i.e. that don't have a read-only hint.
+    pub fn read_only(mut self) -> Self {
+        self.tool_router.map.retain(|name, route| {
+            route.attr.annotations.as_ref().and_then(|a| a.read_only_hint) == Some(true)
+                || SESSION_STATE_TOOLS.contains(&name.as_ref())
+        });
+        self
+    }
+
+    /// Keep only the tools selected by an include or exclude list.
+    pub fn filter_tools(mut self, incl_excl: &IncludeExclude) -> Self {
+        self.tool_router.map.retain(|name, _| incl_excl.is_included(name));
+        self
+    }
+
+    /// Add tools defined in the configuration. They must have been checked with [`CustomTool::check`].
+    pub fn with_custom_tools(mut self, tools: HashMap<String, CustomTool>) -> anyhow::Result<Self> {
+        for route in custom_tools::routes(tools) {
+            if self.tool_router.has_route(route.name()) {
+                anyhow::bail!("Custom tool '{}' has the name of a built-in tool", route.name());
+            }
+            self.tool_router.add_route(route);
+        }
+        Ok(self)
+    }
+
+    /// Add the tools of a sub-server of the cluster, like its Kibana instance.
+    pub fn with_sub_server(mut self, server: impl SubServer) -> Self {
+        for route in server.routes() {
+            self.tool_router.add_route(route);
         }
+        self
+    }
+
+    pub(super) fn es_client(&self, context: RequestContext<RoleServer>) -> EsClient {
+        self.es_client.get(context)
     }
 }
 
@@ -59,6 +260,129 @@ struct GetMappingsParams {
     index: String,
 }
 
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct CompareMappingsParams {
+    /// Name of the reference Elasticsearch index
+    index: String,
+
+    /// Name of the index to compare with (provide either this or `index_template`)
+    other_index: Option<String>,
+
+    /// Name of the index template to compare with (provide either this or `other_index`)
+    index_template: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct SimulateIndexTemplateParams {
+    /// Name of the index to create
+    index: String,
+
+    /// Proposed index template (with `index_patterns`, `priority`, `composed_of`, `template`...) to simulate as if
+    /// it was stored. If omitted, only the stored templates are used
+    index_template: Option<Map<String, Value>>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ListIndexTemplatesParams {
+    /// Name or wildcard pattern of index and component templates. If omitted, all templates except those managed
+    /// by Elastic are returned
+    name: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct GetIndexTemplateParams {
+    /// Name or wildcard pattern of index or component templates
+    name: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct GetIlmPoliciesParams {
+    /// Name of the ILM policy. If omitted, all policies except those managed by Elastic are returned
+    policy: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ExplainIlmParams {
+    /// Name or pattern of the Elasticsearch indices
+    index: String,
+
+    /// Only return indices whose lifecycle is in error (default: false)
+    #[serde(default)]
+    only_errors: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ListIngestPipelinesParams {
+    /// Name or wildcard pattern of ingest pipelines. If omitted, all pipelines except those managed by Elastic
+    /// are returned
+    pipeline: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct SimulateIngestPipelineParams {
+    /// Name of a stored ingest pipeline to simulate
+    pipeline: Option<String>,
+
+    /// Pipeline definition (with `processors`, and optionally `on_failure`) to simulate instead of a stored
+    /// pipeline
+    definition: Option<Map<String, Value>>,
+
+    /// Sample documents to run through the pipeline
+    docs: Vec<Map<String, Value>>,
+
+    /// Report the document after each processor, to find which one fails or changes a field (default: false)
+    #[serde(default)]
+    verbose: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct PutIngestPipelineParams {
+    /// Name of the ingest pipeline. An existing pipeline with this name is replaced
+    pipeline: String,
+
+    /// Pipeline definition, with `processors`, and optionally `description` and `on_failure`. Check it with
+    /// simulate_ingest_pipeline first
+    definition: Map<String, Value>,
+
+    /// Describe the API calls that would be made, without making them (default: false)
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct AnalyzeFieldConfigParams {
+    /// Name of the Elasticsearch index
+    index: String,
+
+    /// Name of the field to analyze
+    field: String,
+
+    /// Number of documents to sample, at most 100 (default: 20)
+    samples: Option<usize>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct DiskUsageParams {
+    /// Name of the Elasticsearch index to analyze
+    index: String,
+
+    /// Number of fields to report, sorted by decreasing storage size (default: 20)
+    top_fields: Option<usize>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct SearchLatencyReportParams {
+    /// Index pattern of Elasticsearch indices to analyze (default: all indices)
+    index_pattern: Option<String>,
+
+    /// Duration of the observation window in seconds, at most 120 (default: 10). With 0, report latency
+    /// since the indices' shards were started.
+    window_seconds: Option<u64>,
+
+    /// Maximum number of indices to report, sorted by decreasing search time (default: 20)
+    top_indices: Option<usize>,
+}
+
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 struct SearchParams {
     /// Name of the Elasticsearch index to search
@@ -69,222 +393,2825 @@ struct SearchParams {
 
     /// Complete Elasticsearch query DSL object that can include query, size, from, sort, etc.
     query_body: Map<String, Value>, // note: just Value doesn't work, as Claude would send a string
+
+    /// Token returned by `open_consistency_token`, for all searches using it to see the same data snapshot
+    /// (optional). The index of the token is searched, and `index` is ignored.
+    consistency_token: Option<String>,
+
+    /// Match `term`, `prefix`, `wildcard` and `match` queries regardless of case and diacritics, e.g. "jose" also
+    /// matches "José". Use it when a text search returns no results for names with accents (default: false)
+    #[serde(default)]
+    fold_text: bool,
+
+    /// Sort values of the last document of the previous page, to get the next page (optional). The query must have a
+    /// `sort`, and no `from`. Use it with a consistency token to page through large results on a stable snapshot.
+    search_after: Option<Vec<Value>>,
+
+    /// Fields computed at search time, by field name (optional). They can be used in the query, sorts and
+    /// aggregations, and are returned with the documents.
+    runtime_mappings: Option<HashMap<String, RuntimeField>>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
-struct EsqlQueryParams {
-    /// Complete Elasticsearch ES|QL query
+struct RuntimeField {
+    /// Type of the field
+    #[serde(rename = "type")]
+    field_type: RuntimeFieldType,
+
+    /// Painless script that computes the value and passes it to `emit`, e.g. `emit(doc['price'].value * 1.2)`. If
+    /// omitted, the value is read from the field of the same name in `_source`
+    script: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum RuntimeFieldType {
+    Keyword,
+    Long,
+    Double,
+    Date,
+    Boolean,
+    Ip,
+    GeoPoint,
+}
+
+impl RuntimeField {
+    /// Definition of the field in the `runtime_mappings` of a search request.
+    fn mapping(self) -> Value {
+        let mut mapping = json!({ "type": self.field_type });
+        if let Some(script) = self.script {
+            mapping["script"] = json!({ "source": script });
+        }
+        mapping
+    }
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct MsearchParams {
+    /// Searches to run in a single request, at most 20. Their results are returned in the same order.
+    searches: Vec<MsearchItem>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct MsearchItem {
+    /// Name or pattern of the Elasticsearch indices to search
+    index: String,
+
+    /// Complete Elasticsearch query DSL object that can include query, size, from, sort, aggs, etc.
+    query_body: Map<String, Value>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ExportSearchParams {
+    /// Name or pattern of the Elasticsearch indices to export documents from
+    index: String,
+
+    /// Elasticsearch query DSL `query` object to select the documents to export (optional, all documents by
+    /// default)
+    query: Option<Map<String, Value>>,
+
+    /// Name of the fields to export (optional, all fields by default)
+    fields: Option<Vec<String>>,
+
+    /// Maximum number of documents to export, at most 100000 (default: 10000)
+    max_docs: Option<usize>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct SemanticSearchParams {
+    /// Name or pattern of the Elasticsearch indices to search
+    index: String,
+
+    /// Field to search, of type `semantic_text`, `dense_vector` or `sparse_vector`
+    field: String,
+
+    /// Natural language text to search for
     query: String,
+
+    /// Inference endpoint that computes the embedding of the text, required for `dense_vector` and
+    /// `sparse_vector` fields. `semantic_text` fields use their own endpoint.
+    inference_id: Option<String>,
+
+    /// Number of documents to return (default: 10)
+    size: Option<u32>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
-struct GetShardsParams {
-    /// Optional index name to get shard information for
-    index: Option<String>,
+struct KnnSearchParams {
+    /// Name or pattern of the Elasticsearch indices to search
+    index: String,
+
+    /// The `dense_vector` field to search
+    field: String,
+
+    /// Query vector, with the same number of dimensions as the field
+    query_vector: Vec<f32>,
+
+    /// Number of nearest neighbors to return (default: 10)
+    k: Option<u32>,
+
+    /// Number of nearest neighbor candidates to consider per shard, at least `k` and at most 10000. Higher values
+    /// improve accuracy but are slower (default: 10 times `k`, at least 100)
+    num_candidates: Option<u32>,
+
+    /// Query DSL filter that documents must match, applied during the search
+    filter: Option<Map<String, Value>>,
 }
 
-#[tool_router]
-impl EsBaseTools {
-    //---------------------------------------------------------------------------------------------
-    /// Tool: list indices
-    #[tool(
-        description = "List all available Elasticsearch indices",
-        annotations(title = "List ES indices", read_only_hint = true)
-    )]
-    async fn list_indices(
-        &self,
-        req_ctx: RequestContext<RoleServer>,
-        Parameters(ListIndicesParams { index_pattern }): Parameters<ListIndicesParams>,
-    ) -> Result<CallToolResult, rmcp::Error> {
-        let es_client = self.es_client.get(req_ctx);
-        let response = es_client
-            .cat()
-            .indices(CatIndicesParts::Index(&[&index_pattern]))
-            .h(&["index", "status", "docs.count"])
-            .format("json")
-            .send()
-            .await;
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ValidateQueryParams {
+    /// Name of the Elasticsearch index to validate the query against
+    index: String,
 
-        let response: Vec<CatIndexResponse> = read_json(response).await?;
+    /// Elasticsearch query DSL object with a `query` property, like the body of a search request. Other
+    /// properties such as aggregations are ignored.
+    query_body: Map<String, Value>,
+}
 
-        Ok(CallToolResult::success(vec![
-            Content::text(format!("Found {} indices:", response.len())),
-            Content::json(response)?,
-        ]))
-    }
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ProfileSearchParams {
+    /// Name or pattern of the Elasticsearch indices to search
+    index: String,
 
-    //---------------------------------------------------------------------------------------------
-    /// Tool: get mappings for an index
-    #[tool(
-        description = "Get field mappings for a specific Elasticsearch index",
-        annotations(title = "Get ES index mappings", read_only_hint = true)
-    )]
-    async fn get_mappings(
-        &self,
-        req_ctx: RequestContext<RoleServer>,
-        Parameters(GetMappingsParams { index }): Parameters<GetMappingsParams>,
-    ) -> Result<CallToolResult, rmcp::Error> {
-        let es_client = self.es_client.get(req_ctx);
-        let response = es_client
-            .indices()
-            .get_mapping(IndicesGetMappingParts::Index(&[&index]))
-            .send()
-            .await;
+    /// Elasticsearch query DSL object, like the body of a search request
+    query_body: Map<String, Value>,
 
-        let response: MappingResponse = read_json(response).await?;
+    /// Number of shards to report, the slowest first (default: 5)
+    top_shards: Option<usize>,
 
-        // use the first mapping (we can have many if the name is a wildcard)
-        let mapping = response.values().next().unwrap();
+    /// Number of queries and aggregations to report per shard, the slowest first (default: 5)
+    top_queries: Option<usize>,
+}
 
-        Ok(CallToolResult::success(vec![
-            Content::text(format!("Mappings for index {index}:")),
-            Content::json(mapping)?,
-        ]))
-    }
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct AggregateParams {
+    /// Name or pattern of the Elasticsearch indices to aggregate
+    index: String,
 
-    //---------------------------------------------------------------------------------------------
-    /// Tool: search an index with the Query DSL
-    ///
-    /// The additional 'fields' parameter helps some LLMs that don't know about the `_source`
-    /// request property to narrow down the data returned and reduce their context size
+    /// Elasticsearch query DSL `query` object to select the documents to aggregate (optional, all
+    /// documents by default)
+    query: Option<Map<String, Value>>,
+
+    /// Date histogram to group documents by time interval, before the group-by fields (optional)
+    date_histogram: Option<aggregate::DateHistogram>,
+
+    /// Fields to group documents by, from outermost to innermost group (optional). Use keyword, numeric or
+    /// boolean fields.
+    #[serde(default)]
+    group_by: Vec<String>,
+
+    /// Maximum number of groups per group-by field, keeping those with the most documents (default: 10)
+    group_size: Option<u32>,
+
+    /// Metrics to compute for each group (optional). The document count of groups is always returned.
+    #[serde(default)]
+    metrics: Vec<aggregate::Metric>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct OpenConsistencyTokenParams {
+    /// Name or pattern of the Elasticsearch indices to search with the token
+    index: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct InferEmbeddingParams {
+    /// Id of a text_embedding or sparse_embedding inference endpoint
+    inference_id: String,
+
+    /// Texts to embed
+    input: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct InferRerankParams {
+    /// Id of a rerank inference endpoint
+    inference_id: String,
+
+    /// Query to rank the documents against
+    query: String,
+
+    /// Texts of the documents to rank
+    documents: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct InferCompletionParams {
+    /// Id of a completion inference endpoint
+    inference_id: String,
+
+    /// Prompt to complete
+    input: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct GetForUpdateParams {
+    /// Name of the Elasticsearch index
+    index: String,
+
+    /// Id of the document
+    id: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct PutIfUnchangedParams {
+    /// Name of the Elasticsearch index
+    index: String,
+
+    /// Id of the document
+    id: String,
+
+    /// Update token returned by get_for_update when the document was read
+    update_token: String,
+
+    /// New content of the document, which replaces the current one
+    document: Map<String, Value>,
+
+    /// When the change becomes visible to searches (default: false)
+    #[serde(default)]
+    refresh: RefreshPolicy,
+
+    /// Describe the API calls that would be made, without making them (default: false)
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ImportNdjsonParams {
+    /// Name of the existing index to import documents into
+    index: String,
+
+    /// URI of a resource of this server containing NDJSON data (provide either this or `path`)
+    resource: Option<String>,
+
+    /// Path of an NDJSON file, relative to the import directory of the server (provide either this or `resource`)
+    path: Option<String>,
+
+    /// When the imported documents become visible to searches (default: false)
+    #[serde(default)]
+    refresh: RefreshPolicy,
+
+    /// Describe the API calls that would be made, without making them (default: false)
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct EsqlQueryParams {
+    /// Complete Elasticsearch ES|QL query
+    query: String,
+
+    /// Values of the `?` placeholders in the query (optional). Use values for positional placeholders, and
+    /// objects like `{"name": value}` for named placeholders like `?name`. Values are never parsed as ES|QL,
+    /// so prefer parameters to inserting user-provided values in the query.
+    params: Option<Vec<Value>>,
+
+    /// Time zone for date functions and date parsing in the query, e.g. `Europe/Paris` or `+02:00` (optional,
+    /// defaults to UTC)
+    time_zone: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct SqlQueryParams {
+    /// Elasticsearch SQL query, e.g. `SELECT host, COUNT(*) FROM "logs-*" GROUP BY host` (provide either this or
+    /// `cursor`)
+    query: Option<String>,
+
+    /// Cursor returned with the previous page of results, to get the next page (provide either this or `query`)
+    cursor: Option<String>,
+
+    /// Values of the `?` placeholders in the query (optional). Values are never parsed as SQL, so prefer
+    /// parameters to inserting user-provided values in the query.
+    params: Option<Vec<Value>>,
+
+    /// Maximum number of rows per page (default: 1000)
+    fetch_size: Option<u32>,
+
+    /// Time zone for date functions and date parsing in the query, e.g. `Europe/Paris` or `+02:00` (optional,
+    /// defaults to UTC)
+    time_zone: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct SqlTranslateParams {
+    /// Elasticsearch SQL query to translate
+    query: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct EqlSearchParams {
+    /// Name or pattern of the Elasticsearch indices or data streams to search
+    index: String,
+
+    /// EQL query, e.g. `process where process.name == "cmd.exe"`, or a sequence of events such as
+    /// `sequence by host.name with maxspan=5m [process where process.name == "cmd.exe"] [network where true]`
+    query: String,
+
+    /// Number of events or sequences to return (default: 10)
+    size: Option<u32>,
+
+    /// Query DSL filter that events must match, e.g. a time range
+    filter: Option<Map<String, Value>>,
+
+    /// Field containing the event timestamp (default: `@timestamp`)
+    timestamp_field: Option<String>,
+
+    /// Fields to return for each event, instead of its whole source (optional)
+    fields: Option<Vec<String>>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ExplainErrorParams {
+    /// Elasticsearch error response, or the error message of a failed tool call
+    error: Option<String>,
+
+    /// URI of an error resource created by a tool, e.g. `elasticsearch-mcp://errors/...`, instead of `error`
+    error_resource: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct GetShardsParams {
+    /// Optional index name to get shard information for
+    index: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct GetAllocationParams {
+    /// Optional node name or id to get disk allocation for
+    node: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ListTasksParams {
+    /// Optional comma-separated list of actions, with wildcards, e.g. `*search*` or `indices:data/write/reindex`
+    actions: Option<String>,
+    /// Optional comma-separated list of node names or ids
+    nodes: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct CancelTaskParams {
+    /// Task id, as `node:id` (from list_tasks)
+    task_id: String,
+    /// Describe the API calls that would be made, without making them (default: false)
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct GetTaskStatusParams {
+    /// Task id, as `node:id` (from list_tasks or reindex)
+    task_id: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ReindexParams {
+    /// Source index, alias or data stream
+    source_index: String,
+    /// Destination index
+    dest_index: String,
+    /// Optional query DSL query selecting the documents to copy, e.g. `{"range": {"@timestamp": {"gte": "now-7d"}}}`
+    query: Option<Map<String, Value>>,
+    /// Optional ingest pipeline that processes documents before they're written to the destination
+    pipeline: Option<String>,
+    /// Optional maximum number of documents to copy
+    max_docs: Option<u64>,
+    /// Refresh the destination index when the copy completes, so that the copied documents are visible to searches
+    /// (default: false). Reindexing doesn't support `wait_for`
+    #[serde(default)]
+    refresh: bool,
+    /// Describe the API calls that would be made, without making them (default: false)
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ListAliasesParams {
+    /// Optional alias name or wildcard pattern
+    alias: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct CreateAliasParams {
+    /// Name of the alias
+    alias: String,
+    /// Index, or wildcard pattern of indices, that the alias points to
+    index: String,
+    /// Optional query DSL query that limits the documents visible through the alias
+    filter: Option<Map<String, Value>>,
+    /// Is it the index that receives writes to the alias, if the alias points to several indices?
+    is_write_index: Option<bool>,
+    /// Describe the API calls that would be made, without making them (default: false)
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct SwapAliasParams {
+    /// Name of an existing alias
+    alias: String,
+    /// Index that the alias will point to, instead of its current indices
+    index: String,
+    /// Describe the API calls that would be made, without making them (default: false)
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct GetThreadPoolsParams {
+    /// Optional thread pool name or wildcard pattern, e.g. `search` or `write*`
+    thread_pool: Option<String>,
+    /// Also list thread pools that have no active, queued or rejected tasks (default: false)
+    include_idle: Option<bool>,
+}
+
+#[tool_router]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: list indices
+    #[tool(
+        description = "List all available Elasticsearch indices",
+        annotations(title = "List ES indices", read_only_hint = true)
+    )]
+    async fn list_indices(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(ListIndicesParams { index_pattern }): Parameters<ListIndicesParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+        let response = send!(
+            es_client
+                .cat()
+                .indices(CatIndicesParts::Index(&[&index_pattern]))
+                .h(&["index", "status", "docs.count"])
+                .format("json")
+        )
+        .await;
+
+        let response: Vec<CatIndexResponse> = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("Found {} indices:", response.len())),
+            Content::json(response)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get mappings for an index
+    #[tool(
+        description = "Get field mappings for a specific Elasticsearch index",
+        annotations(title = "Get ES index mappings", read_only_hint = true)
+    )]
+    async fn get_mappings(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(GetMappingsParams { index }): Parameters<GetMappingsParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+        let response = send!(
+            es_client
+                .indices()
+                .get_mapping(IndicesGetMappingParts::Index(&[&index]))
+        )
+        .await;
+
+        let response: MappingResponse = read_json(response).await?;
+
+        // use the first mapping (we can have many if the name is a wildcard)
+        let mapping = response.values().next().unwrap();
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("Mappings for index {index}:")),
+            Content::json(mapping)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: compare the mappings of an index with another index or an index template
+    #[tool(
+        description = "Compare the field mappings of an Elasticsearch index with another index or an index template, \
+            and report fields that were added, removed or changed type",
+        annotations(title = "Compare ES index mappings", read_only_hint = true)
+    )]
+    async fn compare_mappings(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(CompareMappingsParams {
+            index,
+            other_index,
+            index_template,
+        }): Parameters<CompareMappingsParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let reference = index_mappings(&es_client, &index).await?;
+
+        let (other_name, other) = match (other_index, index_template) {
+            (Some(other_index), None) => {
+                let mappings = index_mappings(&es_client, &other_index).await?;
+                (format!("index {other_index}"), mappings)
+            }
+            (None, Some(template)) => {
+                // Simulate the template to include mappings of its component templates
+                let response = send!(
+                    es_client
+                        .indices()
+                        .simulate_template(IndicesSimulateTemplateParts::Name(&template))
+                )
+                .await;
+                let response: SimulateTemplateResponse = read_json(response).await?;
+                (format!("index template {template}"), response.template.mappings)
+            }
+            _ => {
+                return Err(rmcp::Error::invalid_params(
+                    "Provide either 'other_index' or 'index_template'",
+                    None,
+                ));
+            }
+        };
+
+        let diff = MappingDiff::new(&reference, &other);
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!(
+                "Compared index {index} with {other_name}: {} fields added, {} removed, {} changed type:",
+                diff.added.len(),
+                diff.removed.len(),
+                diff.retyped.len()
+            )),
+            Content::json(diff)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: simulate the index templates applied to a new index
+    #[tool(
+        description = "Show the settings, mappings and aliases that a new Elasticsearch index would get from the \
+            index templates, optionally with a proposed template, without creating anything.",
+        annotations(title = "Simulate ES index templates", read_only_hint = true)
+    )]
+    async fn simulate_index_template(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(SimulateIndexTemplateParams { index, index_template }): Parameters<SimulateIndexTemplateParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let parts = IndicesSimulateIndexTemplateParts::Name(&index);
+        let response = match &index_template {
+            Some(template) => send!(es_client.indices().simulate_index_template(parts).body(template)).await,
+            None => send!(es_client.indices().simulate_index_template(parts)).await,
+        };
+        let response: SimulateIndexTemplateResponse = read_json(response).await?;
+
+        let mut contents = vec![
+            Content::text(format!("Settings, mappings and aliases of a new index '{index}':")),
+            Content::json(response.template)?,
+        ];
+        if !response.overlapping.is_empty() {
+            let overlapping = response
+                .overlapping
+                .iter()
+                .map(|t| format!("{} ({})", t.name, t.index_patterns.join(", ")))
+                .collect::<Vec<_>>();
+            contents.push(Content::text(format!(
+                "Matching templates that are not used, as they have a lower priority: {}",
+                overlapping.join(", ")
+            )));
+        }
+        Ok(CallToolResult::success(contents))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: list index and component templates
+    #[tool(
+        description = "List index templates, with their index patterns, priority and component templates, and \
+            component templates, with the index templates that use them.",
+        annotations(title = "List ES index templates", read_only_hint = true)
+    )]
+    async fn list_index_templates(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(ListIndexTemplatesParams { name }): Parameters<ListIndexTemplatesParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+        let templates = all_templates(&es_client).await?;
+        let list = templates.summaries(name.as_deref());
+
+        let mut msg = format!(
+            "{} index templates and {} component templates",
+            list.index_templates.len(),
+            list.component_templates.len()
+        );
+        if list.managed > 0 {
+            msg.push_str(&format!(" ({} templates managed by Elastic not shown)", list.managed));
+        }
+        Ok(CallToolResult::success(vec![Content::text(msg), Content::json(list)?]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get the effective settings, mappings and aliases of index or component templates
+    #[tool(
+        description = "Get index or component templates with their effective settings, mappings and aliases: \
+            component templates are merged in order, then the index template, and each setting, field and alias \
+            shows the template it comes from and the templates it overrides. Use it to explain unexpected mappings.",
+        annotations(title = "Get ES index template", read_only_hint = true)
+    )]
+    async fn get_index_template(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(GetIndexTemplateParams { name }): Parameters<GetIndexTemplateParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+        let templates = all_templates(&es_client).await?;
+        let details = templates.details(&name);
+
+        if details.is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "No index or component template matches '{name}'. Use list_index_templates to find templates."
+            ))]));
+        }
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("Found {} templates matching '{name}':", details.len())),
+            Content::json(details)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get ILM policies
+    #[tool(
+        description = "Get index lifecycle management (ILM) policies: the actions of their phases, and the indices, \
+            data streams and index templates that use them.",
+        annotations(title = "Get ILM policies", read_only_hint = true)
+    )]
+    async fn get_ilm_policies(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(GetIlmPoliciesParams { policy }): Parameters<GetIlmPoliciesParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let parts = match &policy {
+            Some(policy) => IlmGetLifecycleParts::Policy(policy),
+            None => IlmGetLifecycleParts::None,
+        };
+        let response = send!(es_client.ilm().get_lifecycle(parts)).await;
+        let response: IndexMap<String, StoredPolicy> = read_json(response).await?;
+
+        let total = response.len();
+        let policies = response
+            .into_iter()
+            .filter(|(_, stored)| policy.is_some() || !stored.is_managed())
+            .map(|(name, stored)| stored.summary(name))
+            .collect::<Vec<_>>();
+
+        let mut msg = format!("{} ILM policies", policies.len());
+        if policies.len() < total {
+            msg.push_str(&format!(
+                " ({} policies managed by Elastic not shown)",
+                total - policies.len()
+            ));
+        }
+        Ok(CallToolResult::success(vec![
+            Content::text(msg),
+            Content::json(policies)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: explain the ILM state of indices
+    #[tool(
+        description = "Explain the index lifecycle management (ILM) state of indices: their policy, phase, action \
+            and step, and the error or condition that blocks indices that are stuck.",
+        annotations(title = "Explain ILM state", read_only_hint = true)
+    )]
+    async fn explain_ilm(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(ExplainIlmParams { index, only_errors }): Parameters<ExplainIlmParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let response = send!(
+            es_client
+                .ilm()
+                .explain_lifecycle(IlmExplainLifecycleParts::Index(&index))
+                .only_errors(only_errors)
+        )
+        .await;
+        let response: ilm::ExplainResponse = read_json(response).await?;
+
+        let (mut managed, unmanaged): (Vec<_>, Vec<_>) =
+            response.indices.into_iter().partition(|(_, explain)| explain.managed);
+        // Indices in error first
+        managed.sort_by_key(|(_, explain)| !explain.is_error());
+        let errors = managed.iter().filter(|(_, explain)| explain.is_error()).count();
+        let managed = managed
+            .into_iter()
+            .map(|(name, explain)| explain.summary(name))
+            .collect::<Vec<_>>();
+
+        let mut contents = vec![
+            Content::text(format!("{} indices managed by ILM, {errors} in error", managed.len())),
+            Content::json(managed)?,
+        ];
+        if !unmanaged.is_empty() {
+            let names = unmanaged.into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+            contents.push(Content::text(format!("Not managed by ILM: {}", names.join(", "))));
+        }
+        Ok(CallToolResult::success(contents))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: list ingest pipelines
+    #[tool(
+        description = "List Elasticsearch ingest pipelines, with their description and the types of their \
+            processors.",
+        annotations(title = "List ES ingest pipelines", read_only_hint = true)
+    )]
+    async fn list_ingest_pipelines(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(ListIngestPipelinesParams { pipeline }): Parameters<ListIngestPipelinesParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let parts = match &pipeline {
+            Some(pipeline) => IngestGetPipelineParts::Id(pipeline),
+            None => IngestGetPipelineParts::None,
+        };
+        let response = send!(es_client.ingest().get_pipeline(parts)).await;
+        if let Ok(response) = &response
+            && response.status_code() == StatusCode::NOT_FOUND
+        {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "No ingest pipeline matches '{}'",
+                pipeline.unwrap_or_default()
+            ))]));
+        }
+        let response: IndexMap<String, Value> = read_json(response).await?;
+
+        let total = response.len();
+        let pipelines = response
+            .into_iter()
+            .filter(|(_, definition)| pipeline.is_some() || !ingest::is_managed(definition))
+            .map(|(name, definition)| ingest::pipeline_summary(name, definition))
+            .collect::<Vec<_>>();
+
+        let mut msg = format!("{} ingest pipelines", pipelines.len());
+        if pipelines.len() < total {
+            msg.push_str(&format!(
+                " ({} pipelines managed by Elastic not shown)",
+                total - pipelines.len()
+            ));
+        }
+        Ok(CallToolResult::success(vec![
+            Content::text(msg),
+            Content::json(pipelines)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: simulate an ingest pipeline
+    #[tool(
+        description = "Run sample documents through a stored or proposed Elasticsearch ingest pipeline, without \
+            indexing them, and return the resulting documents or the processor errors.",
+        annotations(title = "Simulate an ES ingest pipeline", read_only_hint = true)
+    )]
+    async fn simulate_ingest_pipeline(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(SimulateIngestPipelineParams {
+            pipeline,
+            definition,
+            docs,
+            verbose,
+        }): Parameters<SimulateIngestPipelineParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let docs = docs
+            .into_iter()
+            .map(|doc| json!({ "_source": doc }))
+            .collect::<Vec<_>>();
+        let (parts, body) = match (&pipeline, definition) {
+            (Some(pipeline), None) => (IngestSimulateParts::Id(pipeline), json!({ "docs": docs })),
+            (None, Some(definition)) => (
+                IngestSimulateParts::None,
+                json!({ "pipeline": definition, "docs": docs }),
+            ),
+            _ => {
+                return Err(rmcp::Error::invalid_params(
+                    "Either 'pipeline' or 'definition' must be provided",
+                    None,
+                ));
+            }
+        };
+
+        let response = send!(es_client.ingest().simulate(parts).verbose(verbose).body(body)).await;
+        let response: Map<String, Value> = read_json(response).await?;
+
+        let results = ingest::simulation_results(&response);
+        let failed = results.iter().filter(|result| result.is_error()).count();
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("{} documents processed, {failed} failed", results.len())),
+            Content::json(results)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: create or replace an ingest pipeline
+    #[tool(
+        description = "Create or replace an Elasticsearch ingest pipeline. Test it first with \
+            simulate_ingest_pipeline.",
+        annotations(
+            title = "Create an ES ingest pipeline",
+            read_only_hint = false,
+            destructive_hint = true,
+            idempotent_hint = false
+        )
+    )]
+    async fn put_ingest_pipeline(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(PutIngestPipelineParams {
+            pipeline,
+            definition,
+            dry_run,
+        }): Parameters<PutIngestPipelineParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        if self.dry_run || dry_run {
+            return writes::dry_run_result(
+                format!("Ingest pipeline '{pipeline}' would be created, or replaced if it exists."),
+                vec![
+                    PlannedCall::new("PUT", format!("/_ingest/pipeline/{pipeline}"))
+                        .with_body(Value::Object(definition)),
+                ],
+            );
+        }
+
+        let es_client = self.es_client.get(req_ctx);
+
+        let response = send!(
+            es_client
+                .ingest()
+                .put_pipeline(IngestPutPipelineParts::Id(&pipeline))
+                .body(definition)
+        )
+        .await;
+        let _: Value = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Ingest pipeline '{pipeline}' saved"
+        ))]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: analyze the configuration of a field
+    #[tool(
+        description = "Check the mapping and analyzer of a text or keyword field against sample values, and report \
+            likely issues with recommendations: analyzer for the wrong language, values in several languages, \
+            missing keyword subfield, ignore_above too large or too small.",
+        annotations(title = "Analyze ES field configuration", read_only_hint = true)
+    )]
+    async fn analyze_field_config(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(AnalyzeFieldConfigParams { index, field, samples }): Parameters<AnalyzeFieldConfigParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let response = send!(
+            es_client
+                .indices()
+                .get_field_mapping(IndicesGetFieldMappingParts::IndexFields(&[&index], &[&field]))
+        )
+        .await;
+        let response: Map<String, Value> = read_json(response).await?;
+        // The mapping is keyed by the leaf name of the field
+        let Some((index_name, mapping)) = response.iter().find_map(|(name, index)| {
+            let mapping = index["mappings"][&field]["mapping"].as_object()?.values().next()?;
+            Some((name.clone(), mapping.clone()))
+        }) else {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Field '{field}' not found in '{index}'"
+            ))]));
+        };
+
+        let response = send!(es_client.indices().get_settings(IndicesGetSettingsParts::IndexName(
+            &[&index_name],
+            &["index.analysis.*"],
+        )))
+        .await;
+        let response: Value = read_json(response).await?;
+        let analysis = &response[&index_name]["settings"]["index"]["analysis"];
+
+        // Multi-fields aren't in the source, their values are those of their parent field
+        let source_field = match field.rsplit_once('.') {
+            Some((parent, _)) => vec![field.as_str(), parent],
+            None => vec![field.as_str()],
+        };
+        let body = json!({
+            "size": samples.unwrap_or(20).min(100),
+            "_source": source_field,
+            "query": {
+                "function_score": { "query": { "exists": { "field": field } }, "random_score": {} }
+            },
+        });
+        let response = send!(es_client.search(SearchParts::Index(&[&index_name])).body(body)).await;
+        let response: SearchResult = read_json(response).await?;
+        let values = response
+            .hits
+            .hits
+            .iter()
+            .flat_map(|hit| {
+                source_field
+                    .iter()
+                    .map(|path| analysis::source_values(&hit.source, path))
+                    .find(|values| !values.is_empty())
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
+
+        let advice = analysis::advise(&field, &mapping, analysis, &values);
+        let msg = match advice.issues.len() {
+            0 => format!("No issues found for field '{field}' of '{index_name}'"),
+            n => format!("{n} issues found for field '{field}' of '{index_name}'"),
+        };
+        Ok(CallToolResult::success(vec![
+            Content::text(msg),
+            Content::json(advice)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: analyze the disk usage of an index's fields
+    #[tool(
+        description = "Analyze the disk usage of each field of an Elasticsearch index, to find the fields that \
+            consume the most storage. CAUTION: this is an expensive operation that reads all shards of the index. \
+            Avoid calling it repeatedly or on very large indices.",
+        annotations(title = "Analyze ES index disk usage", read_only_hint = true, idempotent_hint = true)
+    )]
+    async fn disk_usage(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(DiskUsageParams { index, top_fields }): Parameters<DiskUsageParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+        let response = send!(
+            es_client
+                .indices()
+                .disk_usage(IndicesDiskUsageParts::Index(&index))
+                .run_expensive_tasks(true)
+        )
+        .await;
+
+        let response: HashMap<String, Value> = read_json(response).await?;
+
+        let top_fields = top_fields.unwrap_or(20);
+        let mut results = Vec::new();
+        let mut indices = response
+            .into_iter()
+            .filter(|(name, _)| !name.starts_with('_')) // "_shards"
+            .collect::<Vec<_>>();
+        indices.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for (name, usage) in indices {
+            let usage: IndexDiskUsage = serde_json::from_value(usage).map_err(internal_error)?;
+
+            let mut fields = usage
+                .fields
+                .into_iter()
+                .map(|(field, usage)| FieldDiskUsage::new(field, usage))
+                .collect::<Vec<_>>();
+            fields.sort_by_key(|f| std::cmp::Reverse(f.total_in_bytes));
+            let field_count = fields.len();
+            fields.truncate(top_fields);
+
+            results.push(Content::text(format!(
+                "Index {name} uses {} ({} bytes). Top {} of {field_count} fields by storage size:",
+                usage.store_size,
+                usage.store_size_in_bytes,
+                fields.len()
+            )));
+            results.push(Content::json(fields)?);
+        }
+
+        Ok(CallToolResult::success(results))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: search latency report
+    #[tool(
+        description = "Report the indices that contribute the most to search latency over an observation window: \
+            query and fetch times, and query and request cache hit rates. The tool waits for the window duration.",
+        annotations(title = "ES search latency report", read_only_hint = true)
+    )]
+    async fn search_latency_report(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(SearchLatencyReportParams {
+            index_pattern,
+            window_seconds,
+            top_indices,
+        }): Parameters<SearchLatencyReportParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let ct = req_ctx.ct.clone();
+        let es_client = self.es_client.get(req_ctx);
+
+        let index_pattern = index_pattern.unwrap_or("*".to_string());
+        let window = Duration::from_secs(window_seconds.unwrap_or(10).min(120));
+
+        let sample = async || -> Result<IndicesStatsResponse, rmcp::Error> {
+            let response = send!(es_client.indices().stats(IndicesStatsParts::IndexMetric(
+                &[&index_pattern],
+                &["search", "query_cache", "request_cache"],
+            )))
+            .await;
+            read_json(response).await
+        };
+
+        let report = if window.is_zero() {
+            let empty = IndicesStatsResponse {
+                indices: HashMap::new(),
+            };
+            latency_report(&empty, &sample().await?)
+        } else {
+            let before = sample().await?;
+            tokio::select! {
+                _ = tokio::time::sleep(window) => {},
+                _ = ct.cancelled() => return Err(rmcp::Error::internal_error("Request cancelled", None)),
+            }
+            latency_report(&before, &sample().await?)
+        };
+
+        let period = if window.is_zero() {
+            "since shards were started".to_string()
+        } else {
+            format!("over the last {} seconds", window.as_secs())
+        };
+        let query_count: u64 = report.iter().map(|r| r.queries).sum();
+        let index_count = report.len();
+        let report = report.into_iter().take(top_indices.unwrap_or(20)).collect::<Vec<_>>();
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!(
+                "Search activity {period}: {query_count} queries on {index_count} indices. \
+                Top {} indices by search time:",
+                report.len()
+            )),
+            Content::json(report)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: search an index with the Query DSL
+    ///
+    /// The additional 'fields' parameter helps some LLMs that don't know about the `_source`
+    /// request property to narrow down the data returned and reduce their context size
+    #[tool(
+        description = "Perform an Elasticsearch search with the provided query DSL.",
+        annotations(title = "Elasticsearch search DSL query", read_only_hint = true)
+    )]
+    async fn search(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(SearchParams {
+            index,
+            fields,
+            query_body,
+            consistency_token,
+            fold_text,
+            search_after,
+            runtime_mappings,
+        }): Parameters<SearchParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let owner = Owner::of(&req_ctx);
+        let es_client = self.es_client.get(req_ctx);
+
+        let mut query_body = query_body;
+
+        if fold_text && let Some(query) = query_body.get_mut("query") {
+            folding::fold_query(query);
+        }
+
+        // A PIT search must not target an index
+        let indices = [index.as_str()];
+        let search_parts = match &consistency_token {
+            Some(token) => {
+                pit::set_pit(&mut query_body, token.clone());
+                SearchParts::None
+            }
+            None => SearchParts::Index(&indices),
+        };
+
+        if let Some(fields) = fields {
+            // Augment _source if it exists
+            if let Some(Value::Array(values)) = query_body.get_mut("_source") {
+                for field in fields.into_iter() {
+                    values.push(Value::String(field))
+                }
+            } else {
+                query_body.insert("_source".to_string(), json!(fields));
+            }
+        }
+
+        if let Some(search_after) = search_after {
+            query_body.insert("search_after".to_string(), Value::Array(search_after));
+        }
+
+        if let Some(runtime_mappings) = runtime_mappings {
+            // Runtime fields are not in `_source`, and must be requested to be returned
+            if let Value::Array(values) = query_body.entry("fields").or_insert_with(|| json!([])) {
+                values.extend(runtime_mappings.keys().map(|name| json!(name)));
+            }
+            if let Value::Object(mappings) = query_body.entry("runtime_mappings").or_insert_with(|| json!({})) {
+                for (name, field) in runtime_mappings {
+                    mappings.insert(name, field.mapping());
+                }
+            }
+        }
+
+        let response = send!(es_client.search(search_parts).body(query_body)).await;
+
+        let response: SearchResult = read_json(response).await?;
+
+        let mut results: Vec<Content> = Vec::new();
+
+        // Elasticsearch may update the PIT id, and the most recent one must be used
+        if let Some(new_id) = response
+            .pit_id
+            .as_ref()
+            .filter(|id| consistency_token.as_ref() != Some(*id))
+        {
+            results.push(Content::text(format!(
+                "Use this new consistency token for the next searches: {new_id}"
+            )));
+        }
+
+        // Keep failure details out of the result, they can be very long with many shards
+        if let Some(shards) = response.shards.as_ref().filter(|s| s.failed > 0) {
+            let reason = shards
+                .failures
+                .first()
+                .and_then(|f| f.pointer("/reason/reason").or_else(|| f.pointer("/reason/type")))
+                .and_then(Value::as_str)
+                .unwrap_or("unknown reason");
+            let uri = self.resources.add(
+                &owner,
+                "errors",
+                format!("Shard failures of search on '{index}'"),
+                "application/json",
+                serde_json::to_string_pretty(&shards.failures).map_err(internal_error)?,
+            );
+            results.push(Content::text(format!(
+                "Partial results: {} of {} shards failed, e.g. {reason}. Details of all failures are in resource {uri}",
+                shards.failed, shards.total
+            )));
+        }
+
+        results.extend(search_contents(&response)?);
+
+        // Searches with a sort or a PIT return the sort values of hits
+        if let Some(sort) = response.hits.hits.last().and_then(|hit| hit.sort.as_ref()) {
+            results.push(Content::text(format!(
+                "To get the next page, search again with search_after: {}",
+                Value::from(sort.clone())
+            )));
+        }
+        Ok(CallToolResult::success(results))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: multi-search
+    #[tool(
+        description = "Perform several Elasticsearch searches in a single request, e.g. to compare the results of \
+            several queries. Results are returned in the order of the searches, and a failed search doesn't fail \
+            the others.",
+        annotations(title = "Elasticsearch multi-search", read_only_hint = true)
+    )]
+    async fn msearch(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(MsearchParams { searches }): Parameters<MsearchParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        if searches.is_empty() || searches.len() > MAX_MSEARCH_SEARCHES {
+            return Err(rmcp::Error::invalid_params(
+                format!("Provide between 1 and {MAX_MSEARCH_SEARCHES} searches"),
+                None,
+            ));
+        }
+
+        let es_client = self.es_client.get(req_ctx);
+
+        // A header line with the index, followed by the search body
+        let body = searches
+            .iter()
+            .flat_map(|s| [json!({ "index": s.index }), Value::Object(s.query_body.clone())])
+            .map(JsonBody::new)
+            .collect::<Vec<_>>();
+        let response = send!(es_client.msearch(MsearchParts::None).body(body)).await;
+        let response: MsearchResponse = read_json(response).await?;
+
+        let mut contents = Vec::new();
+        for (i, (search, result)) in searches.iter().zip(response.responses).enumerate() {
+            let index = &search.index;
+            if let Some(error) = result.get("error") {
+                let reason = error
+                    .get("reason")
+                    .or_else(|| error.get("type"))
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown error");
+                contents.push(Content::text(format!("Search {} on '{index}' failed: {reason}", i + 1)));
+                continue;
+            }
+            let result: SearchResult = serde_json::from_value(result).map_err(internal_error)?;
+            contents.push(Content::text(format!("Search {} on '{index}':", i + 1)));
+            contents.extend(search_contents(&result)?);
+        }
+        Ok(CallToolResult::success(contents))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: export search results to a resource
+    #[tool(
+        description = "Export the documents matching a query to an NDJSON resource, one document source per line, \
+            and return the resource URI instead of the documents. Use it to get more documents than a search can \
+            return, e.g. to pass them to import_ndjson or to another MCP server. Exports are truncated at 50 MB.",
+        annotations(title = "Export ES documents", read_only_hint = true)
+    )]
+    async fn export_search(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(ExportSearchParams {
+            index,
+            query,
+            fields,
+            max_docs,
+        }): Parameters<ExportSearchParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let max_docs = max_docs.unwrap_or(10_000).min(MAX_EXPORTED_DOCS);
+        let es_client = self.es_client.get(req_ctx.clone());
+
+        // Read all pages on the same snapshot, sorted by the most efficient order
+        let mut pit_id = pit::open_unmanaged(&es_client, &index).await?;
+        let mut body = Map::new();
+        body.insert("sort".to_string(), json!(["_shard_doc"]));
+        body.insert("track_total_hits".to_string(), json!(true));
+        if let Some(query) = query {
+            body.insert("query".to_string(), Value::Object(query));
+        }
+        if let Some(fields) = fields {
+            body.insert("_source".to_string(), json!(fields));
+        }
+
+        let progress_token = req_ctx.meta.get_progress_token();
+        let mut lines = String::new();
+        let mut exported = 0;
+        let mut total = None;
+        let mut truncated = false;
+        let result = loop {
+            let size = EXPORT_BATCH_SIZE.min(max_docs - exported);
+            body.insert("size".to_string(), json!(size));
+            pit::set_pit(&mut body, pit_id.clone());
+
+            let response = send!(es_client.search(SearchParts::None).body(&body)).await;
+            let response: SearchResult = match read_json(response).await {
+                Ok(response) => response,
+                Err(err) => break Err(err),
+            };
+            if let Some(new_id) = response.pit_id {
+                pit_id = new_id;
+            }
+            total = response.hits.total.map(|t| t.value);
+
+            for hit in &response.hits.hits {
+                let line = hit.source.to_string();
+                if lines.len() + line.len() + 1 > MAX_EXPORT_SIZE {
+                    truncated = true;
+                    break;
+                }
+                lines.push_str(&line);
+                lines.push('\n');
+                exported += 1;
+            }
+            if truncated {
+                break Ok(());
+            }
+
+            let last_sort = response.hits.hits.last().and_then(|hit| hit.sort.clone());
+            match last_sort {
+                Some(sort) if response.hits.hits.len() == size && exported < max_docs => {
+                    body.insert("search_after".to_string(), Value::Array(sort));
+                }
+                _ => break Ok(()),
+            }
+
+            if let Some(progress_token) = &progress_token {
+                let _ = req_ctx
+                    .peer
+                    .notify_progress(ProgressNotificationParam {
+                        progress_token: progress_token.clone(),
+                        progress: exported as u32,
+                        total: total.map(|t| t.min(max_docs as u64) as u32),
+                        message: Some(format!("Exported {exported} documents")),
+                    })
+                    .await;
+            }
+        };
+        pit::close(&es_client, &pit_id).await;
+        result?;
+
+        let uri = self.exports.add(
+            &Owner::of(&req_ctx),
+            "exports",
+            format!("Export of '{index}'"),
+            "application/x-ndjson",
+            lines,
+        );
+        let total = total.map(|t| t.to_string()).unwrap_or("unknown".to_string());
+        let mut text = format!("Exported {exported} of {total} matching documents from '{index}' to resource {uri}");
+        if truncated {
+            text.push_str(&format!(
+                ". The export was truncated at {} MB: select fewer documents or fields to export the others",
+                MAX_EXPORT_SIZE / (1024 * 1024)
+            ));
+        }
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: semantic search
+    #[tool(
+        description = "Search Elasticsearch documents that are semantically close to a natural language text, \
+            using a semantic_text, dense_vector or sparse_vector field.",
+        annotations(title = "Elasticsearch semantic search", read_only_hint = true)
+    )]
+    async fn semantic_search(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(SemanticSearchParams {
+            index,
+            field,
+            query,
+            inference_id,
+            size,
+        }): Parameters<SemanticSearchParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        // The query depends on the field type
+        let response = send!(
+            es_client
+                .indices()
+                .get_field_mapping(IndicesGetFieldMappingParts::IndexFields(&[&index], &[&field]))
+        )
+        .await;
+        let response: Value = read_json(response).await?;
+        let field_type = match semantic::field_type(&response, &field) {
+            Ok(Some(field_type)) => field_type,
+            Ok(None) => {
+                return Ok(CallToolResult::error(vec![Content::text(format!(
+                    "Field '{field}' not found in '{index}'"
+                ))]));
+            }
+            Err(msg) => return Ok(CallToolResult::error(vec![Content::text(msg)])),
+        };
+
+        let body = match semantic::search_body(&field_type, &field, &query, inference_id.as_deref(), size.unwrap_or(10))
+        {
+            Ok(body) => body,
+            Err(msg) => return Ok(CallToolResult::error(vec![Content::text(msg)])),
+        };
+
+        let response = send!(es_client.search(SearchParts::Index(&[&index])).body(body)).await;
+        let response: SearchResult = read_json(response).await?;
+
+        Ok(CallToolResult::success(search_contents(&response)?))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: kNN search
+    #[tool(
+        description = "Search the nearest neighbors of a vector in a dense_vector field of Elasticsearch documents, \
+            optionally filtered with a query DSL filter.",
+        annotations(title = "Elasticsearch kNN search", read_only_hint = true)
+    )]
+    async fn knn_search(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(KnnSearchParams {
+            index,
+            field,
+            query_vector,
+            k,
+            num_candidates,
+            filter,
+        }): Parameters<KnnSearchParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let k = k.unwrap_or(10);
+        let num_candidates = num_candidates.unwrap_or_else(|| semantic::num_candidates(k));
+        if num_candidates < k || num_candidates > 10_000 {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "num_candidates must be between k ({k}) and 10000"
+            ))]));
+        }
+
+        let mut knn = json!({
+            "field": field,
+            "query_vector": query_vector,
+            "k": k,
+            "num_candidates": num_candidates,
+        });
+        if let Some(filter) = filter {
+            knn["filter"] = Value::Object(filter);
+        }
+        let body = json!({
+            "knn": knn,
+            "size": k,
+            // Vectors are large and meaningless to the LLM
+            "_source": { "excludes": [field] },
+        });
+
+        let es_client = self.es_client.get(req_ctx);
+        let response = send!(es_client.search(SearchParts::Index(&[&index])).body(body)).await;
+        let response: SearchResult = read_json(response).await?;
+
+        Ok(CallToolResult::success(search_contents(&response)?))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: profile a search
+    #[tool(
+        description = "Run an Elasticsearch search with profiling, and return where time is spent: the time of \
+            each search phase and the slowest queries and aggregations of the slowest shards. Search hits are \
+            not returned. Use it to understand why a search is slow.",
+        annotations(title = "Profile an Elasticsearch search", read_only_hint = true)
+    )]
+    async fn profile_search(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(ProfileSearchParams {
+            index,
+            mut query_body,
+            top_shards,
+            top_queries,
+        }): Parameters<ProfileSearchParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        query_body.insert("profile".to_string(), Value::Bool(true));
+        let response = send!(es_client.search(SearchParts::Index(&[&index])).body(query_body)).await;
+        let response: ProfileSearchResponse = read_json(response).await?;
+
+        let shard_count = response.profile.shard_count();
+        let shards = response
+            .profile
+            .summary(top_shards.unwrap_or(5), top_queries.unwrap_or(5));
+        Ok(CallToolResult::success(vec![
+            Content::text(format!(
+                "Search took {} ms on {shard_count} shards. Slowest shards:",
+                response.took
+            )),
+            Content::json(shards)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: validate query DSL
+    #[tool(
+        description = "Validate the query of an Elasticsearch query DSL search request without running it, \
+            and explain how Elasticsearch rewrites it. Use it to check a potentially expensive search.",
+        annotations(title = "Validate an Elasticsearch query", read_only_hint = true)
+    )]
+    async fn validate_query(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(ValidateQueryParams { index, query_body }): Parameters<ValidateQueryParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let Some(query) = query_body.get("query") else {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "The query body has no 'query' property",
+            )]));
+        };
+
+        let response = send!(
+            es_client
+                .indices()
+                .validate_query(IndicesValidateQueryParts::Index(&[&index]))
+                .explain(true)
+                .body(json!({ "query": query }))
+        )
+        .await;
+        let response: ValidateQueryResponse = read_json(response).await?;
+
+        if !response.valid {
+            // The top-level error is only set for parsing errors, explanations have shard-level errors
+            let error = response
+                .error
+                .or_else(|| response.explanations.iter().find_map(|e| e.error.clone()))
+                .unwrap_or_else(|| "unknown error".to_string());
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "The query is invalid: {error}"
+            ))]));
+        }
+
+        let explanations = response
+            .explanations
+            .into_iter()
+            .filter_map(|e| Some(json!({ "index": e.index?, "explanation": e.explanation? })))
+            .collect::<Vec<_>>();
+
+        Ok(CallToolResult::success(vec![
+            Content::text("The query is valid. Rewritten query:"),
+            Content::json(explanations)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: aggregate documents
+    ///
+    /// LLMs often get nested aggregations wrong: this tool builds them from a flat description, and
+    /// limits the number of groups to protect the cluster and the context size.
+    #[tool(
+        description = "Compute metrics over groups of documents of Elasticsearch indices, grouped by time \
+            interval and/or field values. Returns one row per group. Prefer it to search for aggregations.",
+        annotations(title = "Aggregate Elasticsearch documents", read_only_hint = true)
+    )]
+    async fn aggregate(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(AggregateParams {
+            index,
+            query,
+            date_histogram,
+            group_by,
+            group_size,
+            metrics,
+        }): Parameters<AggregateParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let agg = Aggregation {
+            date_histogram: date_histogram.as_ref(),
+            group_by: &group_by,
+            group_size: group_size.unwrap_or(aggregate::DEFAULT_GROUP_SIZE),
+            metrics: &metrics,
+        };
+        if agg.max_groups() > aggregate::MAX_BUCKETS {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "The aggregation can return up to {} groups, more than the maximum of {}. \
+                Reduce the group size or the number of group-by fields.",
+                agg.max_groups(),
+                aggregate::MAX_BUCKETS
+            ))]));
+        }
+
+        let mut body = json!({ "size": 0, "track_total_hits": true, "aggs": agg.body() });
+        if let Some(query) = query {
+            body["query"] = Value::Object(query);
+        }
+
+        let response = send!(es_client.search(SearchParts::Index(&[&index])).body(body)).await;
+        let response: Value = read_json(response).await?;
+
+        let total = response["hits"]["total"]["value"].as_u64().unwrap_or_default();
+        let rows = agg.rows(&response["aggregations"], total);
+        if rows.len() as u64 > aggregate::MAX_BUCKETS {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "The aggregation returned {} groups, more than the maximum of {}. \
+                Use a larger date histogram interval, or a query that selects a shorter time range.",
+                rows.len(),
+                aggregate::MAX_BUCKETS
+            ))]));
+        }
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("{total} documents, {} groups:", rows.len())),
+            Content::json(rows)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: open consistency token
+    #[tool(
+        description = "Open a consistency token for an Elasticsearch index, to be passed to several searches \
+            so that they all see the same snapshot of the data, unaffected by later changes. Tokens expire after \
+            5 minutes without being used, and are released at the end of the session. Use it with search_after \
+            to page through large results.",
+        annotations(
+            title = "Open a search consistency token",
+            read_only_hint = false,
+            destructive_hint = false,
+            idempotent_hint = false
+        )
+    )]
+    async fn open_consistency_token(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(OpenConsistencyTokenParams { index }): Parameters<OpenConsistencyTokenParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx.clone());
+        let token = pit::open(&es_client, &index, &req_ctx).await?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Consistency token for index '{index}': {token}"
+        ))]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: compute embeddings
+    #[tool(
+        description = "Compute the embeddings of texts with an Elasticsearch inference endpoint: vectors for \
+            text_embedding endpoints, and token weights for sparse_embedding endpoints.",
+        annotations(title = "Compute embeddings", read_only_hint = true, open_world_hint = true)
+    )]
+    async fn infer_embedding(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(InferEmbeddingParams { inference_id, input }): Parameters<InferEmbeddingParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+        let result = inference::infer(&es_client, &inference_id, json!({ "input": input })).await?;
+
+        let embeddings = match result {
+            InferenceResult::TextEmbedding(embeddings)
+            | InferenceResult::TextEmbeddingBytes(embeddings)
+            | InferenceResult::TextEmbeddingBits(embeddings) => {
+                embeddings.into_iter().map(|e| json!(e.embedding)).collect::<Vec<_>>()
+            }
+            InferenceResult::SparseEmbedding(embeddings) => {
+                embeddings.into_iter().map(|e| Value::Object(e.embedding)).collect()
+            }
+            _ => {
+                let msg = inference::task_type_error(&inference_id, &result, "embedding");
+                return Ok(CallToolResult::error(vec![Content::text(msg)]));
+            }
+        };
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("Embeddings of {} texts:", embeddings.len())),
+            Content::json(embeddings)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: rerank documents
+    #[tool(
+        description = "Rank documents by relevance to a query with an Elasticsearch rerank inference endpoint.",
+        annotations(title = "Rerank documents", read_only_hint = true, open_world_hint = true)
+    )]
+    async fn infer_rerank(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(InferRerankParams {
+            inference_id,
+            query,
+            documents,
+        }): Parameters<InferRerankParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+        let body = json!({ "query": query, "input": documents });
+        let result = inference::infer(&es_client, &inference_id, body).await?;
+
+        let InferenceResult::Rerank(ranked) = result else {
+            let msg = inference::task_type_error(&inference_id, &result, "rerank");
+            return Ok(CallToolResult::error(vec![Content::text(msg)]));
+        };
+
+        let rows = ranked
+            .into_iter()
+            .map(|doc| {
+                json!({
+                    "index": doc.index,
+                    "relevance_score": doc.relevance_score,
+                    "document": documents.get(doc.index),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(CallToolResult::success(vec![
+            Content::text("Documents by decreasing relevance:"),
+            Content::json(rows)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: complete a prompt
+    #[tool(
+        description = "Complete a prompt with an Elasticsearch completion inference endpoint.",
+        annotations(title = "Complete a prompt", read_only_hint = true, open_world_hint = true)
+    )]
+    async fn infer_completion(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(InferCompletionParams { inference_id, input }): Parameters<InferCompletionParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+        let result = inference::infer(&es_client, &inference_id, json!({ "input": input })).await?;
+
+        let InferenceResult::Completion(completions) = result else {
+            let msg = inference::task_type_error(&inference_id, &result, "completion");
+            return Ok(CallToolResult::error(vec![Content::text(msg)]));
+        };
+
+        Ok(CallToolResult::success(
+            completions.into_iter().map(|c| Content::text(c.result)).collect(),
+        ))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get a document for update
+    #[tool(
+        description = "Get an Elasticsearch document to modify it, with an update token to pass to put_if_unchanged \
+            so that the changes don't overwrite concurrent ones.",
+        annotations(title = "Get an ES document for update", read_only_hint = true)
+    )]
+    async fn get_for_update(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(GetForUpdateParams { index, id }): Parameters<GetForUpdateParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let Some((source, token)) = writes::get_for_update(&es_client, &index, &id).await? else {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Document '{id}' not found in index '{index}'"
+            ))]));
+        };
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("Document '{id}' of index '{index}', update token: {token}")),
+            Content::json(source)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: replace a document if it wasn't changed
+    #[tool(
+        description = "Replace an Elasticsearch document read with get_for_update, only if it wasn't changed since. \
+            If it was, get it again and reapply the changes.",
+        annotations(
+            title = "Update an ES document if unchanged",
+            read_only_hint = false,
+            destructive_hint = true,
+            idempotent_hint = false
+        )
+    )]
+    async fn put_if_unchanged(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(PutIfUnchangedParams {
+            index,
+            id,
+            update_token,
+            document,
+            refresh,
+            dry_run,
+        }): Parameters<PutIfUnchangedParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let token = update_token
+            .parse::<UpdateToken>()
+            .map_err(|msg| rmcp::Error::invalid_params(msg, None))?;
+
+        let document = Value::Object(document);
+        if self.dry_run || dry_run {
+            let path = format!(
+                "/{index}/_doc/{id}?if_seq_no={}&if_primary_term={}&refresh={}",
+                token.seq_no,
+                token.primary_term,
+                refresh.as_str()
+            );
+            return writes::dry_run_result(
+                format!(
+                    "Document '{id}' of index '{index}' would be replaced, if it wasn't changed since it was read."
+                ),
+                vec![PlannedCall::new("PUT", path).with_body(document)],
+            );
+        }
+        let Some((result, token)) =
+            writes::put_if_unchanged(&es_client, &index, &id, &document, token, refresh).await?
+        else {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Document '{id}' was changed or deleted since it was read. Get it again with get_for_update and \
+                reapply the changes."
+            ))]));
+        };
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Document '{id}' {result}, new update token: {token}"
+        ))]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: import NDJSON documents
+    #[tool(
+        description = "Import documents from NDJSON data (one JSON document per line) into an existing \
+            Elasticsearch index. Documents are checked against the index mapping, and those that can't be \
+            imported are reported with their line number.",
+        annotations(
+            title = "Import NDJSON documents",
+            read_only_hint = false,
+            destructive_hint = false,
+            idempotent_hint = false
+        )
+    )]
+    async fn import_ndjson(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(ImportNdjsonParams {
+            index,
+            resource,
+            path,
+            refresh,
+            dry_run,
+        }): Parameters<ImportNdjsonParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let owner = Owner::of(&req_ctx);
+        let text = match (resource, path) {
+            (Some(uri), None) => match self.read_stored_resource(&owner, &uri) {
+                Some(ResourceContents::TextResourceContents { text, .. }) => text,
+                _ => {
+                    return Ok(CallToolResult::error(vec![Content::text(format!(
+                        "Resource '{uri}' not found, it may have expired"
+                    ))]));
+                }
+            },
+            (None, Some(path)) => {
+                let Some(root) = &self.import_root else {
+                    return Ok(CallToolResult::error(vec![Content::text(
+                        "File imports are disabled, as no import directory is configured",
+                    )]));
+                };
+                let file = match ndjson::resolve_path(root, &path) {
+                    Ok(file) => file,
+                    Err(msg) => return Ok(CallToolResult::error(vec![Content::text(msg)])),
+                };
+                let size = tokio::fs::metadata(&file).await.map_err(internal_error)?.len();
+                if size > ndjson::MAX_IMPORT_SIZE {
+                    return Ok(CallToolResult::error(vec![Content::text(format!(
+                        "File '{path}' is too large ({size} bytes), the maximum is {} bytes",
+                        ndjson::MAX_IMPORT_SIZE
+                    ))]));
+                }
+                tokio::fs::read_to_string(&file).await.map_err(internal_error)?
+            }
+            _ => {
+                return Err(rmcp::Error::invalid_params("Provide either 'resource' or 'path'", None));
+            }
+        };
+
+        let es_client = self.es_client.get(req_ctx.clone());
+
+        // Check documents against the mapping, to report errors with their line numbers
+        let response = send!(
+            es_client
+                .indices()
+                .get_mapping(IndicesGetMappingParts::Index(&[&index]))
+        )
+        .await;
+        if let Ok(response) = &response
+            && response.status_code() == StatusCode::NOT_FOUND
+        {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Index '{index}' not found"
+            ))]));
+        }
+        let response: Map<String, Value> = read_json(response).await?;
+        let Some(mapping) = response.values().find_map(|index| index.get("mappings")) else {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Index '{index}' not found"
+            ))]));
+        };
+        let validator = DocumentValidator::new(mapping);
+
+        let mut failures = Vec::new();
+        let mut docs = Vec::new();
+        for doc in ndjson::parse(&text) {
+            match doc.and_then(|(line, doc)| match validator.validate(&doc) {
+                Ok(()) => Ok((line, doc)),
+                Err(reason) => Err(LineFailure { line, reason }),
+            }) {
+                Ok(doc) => docs.push(doc),
+                Err(failure) => failures.push(failure),
+            }
+        }
+        let total = docs.len() + failures.len();
+
+        if self.dry_run || dry_run {
+            // Documents are checked against the mapping, but not sent
+            let batches = docs.len().div_ceil(ndjson::BULK_BATCH_SIZE);
+            let calls = (0..batches)
+                .map(|_| PlannedCall::new("POST", format!("/{index}/_bulk?refresh={}", refresh.as_str())))
+                .collect();
+            let mut result = writes::dry_run_result(
+                format!(
+                    "{} of {total} documents would be imported into '{index}' with {batches} bulk requests of at \
+                    most {} documents, {} failed the mapping checks.",
+                    docs.len(),
+                    ndjson::BULK_BATCH_SIZE,
+                    failures.len()
+                ),
+                calls,
+            )?;
+            failures.truncate(MAX_REPORTED_FAILURES);
+            if !failures.is_empty() {
+                result.content.push(Content::json(failures)?);
+            }
+            return Ok(result);
+        }
+
+        let progress_token = req_ctx.meta.get_progress_token();
+        let mut sent = 0;
+        for batch in docs.chunks(ndjson::BULK_BATCH_SIZE) {
+            let lines = batch.iter().map(|(line, _)| *line).collect::<Vec<_>>();
+            let body = batch
+                .iter()
+                .flat_map(|(_, doc)| [json!({ "index": {} }), Value::Object(doc.clone())])
+                .map(JsonBody::new)
+                .collect::<Vec<_>>();
+            let response = send!(
+                es_client
+                    .bulk(BulkParts::Index(&index))
+                    .refresh(refresh.into())
+                    .body(body)
+            )
+            .await;
+            let response: Value = read_json(response).await?;
+            failures.extend(ndjson::bulk_failures(&response, &lines));
+
+            sent += batch.len();
+            if let Some(progress_token) = &progress_token {
+                let _ = req_ctx
+                    .peer
+                    .notify_progress(ProgressNotificationParam {
+                        progress_token: progress_token.clone(),
+                        progress: sent as u32,
+                        total: Some(docs.len() as u32),
+                        message: Some(format!("Sent {sent} of {} documents", docs.len())),
+                    })
+                    .await;
+            }
+        }
+
+        failures.sort_by_key(|f| f.line);
+        let imported = total - failures.len();
+        let mut contents = vec![Content::text(format!(
+            "Imported {imported} of {total} documents into '{index}', {} failed",
+            failures.len()
+        ))];
+        if failures.len() > MAX_REPORTED_FAILURES {
+            let uri = self.resources.add(
+                &owner,
+                "errors",
+                format!("Import failures into '{index}'"),
+                "application/json",
+                serde_json::to_string_pretty(&failures).map_err(internal_error)?,
+            );
+            contents.push(Content::text(format!(
+                "First {MAX_REPORTED_FAILURES} failures, all failures are in resource {uri}"
+            )));
+            failures.truncate(MAX_REPORTED_FAILURES);
+        }
+        if !failures.is_empty() {
+            contents.push(Content::json(failures)?);
+        }
+
+        if imported == 0 && total > 0 {
+            Ok(CallToolResult::error(contents))
+        } else {
+            Ok(CallToolResult::success(contents))
+        }
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: ES|QL
+    #[tool(
+        description = "Perform an Elasticsearch ES|QL query.",
+        annotations(title = "Elasticsearch ES|QL query", read_only_hint = true)
+    )]
+    async fn query_esql(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(EsqlQueryParams {
+            query,
+            params,
+            time_zone,
+        }): Parameters<EsqlQueryParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        if let Some(msg) = esql::check_references(&es_client, &self.index_policy, &query).await? {
+            return Ok(CallToolResult::error(vec![Content::text(msg)]));
+        }
+
+        let request = EsqlQueryRequest {
+            query,
+            params,
+            time_zone,
+        };
+
+        let response = send!(es_client.esql().query().body(request)).await;
+        let response: EsqlQueryResponse = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![
+            Content::text("Results"),
+            Content::json(esql_objects(response))?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: validate ES|QL
+    #[tool(
+        description = "Validate an Elasticsearch ES|QL query without running it, and return its syntax and \
+            semantic errors with their line and column, or the columns of its result if it is valid. \
+            Use it to check a query before running it with the query_esql tool.",
+        annotations(title = "Validate an ES|QL query", read_only_hint = true)
+    )]
+    async fn validate_esql(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(EsqlQueryParams {
+            query,
+            params,
+            time_zone,
+        }): Parameters<EsqlQueryParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        if let Some(msg) = esql::check_references(&es_client, &self.index_policy, &query).await? {
+            return Ok(CallToolResult::error(vec![Content::text(msg)]));
+        }
+
+        // ES|QL has no dry run mode: with a zero limit, the query is parsed, verified and planned, but no
+        // data is read. A new line ends a trailing line comment.
+        let request = EsqlQueryRequest {
+            query: format!("{query}\n| LIMIT 0"),
+            params,
+            time_zone,
+        };
+
+        let response = send!(es_client.esql().query().body(request))
+            .await
+            .map_err(internal_error)?;
+
+        if response.status_code().is_client_error() {
+            let body: Value = response.json().await.map_err(internal_error)?;
+            let reason = body
+                .pointer("/error/reason")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown error");
+            return Ok(CallToolResult::error(vec![
+                Content::text("The query is invalid:"),
+                Content::json(esql::query_errors(reason))?,
+            ]));
+        }
+
+        let response: EsqlQueryResponse = read_json(Ok(response)).await?;
+        Ok(CallToolResult::success(vec![
+            Content::text("The query is valid. Result columns:"),
+            Content::json(response.columns)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: SQL
+    #[tool(
+        description = "Perform an Elasticsearch SQL query. Large results are paginated: call the tool again with \
+            the returned cursor to get the next page.",
+        annotations(title = "Elasticsearch SQL query", read_only_hint = true)
+    )]
+    async fn sql_query(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(SqlQueryParams {
+            query,
+            cursor,
+            params,
+            fetch_size,
+            time_zone,
+        }): Parameters<SqlQueryParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let request = match (query, cursor) {
+            (Some(query), None) => SqlQueryRequest {
+                query: Some(query),
+                cursor: None,
+                params,
+                fetch_size,
+                time_zone,
+            },
+            // Other parameters are those of the query that opened the cursor
+            (None, Some(cursor)) => SqlQueryRequest {
+                query: None,
+                cursor: Some(cursor),
+                params: None,
+                fetch_size: None,
+                time_zone: None,
+            },
+            _ => {
+                return Err(rmcp::Error::invalid_params("Provide either 'query' or 'cursor'", None));
+            }
+        };
+        let is_first_page = request.query.is_some();
+
+        let response = send!(es_client.sql().query().format("json").body(request)).await;
+        let response: SqlQueryResponse = read_json(response).await?;
+
+        // Columns are only returned with the first page
+        let mut contents = if is_first_page {
+            vec![
+                Content::text(format!("{} rows:", response.rows.len())),
+                Content::json(row_objects(&response.columns, response.rows))?,
+            ]
+        } else {
+            vec![
+                Content::text(format!(
+                    "{} rows, with the values of the columns of the first page:",
+                    response.rows.len()
+                )),
+                Content::json(response.rows)?,
+            ]
+        };
+        if let Some(cursor) = response.cursor {
+            contents.push(Content::text(format!(
+                "More rows are available, use this cursor to get the next page: {cursor}"
+            )));
+        }
+        Ok(CallToolResult::success(contents))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: translate SQL to query DSL
+    #[tool(
+        description = "Translate an Elasticsearch SQL query to the query DSL search request that runs it, without \
+            running it. Use it to check how a SQL query is executed, or to write a query DSL search from SQL.",
+        annotations(title = "Translate SQL to ES query DSL", read_only_hint = true)
+    )]
+    async fn sql_translate(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(SqlTranslateParams { query }): Parameters<SqlTranslateParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let response = send!(es_client.sql().translate().body(json!({ "query": query }))).await;
+        let response: Map<String, Value> = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![
+            Content::text("Query DSL search request of the SQL query:"),
+            Content::json(response)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: EQL
+    #[tool(
+        description = "Perform an Elasticsearch EQL search for events, or for sequences of events that happen in \
+            order within a time span, e.g. to hunt for threats in security and log data.",
+        annotations(title = "Elasticsearch EQL search", read_only_hint = true)
+    )]
+    async fn eql_search(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(EqlSearchParams {
+            index,
+            query,
+            size,
+            filter,
+            timestamp_field,
+            fields,
+        }): Parameters<EqlSearchParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let timestamp_field = timestamp_field.unwrap_or_else(|| "@timestamp".to_string());
+        let mut body = json!({
+            "query": query,
+            "size": size.unwrap_or(10),
+            "timestamp_field": timestamp_field,
+        });
+        if let Some(filter) = filter {
+            body["filter"] = Value::Object(filter);
+        }
+        if let Some(fields) = fields {
+            body["fields"] = json!(fields);
+        }
+
+        let response = send!(es_client.eql().search(EqlSearchParts::Index(&index)).body(body)).await;
+        let response: EqlSearchResponse = read_json(response).await?;
+
+        let mut contents = Vec::new();
+        if response.is_partial || response.timed_out {
+            contents.push(Content::text(
+                "Partial results: the search timed out or failed on some shards",
+            ));
+        }
+        let result = response.hits.compact(&timestamp_field);
+        contents.push(Content::text(if result.sequences.is_empty() {
+            format!("Found {} events:", result.events.len())
+        } else {
+            format!("Found {} sequences:", result.sequences.len())
+        }));
+        contents.push(Content::json(result)?);
+        Ok(CallToolResult::success(contents))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: explain an error
+    #[tool(
+        description = "Explain an Elasticsearch error: its root causes, likely reasons, and the tools to call next \
+            to fix it. Use it when a tool call fails with an error that isn't self-explanatory.",
+        annotations(title = "Explain an Elasticsearch error", read_only_hint = true)
+    )]
+    async fn explain_error(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(ExplainErrorParams { error, error_resource }): Parameters<ExplainErrorParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let text = match (error, error_resource) {
+            (Some(error), None) => error,
+            (None, Some(uri)) => match self.resources.read(&Owner::of(&req_ctx), &uri) {
+                Some(ResourceContents::TextResourceContents { text, .. }) => text,
+                _ => {
+                    return Ok(CallToolResult::error(vec![Content::text(format!(
+                        "Resource '{uri}' not found, it may have expired"
+                    ))]));
+                }
+            },
+            _ => {
+                return Err(rmcp::Error::invalid_params(
+                    "Either 'error' or 'error_resource' must be provided",
+                    None,
+                ));
+            }
+        };
+
+        let explanation = error_hints::explain(&text);
+        let msg = if explanation.explanations.is_empty() {
+            "No known explanation for this error. The reasons of its most specific causes describe the problem"
+        } else {
+            "Error explanation"
+        };
+        Ok(CallToolResult::success(vec![
+            Content::text(msg),
+            Content::json(explanation)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    // Tool: get shard information
+    #[tool(
+        description = "Get shard information for all or specific indices.",
+        annotations(title = "Get ES shard information", read_only_hint = true)
+    )]
+    async fn get_shards(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(GetShardsParams { index }): Parameters<GetShardsParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let indices: [&str; 1];
+        let parts = match &index {
+            Some(index) => {
+                indices = [index];
+                CatShardsParts::Index(&indices)
+            }
+            None => CatShardsParts::None,
+        };
+        let response = send!(
+            es_client
+                .cat()
+                .shards(parts)
+                .format("json")
+                .h(&["index", "shard", "prirep", "state", "docs", "store", "node"])
+        )
+        .await;
+
+        let response: Vec<CatShardsResponse> = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("Found {} shards:", response.len())),
+            Content::json(response)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get nodes
+    #[tool(
+        description = "Get the nodes of the cluster, with their roles and resource usage (heap, RAM, CPU, load, disk).",
+        annotations(title = "Get ES nodes", read_only_hint = true)
+    )]
+    async fn get_nodes(&self, req_ctx: RequestContext<RoleServer>) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let response = send!(
+            es_client
+                .cat()
+                .nodes()
+                .format("json")
+                .h(&[
+                    "name",
+                    "ip",
+                    "node.role",
+                    "master",
+                    "heap.percent",
+                    "ram.percent",
+                    "cpu",
+                    "load_1m",
+                    "disk.used_percent",
+                ])
+                .s(&["name"])
+        )
+        .await;
+
+        let response: Vec<CatNodesResponse> = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("Found {} nodes:", response.len())),
+            Content::json(response)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get disk allocation
+    #[tool(
+        description = "Get the number of shards and the disk usage of each data node. Unassigned shards are \
+            reported on an `UNASSIGNED` node.",
+        annotations(title = "Get ES disk allocation", read_only_hint = true)
+    )]
+    async fn get_allocation(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(GetAllocationParams { node }): Parameters<GetAllocationParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let nodes: [&str; 1];
+        let parts = match &node {
+            Some(node) => {
+                nodes = [node];
+                CatAllocationParts::NodeId(&nodes)
+            }
+            None => CatAllocationParts::None,
+        };
+        let response = send!(es_client.cat().allocation(parts).format("json").h(&[
+            "node",
+            "shards",
+            "disk.indices",
+            "disk.used",
+            "disk.avail",
+            "disk.total",
+            "disk.percent",
+        ]))
+        .await;
+
+        let response: Vec<CatAllocationResponse> = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("Disk allocation of {} nodes:", response.len())),
+            Content::json(response)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get thread pools
+    #[tool(
+        description = "Get the active, queued, rejected and completed tasks of the thread pools of each node. \
+            Rejections indicate that a node is overloaded.",
+        annotations(title = "Get ES thread pools", read_only_hint = true)
+    )]
+    async fn get_thread_pools(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(GetThreadPoolsParams {
+            thread_pool,
+            include_idle,
+        }): Parameters<GetThreadPoolsParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let patterns: [&str; 1];
+        let parts = match &thread_pool {
+            Some(thread_pool) => {
+                patterns = [thread_pool];
+                CatThreadPoolParts::ThreadPoolPatterns(&patterns)
+            }
+            None => CatThreadPoolParts::None,
+        };
+        let response = send!(
+            es_client
+                .cat()
+                .thread_pool(parts)
+                .format("json")
+                .h(&["node_name", "name", "active", "queue", "rejected", "completed"])
+                .s(&["node_name", "name"])
+        )
+        .await;
+
+        let mut response: Vec<CatThreadPoolResponse> = read_json(response).await?;
+        let count = response.len();
+        if !include_idle.unwrap_or(false) {
+            response.retain(|pool| !pool.is_idle());
+        }
+
+        let msg = if response.len() < count {
+            format!(
+                "Found {count} thread pools, {} with active, queued or rejected tasks:",
+                response.len()
+            )
+        } else {
+            format!("Found {count} thread pools:")
+        };
+        Ok(CallToolResult::success(vec![
+            Content::text(msg),
+            Content::json(response)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: list tasks
+    #[tool(
+        description = "List the tasks running in the cluster (searches, bulk requests, reindexing, etc.), \
+            slowest first, optionally filtered by action and node.",
+        annotations(title = "List ES tasks", read_only_hint = true)
+    )]
+    async fn list_tasks(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(ListTasksParams { actions, nodes }): Parameters<ListTasksParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let mut query = vec![("detailed", "true"), ("group_by", "none")];
+        if let Some(actions) = &actions {
+            query.push(("actions", actions));
+        }
+        if let Some(nodes) = &nodes {
+            query.push(("nodes", nodes));
+        }
+
+        // The tasks API is experimental in the client, and not enabled in this build
+        let span = tracing::info_span!("elasticsearch_request", otel.kind = "client");
+        let response = es_client
+            .send(
+                Method::Get,
+                "/_tasks",
+                es_client.headers(&span),
+                Some(&query),
+                Option::<JsonBody<()>>::None,
+                None,
+            )
+            .instrument(span)
+            .await;
+
+        let mut response: TasksResponse = read_json(response).await?;
+        response
+            .tasks
+            .sort_by_key(|task| std::cmp::Reverse(task.running_time_in_nanos));
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("Found {} tasks:", response.tasks.len())),
+            Content::json(response.tasks)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get the status of a task
+    #[tool(
+        description = "Get the status of a task, such as a reindex started with the reindex tool: its progress \
+            while it runs, and its result or error once completed.",
+        annotations(title = "Get ES task status", read_only_hint = true)
+    )]
+    async fn get_task_status(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(GetTaskStatusParams { task_id }): Parameters<GetTaskStatusParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        check_task_id(&task_id)?;
+
+        let es_client = self.es_client.get(req_ctx);
+        let span = tracing::info_span!("elasticsearch_request", otel.kind = "client");
+        let response = es_client
+            .send(
+                Method::Get,
+                &format!("/_tasks/{task_id}"),
+                es_client.headers(&span),
+                Option::<&()>::None,
+                Option::<JsonBody<()>>::None,
+                None,
+            )
+            .instrument(span)
+            .await;
+
+        if let Ok(response) = &response
+            && response.status_code() == StatusCode::NOT_FOUND
+        {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Task '{task_id}' not found"
+            ))]));
+        }
+
+        let response: GetTaskResponse = read_json(response).await?;
+        let status = TaskStatus::new(response);
+        let msg = match (status.completed, &status.error, status.progress_percent) {
+            (true, Some(_), _) => format!("Task '{task_id}' failed:"),
+            (true, None, _) => format!("Task '{task_id}' completed:"),
+            (false, _, Some(progress)) => format!("Task '{task_id}' is running, {progress}% done:"),
+            (false, _, None) => format!("Task '{task_id}' is running:"),
+        };
+        Ok(CallToolResult::success(vec![
+            Content::text(msg),
+            Content::json(status)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: cancel a task
+    #[tool(
+        description = "Cancel a running task, such as a long search or a reindex job. Only tasks listed as \
+            cancellable by list_tasks can be cancelled. Work done before cancellation is not rolled back.",
+        annotations(
+            title = "Cancel an ES task",
+            read_only_hint = false,
+            destructive_hint = true,
+            idempotent_hint = false
+        )
+    )]
+    async fn cancel_task(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(CancelTaskParams { task_id, dry_run }): Parameters<CancelTaskParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        check_task_id(&task_id)?;
+
+        if self.dry_run || dry_run {
+            return writes::dry_run_result(
+                format!("Task '{task_id}' and its child tasks would be cancelled."),
+                vec![PlannedCall::new("POST", format!("/_tasks/{task_id}/_cancel"))],
+            );
+        }
+
+        let es_client = self.es_client.get(req_ctx);
+        let span = tracing::info_span!("elasticsearch_request", otel.kind = "client");
+        let response = es_client
+            .send(
+                Method::Post,
+                &format!("/_tasks/{task_id}/_cancel"),
+                es_client.headers(&span),
+                Option::<&()>::None,
+                Option::<JsonBody<()>>::None,
+                None,
+            )
+            .instrument(span)
+            .await;
+
+        if let Ok(response) = &response
+            && response.status_code() == StatusCode::NOT_FOUND
+        {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Task '{task_id}' not found. It may have already completed."
+            ))]));
+        }
+
+        let response: CancelTasksResponse = read_json(response).await?;
+        if let Some(failure) = response.task_failures.first() {
+            let reason = failure.pointer("/reason/reason").and_then(Value::as_str);
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Task '{task_id}' could not be cancelled: {}",
+                reason.unwrap_or("unknown reason")
+            ))]));
+        }
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Task '{task_id}' cancelled. It stops at its next cancellation check, and its child tasks are also \
+            cancelled."
+        ))]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: reindex
+    #[tool(
+        description = "Copy documents from an index to another one, optionally selecting them with a query and \
+            transforming them with an ingest pipeline. The copy runs in the background: the result is a task id \
+            to follow with get_task_status, or to stop with cancel_task. The destination index should be created \
+            first with the desired mappings.",
+        annotations(
+            title = "Reindex ES documents",
+            read_only_hint = false,
+            destructive_hint = true,
+            idempotent_hint = false
+        )
+    )]
+    async fn reindex(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(ReindexParams {
+            source_index,
+            dest_index,
+            query,
+            pipeline,
+            max_docs,
+            refresh,
+            dry_run,
+        }): Parameters<ReindexParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let mut source = json!({ "index": source_index });
+        if let Some(query) = query {
+            source["query"] = Value::Object(query);
+        }
+        let mut dest = json!({ "index": dest_index });
+        if let Some(pipeline) = pipeline {
+            dest["pipeline"] = Value::String(pipeline);
+        }
+        let mut body = json!({ "source": source, "dest": dest });
+        if let Some(max_docs) = max_docs {
+            body["max_docs"] = max_docs.into();
+        }
+
+        if self.dry_run || dry_run {
+            return writes::dry_run_result(
+                format!("Documents of '{source_index}' would be copied to '{dest_index}' by a background task."),
+                vec![
+                    PlannedCall::new("POST", format!("/_reindex?wait_for_completion=false&refresh={refresh}"))
+                        .with_body(body),
+                ],
+            );
+        }
+
+        let es_client = self.es_client.get(req_ctx);
+        let response = send!(
+            es_client
+                .reindex()
+                .wait_for_completion(false)
+                .refresh(refresh)
+                .body(body)
+        )
+        .await;
+        let response: ReindexTaskResponse = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!(
+                "Reindex from '{source_index}' to '{dest_index}' started as task '{}'. Follow its progress with \
+                get_task_status.",
+                response.task
+            )),
+            Content::json(json!({ "task_id": response.task }))?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: list aliases
+    #[tool(
+        description = "List index aliases, with the indices they point to.",
+        annotations(title = "List ES aliases", read_only_hint = true)
+    )]
+    async fn list_aliases(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(ListAliasesParams { alias }): Parameters<ListAliasesParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+        let mut aliases = cat_aliases(&es_client, alias.as_deref()).await?;
+        aliases.retain(|a| self.index_policy.is_allowed(&a.alias) && self.index_policy.is_allowed(&a.index));
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("Found {} aliases:", aliases.len())),
+            Content::json(aliases)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: create an alias
     #[tool(
-        description = "Perform an Elasticsearch search with the provided query DSL.",
-        annotations(title = "Elasticsearch search DSL query", read_only_hint = true)
+        description = "Create an index alias, or add indices to an existing alias. Use swap_alias to move an alias \
+            to another index.",
+        annotations(
+            title = "Create an ES alias",
+            read_only_hint = false,
+            destructive_hint = true,
+            idempotent_hint = false
+        )
     )]
-    async fn search(
+    async fn create_alias(
         &self,
         req_ctx: RequestContext<RoleServer>,
-        Parameters(SearchParams {
+        Parameters(CreateAliasParams {
+            alias,
             index,
-            fields,
-            query_body,
-        }): Parameters<SearchParams>,
+            filter,
+            is_write_index,
+            dry_run,
+        }): Parameters<CreateAliasParams>,
     ) -> Result<CallToolResult, rmcp::Error> {
-        let es_client = self.es_client.get(req_ctx);
-
-        let mut query_body = query_body;
-
-        if let Some(fields) = fields {
-            // Augment _source if it exists
-            if let Some(Value::Array(values)) = query_body.get_mut("_source") {
-                for field in fields.into_iter() {
-                    values.push(Value::String(field))
-                }
-            } else {
-                query_body.insert("_source".to_string(), json!(fields));
-            }
+        let mut add = json!({ "index": index, "alias": alias });
+        if let Some(filter) = filter {
+            add["filter"] = Value::Object(filter);
         }
-
-        let response = es_client
-            .search(SearchParts::Index(&[&index]))
-            .body(query_body)
-            .send()
-            .await;
-
-        let response: SearchResult = read_json(response).await?;
-
-        let mut results: Vec<Content> = Vec::new();
-
-        // Send result stats only if it's not pure aggregation results
-        if response.aggregations.is_empty() || !response.hits.hits.is_empty() {
-            let total = response
-                .hits
-                .total
-                .map(|t| t.value.to_string())
-                .unwrap_or("unknown".to_string());
-
-            results.push(Content::text(format!(
-                "Total results: {}, showing {}.",
-                total,
-                response.hits.hits.len()
-            )));
+        if let Some(is_write_index) = is_write_index {
+            add["is_write_index"] = is_write_index.into();
         }
+        let body = json!({ "actions": [{ "add": add }] });
 
-        // Original prototype sent a separate content for each document, it seems to confuse some LLMs
-        // for hit in &response.hits.hits {
-        //     results.push(Content::json(&hit.source)?);
-        // }
-        if !response.hits.hits.is_empty() {
-            let sources = response.hits.hits.iter().map(|hit| &hit.source).collect::<Vec<_>>();
-            results.push(Content::json(&sources)?);
+        if self.dry_run || dry_run {
+            return writes::dry_run_result(
+                format!("Alias '{alias}' would point to '{index}'."),
+                vec![PlannedCall::new("POST", "/_aliases").with_body(body)],
+            );
         }
 
-        if !response.aggregations.is_empty() {
-            results.push(Content::text("Aggregations results:"));
-            results.push(Content::json(&response.aggregations)?);
-        }
+        let es_client = self.es_client.get(req_ctx);
+        let response = send!(es_client.indices().update_aliases().body(body)).await;
+        let _: Value = read_json(response).await?;
 
-        Ok(CallToolResult::success(results))
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Alias '{alias}' now points to '{index}'"
+        ))]))
     }
 
     //---------------------------------------------------------------------------------------------
-    /// Tool: ES|QL
+    /// Tool: swap an alias
     #[tool(
-        description = "Perform an Elasticsearch ES|QL query.",
-        annotations(title = "Elasticsearch ES|QL query", read_only_hint = true)
+        description = "Atomically move an existing alias from its current indices to another index, e.g. to switch \
+            to a reindexed copy without downtime. Filters and routing of the alias are not copied.",
+        annotations(
+            title = "Swap an ES alias",
+            read_only_hint = false,
+            destructive_hint = true,
+            idempotent_hint = false
+        )
     )]
-    async fn esql(
+    async fn swap_alias(
         &self,
         req_ctx: RequestContext<RoleServer>,
-        Parameters(EsqlQueryParams { query }): Parameters<EsqlQueryParams>,
+        Parameters(SwapAliasParams { alias, index, dry_run }): Parameters<SwapAliasParams>,
     ) -> Result<CallToolResult, rmcp::Error> {
         let es_client = self.es_client.get(req_ctx);
 
-        let request = EsqlQueryRequest { query };
+        let current = cat_aliases(&es_client, Some(&alias))
+            .await?
+            .into_iter()
+            .filter(|a| a.alias == alias)
+            .map(|a| a.index)
+            .collect::<Vec<_>>();
+        if current.is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Alias '{alias}' doesn't exist. Use create_alias to create it."
+            ))]));
+        }
+        if current == [index.as_str()] {
+            return Ok(CallToolResult::success(vec![Content::text(format!(
+                "Alias '{alias}' already points to '{index}'"
+            ))]));
+        }
 
-        let response = es_client.esql().query().body(request).send().await;
-        let response: EsqlQueryResponse = read_json(response).await?;
+        // Removals and addition are applied atomically
+        let mut actions = current
+            .iter()
+            .filter(|i| **i != index)
+            .map(|i| json!({ "remove": { "index": i, "alias": alias } }))
+            .collect::<Vec<_>>();
+        actions.push(json!({ "add": { "index": index, "alias": alias } }));
+        let body = json!({ "actions": actions });
+        let current = current.iter().map(|i| format!("'{i}'")).collect::<Vec<_>>().join(", ");
 
-        // Transform response into an array of objects
-        let mut objects: Vec<Value> = Vec::new();
-        for row in response.values.into_iter() {
-            let mut obj = Map::new();
-            for (i, value) in row.into_iter().enumerate() {
-                obj.insert(response.columns[i].name.clone(), value);
-            }
-            objects.push(Value::Object(obj));
+        if self.dry_run || dry_run {
+            return writes::dry_run_result(
+                format!("Alias '{alias}' would be moved from {current} to '{index}'."),
+                vec![PlannedCall::new("POST", "/_aliases").with_body(body)],
+            );
         }
 
-        Ok(CallToolResult::success(vec![
-            Content::text("Results"),
-            Content::json(objects)?,
-        ]))
+        let response = send!(es_client.indices().update_aliases().body(body)).await;
+        let _: Value = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Alias '{alias}' moved from {current} to '{index}'"
+        ))]))
     }
 
     //---------------------------------------------------------------------------------------------
-    // Tool: get shard information
+    /// Tool: get pending cluster tasks
     #[tool(
-        description = "Get shard information for all or specific indices.",
-        annotations(title = "Get ES shard information", read_only_hint = true)
+        description = "Get the cluster-level changes (index creation, mapping updates, shard allocation, etc.) \
+            that are waiting to be applied by the master node. A long queue slows down the whole cluster.",
+        annotations(title = "Get ES pending cluster tasks", read_only_hint = true)
     )]
-    async fn get_shards(
-        &self,
-        req_ctx: RequestContext<RoleServer>,
-        Parameters(GetShardsParams { index }): Parameters<GetShardsParams>,
-    ) -> Result<CallToolResult, rmcp::Error> {
+    async fn get_pending_tasks(&self, req_ctx: RequestContext<RoleServer>) -> Result<CallToolResult, rmcp::Error> {
         let es_client = self.es_client.get(req_ctx);
 
-        let indices: [&str; 1];
-        let parts = match &index {
-            Some(index) => {
-                indices = [index];
-                CatShardsParts::Index(&indices)
-            }
-            None => CatShardsParts::None,
-        };
-        let response = es_client
-            .cat()
-            .shards(parts)
-            .format("json")
-            .h(&["index", "shard", "prirep", "state", "docs", "store", "node"])
-            .send()
-            .await;
-
-        let response: Vec<CatShardsResponse> = read_json(response).await?;
+        let response = send!(es_client.cluster().pending_tasks()).await;
+        let response: PendingTasksResponse = read_json(response).await?;
 
         Ok(CallToolResult::success(vec![
-            Content::text(format!("Found {} shards:", response.len())),
-            Content::json(response)?,
+            Content::text(format!("Found {} pending cluster tasks:", response.tasks.len())),
+            Content::json(response.tasks)?,
         ]))
     }
 }
@@ -294,11 +3221,168 @@ impl ServerHandler for EsBaseTools {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             protocol_version: ProtocolVersion::V_2025_03_26,
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            capabilities: ServerCapabilities::builder()
+                .enable_completions()
+                .enable_resources()
+                .enable_tools()
+                .build(),
             server_info: Implementation::from_build_env(),
             instructions: Some("Provides access to Elasticsearch".to_string()),
         }
     }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, rmcp::Error> {
+        Ok(ListResourcesResult::with_all_items(
+            self.list_stored_resources(&Owner::of(&context)),
+        ))
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, rmcp::Error> {
+        let contents = self
+            .read_stored_resource(&Owner::of(&context), &request.uri)
+            .ok_or_else(|| rmcp::Error::resource_not_found("resource not found or expired", None))?;
+        Ok(ReadResourceResult {
+            contents: vec![contents],
+        })
+    }
+
+    /// Complete index names.
+    ///
+    /// MCP completion references only target prompts and resources, so completion is driven by the
+    /// argument name: `index` and `index_pattern` arguments are completed with matching index names.
+    async fn complete(
+        &self,
+        request: CompleteRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CompleteResult, rmcp::Error> {
+        let mut values = match request.argument.name.as_str() {
+            "index" | "index_pattern" => self.index_names(&request.argument.value, context).await?,
+            _ => Vec::new(),
+        };
+        values.retain(|index| self.index_policy.is_allowed(index));
+
+        let total = values.len();
+        values.truncate(MAX_COMPLETION_VALUES);
+
+        Ok(CompleteResult {
+            completion: CompletionInfo {
+                has_more: Some(total > values.len()),
+                total: Some(total as u32),
+                values,
+            },
+        })
+    }
+}
+
+/// Aliases matching an optional name or pattern.
+async fn cat_aliases(es_client: &EsClient, alias: Option<&str>) -> Result<Vec<CatAliasesResponse>, rmcp::Error> {
+    let aliases: [&str; 1];
+    let parts = match alias {
+        Some(alias) => {
+            aliases = [alias];
+            CatAliasesParts::Name(&aliases)
+        }
+        None => CatAliasesParts::None,
+    };
+    let response = send!(
+        es_client
+            .cat()
+            .aliases(parts)
+            .format("json")
+            .h(&["alias", "index", "filter", "is_write_index"])
+            .s(&["alias", "index"])
+    )
+    .await;
+    read_json(response).await
+}
+
+/// All index templates and component templates of the cluster.
+async fn all_templates(es_client: &EsClient) -> Result<Templates, rmcp::Error> {
+    let response = send!(
+        es_client
+            .indices()
+            .get_index_template(IndicesGetIndexTemplateParts::None)
+    )
+    .await;
+    let index_templates: IndexTemplatesResponse = read_json(response).await?;
+
+    let response = send!(
+        es_client
+            .cluster()
+            .get_component_template(ClusterGetComponentTemplateParts::None)
+    )
+    .await;
+    let component_templates: ComponentTemplatesResponse = read_json(response).await?;
+
+    Ok(Templates {
+        index_templates: index_templates.index_templates,
+        component_templates: component_templates.component_templates,
+    })
+}
+
+/// Check that a task id is `node:id`, as it's used as is in request paths.
+fn check_task_id(task_id: &str) -> Result<(), rmcp::Error> {
+    let valid = task_id.split_once(':').is_some_and(|(node, id)| {
+        !node.is_empty()
+            && node
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
+            && id.parse::<u64>().is_ok()
+    });
+    if !valid {
+        return Err(rmcp::Error::invalid_params(
+            format!("Invalid task id '{task_id}', expecting 'node:id'"),
+            None,
+        ));
+    }
+    Ok(())
+}
+
+/// Mappings of an index, as a raw JSON value.
+async fn index_mappings(es_client: &EsClient, index: &str) -> Result<Value, rmcp::Error> {
+    let response = send!(es_client.indices().get_mapping(IndicesGetMappingParts::Index(&[index]))).await;
+
+    let response: HashMap<String, RawMappings> = read_json(response).await?;
+
+    // use the first mapping (we can have many if the name is a wildcard)
+    response
+        .into_values()
+        .next()
+        .map(|m| m.mappings)
+        .ok_or_else(|| rmcp::Error::invalid_params(format!("No mappings found for index {index}"), None))
+}
+
+/// Maximum number of values in a completion result, as defined by the MCP spec.
+const MAX_COMPLETION_VALUES: usize = 100;
+
+impl EsBaseTools {
+    /// Sorted names of the indices starting with `prefix`.
+    async fn index_names(&self, prefix: &str, req_ctx: RequestContext<RoleServer>) -> Result<Vec<String>, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+        let pattern = format!("{prefix}*");
+        let response = send!(
+            es_client
+                .cat()
+                .indices(CatIndicesParts::Index(&[&pattern]))
+                .h(&["index"])
+                .format("json")
+        )
+        .await;
+
+        let response: Vec<CatIndexName> = read_json(response).await?;
+
+        let mut names = response.into_iter().map(|i| i.index).collect::<Vec<_>>();
+        names.sort();
+        Ok(names)
+    }
 }
 
 //-------------------------------------------------------------------------------------------------
@@ -306,13 +3390,108 @@ impl ServerHandler for EsBaseTools {
 
 //----- Search request
 
+/// Hit count, documents and aggregations of a search response.
+pub(super) fn search_contents(response: &SearchResult) -> Result<Vec<Content>, rmcp::Error> {
+    let mut contents = Vec::new();
+
+    // Send result stats only if it's not pure aggregation results
+    if response.aggregations.is_empty() || !response.hits.hits.is_empty() {
+        let total = response
+            .hits
+            .total
+            .as_ref()
+            .map(|t| t.value.to_string())
+            .unwrap_or("unknown".to_string());
+
+        contents.push(Content::text(format!(
+            "Total results: {}, showing {}.",
+            total,
+            response.hits.hits.len()
+        )));
+    }
+
+    // Original prototype sent a separate content for each document, it seems to confuse some LLMs
+    // for hit in &response.hits.hits {
+    //     contents.push(Content::json(&hit.source)?);
+    // }
+    if !response.hits.hits.is_empty() {
+        let sources = response.hits.hits.iter().map(Hit::document).collect::<Vec<_>>();
+        contents.push(Content::json(&sources)?);
+    }
+
+    if !response.aggregations.is_empty() {
+        contents.push(Content::text("Aggregations results:"));
+        contents.push(Content::json(&response.aggregations)?);
+    }
+
+    Ok(contents)
+}
+
+/// Transform an ES|QL response into an array of objects.
+pub(super) fn esql_objects(response: EsqlQueryResponse) -> Vec<Value> {
+    row_objects(&response.columns, response.values)
+}
+
+/// Transform rows of values into objects keyed by column name.
+fn row_objects(columns: &[Column], rows: Vec<Vec<Value>>) -> Vec<Value> {
+    let mut objects: Vec<Value> = Vec::new();
+    for row in rows.into_iter() {
+        let mut obj = Map::new();
+        for (i, value) in row.into_iter().enumerate() {
+            obj.insert(columns[i].name.clone(), value);
+        }
+        objects.push(Value::Object(obj));
+    }
+    objects
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct SearchResult {
+    #[serde(default)]
+    pub pit_id: Option<String>,
+    #[serde(rename = "_shards", default)]
+    pub shards: Option<ShardStats>,
     pub hits: Hits,
     #[serde(default)]
     pub aggregations: IndexMap<String, Value>,
 }
 
+#[derive(Deserialize)]
+pub struct MsearchResponse {
+    /// Search results or errors, in the order of the searches
+    pub responses: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+pub struct ProfileSearchResponse {
+    pub took: u64,
+    pub profile: Profile,
+}
+
+#[derive(Deserialize)]
+pub struct ValidateQueryResponse {
+    pub valid: bool,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub explanations: Vec<QueryExplanation>,
+}
+
+#[derive(Deserialize)]
+pub struct QueryExplanation {
+    pub index: Option<String>,
+    pub explanation: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ShardStats {
+    pub total: u64,
+    pub failed: u64,
+    #[serde(default)]
+    pub failures: Vec<Value>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Hits {
     pub total: Option<TotalHits>,
@@ -328,6 +3507,31 @@ pub struct TotalHits {
 pub struct Hit {
     #[serde(rename = "_source")]
     pub source: Value,
+    /// Sort values, to get the next page with `search_after`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<Vec<Value>>,
+    /// Requested fields, such as runtime fields, that are not in `_source`
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub fields: Map<String, Value>,
+}
+
+impl Hit {
+    /// Source of the document, with the values of its requested fields.
+    fn document(&self) -> Cow<'_, Value> {
+        if self.fields.is_empty() {
+            return Cow::Borrowed(&self.source);
+        }
+        let mut document = self.source.as_object().cloned().unwrap_or_default();
+        for (name, values) in &self.fields {
+            // Fields are always arrays, unwrap single values as in `_source`
+            let value = match values.as_array().map(Vec::as_slice) {
+                Some([value]) => value.clone(),
+                _ => values.clone(),
+            };
+            document.insert(name.clone(), value);
+        }
+        Cow::Owned(Value::Object(document))
+    }
 }
 
 //----- Cat responses
@@ -340,6 +3544,11 @@ pub struct CatIndexResponse {
     pub doc_count: u64,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct CatIndexName {
+    pub index: String,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct CatShardsResponse {
     pub index: String,
@@ -353,6 +3562,187 @@ pub struct CatShardsResponse {
     pub node: Option<String>,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct CatAliasesResponse {
+    pub alias: String,
+    pub index: String,
+    /// `*` if the alias has a filter, `-` otherwise
+    pub filter: String,
+    /// `true`, `false`, or `-` if not set
+    pub is_write_index: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CatNodesResponse {
+    pub name: String,
+    pub ip: Option<String>,
+    /// Abbreviated roles, e.g. `m` for master-eligible or `d` for data
+    #[serde(rename = "node.role")]
+    pub role: String,
+    /// `*` for the elected master node
+    pub master: String,
+    #[serde(rename = "heap.percent", deserialize_with = "deserialize_option_number_from_string")]
+    pub heap_percent: Option<u64>,
+    #[serde(rename = "ram.percent", deserialize_with = "deserialize_option_number_from_string")]
+    pub ram_percent: Option<u64>,
+    #[serde(deserialize_with = "deserialize_option_number_from_string")]
+    pub cpu: Option<u64>,
+    #[serde(deserialize_with = "deserialize_option_number_from_string")]
+    pub load_1m: Option<f64>,
+    #[serde(
+        rename = "disk.used_percent",
+        deserialize_with = "deserialize_option_number_from_string"
+    )]
+    pub disk_used_percent: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CatAllocationResponse {
+    pub node: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub shards: u64,
+    #[serde(rename = "disk.indices")]
+    pub disk_indices: Option<String>,
+    #[serde(rename = "disk.used")]
+    pub disk_used: Option<String>,
+    #[serde(rename = "disk.avail")]
+    pub disk_avail: Option<String>,
+    #[serde(rename = "disk.total")]
+    pub disk_total: Option<String>,
+    #[serde(rename = "disk.percent", deserialize_with = "deserialize_option_number_from_string")]
+    pub disk_percent: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CatThreadPoolResponse {
+    pub node_name: String,
+    pub name: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub active: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub queue: u64,
+    /// Rejected tasks since the node started
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub rejected: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub completed: u64,
+}
+
+impl CatThreadPoolResponse {
+    fn is_idle(&self) -> bool {
+        self.active == 0 && self.queue == 0 && self.rejected == 0
+    }
+}
+
+//----- Tasks
+
+#[derive(Serialize, Deserialize)]
+pub struct TasksResponse {
+    #[serde(default)]
+    pub tasks: Vec<TaskInfo>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TaskInfo {
+    pub node: String,
+    pub id: u64,
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub action: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub start_time_in_millis: u64,
+    pub running_time_in_nanos: u64,
+    pub cancellable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cancelled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_task_id: Option<String>,
+    /// Progress of tasks that report it, like reindexing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<Value>,
+}
+
+#[derive(Deserialize)]
+pub struct GetTaskResponse {
+    pub completed: bool,
+    pub task: TaskInfo,
+    pub response: Option<Value>,
+    pub error: Option<Value>,
+}
+
+#[derive(Deserialize)]
+pub struct ReindexTaskResponse {
+    pub task: String,
+}
+
+/// Status of a task, with its progress and at most [`MAX_REPORTED_FAILURES`] failures of its result.
+#[derive(Serialize)]
+pub struct TaskStatus {
+    pub completed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress_percent: Option<f64>,
+    #[serde(flatten)]
+    pub task: TaskInfo,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Value>,
+}
+
+impl TaskStatus {
+    fn new(mut response: GetTaskResponse) -> Self {
+        if let Some(result) = response.response.as_mut()
+            && let Some(failures) = result.get_mut("failures").and_then(Value::as_array_mut)
+            && failures.len() > MAX_REPORTED_FAILURES
+        {
+            let count = failures.len();
+            failures.truncate(MAX_REPORTED_FAILURES);
+            result["failures_count"] = count.into();
+        }
+
+        // Reindex, update by query and delete by query report the number of processed documents
+        let progress_percent = response.task.status.as_ref().and_then(|status| {
+            let count = |name: &str| status.get(name).and_then(Value::as_u64).unwrap_or(0);
+            let total = count("total");
+            let done = ["created", "updated", "deleted", "noops", "version_conflicts"]
+                .into_iter()
+                .map(count)
+                .sum::<u64>();
+            (total > 0).then(|| (1000.0 * done as f64 / total as f64).round() / 10.0)
+        });
+
+        TaskStatus {
+            completed: response.completed,
+            progress_percent: if response.completed { None } else { progress_percent },
+            task: response.task,
+            response: response.response,
+            error: response.error,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CancelTasksResponse {
+    #[serde(default)]
+    pub task_failures: Vec<Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PendingTasksResponse {
+    pub tasks: Vec<PendingTask>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PendingTask {
+    pub insert_order: u64,
+    pub priority: String,
+    pub source: String,
+    #[serde(default)]
+    pub executing: bool,
+    pub time_in_queue_millis: u64,
+}
+
 //----- Index mappings
 
 pub type MappingResponse = HashMap<String, Mappings>;
@@ -377,11 +3767,108 @@ pub struct MappingProperty {
     pub settings: HashMap<String, serde_json::Value>,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct RawMappings {
+    #[serde(default)]
+    pub mappings: Value,
+}
+
+//----- Disk usage
+
+#[derive(Deserialize)]
+pub struct IndexDiskUsage {
+    pub store_size: String,
+    pub store_size_in_bytes: u64,
+    #[serde(default)]
+    pub fields: IndexMap<String, RawFieldDiskUsage>,
+}
+
+#[derive(Deserialize)]
+pub struct RawFieldDiskUsage {
+    pub total_in_bytes: u64,
+    #[serde(default)]
+    pub inverted_index: InvertedIndexDiskUsage,
+    #[serde(default)]
+    pub stored_fields_in_bytes: u64,
+    #[serde(default)]
+    pub doc_values_in_bytes: u64,
+    #[serde(default)]
+    pub points_in_bytes: u64,
+    #[serde(default)]
+    pub norms_in_bytes: u64,
+    #[serde(default)]
+    pub term_vectors_in_bytes: u64,
+    #[serde(default)]
+    pub knn_vectors_in_bytes: u64,
+}
+
+#[derive(Deserialize, Default)]
+pub struct InvertedIndexDiskUsage {
+    pub total_in_bytes: u64,
+}
+
+/// Disk usage of a field. Zero-sized storage structures are omitted to reduce the output size.
+#[derive(Serialize)]
+pub struct FieldDiskUsage {
+    pub field: String,
+    pub total_in_bytes: u64,
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    pub breakdown_in_bytes: IndexMap<&'static str, u64>,
+}
+
+impl FieldDiskUsage {
+    fn new(field: String, usage: RawFieldDiskUsage) -> Self {
+        let breakdown_in_bytes = [
+            ("inverted_index", usage.inverted_index.total_in_bytes),
+            ("stored_fields", usage.stored_fields_in_bytes),
+            ("doc_values", usage.doc_values_in_bytes),
+            ("points", usage.points_in_bytes),
+            ("norms", usage.norms_in_bytes),
+            ("term_vectors", usage.term_vectors_in_bytes),
+            ("knn_vectors", usage.knn_vectors_in_bytes),
+        ]
+        .into_iter()
+        .filter(|(_, size)| *size > 0)
+        .collect();
+
+        FieldDiskUsage {
+            field,
+            total_in_bytes: usage.total_in_bytes,
+            breakdown_in_bytes,
+        }
+    }
+}
+
+//----- Index templates
+
+#[derive(Serialize, Deserialize)]
+pub struct SimulateTemplateResponse {
+    pub template: RawMappings,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SimulateIndexTemplateResponse {
+    /// Settings, mappings and aliases
+    pub template: Value,
+    #[serde(default)]
+    pub overlapping: Vec<OverlappingTemplate>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OverlappingTemplate {
+    pub name: String,
+    pub index_patterns: Vec<String>,
+}
+
 //----- ES|QL
 
 #[derive(Serialize, Deserialize)]
 pub struct EsqlQueryRequest {
     pub query: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<Vec<Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -397,3 +3884,27 @@ pub struct EsqlQueryResponse {
     pub columns: Vec<Column>,
     pub values: Vec<Vec<Value>>,
 }
+
+//----- SQL
+
+#[derive(Serialize, Deserialize)]
+pub struct SqlQueryRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<Vec<Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fetch_size: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SqlQueryResponse {
+    #[serde(default)]
+    pub columns: Vec<Column>,
+    pub rows: Vec<Vec<Value>>,
+    pub cursor: Option<String>,
+}
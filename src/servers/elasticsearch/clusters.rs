@@ -0,0 +1,453 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Several Elasticsearch clusters exposed as a single MCP server.
+
+use crate::metrics::Metrics;
+use crate::servers::DEPRECATED_TOOL_NAMES;
+use crate::servers::elasticsearch::base_tools::EsBaseTools;
+use crate::utils::resource_store::Owner;
+use indexmap::IndexMap;
+use rmcp::model::{
+    CallToolRequestParam, CallToolResult, CompleteRequestParam, CompleteResult, GetPromptRequestParam, GetPromptResult,
+    ListPromptsResult, ListResourcesResult, ListToolsResult, PaginatedRequestParam, Prompt, PromptMessage,
+    PromptMessageRole, PromptsCapability, ReadResourceRequestParam, ReadResourceResult, ServerCapabilities, ServerInfo,
+    Tool,
+};
+use rmcp::service::RequestContext;
+use rmcp::{RoleServer, ServerHandler};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+/// Tools of the default cluster keep their name, and tools of named clusters are prefixed with the
+/// cluster name, e.g. `prod_search`. Their title and description also mention the cluster so that
+/// the LLM can distinguish them.
+///
+/// Clusters share a resource store, so that resource URIs returned by the tools of a cluster can be passed to the
+/// tools of other clusters, e.g. to import into a cluster the documents exported from another one.
+///
+/// Server instructions combine the instructions of all clusters, and capabilities are the union of those of the
+/// clusters.
+///
+/// The usage guide of each cluster is available as a `usage_<cluster>` prompt (`usage_elasticsearch` for
+/// the default cluster), for clients that ignore the server instructions.
+#[derive(Clone)]
+pub struct EsClusters {
+    clusters: Arc<Vec<EsBaseTools>>,
+    tools: Arc<Vec<Tool>>,
+    /// Exposed tool name to cluster index and tool name in that cluster.
+    routes: Arc<HashMap<String, (usize, String)>>,
+    /// Deprecated exposed tool names to their new exposed name.
+    deprecated: Arc<HashMap<String, String>>,
+    /// Usage guide prompts and their text.
+    usage_prompts: Arc<IndexMap<String, (Prompt, String)>>,
+    info: Arc<ServerInfo>,
+}
+
+impl EsClusters {
+    /// Create the server from clusters and their optional name. The first cluster is used for requests
+    /// that don't target a tool, such as completions.
+    pub fn new(clusters: Vec<(Option<String>, EsBaseTools)>) -> anyhow::Result<Self> {
+        if clusters.is_empty() {
+            anyhow::bail!("No Elasticsearch cluster configured");
+        }
+
+        let first = clusters[0].1.clone();
+        let clusters = clusters
+            .into_iter()
+            .map(|(name, cluster)| (name, cluster.with_resources_of(&first)))
+            .collect::<Vec<_>>();
+
+        let mut tools = Vec::new();
+        let mut routes = HashMap::new();
+        let mut deprecated = HashMap::new();
+        let mut usage_prompts = IndexMap::new();
+        let mut info = clusters[0].1.get_info();
+        info.instructions = None;
+        info.capabilities.prompts = Some(PromptsCapability::default());
+        let mut instructions = Vec::new();
+
+        for (idx, (name, cluster)) in clusters.iter().enumerate() {
+            if let Some(name) = name
+                && (name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+            {
+                anyhow::bail!("Invalid cluster name '{name}': only letters, digits, '_' and '-' are allowed");
+            }
+
+            // Tools, their name in the cluster, and whether they're a deprecated name. Former names that are now
+            // used by another tool, such as a custom tool, are dropped.
+            let all_tools = cluster.tools();
+            let mut cluster_tools = Vec::new();
+            for tool in &all_tools {
+                let tool_name = tool.name.to_string();
+                cluster_tools.push((tool.clone(), tool_name.clone(), false));
+                for (old_name, _) in DEPRECATED_TOOL_NAMES.iter().filter(|(old_name, new_name)| {
+                    *new_name == tool_name && !all_tools.iter().any(|t| t.name == *old_name)
+                }) {
+                    let mut old_tool = tool.clone();
+                    old_tool.name = old_name.to_string().into();
+                    cluster_tools.push((old_tool, tool_name.clone(), true));
+                }
+            }
+
+            let first_tool = tools.len();
+            for (tool, tool_name, is_deprecated) in cluster_tools {
+                let (mut tool, new_name) = match name {
+                    Some(name) => (for_cluster(tool, name), format!("{name}_{tool_name}")),
+                    None => (tool, tool_name.clone()),
+                };
+
+                if is_deprecated {
+                    tool.description = Some(
+                        format!(
+                            "Deprecated, use the '{new_name}' tool instead. {}",
+                            tool.description.as_deref().unwrap_or_default()
+                        )
+                        .into(),
+                    );
+                    deprecated.insert(tool.name.to_string(), new_name);
+                }
+
+                if routes.insert(tool.name.to_string(), (idx, tool_name)).is_some() {
+                    anyhow::bail!("Tool name '{}' is used by several clusters", tool.name);
+                }
+                tools.push(tool);
+            }
+
+            let cluster_tools = tools[first_tool..]
+                .iter()
+                .filter(|t| !deprecated.contains_key(t.name.as_ref()));
+            let guide = usage_guide(
+                name.as_deref(),
+                cluster.get_info().instructions.as_deref(),
+                cluster_tools,
+            );
+            let prompt = Prompt::new(
+                format!("usage_{}", name.as_deref().unwrap_or("elasticsearch")),
+                Some(match name {
+                    Some(name) => format!("Usage guide of the tools of Elasticsearch cluster '{name}'"),
+                    None => "Usage guide of the Elasticsearch tools".to_string(),
+                }),
+                None,
+            );
+            if usage_prompts.contains_key(&prompt.name) {
+                anyhow::bail!("Prompt name '{}' is used by several clusters", prompt.name);
+            }
+            usage_prompts.insert(prompt.name.clone(), (prompt, guide));
+
+            let cluster_info = cluster.get_info();
+            merge_capabilities(&mut info.capabilities, cluster_info.capabilities);
+            if let Some(cluster_instructions) = cluster_info.instructions {
+                instructions.push(match name {
+                    Some(name) => {
+                        format!(
+                            "Elasticsearch cluster '{name}' (tools prefixed with `{name}_`): {cluster_instructions}"
+                        )
+                    }
+                    None => cluster_instructions,
+                });
+            }
+        }
+        if !instructions.is_empty() {
+            info.instructions = Some(instructions.join("\n\n"));
+        }
+
+        Ok(EsClusters {
+            clusters: Arc::new(clusters.into_iter().map(|(_, cluster)| cluster).collect()),
+            tools: Arc::new(tools),
+            routes: Arc::new(routes),
+            deprecated: Arc::new(deprecated),
+            usage_prompts: Arc::new(usage_prompts),
+            info: Arc::new(info),
+        })
+    }
+
+    /// Names of the exposed tools, including deprecated names.
+    pub fn tool_names(&self) -> impl Iterator<Item = &str> {
+        self.routes.keys().map(String::as_str)
+    }
+}
+
+/// Generate the usage guide of a cluster from the server instructions and the tools it exposes.
+fn usage_guide<'a>(cluster: Option<&str>, instructions: Option<&str>, tools: impl Iterator<Item = &'a Tool>) -> String {
+    let mut guide = match cluster {
+        Some(name) => format!("# Elasticsearch cluster '{name}'\n"),
+        None => "# Elasticsearch\n".to_string(),
+    };
+    if let Some(instructions) = instructions {
+        let _ = write!(guide, "\n{instructions}\n");
+    }
+    guide.push_str("\n## Tools\n\n");
+    for tool in tools {
+        let _ = writeln!(
+            guide,
+            "- `{}`: {}",
+            tool.name,
+            tool.description.as_deref().unwrap_or_default()
+        );
+    }
+    guide
+}
+
+/// Add the capabilities of a cluster to those of the server.
+fn merge_capabilities(capabilities: &mut ServerCapabilities, other: ServerCapabilities) {
+    // None < Some(false) < Some(true)
+    fn either(a: Option<bool>, b: Option<bool>) -> Option<bool> {
+        a.max(b)
+    }
+
+    if let Some(other) = other.experimental {
+        let experimental = capabilities.experimental.get_or_insert_default();
+        for (name, value) in other {
+            experimental.entry(name).or_default().extend(value);
+        }
+    }
+    capabilities.logging = capabilities.logging.take().or(other.logging);
+    capabilities.completions = capabilities.completions.take().or(other.completions);
+    if let Some(other) = other.prompts {
+        let prompts = capabilities.prompts.get_or_insert_default();
+        prompts.list_changed = either(prompts.list_changed, other.list_changed);
+    }
+    if let Some(other) = other.resources {
+        let resources = capabilities.resources.get_or_insert_default();
+        resources.subscribe = either(resources.subscribe, other.subscribe);
+        resources.list_changed = either(resources.list_changed, other.list_changed);
+    }
+    if let Some(other) = other.tools {
+        let tools = capabilities.tools.get_or_insert_default();
+        tools.list_changed = either(tools.list_changed, other.list_changed);
+    }
+}
+
+/// Rename a tool and update its description for a named cluster.
+fn for_cluster(mut tool: Tool, cluster: &str) -> Tool {
+    tool.name = format!("{cluster}_{}", tool.name).into();
+    tool.description = Some(match tool.description {
+        Some(description) => format!("{description} (Elasticsearch cluster '{cluster}')").into(),
+        None => format!("Elasticsearch cluster '{cluster}'").into(),
+    });
+    if let Some(title) = tool.annotations.as_mut().and_then(|a| a.title.as_mut()) {
+        *title = format!("{title} ({cluster})");
+    }
+    tool
+}
+
+impl ServerHandler for EsClusters {
+    fn get_info(&self) -> ServerInfo {
+        self.info.as_ref().clone()
+    }
+
+    async fn complete(
+        &self,
+        request: CompleteRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CompleteResult, rmcp::Error> {
+        self.clusters[0].complete(request, context).await
+    }
+
+    async fn call_tool(
+        &self,
+        mut request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let (idx, name) = self
+            .routes
+            .get(request.name.as_ref())
+            .ok_or_else(|| rmcp::Error::invalid_params("tool not found", None))?;
+
+        if let Some(new_name) = self.deprecated.get(request.name.as_ref()) {
+            Metrics::global().record_deprecated_call(&request.name);
+            tracing::warn!(tool = %request.name, "Deprecated tool name called, the new name is '{new_name}'");
+        }
+
+        request.name = name.clone().into();
+        let cluster = &self.clusters[*idx];
+        cluster.index_policy().check_call(name, request.arguments.as_ref())?;
+        cluster.guardrails().apply(name, request.arguments.as_mut())?;
+        if !cluster.is_read_only(name) {
+            return cluster.call_tool(request, context).await;
+        }
+        cluster
+            .retry()
+            .run(|| cluster.call_tool(request.clone(), context.clone()))
+            .await
+    }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, rmcp::Error> {
+        let resources = self.clusters[0].list_stored_resources(&Owner::of(&context));
+        Ok(ListResourcesResult::with_all_items(resources))
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, rmcp::Error> {
+        let contents = self.clusters[0]
+            .read_stored_resource(&Owner::of(&context), &request.uri)
+            .ok_or_else(|| rmcp::Error::resource_not_found("resource not found or expired", None))?;
+        Ok(ReadResourceResult {
+            contents: vec![contents],
+        })
+    }
+
+    async fn list_prompts(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListPromptsResult, rmcp::Error> {
+        let prompts = self.usage_prompts.values().map(|(prompt, _)| prompt.clone()).collect();
+        Ok(ListPromptsResult::with_all_items(prompts))
+    }
+
+    async fn get_prompt(
+        &self,
+        request: GetPromptRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<GetPromptResult, rmcp::Error> {
+        let (prompt, guide) = self
+            .usage_prompts
+            .get(&request.name)
+            .ok_or_else(|| rmcp::Error::invalid_params("prompt not found", None))?;
+        Ok(GetPromptResult {
+            description: prompt.description.clone(),
+            messages: vec![PromptMessage::new_text(PromptMessageRole::User, guide)],
+        })
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, rmcp::Error> {
+        Ok(ListToolsResult {
+            tools: self.tools.as_ref().clone(),
+            next_cursor: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use elasticsearch::Elasticsearch;
+
+    fn cluster() -> EsBaseTools {
+        EsBaseTools::new(Elasticsearch::default())
+    }
+
+    #[test]
+    fn named_clusters_have_prefixed_tools() -> anyhow::Result<()> {
+        let server = EsClusters::new(vec![(None, cluster()), (Some("prod".to_string()), cluster())])?;
+
+        // With the deprecated name of query_esql
+        assert_eq!(server.tools.len(), 2 * (cluster().tools().len() + 1));
+        assert_eq!(server.routes.get("search"), Some(&(0, "search".to_string())));
+        assert_eq!(server.routes.get("prod_search"), Some(&(1, "search".to_string())));
+
+        let search = server.tools.iter().find(|t| t.name == "prod_search").unwrap();
+        assert!(search.description.as_ref().unwrap().contains("'prod'"));
+        Ok(())
+    }
+
+    #[test]
+    fn deprecated_names() -> anyhow::Result<()> {
+        let server = EsClusters::new(vec![(None, cluster()), (Some("prod".to_string()), cluster())])?;
+
+        assert_eq!(server.routes.get("esql"), Some(&(0, "query_esql".to_string())));
+        assert_eq!(server.routes.get("prod_esql"), Some(&(1, "query_esql".to_string())));
+        assert_eq!(server.deprecated.get("prod_esql").unwrap(), "prod_query_esql");
+        assert!(!server.deprecated.contains_key("prod_query_esql"));
+
+        let deprecated = server.tools.iter().find(|t| t.name == "prod_esql").unwrap();
+        assert!(
+            deprecated
+                .description
+                .as_ref()
+                .unwrap()
+                .starts_with("Deprecated, use the 'prod_query_esql' tool instead.")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn usage_prompts() -> anyhow::Result<()> {
+        let server = EsClusters::new(vec![(None, cluster()), (Some("prod".to_string()), cluster())])?;
+        assert_eq!(
+            server.usage_prompts.keys().collect::<Vec<_>>(),
+            vec!["usage_elasticsearch", "usage_prod"]
+        );
+
+        let (_, guide) = &server.usage_prompts["usage_prod"];
+        assert!(guide.starts_with("# Elasticsearch cluster 'prod'\n\nProvides access to Elasticsearch\n"));
+        assert!(guide.contains("\n- `prod_query_esql`: "));
+        assert!(!guide.contains("`prod_esql`"));
+        assert!(!guide.contains("`search`"));
+        Ok(())
+    }
+
+    #[test]
+    fn merged_server_info() -> anyhow::Result<()> {
+        let server = EsClusters::new(vec![(Some("prod".to_string()), cluster())])?;
+        let info = server.get_info();
+        assert_eq!(
+            info.instructions.as_deref(),
+            Some("Elasticsearch cluster 'prod' (tools prefixed with `prod_`): Provides access to Elasticsearch")
+        );
+        assert!(info.capabilities.prompts.is_some());
+        assert!(info.capabilities.tools.is_some());
+
+        let mut capabilities = ServerCapabilities::builder().enable_tools().build();
+        let mut other = ServerCapabilities::builder()
+            .enable_tools()
+            .enable_tool_list_changed()
+            .enable_logging()
+            .build();
+        other.experimental = Some([("x".to_string(), serde_json::Map::new())].into());
+        merge_capabilities(&mut capabilities, other);
+        assert_eq!(capabilities.tools.unwrap().list_changed, Some(true));
+        assert!(capabilities.logging.is_some());
+        assert!(capabilities.experimental.unwrap().contains_key("x"));
+        Ok(())
+    }
+
+    #[test]
+    fn clusters_share_resources() -> anyhow::Result<()> {
+        let server = EsClusters::new(vec![(None, cluster()), (Some("prod".to_string()), cluster())])?;
+
+        let owner = Owner::default();
+        let uri = server.clusters[1]
+            .resources()
+            .add(&owner, "errors", "Error", "application/json", "{}".to_string());
+        assert!(server.clusters[0].read_stored_resource(&owner, &uri).is_some());
+        assert_eq!(server.clusters[0].list_stored_resources(&owner).len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_conflicts() {
+        assert!(EsClusters::new(vec![]).is_err());
+        assert!(EsClusters::new(vec![(Some("prod:1".to_string()), cluster())]).is_err());
+        assert!(EsClusters::new(vec![(None, cluster()), (None, cluster())]).is_err());
+        assert!(EsClusters::new(vec![(None, cluster()), (Some("elasticsearch".to_string()), cluster())]).is_err());
+    }
+}
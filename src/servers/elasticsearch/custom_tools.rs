@@ -0,0 +1,155 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Tools defined in the configuration file: ES|QL queries and search templates with typed parameters.
+
+use crate::servers::elasticsearch::base_tools::{
+    EsBaseTools, EsqlQueryRequest, EsqlQueryResponse, SearchResult, esql_objects, search_contents,
+};
+use crate::servers::elasticsearch::{CustomTool, EsqlResultFormat, SearchTemplate, read_json, send, template};
+use elasticsearch::{SearchParts, SearchTemplateParts};
+use futures::FutureExt;
+use rmcp::handler::server::router::tool::ToolRoute;
+use rmcp::handler::server::tool::ToolCallContext;
+use rmcp::model::{CallToolResult, Content, Tool, ToolAnnotations};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Tool routes for custom tools.
+pub fn routes(tools: HashMap<String, CustomTool>) -> impl Iterator<Item = ToolRoute<EsBaseTools>> {
+    tools.into_iter().map(|(name, tool)| {
+        let attr = tool_attr(name, &tool);
+        let tool = Arc::new(tool);
+        ToolRoute::new_dyn(attr, move |context| {
+            let tool = tool.clone();
+            async move { call(&tool, context).await }.boxed()
+        })
+    })
+}
+
+/// Tool definition, with an input schema generated from the parameters.
+fn tool_attr(name: String, tool: &CustomTool) -> Tool {
+    let base = tool.base();
+    let required = base
+        .parameters
+        .iter()
+        .filter(|(_, schema)| schema.metadata.as_ref().and_then(|m| m.default.as_ref()).is_none())
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>();
+    let schema = rmcp::model::object(json!({
+        "type": "object",
+        "properties": base.parameters,
+        "required": required,
+    }));
+
+    // Custom tools run queries, they're read-only unless stated otherwise
+    let annotations = base.annotations.clone().unwrap_or_else(|| ToolAnnotations {
+        read_only_hint: Some(true),
+        ..Default::default()
+    });
+
+    let mut tool = Tool::new(name, base.description.clone(), schema);
+    tool.annotations = Some(annotations);
+    tool
+}
+
+async fn call(tool: &CustomTool, context: ToolCallContext<'_, EsBaseTools>) -> Result<CallToolResult, rmcp::Error> {
+    let args = template::check_arguments(&tool.base().parameters, &context.arguments.unwrap_or_default())
+        .map_err(|err| rmcp::Error::invalid_params(err.to_string(), None))?;
+    let es_client = context.service.es_client(context.request_context);
+
+    match tool {
+        CustomTool::Esql(esql) => {
+            // Arguments are sent as named parameters, never interpolated in the query
+            let params = args.into_iter().map(|(name, value)| json!({ name: value })).collect();
+            let request = EsqlQueryRequest {
+                query: esql.query.clone(),
+                params: Some(params),
+                time_zone: None,
+            };
+            let response = send!(es_client.esql().query().body(request)).await;
+            let response: EsqlQueryResponse = read_json(response).await?;
+            let objects = esql_objects(response);
+
+            let content = match esql.format {
+                EsqlResultFormat::Value => match objects.as_slice() {
+                    [Value::Object(obj)] if obj.len() == 1 => Content::json(obj.values().next())?,
+                    _ => Content::json(objects)?,
+                },
+                EsqlResultFormat::Json => Content::json(objects)?,
+            };
+            Ok(CallToolResult::success(vec![content]))
+        }
+
+        CustomTool::SearchTemplate(search_template) => {
+            let indices = [search_template.index.as_str()];
+            let response = match &search_template.template {
+                // Rendered here rather than by Elasticsearch, to only allow arguments as whole values
+                SearchTemplate::Template(template) => {
+                    let body = template::render(template, &args);
+                    send!(es_client.search(SearchParts::Index(&indices)).body(body)).await
+                }
+                SearchTemplate::TemplateId(id) => {
+                    send!(
+                        es_client
+                            .search_template(SearchTemplateParts::Index(&indices))
+                            .body(json!({ "id": id, "params": Value::Object(args) }))
+                    )
+                    .await
+                }
+            };
+            let response: SearchResult = read_json(response).await?;
+            Ok(CallToolResult::success(search_contents(&response)?))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Map;
+
+    #[test]
+    fn tool_schema() {
+        let tool: CustomTool = serde_json::from_value(json!({
+            "type": "esql",
+            "description": "Slow requests of a service",
+            "query": "FROM logs | WHERE service == ?service AND duration > ?min_duration",
+            "parameters": {
+                "service": { "type": "string", "description": "Service name" },
+                "min_duration": { "type": "integer", "default": 1000 },
+            },
+        }))
+        .unwrap();
+
+        let tool = tool_attr("slow_requests".to_string(), &tool);
+        assert_eq!(tool.name, "slow_requests");
+        assert_eq!(tool.annotations.unwrap().read_only_hint, Some(true));
+        assert_eq!(
+            Value::Object(Map::clone(&tool.input_schema)),
+            json!({
+                "type": "object",
+                "properties": {
+                    "service": { "type": "string", "description": "Service name" },
+                    "min_duration": { "type": "integer", "default": 1000 },
+                },
+                "required": ["service"],
+            })
+        );
+    }
+}
@@ -0,0 +1,212 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! EQL searches: compact events and sequences of events.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+//----- EQL search response (`POST {index}/_eql/search`)
+
+#[derive(Deserialize)]
+pub struct EqlSearchResponse {
+    #[serde(default)]
+    pub is_partial: bool,
+    #[serde(default)]
+    pub timed_out: bool,
+    pub hits: EqlHits,
+}
+
+#[derive(Deserialize)]
+pub struct EqlHits {
+    #[serde(default)]
+    events: Vec<Event>,
+    #[serde(default)]
+    sequences: Vec<Sequence>,
+}
+
+#[derive(Deserialize)]
+struct Event {
+    #[serde(rename = "_source", default)]
+    source: Value,
+    /// Requested fields
+    #[serde(default)]
+    fields: Map<String, Value>,
+    /// Is it a missing event of a sequence, i.e. matching a `![...]` query?
+    #[serde(default)]
+    missing: bool,
+}
+
+#[derive(Deserialize)]
+struct Sequence {
+    #[serde(default)]
+    join_keys: Vec<Value>,
+    events: Vec<Event>,
+}
+
+//----- Compact results
+
+/// Events, or sequences of events, with their requested fields or source.
+#[derive(Serialize)]
+pub struct EqlResult {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sequences: Vec<SequenceSummary>,
+}
+
+#[derive(Serialize)]
+pub struct SequenceSummary {
+    /// Values of the `by` fields shared by the events
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    join_keys: Vec<Value>,
+    /// Timestamps of the first and last events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end: Option<Value>,
+    events: Vec<Value>,
+}
+
+impl EqlHits {
+    pub fn compact(self, timestamp_field: &str) -> EqlResult {
+        let sequences = self
+            .sequences
+            .into_iter()
+            .map(|sequence| {
+                let mut timestamps = sequence
+                    .events
+                    .iter()
+                    .filter(|e| !e.missing)
+                    .filter_map(|e| e.field(timestamp_field));
+                let start = timestamps.next();
+                let end = timestamps.last().or_else(|| start.clone());
+                SequenceSummary {
+                    join_keys: sequence.join_keys,
+                    start,
+                    end,
+                    events: sequence.events.into_iter().map(Event::compact).collect(),
+                }
+            })
+            .collect();
+
+        EqlResult {
+            events: self.events.into_iter().map(Event::compact).collect(),
+            sequences,
+        }
+    }
+}
+
+impl Event {
+    /// Requested fields, with single values unwrapped, or the source if no fields were requested.
+    fn compact(self) -> Value {
+        if self.missing {
+            return json!({ "missing": true });
+        }
+        if self.fields.is_empty() {
+            return self.source;
+        }
+        let fields = self
+            .fields
+            .into_iter()
+            .map(|(name, values)| match values {
+                Value::Array(mut values) if values.len() == 1 => (name, values.remove(0)),
+                values => (name, values),
+            })
+            .collect();
+        Value::Object(fields)
+    }
+
+    /// Value of a field, from requested fields or the source, where it can be a dotted name or nested objects.
+    fn field(&self, name: &str) -> Option<Value> {
+        if let Some(values) = self.fields.get(name) {
+            return values.as_array().and_then(|v| v.first()).cloned();
+        }
+        if let Some(value) = self.source.get(name) {
+            return Some(value.clone());
+        }
+        let mut value = &self.source;
+        for part in name.split('.') {
+            value = value.get(part)?;
+        }
+        Some(value.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compact_sequences() {
+        let response: EqlSearchResponse = serde_json::from_value(json!({
+            "is_partial": false,
+            "is_running": false,
+            "took": 12,
+            "timed_out": false,
+            "hits": {
+                "total": { "value": 1, "relation": "eq" },
+                "sequences": [{
+                    "join_keys": ["host-1"],
+                    "events": [
+                        { "_index": "logs", "_id": "1", "_source": {
+                            "@timestamp": "2025-01-01T00:00:00Z", "process": { "name": "cmd.exe" },
+                        }},
+                        { "_index": "logs", "_id": "2", "_source": {
+                            "@timestamp": "2025-01-01T00:00:05Z", "destination": { "ip": "10.0.0.1" },
+                        }},
+                        { "_index": "", "_id": "", "_source": null, "missing": true },
+                    ],
+                }],
+            },
+        }))
+        .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(response.hits.compact("@timestamp")).unwrap(),
+            json!({ "sequences": [{
+                "join_keys": ["host-1"],
+                "start": "2025-01-01T00:00:00Z",
+                "end": "2025-01-01T00:00:05Z",
+                "events": [
+                    { "@timestamp": "2025-01-01T00:00:00Z", "process": { "name": "cmd.exe" } },
+                    { "@timestamp": "2025-01-01T00:00:05Z", "destination": { "ip": "10.0.0.1" } },
+                    { "missing": true },
+                ],
+            }]})
+        );
+    }
+
+    #[test]
+    fn compact_events_with_fields() {
+        let hits: EqlHits = serde_json::from_value(json!({ "events": [{
+            "_index": "logs",
+            "_id": "1",
+            "_source": { "event": { "created": "2025-01-01T00:00:00Z" }, "message": "long message" },
+            "fields": { "event.created": ["2025-01-01T00:00:00Z"], "tags": ["a", "b"] },
+        }]}))
+        .unwrap();
+
+        let event = &hits.events[0];
+        assert_eq!(event.field("event.created"), Some(json!("2025-01-01T00:00:00Z")));
+
+        assert_eq!(
+            serde_json::to_value(hits.compact("event.created")).unwrap(),
+            json!({ "events": [{ "event.created": "2025-01-01T00:00:00Z", "tags": ["a", "b"] }] })
+        );
+    }
+}
@@ -0,0 +1,483 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Explanations of Elasticsearch errors, from a knowledge base of common errors with their likely causes and
+//! the tools that help to fix them.
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// How a hint applies to an error.
+enum Match {
+    /// Error type
+    Type(&'static str),
+    /// Error type, and a lowercase text contained in the reason
+    TypeAndReason(&'static str, &'static str),
+    /// HTTP status of the response, for errors without a more specific hint
+    Status(u64),
+}
+
+struct Hint {
+    matches: Match,
+    summary: &'static str,
+    causes: &'static [&'static str],
+    /// Tools to call next, and why
+    next_steps: &'static [(&'static str, &'static str)],
+}
+
+/// Hints are checked in order, the first one that matches an error is used.
+const HINTS: &[Hint] = &[
+    Hint {
+        matches: Match::Type("index_not_found_exception"),
+        summary: "The index, alias or data stream doesn't exist.",
+        causes: &[
+            "The name is misspelled, or is a guess that doesn't match actual index names",
+            "The index was deleted, or hasn't been created yet",
+        ],
+        next_steps: &[(
+            "list_indices",
+            "find the actual name with a wildcard pattern, e.g. `*logs*`",
+        )],
+    },
+    Hint {
+        matches: Match::TypeAndReason("illegal_argument_exception", "fielddata is disabled"),
+        summary: "Sorting, aggregating or scripting on a text field, which isn't possible by default.",
+        causes: &["A text field was used instead of its keyword subfield (often named `<field>.keyword`)"],
+        next_steps: &[(
+            "get_mappings",
+            "find a keyword subfield or another keyword field to use instead",
+        )],
+    },
+    Hint {
+        matches: Match::TypeAndReason("illegal_argument_exception", "text fields are not optimised"),
+        summary: "Sorting, aggregating or scripting on a text field, which isn't possible by default.",
+        causes: &["A text field was used instead of its keyword subfield (often named `<field>.keyword`)"],
+        next_steps: &[(
+            "get_mappings",
+            "find a keyword subfield or another keyword field to use instead",
+        )],
+    },
+    Hint {
+        matches: Match::TypeAndReason("parsing_exception", "mismatched input"),
+        summary: "The ES|QL query has a syntax error.",
+        causes: &[
+            "A keyword or function is misspelled, or a pipe `|` is missing between commands",
+            "A string literal isn't quoted with double quotes",
+        ],
+        next_steps: &[(
+            "validate_esql",
+            "locate the error and check the fixed query before running it",
+        )],
+    },
+    Hint {
+        matches: Match::Type("verification_exception"),
+        summary: "The ES|QL query refers to unknown columns or functions, or mixes incompatible types.",
+        causes: &[
+            "A column name doesn't exist in the queried indices, or was renamed by a previous command",
+            "A function is applied to a column of the wrong type",
+        ],
+        next_steps: &[
+            ("get_mappings", "check field names and types"),
+            ("validate_esql", "check the fixed query before running it"),
+        ],
+    },
+    Hint {
+        matches: Match::Type("parsing_exception"),
+        summary: "The query DSL is malformed.",
+        causes: &[
+            "A query type or parameter is misspelled, or is at the wrong nesting level",
+            "A query clause has several keys where a single one is expected",
+        ],
+        next_steps: &[(
+            "validate_query",
+            "check the fixed query, with an explanation of how it is rewritten",
+        )],
+    },
+    Hint {
+        matches: Match::Type("x_content_parse_exception"),
+        summary: "The request body has an unexpected structure or value type.",
+        causes: &["A parameter has the wrong type, e.g. a string instead of an object or an array"],
+        next_steps: &[("validate_query", "check the fixed query")],
+    },
+    Hint {
+        matches: Match::Type("query_shard_exception"),
+        summary: "The query can't be executed on the type of a field.",
+        causes: &[
+            "A value doesn't match the field type, e.g. a text in a date or numeric range",
+            "A query isn't supported by the field type, e.g. a prefix query on a numeric field",
+        ],
+        next_steps: &[
+            ("get_mappings", "check the type of the queried fields"),
+            ("validate_query", "check the fixed query"),
+        ],
+    },
+    Hint {
+        matches: Match::Type("search_phase_execution_exception"),
+        summary: "The search failed on all shards. The root causes give the actual error.",
+        causes: &["See the explanation of the root causes"],
+        next_steps: &[],
+    },
+    Hint {
+        matches: Match::Type("too_many_buckets_exception"),
+        summary: "The aggregation creates more buckets than allowed.",
+        causes: &["A terms or date histogram aggregation on a high cardinality field, or with a small interval"],
+        next_steps: &[(
+            "aggregate",
+            "group by fewer values, with a limit on the number of groups",
+        )],
+    },
+    Hint {
+        matches: Match::Type("circuit_breaking_exception"),
+        summary: "The request would use too much memory on the cluster.",
+        causes: &[
+            "Large aggregations, or a large number of returned documents",
+            "The cluster is already under memory pressure",
+        ],
+        next_steps: &[(
+            "search",
+            "retry with a smaller size, fewer fields and smaller aggregations",
+        )],
+    },
+    Hint {
+        matches: Match::Type("document_parsing_exception"),
+        summary: "A document value doesn't match the mapping of its field.",
+        causes: &[
+            "A value has the wrong type, e.g. a text in a numeric or date field",
+            "The mapping is strict and the document has unmapped fields",
+        ],
+        next_steps: &[("get_mappings", "check the type of the fields of the document")],
+    },
+    Hint {
+        matches: Match::Type("mapper_parsing_exception"),
+        summary: "A document value or mapping definition doesn't match the index mapping.",
+        causes: &[
+            "A value has the wrong type, e.g. a text in a numeric or date field",
+            "A mapping change is incompatible with the existing mapping of a field",
+        ],
+        next_steps: &[("get_mappings", "check the current mapping of the fields")],
+    },
+    Hint {
+        matches: Match::Type("version_conflict_engine_exception"),
+        summary: "The document was changed by another client since it was read.",
+        causes: &["A concurrent update of the same document"],
+        next_steps: &[("get_for_update", "read the current version and reapply the changes")],
+    },
+    Hint {
+        matches: Match::Type("search_context_missing_exception"),
+        summary: "The point in time (consistency token) or scroll expired.",
+        causes: &["The token wasn't used for longer than its keep alive"],
+        next_steps: &[("open_consistency_token", "open a new token and restart the searches")],
+    },
+    Hint {
+        matches: Match::Type("resource_already_exists_exception"),
+        summary: "The index or resource to create already exists.",
+        causes: &["It was created before, possibly by an index template or a previous call"],
+        next_steps: &[("list_indices", "check the existing indices")],
+    },
+    Hint {
+        matches: Match::Type("invalid_index_name_exception"),
+        summary: "The index name isn't valid.",
+        causes: &["Index names must be lowercase, and can't contain spaces or characters like `\\ / * ? \" < > | ,`"],
+        next_steps: &[],
+    },
+    Hint {
+        matches: Match::TypeAndReason("cluster_block_exception", "read-only"),
+        summary: "The index is read-only, usually because a node ran out of disk space.",
+        causes: &["The flood stage disk watermark was reached, and Elasticsearch blocked writes to protect the node"],
+        next_steps: &[
+            ("get_shards", "find the nodes holding the index"),
+            ("disk_usage", "find the fields that use the most storage"),
+        ],
+    },
+    Hint {
+        matches: Match::Type("es_rejected_execution_exception"),
+        summary: "The cluster is overloaded and rejected the request.",
+        causes: &["Too many concurrent requests for the thread pools of the nodes"],
+        next_steps: &[],
+    },
+    Hint {
+        matches: Match::Type("script_exception"),
+        summary: "A script failed to compile or run.",
+        causes: &[
+            "A syntax error in the script",
+            "Access to a missing field or a null value, e.g. `doc['field'].value` on documents without the field",
+        ],
+        next_steps: &[("get_mappings", "check field names and types used by the script")],
+    },
+    Hint {
+        matches: Match::Type("resource_not_found_exception"),
+        summary: "A named resource (pipeline, inference endpoint, policy, template...) doesn't exist.",
+        causes: &["The name is misspelled, or the resource was deleted"],
+        next_steps: &[
+            ("list_ingest_pipelines", "list the ingest pipelines"),
+            ("get_ilm_policies", "list the ILM policies"),
+        ],
+    },
+    Hint {
+        matches: Match::Type("security_exception"),
+        summary: "The request isn't authenticated, or lacks privileges.",
+        causes: &[
+            "Credentials are missing, wrong or expired",
+            "The user or API key doesn't have the privileges required by the request on these indices",
+        ],
+        next_steps: &[],
+    },
+    Hint {
+        matches: Match::Status(401),
+        summary: "The request isn't authenticated.",
+        causes: &["Credentials are missing, wrong or expired"],
+        next_steps: &[],
+    },
+    Hint {
+        matches: Match::Status(403),
+        summary: "The request lacks privileges.",
+        causes: &["The user or API key doesn't have the privileges required by the request on these indices"],
+        next_steps: &[],
+    },
+    Hint {
+        matches: Match::Status(404),
+        summary: "The requested resource doesn't exist.",
+        causes: &["A name or id is misspelled, or the resource was deleted"],
+        next_steps: &[("list_indices", "check index names")],
+    },
+    Hint {
+        matches: Match::Status(429),
+        summary: "Too many requests: the cluster or a rate limit rejected the request.",
+        causes: &["Too many concurrent or recent requests"],
+        next_steps: &[],
+    },
+];
+
+/// An error of an Elasticsearch error response.
+#[derive(Debug, Serialize)]
+pub struct EsError {
+    #[serde(rename = "type")]
+    error_type: String,
+    reason: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    index: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Explanation {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u64>,
+    /// Errors found in the payload: top-level error, root causes and nested causes
+    errors: Vec<EsError>,
+    pub explanations: Vec<ErrorExplanation>,
+}
+
+#[derive(Serialize)]
+pub struct ErrorExplanation {
+    /// Error type, or HTTP status
+    error: String,
+    summary: &'static str,
+    likely_causes: &'static [&'static str],
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    next_steps: Vec<NextStep>,
+}
+
+#[derive(Serialize)]
+struct NextStep {
+    tool: &'static str,
+    purpose: &'static str,
+}
+
+/// Explain an error, given as an error response body or a text that contains one, like the message of a failed
+/// tool call.
+pub fn explain(text: &str) -> Explanation {
+    let payload = text.find(['{', '[']).and_then(|start| {
+        let mut values = serde_json::Deserializer::from_str(&text[start..]).into_iter::<Value>();
+        values.next()?.ok()
+    });
+
+    let mut errors = Vec::new();
+    let status = match &payload {
+        Some(payload) => {
+            collect_errors(payload, &mut errors);
+            payload["status"].as_u64()
+        }
+        None => None,
+    };
+    // Not JSON, or without the usual structure: look for error types in the text
+    let status = status.or_else(|| status_in_text(text));
+    if errors.is_empty() {
+        for hint in HINTS {
+            if let Match::Type(error_type) = hint.matches
+                && text.contains(error_type)
+            {
+                errors.push(EsError {
+                    error_type: error_type.to_string(),
+                    reason: text.to_string(),
+                    index: None,
+                });
+            }
+        }
+    }
+
+    let mut explanations = Vec::new();
+    for error in &errors {
+        let reason = error.reason.to_lowercase();
+        let hint = HINTS.iter().find(|hint| match hint.matches {
+            Match::Type(t) => t == error.error_type,
+            Match::TypeAndReason(t, r) => t == error.error_type && reason.contains(r),
+            Match::Status(_) => false,
+        });
+        if let Some(hint) = hint {
+            add_explanation(&mut explanations, error.error_type.clone(), hint);
+        }
+    }
+    if explanations.is_empty()
+        && let Some(status) = status
+        && let Some(hint) = HINTS
+            .iter()
+            .find(|hint| matches!(hint.matches, Match::Status(s) if s == status))
+    {
+        add_explanation(&mut explanations, format!("HTTP status {status}"), hint);
+    }
+
+    Explanation {
+        status,
+        errors,
+        explanations,
+    }
+}
+
+/// Add the explanation of a hint, unless an error with the same explanation was already explained.
+fn add_explanation(explanations: &mut Vec<ErrorExplanation>, error: String, hint: &Hint) {
+    if explanations.iter().any(|e| e.summary == hint.summary) {
+        return;
+    }
+    explanations.push(ErrorExplanation {
+        error,
+        summary: hint.summary,
+        likely_causes: hint.causes,
+        next_steps: hint
+            .next_steps
+            .iter()
+            .map(|(tool, purpose)| NextStep { tool, purpose })
+            .collect(),
+    });
+}
+
+/// Collect the errors of a payload: objects with a type and a reason, within an `error` object, root causes,
+/// nested causes and shard failures.
+fn collect_errors(value: &Value, errors: &mut Vec<EsError>) {
+    match value {
+        Value::Array(items) => items.iter().for_each(|item| collect_errors(item, errors)),
+        Value::Object(obj) => {
+            if let (Some(error_type), Some(reason)) = (obj.get("type"), obj.get("reason"))
+                && let (Some(error_type), Some(reason)) = (error_type.as_str(), reason.as_str())
+            {
+                let error = EsError {
+                    error_type: error_type.to_string(),
+                    reason: reason.to_string(),
+                    index: obj.get("index").and_then(Value::as_str).map(str::to_string),
+                };
+                // Root causes are often repeated in shard failures
+                if !errors
+                    .iter()
+                    .any(|e| e.error_type == error.error_type && e.reason == error.reason)
+                {
+                    errors.push(error);
+                }
+            }
+            for key in [
+                "error",
+                "root_cause",
+                "caused_by",
+                "failed_shards",
+                "reason",
+                "failures",
+            ] {
+                if let Some(nested) = obj.get(key) {
+                    collect_errors(nested, errors);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// HTTP status in an error message, e.g. "status code 404".
+fn status_in_text(text: &str) -> Option<u64> {
+    let lower = text.to_lowercase();
+    let start = lower.find("status code ").or_else(|| lower.find("status "))?;
+    let digits = lower[start..]
+        .split_whitespace()
+        .find_map(|word| word.trim_matches(|c: char| !c.is_ascii_digit()).parse::<u64>().ok())?;
+    (100..600).contains(&digits).then_some(digits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn explain_root_causes() {
+        let payload = json!({
+            "error": {
+                "root_cause": [{
+                    "type": "illegal_argument_exception",
+                    "reason": "Text fields are not optimised for operations that require per-document field data",
+                    "index": "logs",
+                }],
+                "type": "search_phase_execution_exception",
+                "reason": "all shards failed",
+                "failed_shards": [{ "shard": 0, "index": "logs", "reason": {
+                    "type": "illegal_argument_exception",
+                    "reason": "Text fields are not optimised for operations that require per-document field data",
+                }}],
+            },
+            "status": 400,
+        });
+        let explanation = explain(&format!("Request failed: {payload}"));
+        assert_eq!(explanation.status, Some(400));
+        let types = explanation
+            .errors
+            .iter()
+            .map(|e| e.error_type.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            vec!["search_phase_execution_exception", "illegal_argument_exception"]
+        );
+        let explained = explanation
+            .explanations
+            .iter()
+            .map(|e| e.error.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            explained,
+            vec!["search_phase_execution_exception", "illegal_argument_exception"]
+        );
+        assert_eq!(explanation.explanations[1].next_steps[0].tool, "get_mappings");
+    }
+
+    #[test]
+    fn explain_text() {
+        let explanation = explain("index_not_found_exception: no such index [logz]");
+        assert_eq!(explanation.explanations[0].error, "index_not_found_exception");
+
+        let explanation = explain("request failed with status code 403 Forbidden");
+        assert_eq!(explanation.status, Some(403));
+        assert_eq!(explanation.explanations[0].error, "HTTP status 403");
+
+        assert!(explain("something went wrong").explanations.is_empty());
+    }
+}
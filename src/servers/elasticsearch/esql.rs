@@ -0,0 +1,313 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! ES|QL query helpers.
+
+use crate::servers::elasticsearch::index_policy::IndexPolicy;
+use crate::servers::elasticsearch::{EsClient, read_json, send};
+use elasticsearch::enrich::EnrichGetPolicyParts;
+use elasticsearch::indices::IndicesExistsParts;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Split a query into its processing commands, i.e. on pipes that aren't in strings. Comments are removed.
+pub fn commands(query: &str) -> Vec<String> {
+    let mut commands = Vec::new();
+    let mut current = String::new();
+    let mut rest = query;
+
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with("//") {
+            rest = rest.find('\n').map(|pos| &rest[pos..]).unwrap_or("");
+        } else if rest.starts_with("/*") {
+            rest = rest[2..].find("*/").map(|pos| &rest[pos + 4..]).unwrap_or("");
+        } else if rest.starts_with("\"\"\"") {
+            let end = rest[3..].find("\"\"\"").map(|pos| pos + 6).unwrap_or(rest.len());
+            current.push_str(&rest[..end]);
+            rest = &rest[end..];
+        } else if c == '"' {
+            let end = string_end(rest);
+            current.push_str(&rest[..end]);
+            rest = &rest[end..];
+        } else if c == '|' {
+            commands.push(std::mem::take(&mut current).trim().to_string());
+            rest = &rest[1..];
+        } else {
+            current.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    commands.push(current.trim().to_string());
+    commands.retain(|c| !c.is_empty());
+    commands
+}
+
+/// Length of the quoted string at the start of `s`, including the quotes and escaped characters.
+fn string_end(s: &str) -> usize {
+    let mut escaped = false;
+    for (pos, c) in s.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return pos + 1,
+            _ => {}
+        }
+    }
+    s.len()
+}
+
+/// Enrich policies and lookup indices referenced by a query.
+#[derive(Debug, Default, PartialEq)]
+pub struct References {
+    pub enrich_policies: Vec<String>,
+    pub lookup_indices: Vec<String>,
+}
+
+pub fn references(query: &str) -> References {
+    let mut result = References::default();
+    for command in commands(query) {
+        let words = command.split_whitespace().collect::<Vec<_>>();
+        match words.as_slice() {
+            [enrich, policy, ..] if enrich.eq_ignore_ascii_case("ENRICH") => {
+                // Remove the optional mode, e.g. `_remote:policy`
+                let policy = policy.rsplit(':').next().unwrap_or(policy);
+                result.enrich_policies.push(unquote(policy));
+            }
+            [lookup, join, index, ..] if lookup.eq_ignore_ascii_case("LOOKUP") && join.eq_ignore_ascii_case("JOIN") => {
+                result.lookup_indices.push(unquote(index));
+            }
+            _ => {}
+        }
+    }
+    result
+}
+
+fn unquote(identifier: &str) -> String {
+    identifier.trim_matches('`').to_string()
+}
+
+/// An error in an ES|QL query.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct QueryError {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<u32>,
+    pub message: String,
+}
+
+/// Split the reason of an ES|QL error into its problems, that are prefixed with their position, like
+/// `line 1:23: mismatched input`. Verification errors start with a `Found N problems` line.
+pub fn query_errors(reason: &str) -> Vec<QueryError> {
+    let mut errors = Vec::new();
+    for line in reason.lines() {
+        let position = line.strip_prefix("line ").and_then(|rest| {
+            let (position, message) = rest.split_once(": ")?;
+            let (line, column) = position.split_once(':')?;
+            Some((line.parse().ok()?, column.parse().ok()?, message))
+        });
+
+        match (position, errors.last_mut()) {
+            (Some((line, column, message)), _) => errors.push(QueryError {
+                line: Some(line),
+                column: Some(column),
+                message: message.to_string(),
+            }),
+            // Continuation of a multi-line message
+            (None, Some(last)) => {
+                last.message.push('\n');
+                last.message.push_str(line);
+            }
+            (None, None) if line.starts_with("Found ") && line.ends_with(" problems") => {}
+            (None, None) => errors.push(QueryError {
+                line: None,
+                column: None,
+                message: line.to_string(),
+            }),
+        }
+    }
+    errors
+}
+
+#[derive(Deserialize)]
+struct EnrichPolicies {
+    policies: Vec<EnrichPolicy>,
+}
+
+#[derive(Deserialize)]
+struct EnrichPolicy {
+    /// A single property whose key is the policy type (match, range, etc.)
+    config: Map<String, Value>,
+}
+
+impl EnrichPolicies {
+    /// Names and source indices of the policies.
+    fn sources(&self) -> Vec<(&str, Vec<String>)> {
+        self.policies
+            .iter()
+            .flat_map(|p| p.config.values())
+            .filter_map(|config| {
+                let name = config.get("name").and_then(Value::as_str)?;
+                let indices = match config.get("indices") {
+                    Some(Value::String(index)) => vec![index.clone()],
+                    Some(Value::Array(indices)) => {
+                        indices.iter().filter_map(Value::as_str).map(str::to_string).collect()
+                    }
+                    _ => Vec::new(),
+                };
+                Some((name, indices))
+            })
+            .collect()
+    }
+}
+
+/// Check that enrich policies and lookup indices used by a query exist. ES|QL errors for missing policies
+/// or indices are hard to act upon for agents, so this returns a message that lists what is available.
+///
+/// The source indices of enrich policies are also checked against the index policy, as ES|QL reads them.
+pub async fn check_references(
+    es_client: &EsClient,
+    index_policy: &IndexPolicy,
+    query: &str,
+) -> Result<Option<String>, rmcp::Error> {
+    let references = references(query);
+
+    if !references.enrich_policies.is_empty() {
+        let response = send!(es_client.enrich().get_policy(EnrichGetPolicyParts::None)).await;
+        let response: EnrichPolicies = read_json(response).await?;
+
+        let mut sources = response.sources();
+        sources.sort();
+
+        for policy in &references.enrich_policies {
+            match sources.iter().find(|(name, _)| name == policy) {
+                Some((_, indices)) => index_policy.check_enrich_policy(policy, indices)?,
+                None => {
+                    let available = if sources.is_empty() {
+                        "There are no enrich policies".to_string()
+                    } else {
+                        let names = sources.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+                        format!("Available enrich policies: {}", names.join(", "))
+                    };
+                    return Ok(Some(format!("Unknown enrich policy '{policy}'. {available}.")));
+                }
+            }
+        }
+    }
+
+    for index in &references.lookup_indices {
+        let response = send!(es_client.indices().exists(IndicesExistsParts::Index(&[index])))
+            .await
+            .map_err(crate::servers::elasticsearch::internal_error)?;
+
+        if response.status_code().as_u16() == 404 {
+            return Ok(Some(format!(
+                "Unknown lookup index '{index}'. LOOKUP JOIN requires an existing index created \
+                with the 'index.mode: lookup' setting."
+            )));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_commands() {
+        let query = r#"FROM logs // comment with | pipe
+            | WHERE message == "a | b \" | c" /* block | comment */
+            | EVAL x = """triple | "quoted" """
+            | LIMIT 10"#;
+
+        assert_eq!(
+            commands(query),
+            [
+                "FROM logs",
+                r#"WHERE message == "a | b \" | c""#,
+                r#"EVAL x = """triple | "quoted" """"#,
+                "LIMIT 10",
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let reason = "Found 2 problems\nline 1:20: Unknown column [foo]\nline 2:3: Unknown function [bar]";
+        assert_eq!(
+            query_errors(reason),
+            [
+                QueryError {
+                    line: Some(1),
+                    column: Some(20),
+                    message: "Unknown column [foo]".to_string()
+                },
+                QueryError {
+                    line: Some(2),
+                    column: Some(3),
+                    message: "Unknown function [bar]".to_string()
+                },
+            ]
+        );
+
+        assert_eq!(
+            query_errors("illegal argument"),
+            [QueryError {
+                line: None,
+                column: None,
+                message: "illegal argument".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn find_references() {
+        let query = "FROM logs | ENRICH _remote:hosts ON host | lookup join `users` ON user.id | enrich geo";
+        assert_eq!(
+            references(query),
+            References {
+                enrich_policies: vec!["hosts".to_string(), "geo".to_string()],
+                lookup_indices: vec!["users".to_string()],
+            }
+        );
+
+        assert_eq!(
+            references(r#"FROM logs | WHERE message == "| ENRICH x""#),
+            References::default()
+        );
+    }
+
+    #[test]
+    fn enrich_policy_sources() {
+        let policies: EnrichPolicies = serde_json::from_value(serde_json::json!({
+            "policies": [
+                { "config": { "match": { "name": "hosts", "indices": ["hosts", "hosts-old"], "match_field": "host" } } },
+                { "config": { "range": { "name": "ips", "indices": "networks", "match_field": "range" } } }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(
+            policies.sources(),
+            [
+                ("hosts", vec!["hosts".to_string(), "hosts-old".to_string()]),
+                ("ips", vec!["networks".to_string()])
+            ]
+        );
+    }
+}
@@ -0,0 +1,212 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Case and diacritic folding of queries, for fields whose analysis doesn't fold them.
+//!
+//! Text queries are rewritten as `regexp` queries where each letter matches its accented and uppercase
+//! variants, e.g. `e` becomes `[eèéêëēėęěEÈÉÊËĒĖĘĚ]`. This is slower than folding at index time, but works on
+//! existing indices, and still matches if the field analysis already folds text.
+
+use serde_json::{Map, Value, json};
+
+/// Letters with diacritics, grouped by base letter, lowercase then uppercase.
+const VARIANTS: &[(char, &str)] = &[
+    ('a', "aàáâãäåāăąAÀÁÂÃÄÅĀĂĄ"),
+    ('c', "cçćčCÇĆČ"),
+    ('d', "dďđDĎĐ"),
+    ('e', "eèéêëēėęěEÈÉÊËĒĖĘĚ"),
+    ('g', "gğGĞ"),
+    ('i', "iìíîïīįıIÌÍÎÏĪĮİ"),
+    ('l', "lłLŁ"),
+    ('n', "nñńňNÑŃŇ"),
+    ('o', "oòóôõöøōOÒÓÔÕÖØŌ"),
+    ('r', "rřRŘ"),
+    ('s', "sśšşSŚŠŞ"),
+    ('t', "tťţTŤŢ"),
+    ('u', "uùúûüūůűUÙÚÛÜŪŮŰ"),
+    ('y', "yýÿYÝŸ"),
+    ('z', "zźżžZŹŻŽ"),
+];
+
+/// Characters that have a meaning in Lucene regular expressions.
+const REGEXP_RESERVED: &str = ".?+*|{}[]()\"\\#@&<>~";
+
+/// Rewrite the `term`, `prefix`, `wildcard` and `match` queries of a query, including within compound
+/// queries, to ignore case and diacritics.
+pub fn fold_query(query: &mut Value) {
+    let Some(obj) = query.as_object_mut() else {
+        return;
+    };
+    // A query object has a single key, the query type
+    let Some((kind, spec)) = obj.iter_mut().next() else {
+        return;
+    };
+
+    let folded = match kind.as_str() {
+        "term" | "prefix" | "wildcard" | "match" => fold_leaf(kind, spec),
+        "bool" => {
+            for clause in ["must", "should", "filter", "must_not"] {
+                match spec.get_mut(clause) {
+                    Some(Value::Array(queries)) => queries.iter_mut().for_each(fold_query),
+                    Some(query) => fold_query(query),
+                    None => {}
+                }
+            }
+            None
+        }
+        "dis_max" => {
+            if let Some(Value::Array(queries)) = spec.get_mut("queries") {
+                queries.iter_mut().for_each(fold_query);
+            }
+            None
+        }
+        "boosting" => {
+            fold_queries(spec, &["positive", "negative"]);
+            None
+        }
+        "constant_score" => {
+            fold_queries(spec, &["filter"]);
+            None
+        }
+        "nested" | "function_score" | "has_child" | "has_parent" => {
+            fold_queries(spec, &["query"]);
+            None
+        }
+        _ => None,
+    };
+
+    if let Some(folded) = folded {
+        *query = folded;
+    }
+}
+
+fn fold_queries(spec: &mut Value, keys: &[&str]) {
+    for key in keys {
+        if let Some(query) = spec.get_mut(*key) {
+            fold_query(query);
+        }
+    }
+}
+
+/// Folded version of a leaf query, if it searches text.
+fn fold_leaf(kind: &str, spec: &Value) -> Option<Value> {
+    let (field, params) = spec.as_object()?.iter().next()?;
+    // Short form `{ "field": "text" }`, or long form with parameters
+    let text = match params {
+        Value::String(text) => text,
+        Value::Object(params) => params.get(if kind == "match" { "query" } else { "value" })?.as_str()?,
+        _ => return None,
+    };
+
+    let pattern = match kind {
+        "term" => folding_pattern(text),
+        "prefix" => format!("{}.*", folding_pattern(text)),
+        "wildcard" => text
+            .split('*')
+            .map(|part| part.split('?').map(folding_pattern).collect::<Vec<_>>().join("."))
+            .collect::<Vec<_>>()
+            .join(".*"),
+        // Analyzed text: each word must match an indexed token
+        "match" => {
+            let words = text
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|w| !w.is_empty())
+                .map(|word| regexp(field, &folding_pattern(&word.to_lowercase())))
+                .collect::<Vec<_>>();
+            if words.is_empty() {
+                return None;
+            }
+            let original = json!({ kind: Map::from_iter([(field.clone(), params.clone())]) });
+            return Some(json!({ "bool": { "should": [original, { "bool": { "must": words } }] } }));
+        }
+        _ => return None,
+    };
+    Some(regexp(field, &pattern))
+}
+
+fn regexp(field: &str, pattern: &str) -> Value {
+    json!({ "regexp": { field: { "value": pattern, "case_insensitive": true, "flags": "NONE" } } })
+}
+
+/// A Lucene regular expression that matches `text` regardless of case and diacritics.
+pub fn folding_pattern(text: &str) -> String {
+    let mut pattern = String::with_capacity(text.len() * 4);
+    for c in text.chars() {
+        let lower = c.to_lowercase().next().unwrap_or(c);
+        if let Some((_, variants)) = VARIANTS
+            .iter()
+            .find(|(base, variants)| *base == lower || variants.contains(c))
+        {
+            pattern.push('[');
+            pattern.push_str(variants);
+            pattern.push(']');
+        } else {
+            if REGEXP_RESERVED.contains(c) {
+                pattern.push('\\');
+            }
+            pattern.push(c);
+        }
+    }
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_pattern() {
+        assert_eq!(
+            folding_pattern("Zoë"),
+            "[zźżžZŹŻŽ][oòóôõöøōOÒÓÔÕÖØŌ][eèéêëēėęěEÈÉÊËĒĖĘĚ]"
+        );
+        assert_eq!(folding_pattern("b.1"), "b\\.1");
+    }
+
+    #[test]
+    fn fold_compound_query() {
+        let mut query = json!({ "bool": {
+            "filter": [
+                { "term": { "city": "Malmo" } },
+                { "range": { "term": { "gte": 1 } } },
+            ],
+            "must": { "match": { "name": { "query": "Jose", "operator": "and" } } },
+        }});
+        fold_query(&mut query);
+
+        assert_eq!(query["bool"]["filter"][0], regexp("city", &folding_pattern("Malmo")));
+        // Not a text query, even if the field is named "term"
+        assert_eq!(query["bool"]["filter"][1], json!({ "range": { "term": { "gte": 1 } } }));
+        assert_eq!(
+            query["bool"]["must"],
+            json!({ "bool": { "should": [
+                { "match": { "name": { "query": "Jose", "operator": "and" } } },
+                { "bool": { "must": [regexp("name", &folding_pattern("jose"))] } },
+            ]}})
+        );
+    }
+
+    #[test]
+    fn fold_wildcard() {
+        let mut query = json!({ "wildcard": { "name": { "value": "mu*ller?" } } });
+        fold_query(&mut query);
+        assert_eq!(
+            query["regexp"]["name"]["value"],
+            format!("{}.*{}.", folding_pattern("mu"), folding_pattern("ller"))
+        );
+    }
+}
@@ -0,0 +1,476 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Guardrails on the cost of searches: rules that reject or rewrite the query DSL of tool calls before
+//! they're sent to Elasticsearch.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rmcp::model::JsonObject;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::time::Duration;
+
+/// Default field of the range queries checked by `max_time_range`.
+const DEFAULT_TIME_FIELD: &str = "@timestamp";
+
+/// Arguments of tools that set a number of results, with their default value, that are limited by `max_size`.
+const SIZE_ARGUMENTS: &[(&str, &str, u64)] = &[
+    ("semantic_search", "size", 10),
+    ("knn_search", "k", 10),
+    ("eql_search", "size", 10),
+    // Page size of results, that defaults to 1000 in Elasticsearch
+    ("sql_query", "fetch_size", 1000),
+];
+
+/// Tool whose ES|QL `query` argument is limited by `max_size`.
+const ESQL_TOOL: &str = "query_esql";
+
+/// Guardrails on the cost of searches. All rules are disabled by default.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Guardrails {
+    /// Maximum number of hits returned by a search. Larger `size` values are reduced to it, as well as the
+    /// number of results of the semantic, kNN, EQL, SQL and ES|QL tools.
+    #[serde(default)]
+    pub max_size: Option<u64>,
+
+    /// Reject wildcard, regexp and query string queries with terms that start with a wildcard, as they scan
+    /// all the terms of a field
+    #[serde(default)]
+    pub deny_leading_wildcards: bool,
+
+    /// Maximum time span of range queries on the time field, e.g. `30d` or `12h`. These ranges must also
+    /// have a lower bound.
+    #[serde(default)]
+    pub max_time_range: Option<String>,
+
+    /// Field of the range queries checked with `max_time_range` (default: `@timestamp`)
+    #[serde(default)]
+    pub time_field: Option<String>,
+
+    /// Aggregation types that are rejected, possibly for a single field, e.g. `cardinality` or
+    /// `cardinality:user.id`
+    #[serde(default)]
+    pub forbidden_aggregations: Vec<String>,
+}
+
+impl Guardrails {
+    pub fn is_empty(&self) -> bool {
+        self.max_size.is_none()
+            && !self.deny_leading_wildcards
+            && self.max_time_range.is_none()
+            && self.forbidden_aggregations.is_empty()
+    }
+
+    /// Check the configuration.
+    pub fn check(&self) -> anyhow::Result<()> {
+        if let Some(range) = &self.max_time_range {
+            parse_span(range).ok_or_else(|| anyhow::anyhow!("Invalid max_time_range '{range}', expecting e.g. 30d"))?;
+        }
+        Ok(())
+    }
+
+    /// Check the query DSL arguments of a tool call, and rewrite them if needed.
+    pub fn apply(&self, tool: &str, arguments: Option<&mut JsonObject>) -> Result<(), rmcp::Error> {
+        let Some(arguments) = arguments.filter(|_| !self.is_empty()) else {
+            return Ok(());
+        };
+        self.apply_at(tool, arguments, Utc::now())
+            .map_err(|reason| rmcp::Error::invalid_params(format!("Query rejected by the guardrails: {reason}"), None))
+    }
+
+    fn apply_at(&self, tool: &str, arguments: &mut JsonObject, now: DateTime<Utc>) -> Result<(), String> {
+        if let Some(max_size) = self.max_size {
+            limit_results(tool, arguments, max_size);
+        }
+        // Search request bodies
+        if let Some(Value::Object(body)) = arguments.get_mut("query_body") {
+            self.apply_to_body(body, now)?;
+        }
+        if let Some(Value::Array(searches)) = arguments.get_mut("searches") {
+            for search in searches {
+                if let Some(Value::Object(body)) = search.get_mut("query_body") {
+                    self.apply_to_body(body, now)?;
+                }
+            }
+        }
+        // Queries
+        if let Some(query @ Value::Object(_)) = arguments.get("query") {
+            self.check_query(query, now)?;
+        }
+        // Metrics of the aggregate tool
+        if let Some(Value::Array(metrics)) = arguments.get("metrics") {
+            for metric in metrics {
+                let function = metric.get("function").and_then(Value::as_str).unwrap_or_default();
+                let field = metric.get("field").and_then(Value::as_str);
+                self.check_aggregation(function, field)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_to_body(&self, body: &mut Map<String, Value>, now: DateTime<Utc>) -> Result<(), String> {
+        if let Some(max_size) = self.max_size
+            && body
+                .get("size")
+                .and_then(Value::as_u64)
+                .is_some_and(|size| size > max_size)
+        {
+            body.insert("size".to_string(), max_size.into());
+        }
+
+        // Queries are also found in aggregations (e.g. `filter`) and other parts of the body
+        for value in body.values() {
+            self.check_query(value, now)?;
+        }
+        for key in ["aggs", "aggregations"] {
+            if let Some(Value::Object(aggs)) = body.get(key) {
+                self.check_aggregations(aggs)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the queries found in a value.
+    fn check_query(&self, value: &Value, now: DateTime<Utc>) -> Result<(), String> {
+        match value {
+            Value::Object(object) => {
+                for (key, value) in object {
+                    match key.as_str() {
+                        "wildcard" | "regexp" if self.deny_leading_wildcards => {
+                            check_leading_wildcard(key, value)?;
+                        }
+                        "query_string" if self.deny_leading_wildcards => {
+                            let query = value.get("query").and_then(Value::as_str).unwrap_or_default();
+                            // Terms, possibly prefixed with a field name
+                            let leading = query
+                                .split_whitespace()
+                                .map(|term| term.trim_start_matches(['(', '+', '-']))
+                                .map(|term| term.rsplit_once(':').map_or(term, |(_, term)| term))
+                                .find(|term| term.starts_with(['*', '?']));
+                            if let Some(term) = leading {
+                                return Err(format!("query string term '{term}' starts with a wildcard"));
+                            }
+                        }
+                        "range" => self.check_time_range(value, now)?,
+                        _ => {}
+                    }
+                    self.check_query(value, now)?;
+                }
+            }
+            Value::Array(values) => {
+                for value in values {
+                    self.check_query(value, now)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn check_time_range(&self, range: &Value, now: DateTime<Utc>) -> Result<(), String> {
+        let Some(max_span) = self.max_time_range.as_deref().and_then(parse_span) else {
+            return Ok(());
+        };
+        let field = self.time_field.as_deref().unwrap_or(DEFAULT_TIME_FIELD);
+        let Some(bounds) = range.get(field) else {
+            return Ok(());
+        };
+        let bound = |keys: &[&str]| keys.iter().find_map(|key| bounds.get(*key)).filter(|v| !v.is_null());
+
+        let Some(lower) = bound(&["gte", "gt", "from"]) else {
+            return Err(format!("range on '{field}' must have a lower bound"));
+        };
+        let upper = bound(&["lte", "lt", "to"]);
+        let (Some(lower), Some(upper)) = (
+            parse_time(lower, now),
+            upper.map_or(Some(now), |upper| parse_time(upper, now)),
+        ) else {
+            // Bounds that aren't dates or date math on `now` aren't checked
+            return Ok(());
+        };
+
+        if upper.signed_duration_since(lower).to_std().unwrap_or_default() > max_span {
+            return Err(format!(
+                "range on '{field}' is longer than {}",
+                self.max_time_range.as_deref().unwrap_or_default()
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_aggregations(&self, aggs: &Map<String, Value>) -> Result<(), String> {
+        for agg in aggs.values() {
+            let Value::Object(agg) = agg else {
+                continue;
+            };
+            for (kind, definition) in agg {
+                match (kind.as_str(), definition) {
+                    ("aggs" | "aggregations", Value::Object(sub_aggs)) => self.check_aggregations(sub_aggs)?,
+                    ("meta", _) => {}
+                    _ => self.check_aggregation(kind, definition.get("field").and_then(Value::as_str))?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn check_aggregation(&self, kind: &str, field: Option<&str>) -> Result<(), String> {
+        let forbidden = self
+            .forbidden_aggregations
+            .iter()
+            .any(|forbidden| match forbidden.split_once(':') {
+                Some((forbidden_kind, forbidden_field)) => kind == forbidden_kind && field == Some(forbidden_field),
+                None => kind == forbidden,
+            });
+        if forbidden {
+            return Err(match field {
+                Some(field) => format!("'{kind}' aggregations on '{field}' are not allowed"),
+                None => format!("'{kind}' aggregations are not allowed"),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Check the value of a `wildcard` or `regexp` query, written as `{"field": "value"}` or
+/// `{"field": {"value": "value"}}`.
+fn check_leading_wildcard(kind: &str, query: &Value) -> Result<(), String> {
+    let Value::Object(fields) = query else {
+        return Ok(());
+    };
+    for (field, value) in fields {
+        let value = match value {
+            Value::String(value) => value.as_str(),
+            _ => value.get("value").and_then(Value::as_str).unwrap_or_default(),
+        };
+        let leading = match kind {
+            "regexp" => value.starts_with(".*") || value.starts_with(".+"),
+            _ => value.starts_with(['*', '?']),
+        };
+        if leading {
+            return Err(format!("{kind} query on '{field}' starts with a wildcard"));
+        }
+    }
+    Ok(())
+}
+
+/// Limit the number of results of the tools that don't take a search request body.
+fn limit_results(tool: &str, arguments: &mut JsonObject, max_size: u64) {
+    for (size_tool, key, default) in SIZE_ARGUMENTS {
+        if tool == *size_tool && arguments.get(*key).and_then(Value::as_u64).unwrap_or(*default) > max_size {
+            arguments.insert(key.to_string(), max_size.into());
+        }
+    }
+    // A last LIMIT applies to the result of the query, whatever its other limits (1000 by default)
+    if tool == ESQL_TOOL
+        && let Some(Value::String(query)) = arguments.get_mut("query")
+    {
+        query.push_str(&format!("\n| LIMIT {max_size}"));
+    }
+}
+
+/// Parse a time span like `30d`, `12h`, `15m` or `2w`.
+pub(crate) fn parse_span(span: &str) -> Option<Duration> {
+    let span = span.trim();
+    let unit_pos = span.find(|c: char| !c.is_ascii_digit())?;
+    let count = span[..unit_pos].parse::<u64>().ok()?;
+    let secs = unit_secs(&span[unit_pos..])?;
+    Some(Duration::from_secs(count.checked_mul(secs)?))
+}
+
+/// Length of a date math unit in seconds. Months and years are approximated.
+fn unit_secs(unit: &str) -> Option<u64> {
+    Some(match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" | "H" => 3600,
+        "d" => 86400,
+        "w" => 7 * 86400,
+        "M" => 30 * 86400,
+        "y" => 365 * 86400,
+        _ => return None,
+    })
+}
+
+/// Parse a range bound: epoch milliseconds, an RFC 3339 date and time, a `yyyy-MM-dd` date, or date math on
+/// `now` like `now-7d/d`. Rounding is ignored.
+fn parse_time(value: &Value, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if let Some(millis) = value.as_i64() {
+        return DateTime::from_timestamp_millis(millis);
+    }
+    let text = value.as_str()?.trim();
+
+    if let Some(mut math) = text.strip_prefix("now") {
+        let mut time = now;
+        // Remove rounding
+        if let Some(pos) = math.find('/') {
+            math = &math[..pos];
+        }
+        while !math.is_empty() {
+            let sign = match math.as_bytes()[0] {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return None,
+            };
+            math = &math[1..];
+            let digits = math.find(|c: char| !c.is_ascii_digit())?;
+            let count = math[..digits].parse::<i64>().ok()?;
+            let unit = math[digits..].chars().next()?;
+            let secs = unit_secs(&unit.to_string())? as i64;
+            let delta = chrono::Duration::try_seconds(count.checked_mul(secs)? * sign)?;
+            time = time.checked_add_signed(delta)?;
+            math = &math[digits + unit.len_utf8()..];
+        }
+        return Some(time);
+    }
+
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Some(time.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|time| time.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn apply(guardrails: &Guardrails, arguments: Value) -> Result<Value, String> {
+        let mut arguments = arguments.as_object().cloned().unwrap();
+        let now = DateTime::parse_from_rfc3339("2025-06-30T12:00:00Z").unwrap().to_utc();
+        guardrails.apply_at("search", &mut arguments, now)?;
+        Ok(Value::Object(arguments))
+    }
+
+    #[test]
+    fn max_size() {
+        let guardrails = Guardrails {
+            max_size: Some(100),
+            ..Default::default()
+        };
+        let result = apply(&guardrails, json!({ "query_body": { "size": 10000 } })).unwrap();
+        assert_eq!(result["query_body"]["size"], 100);
+        let result = apply(&guardrails, json!({ "searches": [{ "query_body": { "size": 10 } }] })).unwrap();
+        assert_eq!(result["searches"][0]["query_body"]["size"], 10);
+    }
+
+    #[test]
+    fn max_size_of_tools() {
+        let guardrails = Guardrails {
+            max_size: Some(5),
+            ..Default::default()
+        };
+        let apply = |tool: &str, arguments: Value| {
+            let mut arguments = arguments.as_object().cloned().unwrap();
+            guardrails.apply_at(tool, &mut arguments, Utc::now()).unwrap();
+            Value::Object(arguments)
+        };
+
+        assert_eq!(apply("knn_search", json!({ "k": 50 }))["k"], 5);
+        assert_eq!(apply("knn_search", json!({ "k": 2 }))["k"], 2);
+        // Defaults larger than the maximum are also limited
+        assert_eq!(apply("semantic_search", json!({}))["size"], 5);
+        assert_eq!(apply("eql_search", json!({ "size": 100 }))["size"], 5);
+        assert_eq!(apply("sql_query", json!({ "query": "SELECT 1" }))["fetch_size"], 5);
+        assert_eq!(
+            apply("query_esql", json!({ "query": "FROM logs | LIMIT 100" }))["query"],
+            "FROM logs | LIMIT 100\n| LIMIT 5"
+        );
+        // Other tools are unchanged
+        assert_eq!(apply("list_indices", json!({ "k": 50 }))["k"], 50);
+    }
+
+    #[test]
+    fn overflowing_spans() {
+        assert_eq!(parse_span("12h"), Some(Duration::from_secs(12 * 3600)));
+        assert_eq!(parse_span("99999999999999999y"), None);
+        assert_eq!(parse_time(&json!("now-99999999999999999y"), Utc::now()), None);
+    }
+
+    #[test]
+    fn leading_wildcards() {
+        let guardrails = Guardrails {
+            deny_leading_wildcards: true,
+            ..Default::default()
+        };
+        let search = |query: Value| apply(&guardrails, json!({ "query_body": { "query": query } }));
+
+        assert!(search(json!({ "wildcard": { "name": "jo*" } })).is_ok());
+        assert!(search(json!({ "wildcard": { "name": "*hn" } })).is_err());
+        assert!(search(json!({ "bool": { "must": [{ "wildcard": { "name": { "value": "?ohn" } } }] } })).is_err());
+        assert!(search(json!({ "regexp": { "name": ".*ohn" } })).is_err());
+        assert!(search(json!({ "query_string": { "query": "name:jo* AND city:paris" } })).is_ok());
+        assert!(search(json!({ "query_string": { "query": "name:*ohn" } })).is_err());
+        assert!(apply(&guardrails, json!({ "query": { "wildcard": { "name": "*hn" } } })).is_err());
+    }
+
+    #[test]
+    fn time_ranges() {
+        let guardrails = Guardrails {
+            max_time_range: Some("30d".to_string()),
+            ..Default::default()
+        };
+        assert!(guardrails.check().is_ok());
+        let range = |bounds: Value| apply(&guardrails, json!({ "query": { "range": { "@timestamp": bounds } } }));
+
+        assert!(range(json!({ "gte": "now-7d/d" })).is_ok());
+        assert!(range(json!({ "gte": "now-1M-1d" })).is_err());
+        assert!(range(json!({ "lte": "now" })).is_err());
+        assert!(range(json!({ "gte": "2025-06-01", "lt": "2025-06-15" })).is_ok());
+        assert!(range(json!({ "gte": "2025-01-01T00:00:00Z" })).is_err());
+        assert!(range(json!({ "gte": 1748736000000i64 })).is_ok());
+
+        // Other fields aren't checked
+        let result = apply(&guardrails, json!({ "query": { "range": { "price": { "lte": 10 } } } }));
+        assert!(result.is_ok());
+
+        let invalid = Guardrails {
+            max_time_range: Some("a month".to_string()),
+            ..Default::default()
+        };
+        assert!(invalid.check().is_err());
+    }
+
+    #[test]
+    fn forbidden_aggregations() {
+        let guardrails = Guardrails {
+            forbidden_aggregations: vec!["cardinality:user.id".to_string(), "scripted_metric".to_string()],
+            ..Default::default()
+        };
+        let aggs = |aggs: Value| apply(&guardrails, json!({ "query_body": { "size": 0, "aggs": aggs } }));
+
+        assert!(aggs(json!({ "hosts": { "cardinality": { "field": "host.name" } } })).is_ok());
+        assert!(aggs(json!({ "users": { "cardinality": { "field": "user.id" } } })).is_err());
+        assert!(
+            aggs(json!({
+                "by_day": {
+                    "date_histogram": { "field": "@timestamp", "calendar_interval": "day" },
+                    "aggs": { "users": { "cardinality": { "field": "user.id" } } }
+                }
+            }))
+            .is_err()
+        );
+        assert!(aggs(json!({ "custom": { "scripted_metric": { "map_script": "..." } } })).is_err());
+
+        let metrics = json!({ "metrics": [{ "function": "cardinality", "field": "user.id" }] });
+        assert!(apply(&guardrails, metrics).is_err());
+    }
+}
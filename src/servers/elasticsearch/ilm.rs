@@ -0,0 +1,246 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Index lifecycle management (ILM): compact summaries of policies and of the lifecycle state of indices.
+
+use chrono::{DateTime, SecondsFormat};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Lifecycle phases, in the order indices go through them.
+const PHASES: &[&str] = &["hot", "warm", "cold", "frozen", "delete"];
+
+//----- Policies (`GET _ilm/policy`)
+
+#[derive(Deserialize)]
+pub struct StoredPolicy {
+    policy: Policy,
+    #[serde(default)]
+    in_use_by: InUseBy,
+}
+
+#[derive(Deserialize)]
+struct Policy {
+    #[serde(default)]
+    phases: Map<String, Value>,
+    #[serde(rename = "_meta", default)]
+    meta: Map<String, Value>,
+}
+
+#[derive(Deserialize, Default)]
+struct InUseBy {
+    #[serde(default)]
+    indices: Vec<String>,
+    #[serde(default)]
+    data_streams: Vec<String>,
+    #[serde(default)]
+    composable_templates: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct PolicySummary {
+    name: String,
+    phases: IndexMap<String, PhaseSummary>,
+    /// Number of indices using the policy
+    indices: usize,
+    data_streams: Vec<String>,
+    templates: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct PhaseSummary {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min_age: Option<Value>,
+    actions: Value,
+}
+
+impl StoredPolicy {
+    /// Is it a policy managed by Elasticsearch or another Elastic product?
+    pub fn is_managed(&self) -> bool {
+        self.policy.meta.get("managed").and_then(Value::as_bool) == Some(true)
+    }
+
+    pub fn summary(self, name: String) -> PolicySummary {
+        let mut phases = self
+            .policy
+            .phases
+            .into_iter()
+            .map(|(phase, mut def)| {
+                let summary = PhaseSummary {
+                    min_age: def.get_mut("min_age").map(Value::take),
+                    actions: def.get_mut("actions").map(Value::take).unwrap_or_default(),
+                };
+                (phase, summary)
+            })
+            .collect::<IndexMap<_, _>>();
+        phases.sort_by_cached_key(|phase, _| PHASES.iter().position(|p| p == phase).unwrap_or(PHASES.len()));
+
+        PolicySummary {
+            name,
+            phases,
+            indices: self.in_use_by.indices.len(),
+            data_streams: self.in_use_by.data_streams,
+            templates: self.in_use_by.composable_templates,
+        }
+    }
+}
+
+//----- Lifecycle state of indices (`GET {index}/_ilm/explain`)
+
+#[derive(Deserialize)]
+pub struct ExplainResponse {
+    pub indices: IndexMap<String, IndexExplain>,
+}
+
+#[derive(Deserialize)]
+pub struct IndexExplain {
+    pub managed: bool,
+    policy: Option<String>,
+    age: Option<String>,
+    phase: Option<String>,
+    action: Option<String>,
+    step: Option<String>,
+    step_time_millis: Option<i64>,
+    failed_step: Option<String>,
+    failed_step_retry_count: Option<u32>,
+    step_info: Option<Map<String, Value>>,
+}
+
+#[derive(Serialize)]
+pub struct IndexLifecycle {
+    index: String,
+    policy: Option<String>,
+    /// Age of the index in the lifecycle
+    age: Option<String>,
+    phase: Option<String>,
+    action: Option<String>,
+    step: Option<String>,
+    /// When the index entered the current step
+    #[serde(skip_serializing_if = "Option::is_none")]
+    step_since: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    failed_step: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retries: Option<u32>,
+    /// Error, or what the step is waiting for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    info: Option<String>,
+}
+
+impl IndexExplain {
+    pub fn is_error(&self) -> bool {
+        self.step.as_deref() == Some("ERROR")
+    }
+
+    pub fn summary(self, index: String) -> IndexLifecycle {
+        let step_since = self
+            .step_time_millis
+            .and_then(DateTime::from_timestamp_millis)
+            .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true));
+        let info = self.step_info.and_then(|info| {
+            let text = info.get("reason").or_else(|| info.get("message"))?.as_str()?;
+            Some(text.to_string())
+        });
+
+        IndexLifecycle {
+            index,
+            policy: self.policy,
+            age: self.age,
+            phase: self.phase,
+            action: self.action,
+            step: self.step,
+            step_since,
+            failed_step: self.failed_step,
+            retries: self.failed_step_retry_count,
+            info,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn summarize_policy() {
+        let policy: StoredPolicy = serde_json::from_value(json!({
+            "version": 3,
+            "modified_date": "2025-01-01T00:00:00.000Z",
+            "policy": { "phases": {
+                "delete": { "min_age": "30d", "actions": { "delete": { "delete_searchable_snapshot": true } } },
+                "hot": { "min_age": "0ms", "actions": { "rollover": { "max_age": "1d" } } },
+            }},
+            "in_use_by": { "indices": ["a", "b"], "data_streams": ["logs-app"], "composable_templates": ["logs"] },
+        }))
+        .unwrap();
+        assert!(!policy.is_managed());
+
+        let summary = policy.summary("logs".to_string());
+        assert_eq!(summary.phases.keys().collect::<Vec<_>>(), vec!["hot", "delete"]);
+        assert_eq!(
+            serde_json::to_value(summary).unwrap(),
+            json!({
+                "name": "logs",
+                "phases": {
+                    "hot": { "min_age": "0ms", "actions": { "rollover": { "max_age": "1d" } } },
+                    "delete": { "min_age": "30d", "actions": { "delete": { "delete_searchable_snapshot": true } } },
+                },
+                "indices": 2,
+                "data_streams": ["logs-app"],
+                "templates": ["logs"],
+            })
+        );
+    }
+
+    #[test]
+    fn summarize_index_error() {
+        let explain: IndexExplain = serde_json::from_value(json!({
+            "index": "logs-000001",
+            "managed": true,
+            "policy": "logs",
+            "age": "2.5d",
+            "phase": "hot",
+            "action": "rollover",
+            "step": "ERROR",
+            "step_time_millis": 1_735_689_600_000_i64,
+            "failed_step": "check-rollover-ready",
+            "failed_step_retry_count": 4,
+            "is_auto_retryable_error": true,
+            "step_info": { "type": "illegal_argument_exception", "reason": "index.lifecycle.rollover_alias is empty" },
+        }))
+        .unwrap();
+        assert!(explain.is_error());
+
+        assert_eq!(
+            serde_json::to_value(explain.summary("logs-000001".to_string())).unwrap(),
+            json!({
+                "index": "logs-000001",
+                "policy": "logs",
+                "age": "2.5d",
+                "phase": "hot",
+                "action": "rollover",
+                "step": "ERROR",
+                "step_since": "2025-01-01T00:00:00Z",
+                "failed_step": "check-rollover-ready",
+                "retries": 4,
+                "info": "index.lifecycle.rollover_alias is empty",
+            })
+        );
+    }
+}
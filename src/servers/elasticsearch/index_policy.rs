@@ -0,0 +1,376 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Indices that tools can access.
+//!
+//! Index expressions of tool calls, like `logs-*,metrics`, are checked as they are written: aliases and data
+//! streams aren't resolved to their indices.
+
+use crate::servers::elasticsearch::esql;
+use crate::utils::{matches_pattern, string_or_vec_schema, vec_from_string_or_vec};
+use regex::Regex;
+use rmcp::model::{ErrorCode, JsonObject};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::LazyLock;
+
+/// Arguments of tools that are index expressions.
+const INDEX_ARGUMENTS: [&str; 6] = [
+    "index",
+    "other_index",
+    "index_pattern",
+    "source_index",
+    "dest_index",
+    "alias",
+];
+
+/// Tools that target all indices when an index argument is missing.
+const ALL_INDICES_BY_DEFAULT: [(&str, &str); 2] = [("get_shards", "index"), ("search_latency_report", "index_pattern")];
+
+/// Tools whose `query` argument is an ES|QL query.
+const ESQL_TOOLS: [&str; 2] = ["query_esql", "validate_esql"];
+
+/// Tools whose `query` argument is an SQL query.
+const SQL_TOOLS: [&str; 2] = ["sql_query", "sql_translate"];
+
+/// Indices that tools can access, as patterns with `*` wildcards.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct IndexPolicy {
+    /// Indices that tools can access. All indices if empty.
+    #[serde(default, deserialize_with = "vec_from_string_or_vec")]
+    #[schemars(schema_with = "string_or_vec_schema")]
+    pub allow: Vec<String>,
+    /// Indices that tools can't access, even if they are allowed
+    #[serde(default, deserialize_with = "vec_from_string_or_vec")]
+    #[schemars(schema_with = "string_or_vec_schema")]
+    pub deny: Vec<String>,
+}
+
+impl IndexPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Can tools access an index?
+    pub fn is_allowed(&self, index: &str) -> bool {
+        self.check_expression(index).is_ok()
+    }
+
+    /// Check that all the indices of an index expression, possibly with wildcards, can be accessed. A pattern
+    /// is allowed if it's within an allowed pattern, and denied if it may match a denied index.
+    pub fn check_expression(&self, expression: &str) -> Result<(), String> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        for index in expression.split(',').map(str::trim) {
+            // Exclusions only remove indices
+            if index.starts_with('-') && index.len() > 1 {
+                continue;
+            }
+            let index = normalize(index);
+            if let Some(deny) = self.deny.iter().find(|deny| patterns_overlap(deny, &index)) {
+                return Err(format!("index '{index}' is denied by pattern '{deny}'"));
+            }
+            if !self.allow.is_empty() && !self.allow.iter().any(|allow| matches_pattern(allow, &index)) {
+                return Err(format!(
+                    "index '{index}' is not within the allowed indices: {}",
+                    self.allow.join(", ")
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the indices targeted by a tool call.
+    pub fn check_call(&self, tool: &str, arguments: Option<&JsonObject>) -> Result<(), rmcp::Error> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let empty = JsonObject::new();
+        let arguments = arguments.unwrap_or(&empty);
+        let str_arg = |name: &str| arguments.get(name).and_then(Value::as_str);
+
+        let mut expressions = Vec::new();
+        expressions.extend(
+            INDEX_ARGUMENTS
+                .iter()
+                .filter_map(|name| str_arg(name))
+                .map(str::to_string),
+        );
+        for (_, argument) in ALL_INDICES_BY_DEFAULT.iter().filter(|(name, _)| *name == tool) {
+            if str_arg(argument).is_none() {
+                expressions.push("*".to_string());
+            }
+        }
+        // Multi-search
+        if let Some(Value::Array(searches)) = arguments.get("searches") {
+            expressions.extend(
+                searches
+                    .iter()
+                    .filter_map(|search| search.get("index").and_then(Value::as_str))
+                    .map(str::to_string),
+            );
+        }
+        if let Some(query) = str_arg("query") {
+            if ESQL_TOOLS.contains(&tool) {
+                expressions.extend(esql_indices(query));
+            } else if SQL_TOOLS.contains(&tool) {
+                expressions.extend(sql_indices(query));
+            }
+        }
+
+        for expression in expressions {
+            self.check_expression(&expression)
+                .map_err(|reason| denied(format!("Tool '{tool}' denied by the index policy: {reason}")))?;
+        }
+        Ok(())
+    }
+
+    /// Check the source indices of an enrich policy used by an ES|QL query.
+    pub fn check_enrich_policy(&self, policy: &str, indices: &[String]) -> Result<(), rmcp::Error> {
+        for index in indices {
+            self.check_expression(index)
+                .map_err(|reason| denied(format!("Enrich policy '{policy}' denied by the index policy: {reason}")))?;
+        }
+        Ok(())
+    }
+}
+
+fn denied(message: String) -> rmcp::Error {
+    rmcp::Error::new(ErrorCode::INVALID_REQUEST, message, None)
+}
+
+/// Index patterns of an index name: `_all` and empty names target all indices, and date math names like
+/// `<logs-{now/d}>` are turned into patterns.
+fn normalize(index: &str) -> String {
+    static DATE_MATH: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{[^}]*\}+").unwrap());
+
+    match index {
+        "" | "_all" => "*".to_string(),
+        _ if index.starts_with('<') && index.ends_with('>') => {
+            DATE_MATH.replace_all(&index[1..index.len() - 1], "*").to_string()
+        }
+        _ => index.to_string(),
+    }
+}
+
+/// Can two patterns with `*` wildcards match the same name?
+fn patterns_overlap(a: &str, b: &str) -> bool {
+    fn overlap(a: &[u8], b: &[u8]) -> bool {
+        match (a.first(), b.first()) {
+            (None, None) => true,
+            (Some(b'*'), _) => overlap(&a[1..], b) || (!b.is_empty() && overlap(a, &b[1..])),
+            (_, Some(b'*')) => overlap(a, &b[1..]) || (!a.is_empty() && overlap(&a[1..], b)),
+            (Some(x), Some(y)) => x == y && overlap(&a[1..], &b[1..]),
+            _ => false,
+        }
+    }
+    overlap(a.as_bytes(), b.as_bytes())
+}
+
+/// Indices read by an ES|QL query: the sources of its first command, and the indices of lookup joins.
+fn esql_indices(query: &str) -> Vec<String> {
+    let mut indices = Vec::new();
+    if let Some(first) = esql::commands(query).first() {
+        let mut words = first.splitn(2, char::is_whitespace);
+        let command = words.next().unwrap_or_default();
+        if ["FROM", "TS", "METRICS"]
+            .iter()
+            .any(|c| command.eq_ignore_ascii_case(c))
+        {
+            let sources = words.next().unwrap_or_default();
+            // Sources end with the first word that isn't an index pattern, like METADATA
+            let sources = sources
+                .split_whitespace()
+                .take_while(|word| !word.eq_ignore_ascii_case("METADATA"))
+                .collect::<String>();
+            indices.extend(sources.split(',').map(|s| s.trim_matches(['"', '`']).to_string()));
+        }
+    }
+    indices.extend(esql::references(query).lookup_indices);
+    indices
+}
+
+/// Tables of an SQL query, i.e. the names that follow `FROM`, `JOIN`, `DESCRIBE` and `SHOW COLUMNS IN`. The
+/// `LIKE` patterns of `DESCRIBE` and `SHOW COLUMNS` are turned into wildcard patterns.
+fn sql_indices(query: &str) -> Vec<String> {
+    static TABLE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(
+            r#"(?i)(?:\b(?:FROM|JOIN)|^\s*DESC(?:RIBE)?|\bSHOW\s+COLUMNS)(?:\s+INCLUDE\s+FROZEN)?(?:\s+(?:IN|FROM))?\s+(?:LIKE\s+'([^']*)'|("[^"]*"|[^\s,()]+))"#,
+        )
+        .unwrap()
+    });
+
+    TABLE
+        .captures_iter(query)
+        .map(|c| match c.get(1) {
+            Some(like) => like.as_str().replace(['%', '_'], "*"),
+            None => c[2].trim_matches('"').to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn policy(allow: &[&str], deny: &[&str]) -> IndexPolicy {
+        IndexPolicy {
+            allow: allow.iter().map(|s| s.to_string()).collect(),
+            deny: deny.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn check_expressions() {
+        let policy = policy(&["logs-*", "metrics"], &["logs-secret*"]);
+        assert!(policy.check_expression("logs-web").is_ok());
+        assert!(policy.check_expression("logs-web-*,metrics").is_ok());
+        assert!(policy.check_expression("logs-*,-logs-secret").is_err());
+        assert!(policy.check_expression("logs-secret-1").is_err());
+        assert!(policy.check_expression("users").is_err());
+        assert!(policy.check_expression("*").is_err());
+        assert!(policy.check_expression("_all").is_err());
+        assert!(policy.check_expression("<logs-web-{now/d}>").is_ok());
+
+        let deny_only = self::policy(&[], &["secrets"]);
+        assert!(deny_only.check_expression("logs").is_ok());
+        assert!(deny_only.check_expression("sec*").is_err());
+        assert!(deny_only.check_expression("*").is_err());
+        assert!(deny_only.is_allowed("secrets-2"));
+    }
+
+    #[test]
+    fn overlapping_patterns() {
+        assert!(patterns_overlap("logs-*", "*-web"));
+        assert!(patterns_overlap("*", "secrets"));
+        assert!(patterns_overlap("a*c", "ab*"));
+        assert!(!patterns_overlap("logs-*", "metrics-*"));
+        assert!(!patterns_overlap("a*c", "*b"));
+    }
+
+    #[test]
+    fn check_tool_calls() {
+        let policy = policy(&["logs-*"], &[]);
+        let args = |value: Value| value.as_object().cloned();
+
+        assert!(
+            policy
+                .check_call("search", args(json!({ "index": "logs-web" })).as_ref())
+                .is_ok()
+        );
+        let err = policy
+            .check_call("search", args(json!({ "index": "users" })).as_ref())
+            .unwrap_err();
+        assert!(
+            err.message.contains("index 'users' is not within the allowed indices"),
+            "{err}"
+        );
+
+        assert!(policy.check_call("get_shards", None).is_err());
+        assert!(policy.check_call("get_nodes", None).is_ok());
+
+        let msearch = json!({ "searches": [{ "index": "logs-web" }, { "index": "users" }] });
+        assert!(policy.check_call("msearch", args(msearch).as_ref()).is_err());
+
+        let esql = |query: &str| args(json!({ "query": query }));
+        assert!(
+            policy
+                .check_call(
+                    "query_esql",
+                    esql("FROM logs-web, \"logs-db\" METADATA _id | LIMIT 1").as_ref()
+                )
+                .is_ok()
+        );
+        assert!(
+            policy
+                .check_call("query_esql", esql("from users | limit 1").as_ref())
+                .is_err()
+        );
+        assert!(
+            policy
+                .check_call("query_esql", esql("FROM logs-web | LOOKUP JOIN users ON id").as_ref())
+                .is_err()
+        );
+        assert!(
+            policy
+                .check_call("sql_query", esql("SELECT * FROM \"logs-web\" LIMIT 1").as_ref())
+                .is_ok()
+        );
+        assert!(
+            policy
+                .check_call("sql_query", esql("SELECT * FROM users").as_ref())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn check_aliases() {
+        let policy = policy(&["logs-*"], &[]);
+        let args = |value: Value| value.as_object().cloned();
+
+        let create = json!({ "alias": "logs-current", "index": "logs-web-2" });
+        assert!(policy.check_call("create_alias", args(create).as_ref()).is_ok());
+        let create = json!({ "alias": "users", "index": "logs-web-2" });
+        assert!(policy.check_call("create_alias", args(create).as_ref()).is_err());
+        let swap = json!({ "alias": "users", "index": "logs-web-2" });
+        assert!(policy.check_call("swap_alias", args(swap).as_ref()).is_err());
+    }
+
+    #[test]
+    fn sql_describe_and_show_columns() {
+        assert_eq!(sql_indices("DESCRIBE users"), ["users"]);
+        assert_eq!(sql_indices("desc \"logs-web\""), ["logs-web"]);
+        assert_eq!(sql_indices("SHOW COLUMNS IN users"), ["users"]);
+        assert_eq!(sql_indices("SHOW COLUMNS FROM users"), ["users"]);
+        assert_eq!(sql_indices("SHOW COLUMNS INCLUDE FROZEN IN users"), ["users"]);
+        assert_eq!(sql_indices("SHOW COLUMNS users"), ["users"]);
+        assert_eq!(sql_indices("DESCRIBE LIKE 'sec%'"), ["sec*"]);
+        assert_eq!(sql_indices("SELECT a FROM logs ORDER BY a DESC LIMIT 1"), ["logs"]);
+
+        let policy = policy(&[], &["secrets"]);
+        let sql = |query: &str| json!({ "query": query }).as_object().cloned();
+        assert!(
+            policy
+                .check_call("sql_query", sql("DESCRIBE secrets").as_ref())
+                .is_err()
+        );
+        assert!(
+            policy
+                .check_call("sql_query", sql("SHOW COLUMNS IN secrets").as_ref())
+                .is_err()
+        );
+        assert!(
+            policy
+                .check_call("sql_query", sql("DESCRIBE LIKE 'sec%'").as_ref())
+                .is_err()
+        );
+        assert!(policy.check_call("sql_query", sql("DESCRIBE logs").as_ref()).is_ok());
+    }
+
+    #[test]
+    fn check_enrich_policies() {
+        let policy = policy(&["logs-*"], &[]);
+        assert!(policy.check_enrich_policy("hosts", &["logs-hosts".to_string()]).is_ok());
+        let err = policy
+            .check_enrich_policy("users", &["logs-hosts".to_string(), "users".to_string()])
+            .unwrap_err();
+        assert!(err.message.contains("Enrich policy 'users' denied"), "{err}");
+    }
+}
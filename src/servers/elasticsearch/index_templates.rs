@@ -0,0 +1,499 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Index templates and component templates: compact summaries, and the effective settings, mappings and
+//! aliases of a template, with the template each value comes from.
+
+use crate::utils::matches_pattern;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+//----- Stored templates (`GET _index_template` and `GET _component_template`)
+
+#[derive(Deserialize)]
+pub struct IndexTemplatesResponse {
+    pub index_templates: Vec<NamedIndexTemplate>,
+}
+
+#[derive(Deserialize)]
+pub struct NamedIndexTemplate {
+    name: String,
+    index_template: IndexTemplate,
+}
+
+#[derive(Deserialize)]
+struct IndexTemplate {
+    #[serde(default)]
+    index_patterns: Vec<String>,
+    priority: Option<i64>,
+    #[serde(default)]
+    composed_of: Vec<String>,
+    #[serde(default)]
+    template: Template,
+    data_stream: Option<Value>,
+    #[serde(rename = "_meta", default)]
+    meta: Map<String, Value>,
+}
+
+#[derive(Deserialize)]
+pub struct ComponentTemplatesResponse {
+    pub component_templates: Vec<NamedComponentTemplate>,
+}
+
+#[derive(Deserialize)]
+pub struct NamedComponentTemplate {
+    name: String,
+    component_template: ComponentTemplate,
+}
+
+#[derive(Deserialize)]
+struct ComponentTemplate {
+    #[serde(default)]
+    template: Template,
+    #[serde(rename = "_meta", default)]
+    meta: Map<String, Value>,
+}
+
+#[derive(Deserialize, Default)]
+struct Template {
+    #[serde(default)]
+    settings: Map<String, Value>,
+    #[serde(default)]
+    mappings: Map<String, Value>,
+    #[serde(default)]
+    aliases: Map<String, Value>,
+}
+
+impl Template {
+    /// Parts of an index definition that the template provides.
+    fn defines(&self) -> Vec<&'static str> {
+        [
+            ("settings", &self.settings),
+            ("mappings", &self.mappings),
+            ("aliases", &self.aliases),
+        ]
+        .into_iter()
+        .filter(|(_, part)| !part.is_empty())
+        .map(|(name, _)| name)
+        .collect()
+    }
+}
+
+/// Is it a template managed by Elasticsearch or another Elastic product?
+fn is_managed(meta: &Map<String, Value>) -> bool {
+    meta.get("managed").and_then(Value::as_bool) == Some(true)
+}
+
+//----- Summaries
+
+#[derive(Serialize)]
+pub struct TemplateList {
+    pub index_templates: Vec<IndexTemplateSummary>,
+    pub component_templates: Vec<ComponentTemplateSummary>,
+    /// Number of templates managed by Elastic that are not listed
+    #[serde(skip)]
+    pub managed: usize,
+}
+
+#[derive(Serialize)]
+pub struct IndexTemplateSummary {
+    name: String,
+    index_patterns: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    priority: Option<i64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    composed_of: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data_stream: Option<Value>,
+}
+
+#[derive(Serialize)]
+pub struct ComponentTemplateSummary {
+    name: String,
+    /// Parts of an index definition that it provides: `settings`, `mappings` or `aliases`
+    defines: Vec<&'static str>,
+    /// Index templates composed of it
+    used_by: Vec<String>,
+}
+
+//----- Effective settings, mappings and aliases
+
+#[derive(Serialize)]
+pub struct TemplateDetails {
+    name: String,
+    /// `index_template` or `component_template`
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    index_patterns: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    priority: Option<i64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    composed_of: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data_stream: Option<Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    used_by: Vec<String>,
+    #[serde(flatten)]
+    effective: EffectiveTemplate,
+}
+
+/// Settings, mappings and aliases merged from component templates in order, and then from the index template.
+#[derive(Serialize, Default)]
+pub struct EffectiveTemplate {
+    /// Settings with their full name, e.g. `index.number_of_shards`
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    settings: IndexMap<String, Resolved>,
+    /// Mapping parameters other than fields, e.g. `dynamic` or `_source`
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    mapping_options: IndexMap<String, Resolved>,
+    /// Fields with their full path, e.g. `user.name`. Sub-fields of objects are listed separately.
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    fields: IndexMap<String, Resolved>,
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    aliases: IndexMap<String, Resolved>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    missing_component_templates: Vec<String>,
+}
+
+/// A value, the template it comes from, and the templates whose value it replaces.
+#[derive(Serialize)]
+struct Resolved {
+    value: Value,
+    from: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    overrides: Vec<String>,
+}
+
+impl EffectiveTemplate {
+    /// Merge a template, whose values replace those of previously merged templates.
+    fn merge(&mut self, source: &str, template: &Template) {
+        let mut settings = Vec::new();
+        flatten_settings("", &template.settings, &mut settings);
+        for (name, value) in settings {
+            // Elasticsearch adds the `index.` prefix to settings that don't have it
+            let name = if name.starts_with("index.") {
+                name
+            } else {
+                format!("index.{name}")
+            };
+            resolve(&mut self.settings, name, value, source);
+        }
+
+        for (key, value) in &template.mappings {
+            match (key.as_str(), value) {
+                ("properties", Value::Object(properties)) => {
+                    let mut fields = Vec::new();
+                    flatten_fields("", properties, &mut fields);
+                    for (path, definition) in fields {
+                        resolve(&mut self.fields, path, definition, source);
+                    }
+                }
+                _ => resolve(&mut self.mapping_options, key.clone(), value.clone(), source),
+            }
+        }
+
+        for (name, alias) in &template.aliases {
+            resolve(&mut self.aliases, name.clone(), alias.clone(), source);
+        }
+    }
+}
+
+fn resolve(values: &mut IndexMap<String, Resolved>, key: String, value: Value, source: &str) {
+    match values.get_mut(&key) {
+        Some(resolved) => {
+            let previous = std::mem::replace(&mut resolved.from, source.to_string());
+            resolved.overrides.push(previous);
+            resolved.value = value;
+        }
+        None => {
+            values.insert(
+                key,
+                Resolved {
+                    value,
+                    from: source.to_string(),
+                    overrides: Vec::new(),
+                },
+            );
+        }
+    }
+}
+
+/// Flatten nested settings objects to dotted names.
+fn flatten_settings(prefix: &str, settings: &Map<String, Value>, out: &mut Vec<(String, Value)>) {
+    for (key, value) in settings {
+        let name = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match value {
+            Value::Object(nested) => flatten_settings(&name, nested, out),
+            _ => out.push((name, value.clone())),
+        }
+    }
+}
+
+/// Flatten mapping properties to field paths. Objects are listed without their `properties`, and only if they
+/// have other parameters, such as a `nested` type.
+fn flatten_fields(prefix: &str, properties: &Map<String, Value>, out: &mut Vec<(String, Value)>) {
+    for (name, definition) in properties {
+        let path = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{prefix}.{name}")
+        };
+        let Value::Object(definition) = definition else {
+            out.push((path, definition.clone()));
+            continue;
+        };
+        let mut definition = definition.clone();
+        let properties = definition.remove("properties");
+        if properties.is_none() || !definition.is_empty() {
+            out.push((path.clone(), Value::Object(definition)));
+        }
+        if let Some(Value::Object(properties)) = properties {
+            flatten_fields(&path, &properties, out);
+        }
+    }
+}
+
+//----- All templates of a cluster
+
+pub struct Templates {
+    pub index_templates: Vec<NamedIndexTemplate>,
+    pub component_templates: Vec<NamedComponentTemplate>,
+}
+
+impl Templates {
+    /// Summaries of the templates matching a name pattern, or of all templates except those managed by Elastic.
+    pub fn summaries(&self, pattern: Option<&str>) -> TemplateList {
+        let selected = |name: &str, meta: &Map<String, Value>| match pattern {
+            Some(pattern) => matches_pattern(pattern, name),
+            None => !is_managed(meta),
+        };
+
+        let index_templates = self
+            .index_templates
+            .iter()
+            .filter(|t| selected(&t.name, &t.index_template.meta))
+            .map(|t| IndexTemplateSummary {
+                name: t.name.clone(),
+                index_patterns: t.index_template.index_patterns.clone(),
+                priority: t.index_template.priority,
+                composed_of: t.index_template.composed_of.clone(),
+                data_stream: t.index_template.data_stream.clone(),
+            })
+            .collect::<Vec<_>>();
+        let component_templates = self
+            .component_templates
+            .iter()
+            .filter(|t| selected(&t.name, &t.component_template.meta))
+            .map(|t| ComponentTemplateSummary {
+                name: t.name.clone(),
+                defines: t.component_template.template.defines(),
+                used_by: self.used_by(&t.name),
+            })
+            .collect::<Vec<_>>();
+
+        let managed = match pattern {
+            Some(_) => 0,
+            None => {
+                self.index_templates.len() + self.component_templates.len()
+                    - index_templates.len()
+                    - component_templates.len()
+            }
+        };
+        TemplateList {
+            index_templates,
+            component_templates,
+            managed,
+        }
+    }
+
+    /// Details and effective settings, mappings and aliases of the templates matching a name pattern.
+    pub fn details(&self, pattern: &str) -> Vec<TemplateDetails> {
+        let index_templates = self
+            .index_templates
+            .iter()
+            .filter(|t| matches_pattern(pattern, &t.name))
+            .map(|t| TemplateDetails {
+                name: t.name.clone(),
+                kind: "index_template",
+                index_patterns: t.index_template.index_patterns.clone(),
+                priority: t.index_template.priority,
+                composed_of: t.index_template.composed_of.clone(),
+                data_stream: t.index_template.data_stream.clone(),
+                used_by: Vec::new(),
+                effective: self.effective(t),
+            });
+        let component_templates = self
+            .component_templates
+            .iter()
+            .filter(|t| matches_pattern(pattern, &t.name))
+            .map(|t| {
+                let mut effective = EffectiveTemplate::default();
+                effective.merge(&t.name, &t.component_template.template);
+                TemplateDetails {
+                    name: t.name.clone(),
+                    kind: "component_template",
+                    index_patterns: Vec::new(),
+                    priority: None,
+                    composed_of: Vec::new(),
+                    data_stream: None,
+                    used_by: self.used_by(&t.name),
+                    effective,
+                }
+            });
+        index_templates.chain(component_templates).collect()
+    }
+
+    /// Merge the component templates of an index template, and then the index template itself.
+    fn effective(&self, template: &NamedIndexTemplate) -> EffectiveTemplate {
+        let mut effective = EffectiveTemplate::default();
+        for name in &template.index_template.composed_of {
+            match self.component_templates.iter().find(|c| c.name == *name) {
+                Some(component) => effective.merge(name, &component.component_template.template),
+                None => effective.missing_component_templates.push(name.clone()),
+            }
+        }
+        effective.merge(&template.name, &template.index_template.template);
+        effective
+    }
+
+    /// Names of the index templates composed of a component template.
+    fn used_by(&self, component: &str) -> Vec<String> {
+        self.index_templates
+            .iter()
+            .filter(|t| t.index_template.composed_of.iter().any(|c| c == component))
+            .map(|t| t.name.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn templates() -> Templates {
+        let index_templates: IndexTemplatesResponse = serde_json::from_value(json!({ "index_templates": [
+            { "name": "logs-app", "index_template": {
+                "index_patterns": ["logs-app-*"],
+                "priority": 200,
+                "composed_of": ["logs@mappings", "app@settings", "app@custom"],
+                "template": {
+                    "settings": { "index": { "number_of_replicas": "2" } },
+                    "mappings": { "properties": { "message": { "type": "match_only_text" } } },
+                },
+            }},
+            { "name": "logs", "index_template": {
+                "index_patterns": ["logs-*-*"],
+                "priority": 100,
+                "composed_of": ["logs@mappings"],
+                "data_stream": { "hidden": false },
+                "_meta": { "managed": true },
+            }},
+        ]}))
+        .unwrap();
+        let component_templates: ComponentTemplatesResponse = serde_json::from_value(json!({ "component_templates": [
+            { "name": "logs@mappings", "component_template": {
+                "template": { "mappings": {
+                    "dynamic": "true",
+                    "properties": {
+                        "@timestamp": { "type": "date" },
+                        "message": { "type": "text" },
+                        "host": { "properties": { "name": { "type": "keyword" } } },
+                    },
+                }},
+                "_meta": { "managed": true },
+            }},
+            { "name": "app@settings", "component_template": {
+                "template": {
+                    "settings": { "number_of_shards": 1, "index": { "number_of_replicas": "1" } },
+                    "aliases": { "app": {} },
+                },
+            }},
+        ]}))
+        .unwrap();
+
+        Templates {
+            index_templates: index_templates.index_templates,
+            component_templates: component_templates.component_templates,
+        }
+    }
+
+    #[test]
+    fn summarize_templates() {
+        let list = templates().summaries(None);
+        assert_eq!(list.managed, 2);
+        assert_eq!(
+            serde_json::to_value(list).unwrap(),
+            json!({
+                "index_templates": [{
+                    "name": "logs-app",
+                    "index_patterns": ["logs-app-*"],
+                    "priority": 200,
+                    "composed_of": ["logs@mappings", "app@settings", "app@custom"],
+                }],
+                "component_templates": [
+                    { "name": "app@settings", "defines": ["settings", "aliases"], "used_by": ["logs-app"] },
+                ],
+            })
+        );
+
+        let list = templates().summaries(Some("logs*"));
+        assert_eq!(list.managed, 0);
+        assert_eq!(list.index_templates.len(), 2);
+        assert_eq!(list.component_templates[0].used_by, vec!["logs-app", "logs"]);
+    }
+
+    #[test]
+    fn merge_effective_template() {
+        let details = templates().details("logs-app");
+        assert_eq!(details.len(), 1);
+        assert_eq!(
+            serde_json::to_value(&details[0].effective).unwrap(),
+            json!({
+                "settings": {
+                    "index.number_of_shards": { "value": 1, "from": "app@settings" },
+                    "index.number_of_replicas": { "value": "2", "from": "logs-app", "overrides": ["app@settings"] },
+                },
+                "mapping_options": {
+                    "dynamic": { "value": "true", "from": "logs@mappings" },
+                },
+                "fields": {
+                    "@timestamp": { "value": { "type": "date" }, "from": "logs@mappings" },
+                    "message": {
+                        "value": { "type": "match_only_text" },
+                        "from": "logs-app",
+                        "overrides": ["logs@mappings"],
+                    },
+                    "host.name": { "value": { "type": "keyword" }, "from": "logs@mappings" },
+                },
+                "aliases": {
+                    "app": { "value": {}, "from": "app@settings" },
+                },
+                "missing_component_templates": ["app@custom"],
+            })
+        );
+
+        let details = templates().details("app@settings");
+        assert_eq!(details[0].kind, "component_template");
+        assert_eq!(details[0].used_by, vec!["logs-app"]);
+    }
+}
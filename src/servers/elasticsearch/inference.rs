@@ -0,0 +1,133 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Calls to inference endpoints, with the `_inference` API.
+
+use crate::servers::elasticsearch::{EsClient, read_json};
+use elasticsearch::http::Method;
+use elasticsearch::http::request::JsonBody;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use tracing::Instrument;
+
+/// Result of an inference request, keyed by the task type of the endpoint.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InferenceResult {
+    TextEmbedding(Vec<DenseEmbedding>),
+    TextEmbeddingBytes(Vec<DenseEmbedding>),
+    TextEmbeddingBits(Vec<DenseEmbedding>),
+    SparseEmbedding(Vec<SparseEmbedding>),
+    Rerank(Vec<RankedDocument>),
+    Completion(Vec<Completion>),
+}
+
+impl InferenceResult {
+    pub fn task_type(&self) -> &'static str {
+        match self {
+            InferenceResult::TextEmbedding(_) => "text_embedding",
+            InferenceResult::TextEmbeddingBytes(_) => "text_embedding_bytes",
+            InferenceResult::TextEmbeddingBits(_) => "text_embedding_bits",
+            InferenceResult::SparseEmbedding(_) => "sparse_embedding",
+            InferenceResult::Rerank(_) => "rerank",
+            InferenceResult::Completion(_) => "completion",
+        }
+    }
+}
+
+/// Error message for an endpoint whose task type isn't the one expected by a tool.
+pub fn task_type_error(inference_id: &str, result: &InferenceResult, expected: &str) -> String {
+    format!(
+        "Inference endpoint '{inference_id}' is a {} endpoint, not a {expected} endpoint",
+        result.task_type()
+    )
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DenseEmbedding {
+    pub embedding: Vec<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SparseEmbedding {
+    /// Weight of each token
+    pub embedding: Map<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RankedDocument {
+    /// Position of the document in the input
+    pub index: usize,
+    pub relevance_score: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Completion {
+    pub result: String,
+}
+
+/// Call an inference endpoint with the input of its task type (`input`, and `query` for reranking).
+pub async fn infer(es_client: &EsClient, inference_id: &str, body: Value) -> Result<InferenceResult, rmcp::Error> {
+    // Ids are used as is in the path
+    if inference_id.is_empty()
+        || !inference_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    {
+        return Err(rmcp::Error::invalid_params(
+            format!("Invalid inference endpoint id '{inference_id}'"),
+            None,
+        ));
+    }
+
+    let span = tracing::info_span!("elasticsearch_request", otel.kind = "client");
+    let response = es_client
+        .send(
+            Method::Post,
+            &format!("/_inference/{inference_id}"),
+            es_client.headers(&span),
+            Option::<&()>::None,
+            Some(JsonBody::new(body)),
+            None,
+        )
+        .instrument(span)
+        .await;
+    read_json(response).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_results() {
+        let result: InferenceResult =
+            serde_json::from_value(json!({ "text_embedding": [{ "embedding": [0.1, 0.2] }] })).unwrap();
+        assert_eq!(result.task_type(), "text_embedding");
+
+        let result: InferenceResult = serde_json::from_value(json!({ "rerank": [
+            { "index": 1, "relevance_score": 0.9 },
+            { "index": 0, "relevance_score": 0.2 },
+        ]}))
+        .unwrap();
+        let InferenceResult::Rerank(docs) = result else {
+            panic!("expecting a rerank result");
+        };
+        assert_eq!(docs[0].index, 1);
+    }
+}
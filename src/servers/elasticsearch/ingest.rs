@@ -0,0 +1,186 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Ingest pipelines: compact summaries of stored pipelines and of simulation results.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+#[derive(Serialize)]
+pub struct PipelineSummary {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<Value>,
+    /// Processor types, in order
+    processors: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    on_failure: Vec<String>,
+}
+
+/// Is it a pipeline managed by Elasticsearch or an integration?
+pub fn is_managed(pipeline: &Value) -> bool {
+    pipeline.pointer("/_meta/managed").and_then(Value::as_bool) == Some(true)
+}
+
+pub fn pipeline_summary(name: String, mut pipeline: Value) -> PipelineSummary {
+    PipelineSummary {
+        name,
+        description: pipeline.get_mut("description").map(Value::take),
+        processors: processor_types(&pipeline["processors"]),
+        on_failure: processor_types(&pipeline["on_failure"]),
+    }
+}
+
+/// Types of a list of processors, which are objects with the type as their single key.
+fn processor_types(processors: &Value) -> Vec<String> {
+    processors
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|p| p.as_object()?.keys().next().cloned())
+        .collect()
+}
+
+/// Result of a simulation for a document: its final source, or the error that stopped the pipeline, and the
+/// outcome of each processor in verbose mode.
+#[derive(Serialize)]
+pub struct DocumentResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    processors: Vec<ProcessorResult>,
+}
+
+impl DocumentResult {
+    pub fn is_error(&self) -> bool {
+        self.error.is_some()
+    }
+}
+
+#[derive(Serialize)]
+pub struct ProcessorResult {
+    processor: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag: Option<Value>,
+    status: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+fn error_reason(error: &Value) -> Option<String> {
+    let reason = error.get("reason").or_else(|| error.get("type"))?;
+    Some(
+        reason
+            .as_str()
+            .map(str::to_string)
+            .unwrap_or_else(|| reason.to_string()),
+    )
+}
+
+fn source(doc: &Value) -> Option<Value> {
+    doc.get("_source").cloned()
+}
+
+/// Summarize the `docs` of a simulation response.
+pub fn simulation_results(response: &Map<String, Value>) -> Vec<DocumentResult> {
+    let docs = response
+        .get("docs")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    docs.iter()
+        .map(|doc| {
+            let Some(steps) = doc.get("processor_results").and_then(Value::as_array) else {
+                return DocumentResult {
+                    source: doc.get("doc").and_then(source),
+                    error: doc.get("error").and_then(error_reason),
+                    processors: Vec::new(),
+                };
+            };
+
+            // Verbose mode: the document after each processor
+            let processors = steps
+                .iter()
+                .map(|step| ProcessorResult {
+                    processor: step["processor_type"].as_str().unwrap_or("unknown").to_string(),
+                    tag: step.get("tag").cloned(),
+                    status: step["status"].clone(),
+                    error: step.get("error").and_then(error_reason),
+                })
+                .collect();
+            let failed = steps.iter().rev().find(|step| step["status"] == "error");
+            DocumentResult {
+                source: steps.iter().rev().find_map(|step| step.get("doc").and_then(source)),
+                error: failed.and_then(|step| error_reason(&step["error"])),
+                processors,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn summarize_pipeline() {
+        let pipeline = json!({
+            "description": "Parse logs",
+            "processors": [{ "grok": { "field": "message", "patterns": ["%{IP:ip}"] } }, { "remove": { "field": "x" } }],
+            "on_failure": [{ "set": { "field": "error", "value": "{{ _ingest.on_failure_message }}" } }],
+        });
+        assert!(!is_managed(&pipeline));
+        assert_eq!(
+            serde_json::to_value(pipeline_summary("logs".to_string(), pipeline)).unwrap(),
+            json!({ "name": "logs", "description": "Parse logs", "processors": ["grok", "remove"], "on_failure": ["set"] })
+        );
+    }
+
+    #[test]
+    fn summarize_simulation() {
+        let response = json!({ "docs": [
+            { "doc": { "_index": "_index", "_id": "_id", "_source": { "ip": "10.0.0.1" } } },
+            { "error": { "type": "illegal_argument_exception", "reason": "Provided Grok expressions do not match" } },
+        ]});
+        assert_eq!(
+            serde_json::to_value(simulation_results(response.as_object().unwrap())).unwrap(),
+            json!([
+                { "source": { "ip": "10.0.0.1" } },
+                { "error": "Provided Grok expressions do not match" },
+            ])
+        );
+
+        let response = json!({ "docs": [{ "processor_results": [
+            { "processor_type": "set", "status": "success", "doc": { "_source": { "a": 1 } } },
+            { "processor_type": "grok", "tag": "parse", "status": "error", "error": { "reason": "no match" } },
+        ]}]});
+        assert_eq!(
+            serde_json::to_value(simulation_results(response.as_object().unwrap())).unwrap(),
+            json!([{
+                "source": { "a": 1 },
+                "error": "no match",
+                "processors": [
+                    { "processor": "set", "status": "success" },
+                    { "processor": "grok", "tag": "parse", "status": "error", "error": "no match" },
+                ],
+            }])
+        );
+    }
+}
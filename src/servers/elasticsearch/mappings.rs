@@ -0,0 +1,150 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Utilities to work with index mappings.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Field paths and their type in a mapping. Object fields are included with type `object` if they have
+/// no explicit type, and multi-fields are named after their parent field, e.g. `title.keyword`.
+pub fn field_types(mapping: &Value) -> BTreeMap<String, String> {
+    let mut result = BTreeMap::new();
+    if let Some(properties) = mapping.get("properties").and_then(Value::as_object) {
+        collect_fields("", properties, &mut result);
+    }
+    result
+}
+
+fn collect_fields(prefix: &str, properties: &Map<String, Value>, result: &mut BTreeMap<String, String>) {
+    for (name, field) in properties {
+        let path = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{prefix}.{name}")
+        };
+
+        let sub_properties = field.get("properties").and_then(Value::as_object);
+        let field_type = match field.get("type").and_then(Value::as_str) {
+            Some(t) => t,
+            None if sub_properties.is_some() => "object",
+            None => "unknown",
+        };
+        result.insert(path.clone(), field_type.to_string());
+
+        if let Some(sub_properties) = sub_properties {
+            collect_fields(&path, sub_properties, result);
+        }
+        if let Some(multi_fields) = field.get("fields").and_then(Value::as_object) {
+            collect_fields(&path, multi_fields, result);
+        }
+    }
+}
+
+/// Differences between the fields of two mappings.
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct MappingDiff {
+    /// Fields that only exist in the other mapping
+    pub added: Vec<FieldType>,
+    /// Fields that only exist in the reference mapping
+    pub removed: Vec<FieldType>,
+    /// Fields that have a different type
+    pub retyped: Vec<RetypedField>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct FieldType {
+    pub field: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct RetypedField {
+    pub field: String,
+    pub from: String,
+    pub to: String,
+}
+
+impl MappingDiff {
+    pub fn new(reference: &Value, other: &Value) -> Self {
+        let reference = field_types(reference);
+        let other = field_types(other);
+        let mut diff = MappingDiff::default();
+
+        for (field, type_) in &reference {
+            match other.get(field) {
+                None => diff.removed.push(FieldType {
+                    field: field.clone(),
+                    type_: type_.clone(),
+                }),
+                Some(other_type) if other_type != type_ => diff.retyped.push(RetypedField {
+                    field: field.clone(),
+                    from: type_.clone(),
+                    to: other_type.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+
+        for (field, type_) in other {
+            if !reference.contains_key(&field) {
+                diff.added.push(FieldType { field, type_ });
+            }
+        }
+
+        diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn diff_mappings() {
+        let reference = json!({
+            "properties": {
+                "title": { "type": "text", "fields": { "keyword": { "type": "keyword" } } },
+                "user": { "properties": { "id": { "type": "keyword" }, "age": { "type": "integer" } } },
+                "status": { "type": "keyword" },
+            }
+        });
+        let other = json!({
+            "properties": {
+                "title": { "type": "text" },
+                "user": { "properties": { "id": { "type": "long" }, "age": { "type": "integer" } } },
+                "status": { "type": "keyword" },
+                "tags": { "type": "keyword" },
+            }
+        });
+
+        let diff = MappingDiff::new(&reference, &other);
+        assert_eq!(
+            serde_json::to_value(diff).unwrap(),
+            json!({
+                "added": [{ "field": "tags", "type": "keyword" }],
+                "removed": [{ "field": "title.keyword", "type": "keyword" }],
+                "retyped": [{ "field": "user.id", "from": "keyword", "to": "long" }],
+            })
+        );
+
+        assert_eq!(MappingDiff::new(&reference, &reference), MappingDiff::default());
+    }
+}
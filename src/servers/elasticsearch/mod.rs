@@ -15,49 +15,117 @@
 // specific language governing permissions and limitations
 // under the License.
 
+mod aggregate;
+mod analysis;
 mod base_tools;
+mod clusters;
+mod custom_tools;
+mod eql;
+mod error_hints;
+mod esql;
+mod folding;
+pub mod guardrails;
+mod ilm;
+pub mod index_policy;
+mod index_templates;
+mod inference;
+mod ingest;
+mod mappings;
+mod ndjson;
+mod pit;
+mod profile;
+pub mod retry;
+mod semantic;
+mod stats;
+mod template;
+pub(crate) mod writes;
 
 use crate::servers::IncludeExclude;
-use crate::utils::none_if_empty_string;
+use crate::servers::apm::{ApmConfig, ApmTools};
+use crate::servers::kibana::{KibanaConfig, KibanaTools};
+use crate::servers::logs::{LogsConfig, LogsTools};
+use crate::servers::security::{SecurityConfig, SecurityTools};
+use crate::utils::rmcp_ext::session_id;
+use crate::utils::{bool_or_string_schema, none_if_empty_string, string_or_vec_schema, vec_from_string_or_vec};
 use elasticsearch::Elasticsearch;
 use elasticsearch::auth::Credentials;
 use elasticsearch::cert::CertificateValidation;
 use elasticsearch::http::Url;
 use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::{
+    CloudConnectionPool, MultiNodeConnectionPool, SingleNodeConnectionPool, TransportBuilder,
+};
 use http::header::USER_AGENT;
 use http::request::Parts;
-use http::{HeaderValue, header};
+use http::{HeaderMap, HeaderValue, header};
 use indexmap::IndexMap;
 use rmcp::RoleServer;
 use rmcp::model::ToolAnnotations;
 use rmcp::service::RequestContext;
+use schemars::JsonSchema;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_aux::field_attributes::deserialize_bool_from_anything;
-use std::borrow::Cow;
 use std::collections::HashMap;
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::time::Duration;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
 pub struct ElasticsearchMcpConfig {
     /// Cluster URL
+    #[serde(default)]
     pub url: String,
 
-    /// API key
+    /// Cluster node URLs, as an alternative to `url`. Requests are load-balanced across nodes.
+    #[serde(default, alias = "elasticsearch.hosts", deserialize_with = "vec_from_string_or_vec")]
+    #[schemars(schema_with = "string_or_vec_schema")]
+    pub hosts: Vec<String>,
+
+    /// Interval in seconds between refreshes of the node list from the cluster (sniffing). Nodes are first
+    /// discovered from `url` or `hosts`.
+    #[serde(default)]
+    pub sniff_interval_secs: Option<u64>,
+
+    /// Elastic Cloud deployment id, as an alternative to `url`
+    #[serde(default, alias = "cloud.id", deserialize_with = "none_if_empty_string")]
+    pub cloud_id: Option<String>,
+
+    /// Elastic Cloud credentials, as `login:password`
+    #[serde(default, alias = "cloud.auth", deserialize_with = "none_if_empty_string")]
+    pub cloud_auth: Option<String>,
+
+    /// API key, either encoded or as `id:api_key`
     #[serde(default, deserialize_with = "none_if_empty_string")]
     pub api_key: Option<String>,
 
     /// Login
-    #[serde(default, deserialize_with = "none_if_empty_string")]
+    #[serde(default, alias = "elasticsearch.username", deserialize_with = "none_if_empty_string")]
     pub login: Option<String>,
 
     /// Password
-    #[serde(default, deserialize_with = "none_if_empty_string")]
+    #[serde(default, alias = "elasticsearch.password", deserialize_with = "none_if_empty_string")]
     pub password: Option<String>,
 
     /// Should we skip SSL certificate verification?
     #[serde(default, deserialize_with = "deserialize_bool_from_anything")]
+    #[schemars(schema_with = "bool_or_string_schema")]
     pub ssl_skip_verify: bool,
 
+    /// Enable the tools that modify data
+    #[serde(default, deserialize_with = "deserialize_bool_from_anything")]
+    #[schemars(schema_with = "bool_or_string_schema")]
+    pub allow_writes: bool,
+
+    /// Write tools describe the API calls they would make instead of making them
+    #[serde(default, deserialize_with = "deserialize_bool_from_anything")]
+    #[schemars(schema_with = "bool_or_string_schema")]
+    pub dry_run: bool,
+
+    /// Directory of the files that can be imported with `import_ndjson`
+    #[serde(default)]
+    pub import_root: Option<PathBuf>,
+
     /// Search templates to expose as tools or resources
     #[serde(default)]
     pub tools: Tools,
@@ -65,6 +133,34 @@ pub struct ElasticsearchMcpConfig {
     /// Prompts
     #[serde(default)]
     pub prompts: Vec<String>,
+
+    /// Retries of read-only tool calls that fail with transient errors
+    #[serde(default)]
+    pub retry: retry::RetryConfig,
+
+    /// Indices that tools can access
+    #[serde(default)]
+    pub indices: index_policy::IndexPolicy,
+
+    /// Guardrails on the cost of searches
+    #[serde(default)]
+    pub guardrails: guardrails::Guardrails,
+
+    /// Kibana instance of the cluster, to access its dashboards and saved searches
+    #[serde(default)]
+    pub kibana: Option<KibanaConfig>,
+
+    /// APM data streams of the cluster, to enable the APM tools
+    #[serde(default)]
+    pub apm: Option<ApmConfig>,
+
+    /// Log data streams of the cluster, to enable the logs tools
+    #[serde(default)]
+    pub logs: Option<LogsConfig>,
+
+    /// Detection alert indices of the cluster, to enable the security tools
+    #[serde(default)]
+    pub security: Option<SecurityConfig>,
     // TODO: search as resources?
 }
 
@@ -78,42 +174,110 @@ impl EsClientProvider {
         EsClientProvider(client)
     }
 
-    /// If the incoming request is a http request and has an `Authorization` header, use it
-    /// to authenticate to the remote ES instance.
-    pub fn get(&self, context: RequestContext<RoleServer>) -> Cow<Elasticsearch> {
-        let client = &self.0;
-
-        let Some(mut auth) = context
-            .extensions
-            .get::<Parts>()
-            .and_then(|p| p.headers.get(header::AUTHORIZATION))
-            .and_then(|h| h.to_str().ok())
-        else {
+    /// If the incoming request is a http request and has an `X-Elastic-Api-Key` or `Authorization` header,
+    /// use it to authenticate to the remote ES instance.
+    pub fn get(&self, context: RequestContext<RoleServer>) -> EsClient {
+        let parts = context.extensions.get::<Parts>();
+        let opaque_id = session_id(&context).and_then(|id| HeaderValue::from_str(id).ok());
+
+        let Some(auth) = parts.and_then(auth_header) else {
             // No auth
-            return Cow::Borrowed(client);
+            return EsClient {
+                client: self.0.clone(),
+                opaque_id,
+            };
         };
 
-        // MCP inspector insists on sending a bearer token and prepends "Bearer" to the value provided
-        if auth.starts_with("Bearer ApiKey ") || auth.starts_with("Bearer Basic ") {
-            auth = auth.trim_start_matches("Bearer ");
+        let transport = self
+            .0
+            .transport()
+            .clone_with_auth(Some(Credentials::AuthorizationHeader(auth)));
+
+        EsClient {
+            client: Elasticsearch::new(transport),
+            opaque_id,
         }
+    }
+}
 
-        let transport = client
-            .transport()
-            .clone_with_auth(Some(Credentials::AuthorizationHeader(auth.to_string())));
+/// The ES client of a tool call. Requests should be sent with [`send!`], which adds the [`headers`](Self::headers)
+/// of the tool call and traces the request in its own span.
+#[derive(Clone)]
+pub struct EsClient {
+    client: Elasticsearch,
+    /// `X-Opaque-Id` of requests, to find those of an MCP session in the Elasticsearch tasks and slow logs.
+    opaque_id: Option<HeaderValue>,
+}
 
-        Cow::Owned(Elasticsearch::new(transport))
+impl EsClient {
+    /// Headers of a request sent in `span`: the `X-Opaque-Id` of the MCP session, and with the `otel` feature
+    /// the `traceparent` of the span, so that Elasticsearch traces are children of the tool call's trace.
+    pub fn headers(&self, span: &tracing::Span) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(opaque_id) = &self.opaque_id {
+            headers.insert(X_OPAQUE_ID, opaque_id.clone());
+        }
+        #[cfg(feature = "otel")]
+        crate::telemetry::inject_context(span, &mut headers);
+        #[cfg(not(feature = "otel"))]
+        let _ = span;
+        headers
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+impl Deref for EsClient {
+    type Target = Elasticsearch;
+
+    fn deref(&self) -> &Elasticsearch {
+        &self.client
+    }
+}
+
+/// Send a request of an [`EsClient`] in an `elasticsearch_request` span, with the headers of the tool call:
+/// `send!(es_client.search(parts).body(body))` instead of `es_client.search(parts).body(body).send()`.
+macro_rules! send {
+    ($es_client:ident . $($request:tt)+) => {{
+        let span = tracing::info_span!("elasticsearch_request", otel.kind = "client");
+        let mut request = $es_client.$($request)+;
+        for (name, value) in &$es_client.headers(&span) {
+            request = request.header(name.clone(), value.clone());
+        }
+        tracing::Instrument::instrument(request.send(), span)
+    }};
+}
+pub(crate) use send;
+
+const X_OPAQUE_ID: &str = "x-opaque-id";
+
+/// Header for clients to provide an Elasticsearch API key. It allows using different credentials for the MCP
+/// server (see `HttpAuth`) and for Elasticsearch, as the `Authorization` header is then used by the MCP server.
+const X_ELASTIC_API_KEY: &str = "x-elastic-api-key";
+
+/// The Elasticsearch `Authorization` header value for an incoming http request, if any.
+fn auth_header(parts: &Parts) -> Option<String> {
+    if let Some(api_key) = parts.headers.get(X_ELASTIC_API_KEY).and_then(|h| h.to_str().ok()) {
+        return Some(format!("ApiKey {}", api_key.trim()));
+    }
+
+    let mut auth = parts.headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+
+    // MCP inspector insists on sending a bearer token and prepends "Bearer" to the value provided
+    if auth.starts_with("Bearer ApiKey ") || auth.starts_with("Bearer Basic ") {
+        auth = auth.trim_start_matches("Bearer ");
+    }
+
+    Some(auth.to_string())
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, JsonSchema)]
 pub struct Tools {
     #[serde(flatten)]
     pub incl_excl: Option<IncludeExclude>,
+    #[serde(default)]
     pub custom: HashMap<String, CustomTool>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum CustomTool {
     Esql(EsqlTool),
@@ -127,16 +291,34 @@ impl CustomTool {
             CustomTool::SearchTemplate(search_template) => &search_template.base,
         }
     }
+
+    /// Check that parameters are typed and that the query only uses them in allowed positions.
+    pub fn check(&self) -> Result<(), template::TemplateError> {
+        match self {
+            CustomTool::Esql(esql) => template::check_esql(&esql.query, &esql.base.parameters),
+            CustomTool::SearchTemplate(search_template) => match &search_template.template {
+                SearchTemplate::Template(template) => {
+                    template::check_template(template, &search_template.base.parameters)
+                }
+                // Stored templates are rendered by Elasticsearch, only check parameters
+                SearchTemplate::TemplateId(_) => {
+                    template::check_template(&serde_json::Value::Null, &search_template.base.parameters)
+                }
+            },
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ToolBase {
     pub description: String,
+    #[schemars(with = "HashMap<String, serde_json::Value>")]
     pub parameters: IndexMap<String, schemars::schema::SchemaObject>,
+    #[schemars(with = "Option<serde_json::Value>")]
     pub annotations: Option<ToolAnnotations>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct EsqlTool {
     #[serde(flatten)]
     base: ToolBase,
@@ -145,7 +327,7 @@ pub struct EsqlTool {
     format: EsqlResultFormat,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Serialize, Deserialize, Default, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum EsqlResultFormat {
     #[default]
@@ -156,15 +338,17 @@ pub enum EsqlResultFormat {
     //Csv,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct SearchTemplateTool {
     #[serde(flatten)]
     base: ToolBase,
+    /// Name or pattern of the indices to search
+    index: String,
     #[serde(flatten)]
     template: SearchTemplate,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum SearchTemplate {
     TemplateId(String),
@@ -175,28 +359,103 @@ pub enum SearchTemplate {
 pub struct ElasticsearchMcp {}
 
 impl ElasticsearchMcp {
-    pub fn new_with_config(config: ElasticsearchMcpConfig, container_mode: bool) -> anyhow::Result<base_tools::EsBaseTools> {
-        let creds = if let Some(api_key) = config.api_key.clone() {
-            Some(Credentials::EncodedApiKey(api_key))
-        } else if let Some(login) = config.login.clone() {
-            let pwd = config.password.clone().ok_or(anyhow::Error::msg("missing password"))?;
-            Some(Credentials::Basic(login, pwd))
-        } else {
-            None
-        };
-
-        let url = config.url.as_str();
-        if url.is_empty() {
-            return Err(anyhow::Error::msg("Elasticsearch URL is empty"));
+    pub fn new_with_config(
+        config: ElasticsearchMcpConfig,
+        container_mode: bool,
+    ) -> anyhow::Result<base_tools::EsBaseTools> {
+        for (name, tool) in &config.tools.custom {
+            tool.check()
+                .map_err(|err| anyhow::anyhow!("Invalid custom tool '{name}': {err}"))?;
         }
 
-        let mut url = Url::parse(url)?;
-        if container_mode {
-            rewrite_localhost(&mut url)?;
+        config
+            .guardrails
+            .check()
+            .map_err(|err| anyhow::anyhow!("Invalid guardrails: {err}"))?;
+
+        let es_client = Self::new_client(&config, container_mode)?;
+        let kibana = config
+            .kibana
+            .as_ref()
+            .map(|kibana| KibanaTools::new(kibana, &config, container_mode))
+            .transpose()
+            .map_err(|err| anyhow::anyhow!("Invalid Kibana configuration: {err}"))?;
+        if let Some(apm) = &config.apm {
+            apm.check(&config.indices)?;
+        }
+        let apm = config.apm.map(|apm| ApmTools::new(es_client.clone(), apm));
+        if let Some(logs) = &config.logs {
+            logs.check(&config.indices)?;
+        }
+        let logs = config.logs.map(|logs| LogsTools::new(es_client.clone(), logs));
+        if let Some(security) = &config.security {
+            security.check(&config.indices)?;
         }
+        let security = config.security.map(|security| {
+            SecurityTools::new(es_client.clone(), security)
+                .with_index_policy(config.indices.clone())
+                .with_dry_run(config.dry_run)
+        });
+
+        let mut tools = base_tools::EsBaseTools::new(es_client)
+            .with_import_root(config.import_root)
+            .with_retry(config.retry)
+            .with_index_policy(config.indices)
+            .with_guardrails(config.guardrails)
+            .with_dry_run(config.dry_run);
+        if let Some(kibana) = kibana {
+            tools = tools.with_sub_server(kibana);
+        }
+        if let Some(apm) = apm {
+            tools = tools.with_sub_server(apm);
+        }
+        if let Some(logs) = logs {
+            tools = tools.with_sub_server(logs);
+        }
+        if let Some(security) = security {
+            tools = tools.with_sub_server(security);
+        }
+        if !config.allow_writes {
+            tools = tools.read_only();
+        }
+        let mut tools = tools.with_custom_tools(config.tools.custom)?;
+        if let Some(incl_excl) = &config.tools.incl_excl {
+            tools = tools.filter_tools(incl_excl);
+        }
+        Ok(tools)
+    }
+
+    /// Create an Elasticsearch client for a cluster configuration.
+    pub fn new_client(config: &ElasticsearchMcpConfig, container_mode: bool) -> anyhow::Result<Elasticsearch> {
+        let creds = credentials(config)?;
+
+        let sniff_interval = config.sniff_interval_secs.map(Duration::from_secs);
+
+        let mut transport = match (&config.cloud_id, config.url.is_empty(), config.hosts.is_empty()) {
+            (Some(_), true, true) if sniff_interval.is_some() => {
+                anyhow::bail!("Sniffing isn't supported with 'cloud_id', whose nodes are behind a proxy")
+            }
+            (Some(cloud_id), true, true) => TransportBuilder::new(CloudConnectionPool::new(cloud_id)?),
+            (None, false, true) if sniff_interval.is_some() => {
+                let url = node_url(&config.url, container_mode)?;
+                TransportBuilder::new(MultiNodeConnectionPool::round_robin(vec![url], sniff_interval))
+            }
+            (None, false, true) => {
+                let url = node_url(&config.url, container_mode)?;
+                TransportBuilder::new(SingleNodeConnectionPool::new(url))
+            }
+            (None, true, false) => {
+                let urls = config
+                    .hosts
+                    .iter()
+                    .map(|host| node_url(host, container_mode))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                TransportBuilder::new(MultiNodeConnectionPool::round_robin(urls, sniff_interval))
+            }
+            (None, true, true) => anyhow::bail!("Elasticsearch URL is empty"),
+            _ => anyhow::bail!("Only one of 'url', 'hosts' and 'cloud_id' can be set"),
+        };
 
-        let pool = elasticsearch::http::transport::SingleNodeConnectionPool::new(url.clone());
-        let mut transport = elasticsearch::http::transport::TransportBuilder::new(pool);
         if let Some(creds) = creds {
             transport = transport.auth(creds);
         }
@@ -208,15 +467,67 @@ impl ElasticsearchMcp {
             HeaderValue::from_str(&format!("elastic-mcp/{}", env!("CARGO_PKG_VERSION")))?,
         );
         let transport = transport.build()?;
-        let es_client = Elasticsearch::new(transport);
+        Ok(Elasticsearch::new(transport))
+    }
 
-        Ok(base_tools::EsBaseTools::new(es_client))
+    /// Create a server for an optional default cluster and named clusters, whose tool names are prefixed
+    /// with the cluster name.
+    pub fn new_with_clusters(
+        default: Option<ElasticsearchMcpConfig>,
+        named: Vec<(String, ElasticsearchMcpConfig)>,
+        container_mode: bool,
+    ) -> anyhow::Result<clusters::EsClusters> {
+        let mut clusters = Vec::new();
+        if let Some(config) = default {
+            clusters.push((None, Self::new_with_config(config, container_mode)?));
+        }
+        for (name, config) in named {
+            let cluster =
+                Self::new_with_config(config, container_mode).map_err(|e| anyhow::anyhow!("Cluster '{name}': {e}"))?;
+            clusters.push((Some(name), cluster));
+        }
+        clusters::EsClusters::new(clusters)
     }
 }
 
 //------------------------------------------------------------------------------------------------
 // Utilities
 
+/// Credentials of a cluster. API keys and logins have precedence over Elastic Cloud credentials.
+fn credentials(config: &ElasticsearchMcpConfig) -> anyhow::Result<Option<Credentials>> {
+    if let Some(api_key) = &config.api_key {
+        // Encoded API keys are base64, which doesn't contain ':'
+        return Ok(Some(match api_key.split_once(':') {
+            Some((id, key)) => Credentials::ApiKey(id.to_string(), key.to_string()),
+            None => Credentials::EncodedApiKey(api_key.clone()),
+        }));
+    }
+    if let Some(login) = &config.login {
+        let pwd = config.password.clone().ok_or(anyhow::Error::msg("missing password"))?;
+        return Ok(Some(Credentials::Basic(login.clone(), pwd)));
+    }
+    if let Some(auth) = &config.cloud_auth {
+        let Some((login, pwd)) = auth.split_once(':') else {
+            anyhow::bail!("'cloud_auth' must be 'login:password'");
+        };
+        return Ok(Some(Credentials::Basic(login.to_string(), pwd.to_string())));
+    }
+    Ok(None)
+}
+
+/// Parse the URL of a node. Beats and Kibana allow hosts without a scheme, which defaults to http.
+fn node_url(url: &str, container_mode: bool) -> anyhow::Result<Url> {
+    let mut url = if url.contains("://") {
+        Url::parse(url)?
+    } else {
+        Url::parse(&format!("http://{url}"))?
+    };
+    if container_mode {
+        rewrite_localhost(&mut url)?;
+    }
+    Ok(url)
+}
+
 /// Rewrite urls targeting `localhost` to a hostname that maps to the container host, if possible.
 ///
 /// The host name for the container host depends on the OCI runtime used. This is useful to accept
@@ -224,13 +535,17 @@ impl ElasticsearchMcp {
 fn rewrite_localhost(url: &mut Url) -> anyhow::Result<()> {
     use std::net::ToSocketAddrs;
     let aliases = &[
-        "host.docker.internal", // Docker
+        "host.docker.internal",     // Docker
         "host.containers.internal", // Podman, maybe others
     ];
 
-    if let Some(host) = url.host_str() && host == "localhost" {
+    if let Some(host) = url.host_str()
+        && host == "localhost"
+    {
         for alias in aliases {
-            if let Ok(mut alias_add) = (*alias, 80).to_socket_addrs() && alias_add.next().is_some() {
+            if let Ok(mut alias_add) = (*alias, 80).to_socket_addrs()
+                && alias_add.next().is_some()
+            {
                 url.set_host(Some(alias))?;
                 tracing::info!("Container mode: using '{alias}' instead of 'localhost'");
                 return Ok(());
@@ -254,13 +569,20 @@ pub fn internal_error(e: impl std::error::Error) -> rmcp::Error {
 /// allow to use the '?' operator while sending a result to the client.
 pub fn handle_error(result: Result<Response, elasticsearch::Error>) -> Result<Response, rmcp::Error> {
     match result {
-        Ok(resp) => resp.error_for_status_code(),
+        Ok(resp) if retry::is_transient_status(resp.status_code()) => {
+            Err(retry::transient_error(resp.error_for_status_code().unwrap_err()))
+        }
+        Ok(resp) => resp.error_for_status_code().map_err(internal_error),
         Err(e) => {
             tracing::error!("Error: {:?}", &e);
-            Err(e)
+            // Connection errors are transient, but a timed out request may still be running on the cluster
+            if e.is_timeout() {
+                Err(internal_error(e))
+            } else {
+                Err(retry::transient_error(e))
+            }
         }
     }
-    .map_err(internal_error)
 }
 
 pub async fn read_json<T: DeserializeOwned>(
@@ -279,3 +601,102 @@ pub async fn read_text(result: Result<Response, elasticsearch::Error>) -> Result
     let response = handle_error(result)?;
     response.text().await.map_err(internal_error)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts(headers: &[(&str, &str)]) -> Parts {
+        let mut request = http::Request::builder();
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        request.body(()).unwrap().into_parts().0
+    }
+
+    #[test]
+    fn es_auth_from_headers() {
+        assert_eq!(auth_header(&parts(&[])), None);
+        assert_eq!(
+            auth_header(&parts(&[("Authorization", "Bearer ApiKey abc")])).as_deref(),
+            Some("ApiKey abc")
+        );
+        assert_eq!(
+            auth_header(&parts(&[
+                ("Authorization", "Bearer token"),
+                ("X-Elastic-Api-Key", "abc")
+            ]))
+            .as_deref(),
+            Some("ApiKey abc")
+        );
+    }
+
+    #[test]
+    fn opaque_id_header() {
+        let span = tracing::Span::none();
+        let client = EsClient {
+            client: Elasticsearch::default(),
+            opaque_id: None,
+        };
+        assert!(client.headers(&span).get(X_OPAQUE_ID).is_none());
+
+        let client = EsClient {
+            opaque_id: Some(HeaderValue::from_static("session-1")),
+            ..client
+        };
+        assert_eq!(client.headers(&span).get(X_OPAQUE_ID).unwrap(), "session-1");
+    }
+
+    #[test]
+    fn beats_and_kibana_settings() -> anyhow::Result<()> {
+        let config: ElasticsearchMcpConfig = serde_json::from_value(serde_json::json!({
+            "elasticsearch.hosts": ["https://es-1:9200", "es-2:9200"],
+            "elasticsearch.username": "kibana_system",
+            "elasticsearch.password": "changeme",
+        }))?;
+        assert_eq!(config.hosts, vec!["https://es-1:9200", "es-2:9200"]);
+        assert_eq!(node_url(&config.hosts[1], false)?.as_str(), "http://es-2:9200/");
+        assert!(matches!(
+            credentials(&config)?,
+            Some(Credentials::Basic(login, pwd)) if login == "kibana_system" && pwd == "changeme"
+        ));
+        ElasticsearchMcp::new_client(&config, false)?;
+
+        let config: ElasticsearchMcpConfig = serde_json::from_value(serde_json::json!({
+            "cloud.id": "my-deployment:ZXUtd2VzdC0xLmF3cy5mb3VuZC5pbyRhYmMkZGVm",
+            "cloud.auth": "elastic:secret",
+            "hosts": "",
+        }))?;
+        assert!(config.hosts.is_empty());
+        assert!(matches!(
+            credentials(&config)?,
+            Some(Credentials::Basic(login, pwd)) if login == "elastic" && pwd == "secret"
+        ));
+        ElasticsearchMcp::new_client(&config, false)?;
+
+        let config: ElasticsearchMcpConfig = serde_json::from_value(serde_json::json!({
+            "url": "http://localhost:9200",
+            "api_key": "VuaCfGcBCdbkQm-e5aOx:ui2lp2axTNmsyakw9tvNnw",
+            "cloud.id": "my-deployment:ZXUtd2VzdC0xLmF3cy5mb3VuZC5pbyRhYmMkZGVm",
+        }))?;
+        assert!(matches!(credentials(&config)?, Some(Credentials::ApiKey(id, _)) if id == "VuaCfGcBCdbkQm-e5aOx"));
+        assert!(ElasticsearchMcp::new_client(&config, false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn sniffing() -> anyhow::Result<()> {
+        let config: ElasticsearchMcpConfig = serde_json::from_value(serde_json::json!({
+            "url": "http://localhost:9200",
+            "sniff_interval_secs": 300,
+        }))?;
+        ElasticsearchMcp::new_client(&config, false)?;
+
+        let config: ElasticsearchMcpConfig = serde_json::from_value(serde_json::json!({
+            "cloud_id": "my-deployment:ZXUtd2VzdC0xLmF3cy5mb3VuZC5pbyRhYmMkZGVm",
+            "sniff_interval_secs": 300,
+        }))?;
+        assert!(ElasticsearchMcp::new_client(&config, false).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,263 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Import of NDJSON documents: reading files, and checking documents against the mapping of the target index.
+
+use crate::servers::elasticsearch::mappings;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Maximum size of imported data.
+pub const MAX_IMPORT_SIZE: u64 = 50 * 1024 * 1024;
+
+/// Number of documents per bulk request.
+pub const BULK_BATCH_SIZE: usize = 500;
+
+/// A document that couldn't be imported.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct LineFailure {
+    /// Line number in the NDJSON data, starting at 1
+    pub line: usize,
+    pub reason: String,
+}
+
+/// Resolve a path relative to the import root, rejecting paths that would escape it.
+pub fn resolve_path(root: &Path, path: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(path);
+    if !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(format!(
+            "Invalid path '{path}': it must be relative to the import directory, without '..'"
+        ));
+    }
+
+    // Symbolic links may still point outside the import directory
+    let root = root.canonicalize().map_err(|e| format!("Import directory: {e}"))?;
+    let resolved = root
+        .join(relative)
+        .canonicalize()
+        .map_err(|e| format!("Cannot read '{path}': {e}"))?;
+    if !resolved.starts_with(&root) {
+        return Err(format!("Invalid path '{path}': it is outside of the import directory"));
+    }
+    Ok(resolved)
+}
+
+/// A document and its line number.
+pub type LineDocument = (usize, Map<String, Value>);
+
+/// Parse the documents of NDJSON data. Blank lines are ignored.
+pub fn parse(text: &str) -> Vec<Result<LineDocument, LineFailure>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| match serde_json::from_str(line) {
+            Ok(Value::Object(doc)) => Ok((i + 1, doc)),
+            Ok(_) => Err(LineFailure {
+                line: i + 1,
+                reason: "not a JSON object".to_string(),
+            }),
+            Err(err) => Err(LineFailure {
+                line: i + 1,
+                reason: format!("invalid JSON: {err}"),
+            }),
+        })
+        .collect()
+}
+
+/// Failures of a bulk response, given the line numbers of its documents.
+pub fn bulk_failures(response: &Value, lines: &[usize]) -> Vec<LineFailure> {
+    let items = response["items"].as_array().map(Vec::as_slice).unwrap_or_default();
+    items
+        .iter()
+        .zip(lines)
+        .filter_map(|(item, line)| {
+            let error = item["index"].get("error")?;
+            let reason = error["reason"].as_str().or_else(|| error["type"].as_str());
+            Some(LineFailure {
+                line: *line,
+                reason: reason.unwrap_or("unknown error").to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Checks documents against the fields of an index mapping, to report errors before indexing.
+pub struct DocumentValidator {
+    field_types: BTreeMap<String, String>,
+    /// Are unmapped fields rejected?
+    strict: bool,
+}
+
+impl DocumentValidator {
+    pub fn new(mapping: &Value) -> Self {
+        DocumentValidator {
+            field_types: mappings::field_types(mapping),
+            strict: mapping.get("dynamic").and_then(Value::as_str) == Some("strict"),
+        }
+    }
+
+    pub fn validate(&self, doc: &Map<String, Value>) -> Result<(), String> {
+        self.validate_object("", doc)
+    }
+
+    fn validate_object(&self, prefix: &str, obj: &Map<String, Value>) -> Result<(), String> {
+        for (name, value) in obj {
+            let path = format!("{prefix}{name}");
+            self.validate_value(&path, value)?;
+        }
+        Ok(())
+    }
+
+    fn validate_value(&self, path: &str, value: &Value) -> Result<(), String> {
+        let field_type = self.field_types.get(path).map(String::as_str);
+
+        let valid = match (field_type, value) {
+            (_, Value::Null) => true,
+            (_, Value::Array(values)) => {
+                return values.iter().try_for_each(|v| self.validate_value(path, v));
+            }
+
+            // Objects, mapped or dynamic
+            (Some("object" | "nested") | None, Value::Object(obj)) => {
+                return self.validate_object(&format!("{path}."), obj);
+            }
+            (Some("object" | "nested"), _) => false,
+            (None, _) => {
+                if self.strict {
+                    return Err(format!("field '{path}' is not in the mapping, which is strict"));
+                }
+                true
+            }
+
+            // Elasticsearch coerces numeric strings
+            (Some(t), Value::Number(_)) if is_numeric(t) => true,
+            (Some(t), Value::String(s)) if is_numeric(t) => s.trim().parse::<f64>().is_ok(),
+            (Some(t), _) if is_numeric(t) => false,
+
+            (Some("boolean"), value) => {
+                matches!(value, Value::Bool(_)) || matches!(value.as_str(), Some("true" | "false"))
+            }
+            (Some("date" | "date_nanos"), value) => value.is_string() || value.is_number(),
+            (Some(t), value) if is_text(t) => !value.is_object(),
+
+            // Other types (geo, vectors, ranges...) have too many formats to check
+            (Some(_), _) => true,
+        };
+
+        if valid {
+            Ok(())
+        } else {
+            Err(format!(
+                "field '{path}' of type {} can't accept {value}",
+                field_type.unwrap_or("object")
+            ))
+        }
+    }
+}
+
+fn is_numeric(field_type: &str) -> bool {
+    matches!(
+        field_type,
+        "long" | "integer" | "short" | "byte" | "double" | "float" | "half_float" | "scaled_float" | "unsigned_long"
+    )
+}
+
+fn is_text(field_type: &str) -> bool {
+    matches!(
+        field_type,
+        "text" | "keyword" | "constant_keyword" | "wildcard" | "match_only_text" | "ip" | "version"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_lines() {
+        let docs = parse("{\"a\": 1}\n\n[1]\n{bad\n");
+        assert_eq!(docs.len(), 3);
+        assert_eq!(docs[0].as_ref().unwrap().0, 1);
+        assert_eq!(docs[1].as_ref().unwrap_err().line, 3);
+        assert_eq!(docs[2].as_ref().unwrap_err().line, 4);
+    }
+
+    #[test]
+    fn failures_of_bulk() {
+        let response = json!({ "errors": true, "items": [
+            { "index": { "status": 201, "result": "created" } },
+            { "index": { "status": 400, "error": { "type": "document_parsing_exception", "reason": "bad date" } } },
+        ]});
+        assert_eq!(
+            bulk_failures(&response, &[3, 5]),
+            vec![LineFailure {
+                line: 5,
+                reason: "bad date".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn validate_documents() {
+        let mapping = json!({
+            "dynamic": "strict",
+            "properties": {
+                "name": { "type": "keyword" },
+                "price": { "type": "float" },
+                "in_stock": { "type": "boolean" },
+                "location": { "type": "geo_point" },
+                "seller": { "properties": { "id": { "type": "long" } } },
+            }
+        });
+        let validator = DocumentValidator::new(&mapping);
+        let check = |doc: Value| validator.validate(doc.as_object().unwrap());
+
+        assert!(
+            check(json!({
+                "name": "shoe", "price": "12.5", "in_stock": true,
+                "location": { "lat": 1, "lon": 2 }, "seller": { "id": [1, 2] },
+            }))
+            .is_ok()
+        );
+        assert_eq!(
+            check(json!({ "price": "cheap" })),
+            Err("field 'price' of type float can't accept \"cheap\"".to_string())
+        );
+        assert!(check(json!({ "name": { "first": "a" } })).is_err());
+        assert!(check(json!({ "seller": { "id": "x" } })).is_err());
+        assert!(check(json!({ "color": "red" })).is_err());
+
+        // Unmapped fields are accepted by dynamic mappings
+        let validator = DocumentValidator::new(&json!({ "properties": {} }));
+        assert!(
+            validator
+                .validate(json!({ "color": "red" }).as_object().unwrap())
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn reject_paths_outside_root() {
+        let root = std::env::temp_dir();
+        assert!(resolve_path(&root, "../etc/passwd").is_err());
+        assert!(resolve_path(&root, "/etc/passwd").is_err());
+        assert!(resolve_path(&root, "a/../../b").is_err());
+    }
+}
@@ -0,0 +1,103 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Consistency tokens, backed by point in times (PIT), so that several searches see the same data snapshot.
+
+use crate::servers::elasticsearch::{EsClient, read_json, send};
+use crate::utils::maintenance::Scheduler;
+use crate::utils::rmcp_ext::session_id;
+use elasticsearch::OpenPointInTimeParts;
+use http::request::Parts;
+use rmcp::RoleServer;
+use rmcp::service::RequestContext;
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use std::ops::ControlFlow;
+use std::time::Duration;
+
+/// How long a PIT is kept after its last use.
+pub const KEEP_ALIVE: &str = "5m";
+
+/// Interval between two checks for the end of the session that opened a PIT.
+const SESSION_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize)]
+struct OpenPitResponse {
+    id: String,
+}
+
+/// Open a PIT on an index. If the request belongs to a session, or comes from stdio, the PIT is closed when the
+/// session ends. Otherwise (stateless HTTP) it expires after [`KEEP_ALIVE`] without being used.
+///
+/// Sessions are those of the transport, see [`session_id`]: the `Mcp-Session-Id` header of stateless requests
+/// can't make a PIT outlive its request, or be closed with the session of another client.
+pub async fn open(
+    es_client: &EsClient,
+    index: &str,
+    context: &RequestContext<RoleServer>,
+) -> Result<String, rmcp::Error> {
+    let id = open_unmanaged(es_client, index).await?;
+
+    // Stateless HTTP requests have no session, and their transport is closed at the end of the request.
+    let stateless = context.extensions.get::<Parts>().is_some() && session_id(context).is_none();
+    if !stateless {
+        let peer = context.peer.clone();
+        let es_client = es_client.clone();
+        let id = id.clone();
+        Scheduler::global().spawn("pit_cleanup", SESSION_POLL_INTERVAL, move || {
+            let closed = peer.is_transport_closed();
+            let es_client = es_client.clone();
+            let id = id.clone();
+            async move {
+                if !closed {
+                    return Ok(ControlFlow::Continue(()));
+                }
+                close(&es_client, &id).await;
+                Ok(ControlFlow::Break(()))
+            }
+        });
+    }
+
+    Ok(id)
+}
+
+/// Open a PIT on an index, that the caller must [`close`] when done.
+pub async fn open_unmanaged(es_client: &EsClient, index: &str) -> Result<String, rmcp::Error> {
+    let response = send!(
+        es_client
+            .open_point_in_time(OpenPointInTimeParts::Index(&[index]))
+            .keep_alive(KEEP_ALIVE)
+    )
+    .await;
+    let response: OpenPitResponse = read_json(response).await?;
+    Ok(response.id)
+}
+
+/// Close a PIT. Errors are only logged, as the PIT may already have expired.
+pub async fn close(es_client: &EsClient, id: &str) {
+    let response = send!(es_client.close_point_in_time().body(json!({ "id": id }))).await;
+    match response.map(|r| r.status_code()) {
+        Ok(status) if status.is_success() => tracing::debug!("Closed point in time"),
+        Ok(status) => tracing::debug!("Failed to close point in time: status {status}"),
+        Err(err) => tracing::warn!("Failed to close point in time: {err}"),
+    }
+}
+
+/// Update a search request body to search a PIT.
+pub fn set_pit(query_body: &mut Map<String, Value>, id: String) {
+    query_body.insert("pit".to_string(), json!({ "id": id, "keep_alive": KEEP_ALIVE }));
+}
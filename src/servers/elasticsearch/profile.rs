@@ -0,0 +1,253 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Summaries of search profiles (`"profile": true`).
+//!
+//! A raw profile has a tree of queries and collectors per shard, with a breakdown of low-level timings for each
+//! node, and is often larger than the search results. Summaries keep the time of each search phase, and the
+//! slowest queries and aggregations of the slowest shards.
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+/// Maximum length of query descriptions, which can contain large term lists.
+const MAX_DESCRIPTION_LENGTH: usize = 200;
+
+/// Number of breakdown timings reported for a query.
+const MAIN_COSTS: usize = 3;
+
+#[derive(Deserialize)]
+pub struct Profile {
+    #[serde(default)]
+    shards: Vec<ShardProfile>,
+}
+
+#[derive(Deserialize)]
+struct ShardProfile {
+    /// `[node][index][shard]`
+    id: String,
+    #[serde(default)]
+    searches: Vec<SearchProfile>,
+    #[serde(default)]
+    aggregations: Vec<ProfileNode>,
+    fetch: Option<ProfileNode>,
+}
+
+#[derive(Deserialize)]
+struct SearchProfile {
+    #[serde(default)]
+    query: Vec<ProfileNode>,
+    #[serde(default)]
+    rewrite_time: u64,
+    #[serde(default)]
+    collector: Vec<ProfileNode>,
+}
+
+/// A query, aggregation, collector or fetch node.
+#[derive(Deserialize)]
+struct ProfileNode {
+    /// Collectors have a name rather than a type
+    #[serde(rename = "type", alias = "name", default)]
+    node_type: String,
+    #[serde(alias = "reason", default)]
+    description: String,
+    time_in_nanos: u64,
+    /// Timings (in nanoseconds) and counts (`*_count`) of low-level operations
+    #[serde(default)]
+    breakdown: IndexMap<String, u64>,
+    #[serde(default)]
+    children: Vec<ProfileNode>,
+}
+
+#[derive(Serialize)]
+pub struct ShardSummary {
+    shard: String,
+    /// Query, rewrite, aggregation and fetch time. Collection overlaps with query execution.
+    total_ms: f64,
+    query_ms: f64,
+    rewrite_ms: f64,
+    collector_ms: f64,
+    aggregations_ms: f64,
+    fetch_ms: f64,
+    slowest_queries: Vec<NodeSummary>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    slowest_aggregations: Vec<NodeSummary>,
+}
+
+#[derive(Serialize)]
+struct NodeSummary {
+    #[serde(rename = "type")]
+    node_type: String,
+    description: String,
+    time_ms: f64,
+    /// Time not spent in child nodes
+    self_ms: f64,
+    /// Slowest low-level operations
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    main_costs: Vec<String>,
+}
+
+impl Profile {
+    /// Summaries of the `top_shards` slowest shards, with their `top_nodes` slowest queries and aggregations.
+    pub fn summary(self, top_shards: usize, top_nodes: usize) -> Vec<ShardSummary> {
+        let mut shards = self
+            .shards
+            .into_iter()
+            .map(|shard| shard.summary(top_nodes))
+            .collect::<Vec<_>>();
+        shards.sort_by(|a, b| b.total_ms.total_cmp(&a.total_ms));
+        shards.truncate(top_shards);
+        shards
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+}
+
+impl ShardProfile {
+    fn summary(self, top_nodes: usize) -> ShardSummary {
+        let sum = |nodes: &[ProfileNode]| nodes.iter().map(|n| n.time_in_nanos).sum::<u64>();
+        let query = self.searches.iter().map(|s| sum(&s.query)).sum::<u64>();
+        let rewrite = self.searches.iter().map(|s| s.rewrite_time).sum::<u64>();
+        let collector = self.searches.iter().map(|s| sum(&s.collector)).sum::<u64>();
+        let aggregations = sum(&self.aggregations);
+        let fetch = self.fetch.as_ref().map_or(0, |f| f.time_in_nanos);
+
+        let queries = self.searches.iter().flat_map(|s| &s.query).collect::<Vec<_>>();
+        ShardSummary {
+            shard: self.id,
+            total_ms: millis(query + rewrite + aggregations + fetch),
+            query_ms: millis(query),
+            rewrite_ms: millis(rewrite),
+            collector_ms: millis(collector),
+            aggregations_ms: millis(aggregations),
+            fetch_ms: millis(fetch),
+            slowest_queries: slowest(&queries, top_nodes),
+            slowest_aggregations: slowest(&self.aggregations.iter().collect::<Vec<_>>(), top_nodes),
+        }
+    }
+}
+
+/// The slowest nodes of trees, by time not spent in their children so that a slow leaf query isn't hidden
+/// behind the compound queries that contain it.
+fn slowest(roots: &[&ProfileNode], top_nodes: usize) -> Vec<NodeSummary> {
+    let mut nodes = Vec::new();
+    let mut stack = roots.to_vec();
+    while let Some(node) = stack.pop() {
+        stack.extend(&node.children);
+        nodes.push(node);
+    }
+    nodes.sort_by_key(|node| std::cmp::Reverse(node.self_time()));
+    nodes.truncate(top_nodes);
+    nodes.into_iter().map(ProfileNode::summary).collect()
+}
+
+impl ProfileNode {
+    fn self_time(&self) -> u64 {
+        let children = self.children.iter().map(|c| c.time_in_nanos).sum::<u64>();
+        self.time_in_nanos.saturating_sub(children)
+    }
+
+    fn summary(&self) -> NodeSummary {
+        let mut costs = self
+            .breakdown
+            .iter()
+            .filter(|(name, nanos)| !name.ends_with("_count") && **nanos > 0)
+            .collect::<Vec<_>>();
+        costs.sort_by_key(|(_, nanos)| std::cmp::Reverse(**nanos));
+
+        let mut description = self.description.clone();
+        if let Some((end, _)) = description.char_indices().nth(MAX_DESCRIPTION_LENGTH) {
+            description.truncate(end);
+            description.push_str("...");
+        }
+
+        NodeSummary {
+            node_type: self.node_type.clone(),
+            description,
+            time_ms: millis(self.time_in_nanos),
+            self_ms: millis(self.self_time()),
+            main_costs: costs
+                .into_iter()
+                .take(MAIN_COSTS)
+                .map(|(name, nanos)| format!("{name}: {} ms", millis(*nanos)))
+                .collect(),
+        }
+    }
+}
+
+/// Nanoseconds to milliseconds, rounded to microseconds.
+fn millis(nanos: u64) -> f64 {
+    (nanos as f64 / 1000.0).round() / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn summarize_profile() {
+        let node = |t: &str, d: &str, nanos: u64, children: serde_json::Value| {
+            json!({
+                "type": t, "description": d, "time_in_nanos": nanos,
+                "breakdown": { "score": nanos / 2, "score_count": 10, "next_doc": nanos / 4, "advance": 0 },
+                "children": children,
+            })
+        };
+        let profile: Profile = serde_json::from_value(json!({ "shards": [
+            {
+                "id": "[n1][logs][0]",
+                "searches": [{
+                    "query": [node("BooleanQuery", "+message:error #status:500", 5_000_000, json!([
+                        node("TermQuery", "message:error", 1_000_000, json!([])),
+                        node("WildcardQuery", "status:5*", 3_500_000, json!([])),
+                    ]))],
+                    "rewrite_time": 200_000,
+                    "collector": [{ "name": "QueryPhaseCollector", "reason": "search_query_phase", "time_in_nanos": 800_000 }],
+                }],
+                "aggregations": [],
+                "fetch": { "type": "fetch", "description": "", "time_in_nanos": 300_000 },
+            },
+            {
+                "id": "[n2][logs][1]",
+                "searches": [{ "query": [node("TermQuery", "message:error", 100_000, json!([]))], "rewrite_time": 0 }],
+            },
+        ]}))
+        .unwrap();
+        assert_eq!(profile.shard_count(), 2);
+
+        let shards = profile.summary(1, 2);
+        assert_eq!(shards.len(), 1);
+        let shard = &shards[0];
+        assert_eq!(shard.shard, "[n1][logs][0]");
+        assert_eq!(shard.total_ms, 5.5);
+        assert_eq!(shard.collector_ms, 0.8);
+
+        let queries = shard
+            .slowest_queries
+            .iter()
+            .map(|q| (q.node_type.as_str(), q.self_ms))
+            .collect::<Vec<_>>();
+        assert_eq!(queries, vec![("WildcardQuery", 3.5), ("TermQuery", 1.0)]);
+        assert_eq!(
+            shard.slowest_queries[0].main_costs,
+            vec!["score: 1.75 ms", "next_doc: 0.875 ms"]
+        );
+    }
+}
@@ -0,0 +1,146 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Retry with exponential backoff of tool calls that fail with transient Elasticsearch errors: connection
+//! errors, and `429 Too Many Requests` and `503 Service Unavailable` responses of a cluster under pressure.
+
+use elasticsearch::http::StatusCode;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// Maximum number of attempts of a tool call, including the first one. 1 disables retries.
+    pub attempts: u32,
+    /// Delay before the first retry in milliseconds, which doubles at each retry
+    pub initial_backoff_ms: u64,
+    /// Maximum delay between two attempts in milliseconds
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            attempts: 3,
+            initial_backoff_ms: 200,
+            max_backoff_ms: 5000,
+        }
+    }
+}
+
+/// Is it a response status that a later attempt may not get?
+pub fn is_transient_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE
+}
+
+/// An error that a later attempt may not get.
+pub fn transient_error(e: impl std::error::Error) -> rmcp::Error {
+    rmcp::Error::internal_error(e.to_string(), Some(json!({ "transient": true })))
+}
+
+/// Is it an error that a later attempt may not get?
+pub fn is_transient(err: &rmcp::Error) -> bool {
+    err.data.as_ref().and_then(|data| data.get("transient")) == Some(&json!(true))
+}
+
+impl RetryConfig {
+    /// Delay before a retry, starting at 0.
+    fn backoff(&self, retry: u32) -> Duration {
+        let delay = self.initial_backoff_ms.saturating_mul(1 << retry.min(16));
+        Duration::from_millis(delay.min(self.max_backoff_ms))
+    }
+
+    /// Run a call until it succeeds, fails with an error that isn't transient, or the attempts are exhausted.
+    pub async fn run<F, Fut, T>(&self, mut call: F) -> Result<T, rmcp::Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, rmcp::Error>>,
+    {
+        let mut retry = 0;
+        loop {
+            match call().await {
+                Err(err) if retry + 1 < self.attempts && is_transient(&err) => {
+                    let delay = self.backoff(retry);
+                    tracing::warn!("Transient Elasticsearch error, retrying in {delay:?}: {}", err.message);
+                    tokio::time::sleep(delay).await;
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn config(attempts: u32) -> RetryConfig {
+        RetryConfig {
+            attempts,
+            initial_backoff_ms: 1,
+            max_backoff_ms: 2,
+        }
+    }
+
+    /// A call that fails with `error` until its `successful_attempt`.
+    async fn call(attempts: &AtomicU32, successful_attempt: u32, error: rmcp::Error) -> Result<u32, rmcp::Error> {
+        let attempt = attempts.fetch_add(1, Ordering::Relaxed) + 1;
+        if attempt < successful_attempt {
+            return Err(error);
+        }
+        Ok(attempt)
+    }
+
+    #[test]
+    fn exponential_backoff() {
+        let config = RetryConfig::default();
+        assert_eq!(config.backoff(0), Duration::from_millis(200));
+        assert_eq!(config.backoff(1), Duration::from_millis(400));
+        assert_eq!(config.backoff(4), Duration::from_millis(3200));
+        assert_eq!(config.backoff(5), Duration::from_millis(5000));
+        assert_eq!(config.backoff(100), Duration::from_millis(5000));
+    }
+
+    #[tokio::test]
+    async fn retries_transient_errors() {
+        let transient = transient_error(std::io::Error::other("connection refused"));
+
+        let attempts = AtomicU32::new(0);
+        let result = config(3).run(|| call(&attempts, 3, transient.clone())).await;
+        assert_eq!(result, Ok(3));
+
+        let attempts = AtomicU32::new(0);
+        let result = config(2).run(|| call(&attempts, 3, transient.clone())).await;
+        assert_eq!(result, Err(transient));
+        assert_eq!(attempts.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn fails_on_other_errors() {
+        let error = rmcp::Error::internal_error("index_not_found_exception", None);
+
+        let attempts = AtomicU32::new(0);
+        let result = config(3).run(|| call(&attempts, 3, error.clone())).await;
+        assert_eq!(result, Err(error));
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+    }
+}
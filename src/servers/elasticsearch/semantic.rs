@@ -0,0 +1,134 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Semantic search requests, built according to the type of the searched field.
+
+use serde_json::{Value, json};
+
+/// Type of a field, from a field mapping response (`GET /{index}/_mapping/field/{field}`). Returns `None` if
+/// no index has this field, and an error if indices map it with different types.
+pub fn field_type(response: &Value, field: &str) -> Result<Option<String>, String> {
+    let mut types = response
+        .as_object()
+        .into_iter()
+        .flat_map(|indices| indices.values())
+        .filter_map(|index| index["mappings"][field]["mapping"].as_object())
+        // The mapping is keyed by the leaf name of the field
+        .filter_map(|mapping| mapping.values().next()?["type"].as_str())
+        .collect::<Vec<_>>();
+    types.sort();
+    types.dedup();
+
+    match types.as_slice() {
+        [] => Ok(None),
+        [field_type] => Ok(Some(field_type.to_string())),
+        _ => Err(format!(
+            "Field '{field}' has different types in the searched indices: {}",
+            types.join(", ")
+        )),
+    }
+}
+
+/// Default number of nearest neighbor candidates per shard of a kNN search returning `k` documents.
+pub fn num_candidates(k: u32) -> u32 {
+    k.saturating_mul(10).clamp(100, 10_000)
+}
+
+/// Body of a search request for `text` on a field of type `field_type`.
+///
+/// `semantic_text` fields embed the query with their own inference endpoint. Other vector fields need the
+/// endpoint that was used to compute their vectors.
+pub fn search_body(
+    field_type: &str,
+    field: &str,
+    text: &str,
+    inference_id: Option<&str>,
+    size: u32,
+) -> Result<Value, String> {
+    let inference_id = || {
+        inference_id
+            .ok_or_else(|| format!("An inference endpoint id is required to search the {field_type} field '{field}'"))
+    };
+
+    let body = match field_type {
+        "semantic_text" => json!({
+            "query": { "semantic": { "field": field, "query": text } },
+            "size": size,
+        }),
+        "dense_vector" => json!({
+            "knn": {
+                "field": field,
+                "k": size,
+                "num_candidates": num_candidates(size),
+                "query_vector_builder": {
+                    "text_embedding": { "model_id": inference_id()?, "model_text": text }
+                },
+            },
+            "size": size,
+            // Vectors are large and meaningless to the LLM
+            "_source": { "excludes": [field] },
+        }),
+        "sparse_vector" | "rank_features" => json!({
+            "query": { "sparse_vector": { "field": field, "inference_id": inference_id()?, "query": text } },
+            "size": size,
+            "_source": { "excludes": [field] },
+        }),
+        _ => {
+            return Err(format!(
+                "Field '{field}' has type {field_type}. Semantic search needs a semantic_text, dense_vector \
+                or sparse_vector field."
+            ));
+        }
+    };
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_field_type() {
+        let mapping = |t: &str| json!({ "mappings": { "doc.body": { "full_name": "doc.body", "mapping": { "body": { "type": t } } } } });
+
+        let response = json!({ "a": mapping("semantic_text"), "b": mapping("semantic_text"), "c": { "mappings": {} } });
+        assert_eq!(field_type(&response, "doc.body"), Ok(Some("semantic_text".to_string())));
+        assert_eq!(field_type(&response, "other"), Ok(None));
+
+        let response = json!({ "a": mapping("semantic_text"), "b": mapping("text") });
+        assert!(field_type(&response, "doc.body").is_err());
+    }
+
+    #[test]
+    fn build_search_body() {
+        let body = search_body("dense_vector", "embedding", "red shoes", Some("e5"), 5).unwrap();
+        assert_eq!(
+            body["knn"]["query_vector_builder"],
+            json!({ "text_embedding": { "model_id": "e5", "model_text": "red shoes" } })
+        );
+        assert_eq!(body["knn"]["num_candidates"], 100);
+
+        let body = search_body("semantic_text", "body", "red shoes", None, 5).unwrap();
+        assert_eq!(
+            body["query"],
+            json!({ "semantic": { "field": "body", "query": "red shoes" } })
+        );
+
+        assert!(search_body("sparse_vector", "tokens", "red shoes", None, 5).is_err());
+        assert!(search_body("text", "body", "red shoes", None, 5).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Index statistics, and search latency analysis.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Deserialize)]
+pub struct IndicesStatsResponse {
+    #[serde(default)]
+    pub indices: HashMap<String, IndexStats>,
+}
+
+#[derive(Deserialize)]
+pub struct IndexStats {
+    pub total: IndexStatsTotal,
+}
+
+#[derive(Deserialize)]
+pub struct IndexStatsTotal {
+    #[serde(default)]
+    pub search: SearchStats,
+    #[serde(default)]
+    pub query_cache: CacheStats,
+    #[serde(default)]
+    pub request_cache: CacheStats,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+pub struct SearchStats {
+    pub query_total: u64,
+    pub query_time_in_millis: u64,
+    pub fetch_total: u64,
+    pub fetch_time_in_millis: u64,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+pub struct CacheStats {
+    pub hit_count: u64,
+    pub miss_count: u64,
+}
+
+/// Search latency contributors of an index.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct IndexLatency {
+    pub index: String,
+    pub queries: u64,
+    pub query_time_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_query_ms: Option<f64>,
+    pub fetches: u64,
+    pub fetch_time_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_fetch_ms: Option<f64>,
+    /// Percentage of the search time of all indices spent in this index
+    pub search_time_pct: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query_cache_hit_rate: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_cache_hit_rate: Option<f64>,
+}
+
+/// Compute per-index search latency from the difference between two stats samples, sorted by decreasing
+/// search time. Indices that had no search activity are omitted.
+///
+/// Stats are cumulative since shards were started, so an empty `before` sample gives the latency since then.
+pub fn latency_report(before: &IndicesStatsResponse, after: &IndicesStatsResponse) -> Vec<IndexLatency> {
+    let mut deltas = Vec::new();
+    for (name, stats) in &after.indices {
+        let after = &stats.total;
+        // Counters can go down if shards are relocated, hence the saturating subtractions.
+        let (search, query_cache, request_cache) = match before.indices.get(name) {
+            Some(before) => {
+                let before = &before.total;
+                (
+                    SearchStats {
+                        query_total: after.search.query_total.saturating_sub(before.search.query_total),
+                        query_time_in_millis: after
+                            .search
+                            .query_time_in_millis
+                            .saturating_sub(before.search.query_time_in_millis),
+                        fetch_total: after.search.fetch_total.saturating_sub(before.search.fetch_total),
+                        fetch_time_in_millis: after
+                            .search
+                            .fetch_time_in_millis
+                            .saturating_sub(before.search.fetch_time_in_millis),
+                    },
+                    cache_delta(&after.query_cache, &before.query_cache),
+                    cache_delta(&after.request_cache, &before.request_cache),
+                )
+            }
+            None => (after.search, after.query_cache, after.request_cache),
+        };
+
+        if search.query_total > 0 || search.fetch_total > 0 {
+            deltas.push((name, search, query_cache, request_cache));
+        }
+    }
+
+    let total_time: u64 = deltas
+        .iter()
+        .map(|(_, s, _, _)| s.query_time_in_millis + s.fetch_time_in_millis)
+        .sum();
+
+    let mut result = deltas
+        .into_iter()
+        .map(|(name, search, query_cache, request_cache)| IndexLatency {
+            index: name.clone(),
+            queries: search.query_total,
+            query_time_ms: search.query_time_in_millis,
+            avg_query_ms: ratio(search.query_time_in_millis, search.query_total),
+            fetches: search.fetch_total,
+            fetch_time_ms: search.fetch_time_in_millis,
+            avg_fetch_ms: ratio(search.fetch_time_in_millis, search.fetch_total),
+            search_time_pct: ratio(
+                100 * (search.query_time_in_millis + search.fetch_time_in_millis),
+                total_time,
+            )
+            .unwrap_or(0.0),
+            query_cache_hit_rate: ratio(query_cache.hit_count, query_cache.hit_count + query_cache.miss_count),
+            request_cache_hit_rate: ratio(
+                request_cache.hit_count,
+                request_cache.hit_count + request_cache.miss_count,
+            ),
+        })
+        .collect::<Vec<_>>();
+
+    result.sort_by(|a, b| {
+        (b.query_time_ms + b.fetch_time_ms)
+            .cmp(&(a.query_time_ms + a.fetch_time_ms))
+            .then_with(|| a.index.cmp(&b.index))
+    });
+    result
+}
+
+fn cache_delta(after: &CacheStats, before: &CacheStats) -> CacheStats {
+    CacheStats {
+        hit_count: after.hit_count.saturating_sub(before.hit_count),
+        miss_count: after.miss_count.saturating_sub(before.miss_count),
+    }
+}
+
+/// `num / denom` rounded to 2 decimals, or `None` if `denom` is zero.
+fn ratio(num: u64, denom: u64) -> Option<f64> {
+    (denom > 0).then(|| (num as f64 / denom as f64 * 100.0).round() / 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn stats(value: serde_json::Value) -> IndicesStatsResponse {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn index(queries: u64, query_ms: u64, fetches: u64, fetch_ms: u64, cache_hits: u64) -> serde_json::Value {
+        json!({ "total": {
+            "search": {
+                "query_total": queries, "query_time_in_millis": query_ms,
+                "fetch_total": fetches, "fetch_time_in_millis": fetch_ms,
+            },
+            "query_cache": { "hit_count": cache_hits, "miss_count": 10 },
+        }})
+    }
+
+    #[test]
+    fn latency_over_window() {
+        let before = stats(json!({ "indices": {
+            "logs": index(100, 1000, 50, 100, 0),
+            "metrics": index(10, 10, 10, 10, 0),
+            "idle": index(5, 5, 5, 5, 0),
+        }}));
+        let after = stats(json!({ "indices": {
+            "logs": index(110, 1900, 60, 200, 10),
+            "metrics": index(20, 110, 20, 10, 0),
+            "idle": index(5, 5, 5, 5, 0),
+            "new": index(0, 0, 0, 0, 0),
+        }}));
+
+        let report = latency_report(&before, &after);
+        assert_eq!(
+            report.iter().map(|r| r.index.as_str()).collect::<Vec<_>>(),
+            ["logs", "metrics"]
+        );
+
+        let logs = &report[0];
+        assert_eq!(logs.queries, 10);
+        assert_eq!(logs.avg_query_ms, Some(90.0));
+        assert_eq!(logs.avg_fetch_ms, Some(10.0));
+        assert_eq!(logs.search_time_pct, 90.91);
+        // Cache misses didn't change: all lookups in the window were hits
+        assert_eq!(logs.query_cache_hit_rate, Some(1.0));
+        assert_eq!(report[1].query_cache_hit_rate, None);
+    }
+}
@@ -0,0 +1,307 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Strict parameter interpolation for operator-defined query templates.
+//!
+//! Templates are written by operators and arguments are provided by agents, so arguments must not be able
+//! to change the structure of a query:
+//! - in JSON templates, a parameter can only be used as a whole string value `"{{name}}"`, which is replaced
+//!   by the argument's JSON value. Placeholders in object keys or inside larger strings are rejected.
+//! - ES|QL queries can't contain placeholders at all, and must use `?name` parameters that are sent
+//!   separately from the query.
+//! - arguments are checked against the parameters' declared schema before being used.
+
+use indexmap::IndexMap;
+use schemars::schema::{InstanceType, Schema, SchemaObject, SingleOrVec};
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+pub type Parameters = IndexMap<String, SchemaObject>;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum TemplateError {
+    #[error("parameter '{0}' must declare a type")]
+    UntypedParameter(String),
+    #[error("placeholder in key '{0}': parameters can only be used as values")]
+    PlaceholderInKey(String),
+    #[error("placeholder in '{0}': parameters must be the whole string value, as in \"{{{{name}}}}\"")]
+    EmbeddedPlaceholder(String),
+    #[error("placeholder '{0}' doesn't match any declared parameter")]
+    UnknownPlaceholder(String),
+    #[error("ES|QL queries must use '?name' parameters instead of placeholders")]
+    EsqlPlaceholder,
+    #[error("unexpected argument '{0}'")]
+    UnexpectedArgument(String),
+    #[error("missing argument '{0}'")]
+    MissingArgument(String),
+    #[error("argument '{0}' doesn't match the parameter's schema")]
+    InvalidArgument(String),
+}
+
+/// Check that all parameters are typed, and that the template's placeholders are well-formed and refer to
+/// declared parameters.
+pub fn check_template(template: &Value, params: &Parameters) -> Result<(), TemplateError> {
+    check_parameters(params)?;
+    check_placeholders(template, params)
+}
+
+/// Check that all parameters are typed, and that the ES|QL query doesn't contain placeholders.
+pub fn check_esql(query: &str, params: &Parameters) -> Result<(), TemplateError> {
+    check_parameters(params)?;
+    if query.contains("{{") {
+        return Err(TemplateError::EsqlPlaceholder);
+    }
+    Ok(())
+}
+
+/// Check arguments against the parameters' schema, and add default values for missing arguments.
+pub fn check_arguments(params: &Parameters, args: &Map<String, Value>) -> Result<Map<String, Value>, TemplateError> {
+    if let Some(name) = args.keys().find(|name| !params.contains_key(*name)) {
+        return Err(TemplateError::UnexpectedArgument(name.clone()));
+    }
+
+    let mut result = Map::new();
+    for (name, schema) in params {
+        let value = match args.get(name) {
+            Some(value) => value.clone(),
+            None => schema
+                .metadata
+                .as_ref()
+                .and_then(|m| m.default.clone())
+                .ok_or_else(|| TemplateError::MissingArgument(name.clone()))?,
+        };
+        if !matches_schema(&value, schema) {
+            return Err(TemplateError::InvalidArgument(name.clone()));
+        }
+        result.insert(name.clone(), value);
+    }
+    Ok(result)
+}
+
+/// Replace placeholders with their argument value. The template and arguments must have been checked with
+/// [`check_template`] and [`check_arguments`].
+pub fn render(template: &Value, args: &Map<String, Value>) -> Value {
+    match template {
+        Value::String(s) => match placeholder(s).and_then(|name| args.get(name)) {
+            Some(value) => value.clone(),
+            None => template.clone(),
+        },
+        Value::Array(values) => Value::Array(values.iter().map(|v| render(v, args)).collect()),
+        Value::Object(obj) => Value::Object(obj.iter().map(|(k, v)| (k.clone(), render(v, args))).collect()),
+        _ => template.clone(),
+    }
+}
+
+fn check_parameters(params: &Parameters) -> Result<(), TemplateError> {
+    match params.iter().find(|(_, schema)| schema.instance_type.is_none()) {
+        Some((name, _)) => Err(TemplateError::UntypedParameter(name.clone())),
+        None => Ok(()),
+    }
+}
+
+fn check_placeholders(template: &Value, params: &Parameters) -> Result<(), TemplateError> {
+    match template {
+        Value::String(s) => match placeholder(s) {
+            Some(name) if params.contains_key(name) => Ok(()),
+            Some(name) => Err(TemplateError::UnknownPlaceholder(name.to_string())),
+            None if s.contains("{{") => Err(TemplateError::EmbeddedPlaceholder(s.clone())),
+            None => Ok(()),
+        },
+        Value::Array(values) => values.iter().try_for_each(|v| check_placeholders(v, params)),
+        Value::Object(obj) => obj.iter().try_for_each(|(k, v)| {
+            if k.contains("{{") {
+                return Err(TemplateError::PlaceholderInKey(k.clone()));
+            }
+            check_placeholders(v, params)
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// If `s` is a placeholder, returns the parameter name. Names are identifiers, which excludes mustache
+/// sections and triple-brace placeholders.
+fn placeholder(s: &str) -> Option<&str> {
+    let name = s.strip_prefix("{{")?.strip_suffix("}}")?.trim();
+    let is_ident = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    is_ident.then_some(name)
+}
+
+fn matches_schema(value: &Value, schema: &SchemaObject) -> bool {
+    if let Some(types) = &schema.instance_type {
+        let matches = match types {
+            SingleOrVec::Single(t) => matches_type(value, t),
+            SingleOrVec::Vec(ts) => ts.iter().any(|t| matches_type(value, t)),
+        };
+        if !matches {
+            return false;
+        }
+    }
+
+    if let Some(allowed) = &schema.enum_values
+        && !allowed.contains(value)
+    {
+        return false;
+    }
+
+    if let (Value::Array(values), Some(array)) = (value, &schema.array)
+        && let Some(SingleOrVec::Single(items)) = &array.items
+        && let Schema::Object(items) = items.as_ref()
+    {
+        return values.iter().all(|v| matches_schema(v, items));
+    }
+
+    true
+}
+
+fn matches_type(value: &Value, instance_type: &InstanceType) -> bool {
+    match instance_type {
+        InstanceType::Null => value.is_null(),
+        InstanceType::Boolean => value.is_boolean(),
+        InstanceType::Object => value.is_object(),
+        InstanceType::Array => value.is_array(),
+        InstanceType::Number => value.is_number(),
+        InstanceType::String => value.is_string(),
+        InstanceType::Integer => value.is_i64() || value.is_u64(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn params(value: Value) -> Parameters {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn args(value: Value) -> Map<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn renders_values() {
+        let params = params(json!({
+            "name": { "type": "string" },
+            "size": { "type": "integer", "default": 10 },
+        }));
+        let template = json!({
+            "size": "{{size}}",
+            "query": { "match": { "name": "{{ name }}" } },
+            "literal": "no {placeholder} here",
+        });
+
+        check_template(&template, &params).unwrap();
+        let args = check_arguments(&params, &args(json!({ "name": "foo" }))).unwrap();
+
+        assert_eq!(
+            render(&template, &args),
+            json!({
+                "size": 10,
+                "query": { "match": { "name": "foo" } },
+                "literal": "no {placeholder} here",
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        let params = params(json!({ "name": { "type": "string" } }));
+
+        let check = |template: Value| check_template(&template, &params).unwrap_err();
+        assert_eq!(
+            check(json!({ "{{name}}": 1 })),
+            TemplateError::PlaceholderInKey("{{name}}".to_string())
+        );
+        assert_eq!(
+            check(json!({ "q": "name:{{name}}" })),
+            TemplateError::EmbeddedPlaceholder("name:{{name}}".to_string())
+        );
+        assert_eq!(
+            check(json!({ "q": "{{{name}}}" })),
+            TemplateError::EmbeddedPlaceholder("{{{name}}}".to_string())
+        );
+        assert_eq!(
+            check(json!({ "q": ["{{#name}}"] })),
+            TemplateError::EmbeddedPlaceholder("{{#name}}".to_string())
+        );
+        assert_eq!(
+            check(json!({ "q": "{{other}}" })),
+            TemplateError::UnknownPlaceholder("other".to_string())
+        );
+
+        let untyped = self::params(json!({ "name": { "description": "a name" } }));
+        assert_eq!(
+            check_template(&json!({}), &untyped).unwrap_err(),
+            TemplateError::UntypedParameter("name".to_string())
+        );
+
+        assert_eq!(
+            check_esql("FROM logs | WHERE host == \"{{name}}\"", &params).unwrap_err(),
+            TemplateError::EsqlPlaceholder
+        );
+        check_esql("FROM logs | WHERE host == ?name", &params).unwrap();
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        let params = params(json!({
+            "name": { "type": "string" },
+            "level": { "type": "string", "enum": ["info", "error"], "default": "error" },
+            "ids": { "type": "array", "items": { "type": "integer" }, "default": [] },
+        }));
+
+        let check = |value: Value| check_arguments(&params, &args(value));
+
+        assert_eq!(
+            check(json!({})),
+            Err(TemplateError::MissingArgument("name".to_string()))
+        );
+        assert_eq!(
+            check(json!({ "name": "a", "index": "secret" })),
+            Err(TemplateError::UnexpectedArgument("index".to_string()))
+        );
+        assert_eq!(
+            check(json!({ "name": { "match_all": {} } })),
+            Err(TemplateError::InvalidArgument("name".to_string()))
+        );
+        assert_eq!(
+            check(json!({ "name": "a", "level": "debug" })),
+            Err(TemplateError::InvalidArgument("level".to_string()))
+        );
+        assert_eq!(
+            check(json!({ "name": "a", "ids": [1, "2"] })),
+            Err(TemplateError::InvalidArgument("ids".to_string()))
+        );
+        assert!(check(json!({ "name": "a", "ids": [1, 2] })).is_ok());
+    }
+
+    #[test]
+    fn arguments_cannot_inject_structure() {
+        let params = params(json!({ "name": { "type": "string" } }));
+        let template = json!({ "query": { "term": { "name": "{{name}}" } } });
+        check_template(&template, &params).unwrap();
+
+        // Strings that look like JSON or placeholders stay plain string values
+        for payload in [r#""}}, "match_all": {"#, "{{name}}", r#"{"match_all": {}}"#] {
+            let args = check_arguments(&params, &args(json!({ "name": payload }))).unwrap();
+            assert_eq!(
+                render(&template, &args),
+                json!({ "query": { "term": { "name": payload } } })
+            );
+        }
+    }
+}
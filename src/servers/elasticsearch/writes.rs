@@ -0,0 +1,238 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Document writes. Tools that modify data are only available when `allow_writes` is set in the cluster
+//! configuration.
+//!
+//! In dry runs, write tools describe the Elasticsearch API calls they would make instead of making them.
+
+use crate::servers::elasticsearch::{EsClient, read_json, send};
+use elasticsearch::http::StatusCode;
+use elasticsearch::params::Refresh;
+use elasticsearch::{GetParts, IndexParts};
+use rmcp::model::{CallToolResult, Content};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// When the changes of a write become visible to searches.
+#[derive(Debug, Clone, Copy, Default, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RefreshPolicy {
+    /// Visible after the next periodic refresh, usually within a second
+    #[default]
+    False,
+    /// Visible immediately, by refreshing the affected shards. Expensive, avoid it for frequent writes
+    True,
+    /// Wait for the next periodic refresh before returning
+    WaitFor,
+}
+
+impl RefreshPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RefreshPolicy::False => "false",
+            RefreshPolicy::True => "true",
+            RefreshPolicy::WaitFor => "wait_for",
+        }
+    }
+}
+
+impl From<RefreshPolicy> for Refresh {
+    fn from(policy: RefreshPolicy) -> Self {
+        match policy {
+            RefreshPolicy::False => Refresh::False,
+            RefreshPolicy::True => Refresh::True,
+            RefreshPolicy::WaitFor => Refresh::WaitFor,
+        }
+    }
+}
+
+/// The version of a document when it was read, to only update it if it wasn't changed since.
+///
+/// It's given to clients as an opaque `seq_no:primary_term` string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpdateToken {
+    pub seq_no: i64,
+    pub primary_term: i64,
+}
+
+impl Display for UpdateToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.seq_no, self.primary_term)
+    }
+}
+
+impl FromStr for UpdateToken {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split_once(':')
+            .and_then(|(seq_no, primary_term)| {
+                Some(UpdateToken {
+                    seq_no: seq_no.parse().ok()?,
+                    primary_term: primary_term.parse().ok()?,
+                })
+            })
+            .ok_or_else(|| format!("Invalid update token '{s}', it must be the one returned by get_for_update"))
+    }
+}
+
+/// An Elasticsearch API call that a write tool would make.
+#[derive(Debug, Serialize)]
+pub struct PlannedCall {
+    pub method: &'static str,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+}
+
+impl PlannedCall {
+    pub fn new(method: &'static str, path: impl Into<String>) -> Self {
+        PlannedCall {
+            method,
+            path: path.into(),
+            body: None,
+        }
+    }
+
+    pub fn with_body(mut self, body: Value) -> Self {
+        self.body = Some(body);
+        self
+    }
+}
+
+/// Result of a dry run: a description of what the tool would do, and the API calls it would make.
+pub fn dry_run_result(description: String, calls: Vec<PlannedCall>) -> Result<CallToolResult, rmcp::Error> {
+    Ok(CallToolResult::success(vec![
+        Content::text(format!("Dry run, nothing was changed. {description}")),
+        Content::json(json!({ "dry_run": true, "calls": calls }))?,
+    ]))
+}
+
+#[derive(Deserialize)]
+struct GetResponse {
+    _seq_no: i64,
+    _primary_term: i64,
+    _source: Value,
+}
+
+#[derive(Deserialize)]
+struct IndexResponse {
+    _seq_no: i64,
+    _primary_term: i64,
+    result: String,
+}
+
+/// Get the source of a document and its update token, or `None` if it doesn't exist.
+pub async fn get_for_update(
+    es_client: &EsClient,
+    index: &str,
+    id: &str,
+) -> Result<Option<(Value, UpdateToken)>, rmcp::Error> {
+    let response = send!(es_client.get(GetParts::IndexId(index, id))).await;
+    if let Ok(response) = &response
+        && response.status_code() == StatusCode::NOT_FOUND
+    {
+        return Ok(None);
+    }
+
+    let response: GetResponse = read_json(response).await?;
+    let token = UpdateToken {
+        seq_no: response._seq_no,
+        primary_term: response._primary_term,
+    };
+    Ok(Some((response._source, token)))
+}
+
+/// Replace a document if it wasn't changed since `token` was obtained. Returns the result (`updated`) and the
+/// new update token, or `None` if the document was changed or deleted in the meantime.
+pub async fn put_if_unchanged(
+    es_client: &EsClient,
+    index: &str,
+    id: &str,
+    document: &Value,
+    token: UpdateToken,
+    refresh: RefreshPolicy,
+) -> Result<Option<(String, UpdateToken)>, rmcp::Error> {
+    let response = send!(
+        es_client
+            .index(IndexParts::IndexId(index, id))
+            .if_seq_no(token.seq_no)
+            .if_primary_term(token.primary_term)
+            .refresh(refresh.into())
+            .body(document)
+    )
+    .await;
+    if let Ok(response) = &response
+        && response.status_code() == StatusCode::CONFLICT
+    {
+        return Ok(None);
+    }
+
+    let response: IndexResponse = read_json(response).await?;
+    let token = UpdateToken {
+        seq_no: response._seq_no,
+        primary_term: response._primary_term,
+    };
+    Ok(Some((response.result, token)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_update_token() {
+        let token = UpdateToken {
+            seq_no: 12,
+            primary_term: 3,
+        };
+        assert_eq!(token.to_string(), "12:3");
+        assert_eq!("12:3".parse(), Ok(token));
+
+        assert!("12".parse::<UpdateToken>().is_err());
+        assert!("12:x".parse::<UpdateToken>().is_err());
+        assert!("".parse::<UpdateToken>().is_err());
+    }
+
+    #[test]
+    fn dry_run() -> anyhow::Result<()> {
+        let calls = vec![
+            PlannedCall::new("POST", "/_aliases").with_body(json!({ "actions": [] })),
+            PlannedCall::new("POST", "/_tasks/node:1/_cancel"),
+        ];
+        let result = dry_run_result("Task 'node:1' would be cancelled.".to_string(), calls)?;
+        assert_eq!(
+            result.content[0].as_text().unwrap().text,
+            "Dry run, nothing was changed. Task 'node:1' would be cancelled."
+        );
+        let calls: Value = serde_json::from_str(&result.content[1].as_text().unwrap().text)?;
+        assert_eq!(
+            calls,
+            json!({
+                "dry_run": true,
+                "calls": [
+                    { "method": "POST", "path": "/_aliases", "body": { "actions": [] } },
+                    { "method": "POST", "path": "/_tasks/node:1/_cancel" },
+                ]
+            })
+        );
+        Ok(())
+    }
+}
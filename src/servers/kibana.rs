@@ -0,0 +1,1112 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Tools of the Kibana instance of a cluster: dashboards and saved searches, to find which ones show some data, and
+//! alerting rules, to triage firing alerts.
+//!
+//! Kibana tools are added to the tools of their cluster, and share its credentials, write and tool settings.
+
+use crate::servers::SubServer;
+use crate::servers::elasticsearch::writes::{self, PlannedCall};
+use crate::servers::elasticsearch::{
+    ElasticsearchMcp, ElasticsearchMcpConfig, EsClientProvider, handle_error, read_json,
+};
+use crate::utils::none_if_empty_string;
+use elasticsearch::http::headers::{HeaderName, HeaderValue};
+use elasticsearch::http::request::JsonBody;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::{Method, StatusCode};
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::{Parameters, ToolRouter};
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use tracing::Instrument;
+
+/// Maximum number of saved objects returned by searches.
+const MAX_RESULTS: usize = 100;
+
+/// The Kibana instance of a cluster.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct KibanaConfig {
+    /// Kibana URL
+    pub url: String,
+
+    /// API key, either encoded or as `id:api_key`. Defaults to the credentials of the cluster
+    #[serde(default, deserialize_with = "none_if_empty_string")]
+    pub api_key: Option<String>,
+
+    /// Kibana space of the saved objects. Defaults to the default space
+    #[serde(default, deserialize_with = "none_if_empty_string")]
+    pub space: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct KibanaTools {
+    client: EsClientProvider,
+    /// Path prefix of the space, empty for the default space
+    space_prefix: String,
+    /// Describe the API calls of write tools instead of making them, for all calls
+    dry_run: bool,
+    tool_router: ToolRouter<KibanaTools>,
+}
+
+impl KibanaTools {
+    /// Kibana tools for a cluster, using the credentials of the cluster if the Kibana configuration has none.
+    pub fn new(config: &KibanaConfig, cluster: &ElasticsearchMcpConfig, container_mode: bool) -> anyhow::Result<Self> {
+        if config.url.is_empty() {
+            anyhow::bail!("Kibana URL is empty");
+        }
+        let client_config = ElasticsearchMcpConfig {
+            url: config.url.clone(),
+            api_key: config.api_key.clone().or_else(|| cluster.api_key.clone()),
+            login: cluster.login.clone(),
+            password: cluster.password.clone(),
+            cloud_auth: cluster.cloud_auth.clone(),
+            ssl_skip_verify: cluster.ssl_skip_verify,
+            ..Default::default()
+        };
+        let client = ElasticsearchMcp::new_client(&client_config, container_mode)?;
+
+        Ok(KibanaTools {
+            client: EsClientProvider::new(client),
+            space_prefix: config.space.as_ref().map(|s| format!("/s/{s}")).unwrap_or_default(),
+            dry_run: cluster.dry_run,
+            tool_router: Self::tool_router(),
+        })
+    }
+
+    /// Send a request to a Kibana API of the space.
+    async fn send(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        method: Method,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Option<Value>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let client = self.client.get(req_ctx);
+        let span = tracing::info_span!("kibana_request", otel.kind = "client");
+        let mut headers = client.headers(&span);
+        // Required by Kibana for requests that aren't GETs
+        headers.insert(HeaderName::from_static("kbn-xsrf"), HeaderValue::from_static("true"));
+        client
+            .send(
+                method,
+                &format!("{}{path}", self.space_prefix),
+                headers,
+                Some(query),
+                body.map(JsonBody::new),
+                None,
+            )
+            .instrument(span)
+            .await
+    }
+
+    /// Find saved objects of a type, optionally matching words of their title or description.
+    async fn find(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        kind: &str,
+        search: Option<&str>,
+        limit: usize,
+    ) -> Result<FindResponse, rmcp::Error> {
+        let per_page = limit.min(MAX_RESULTS).to_string();
+        let mut query = vec![("type", kind), ("per_page", per_page.as_str())];
+        if let Some(search) = search {
+            query.extend([
+                ("search", search),
+                ("search_fields", "title"),
+                ("search_fields", "description"),
+            ]);
+        }
+        let response = self
+            .send(req_ctx, Method::Get, "/api/saved_objects/_find", &query, None)
+            .await;
+        read_json(response).await
+    }
+
+    /// Get saved objects by type and id. Objects that aren't found are omitted.
+    async fn bulk_get(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        objects: &[(String, String)],
+    ) -> Result<Vec<SavedObject>, rmcp::Error> {
+        if objects.is_empty() {
+            return Ok(Vec::new());
+        }
+        let body = objects
+            .iter()
+            .map(|(kind, id)| json!({ "type": kind, "id": id }))
+            .collect();
+        let response = self
+            .send(
+                req_ctx,
+                Method::Post,
+                "/api/saved_objects/_bulk_get",
+                &[],
+                Some(Value::Array(body)),
+            )
+            .await;
+        let response: BulkGetResponse = read_json(response).await?;
+        Ok(response
+            .saved_objects
+            .into_iter()
+            .filter(|o| o.error.is_none())
+            .collect())
+    }
+
+    /// Mute or unmute all the alerts of a rule, or one of its alerts.
+    async fn set_muted(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        muted: bool,
+        RuleAlertParams { id, alert_id, dry_run }: RuleAlertParams,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        check_id(&id)?;
+        let (action, past) = if muted {
+            ("mute", "muted")
+        } else {
+            ("unmute", "unmuted")
+        };
+        let (path, target) = match &alert_id {
+            Some(alert_id) => {
+                check_id(alert_id)?;
+                (
+                    format!("/api/alerting/rule/{id}/alert/{alert_id}/_{action}"),
+                    format!("Alert '{alert_id}' of rule '{id}'"),
+                )
+            }
+            None => (
+                format!("/api/alerting/rule/{id}/_{action}_all"),
+                format!("All the alerts of rule '{id}'"),
+            ),
+        };
+
+        if self.dry_run || dry_run {
+            return writes::dry_run_result(
+                format!("{target} would be {past}."),
+                vec![PlannedCall::new("POST", format!("{}{path}", self.space_prefix))],
+            );
+        }
+
+        let response = self.send(req_ctx, Method::Post, &path, &[], None).await;
+        if let Ok(response) = &response
+            && response.status_code() == StatusCode::NOT_FOUND
+        {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Rule '{id}' not found. Use list_alerting_rules to find rule ids."
+            ))]));
+        }
+        handle_error(response)?;
+
+        let msg = if muted {
+            format!("{target} {past}: the rule keeps running, but doesn't run the actions of muted alerts.")
+        } else {
+            format!("{target} {past}: the rule runs its actions again.")
+        };
+        Ok(CallToolResult::success(vec![Content::text(msg)]))
+    }
+
+    /// Index patterns of data views, by id. Data views that aren't found keep their id.
+    async fn index_patterns(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        data_views: impl Iterator<Item = &String>,
+    ) -> Result<HashMap<String, String>, rmcp::Error> {
+        let mut ids = data_views.cloned().collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+        let objects = ids
+            .iter()
+            .map(|id| ("index-pattern".to_string(), id.clone()))
+            .collect::<Vec<_>>();
+
+        let mut patterns = ids.into_iter().map(|id| (id.clone(), id)).collect::<HashMap<_, _>>();
+        for data_view in self.bulk_get(req_ctx, &objects).await? {
+            if let Some(title) = data_view.title() {
+                patterns.insert(data_view.id, title.to_string());
+            }
+        }
+        Ok(patterns)
+    }
+}
+
+impl SubServer for KibanaTools {
+    fn router(&self) -> &ToolRouter<Self> {
+        &self.tool_router
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ListDashboardsParams {
+    /// Words of the dashboard title or description, with `*` wildcards for prefixes, e.g. `latency*`. If
+    /// omitted, all dashboards are listed
+    search: Option<String>,
+
+    /// Maximum number of dashboards, at most 100 (default: 20)
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ExportDashboardParams {
+    /// Id of the dashboard, as returned by list_dashboards
+    id: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct FindSavedSearchesParams {
+    /// Words of the saved search title or description, with `*` wildcards for prefixes. If omitted, all saved
+    /// searches are listed
+    search: Option<String>,
+
+    /// Maximum number of saved searches, at most 100 (default: 20)
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ListAlertingRulesParams {
+    /// Words of the rule name, with `*` wildcards for prefixes. If omitted, all rules are listed
+    search: Option<String>,
+
+    /// Only list the rules that have active alerts (default: false)
+    #[serde(default)]
+    active_only: bool,
+
+    /// Maximum number of rules, at most 100 (default: 20)
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct GetRuleStatusParams {
+    /// Id of the rule, as returned by list_alerting_rules
+    id: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct RuleAlertParams {
+    /// Id of the rule, as returned by list_alerting_rules
+    id: String,
+
+    /// Id of a single alert of the rule, e.g. a host name for a rule grouped by host. If omitted, all the alerts
+    /// of the rule are targeted
+    alert_id: Option<String>,
+
+    /// Describe the API calls that would be made, without making them (default: false)
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[tool_router]
+impl KibanaTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: list dashboards
+    #[tool(
+        description = "List Kibana dashboards, optionally matching words of their title or description. Use \
+            export_dashboard to see the queries and indices of their panels",
+        annotations(title = "List Kibana dashboards", read_only_hint = true)
+    )]
+    async fn list_dashboards(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(ListDashboardsParams { search, limit }): Parameters<ListDashboardsParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let response = self
+            .find(req_ctx, "dashboard", search.as_deref(), limit.unwrap_or(20))
+            .await?;
+        let dashboards = response
+            .saved_objects
+            .iter()
+            .map(SavedObjectSummary::new)
+            .collect::<Vec<_>>();
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!(
+                "Found {} dashboards, showing {}:",
+                response.total,
+                dashboards.len()
+            )),
+            Content::json(dashboards)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: export the panels of a dashboard
+    #[tool(
+        description = "Export the panels of a Kibana dashboard with their type, title, query, filters and the index \
+            patterns they read, along with the dashboard query, filters and time range",
+        annotations(title = "Export a Kibana dashboard", read_only_hint = true)
+    )]
+    async fn export_dashboard(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(ExportDashboardParams { id }): Parameters<ExportDashboardParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        check_id(&id)?;
+        let response = self
+            .send(
+                req_ctx.clone(),
+                Method::Get,
+                &format!("/api/saved_objects/dashboard/{id}"),
+                &[],
+                None,
+            )
+            .await;
+        if let Ok(response) = &response
+            && response.status_code() == StatusCode::NOT_FOUND
+        {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Dashboard '{id}' not found. Use list_dashboards to find dashboard ids."
+            ))]));
+        }
+        let dashboard: SavedObject = read_json(response).await?;
+
+        let panels = dashboard_panels(&dashboard);
+        let linked = panels.iter().filter_map(|p| p.reference.clone()).collect::<Vec<_>>();
+        let linked = self.bulk_get(req_ctx.clone(), &linked).await?;
+        let panels = panels
+            .into_iter()
+            .map(|panel| {
+                let object = panel
+                    .reference
+                    .as_ref()
+                    .and_then(|(kind, id)| linked.iter().find(|o| &o.kind == kind && &o.id == id));
+                panel.resolve(object)
+            })
+            .collect::<Vec<_>>();
+
+        let data_views = dashboard
+            .data_views()
+            .chain(panels.iter().flat_map(|p| p.data_views.iter()));
+        let patterns = self.index_patterns(req_ctx, data_views).await?;
+
+        let export = DashboardExport::new(&dashboard, panels, &patterns);
+        Ok(CallToolResult::success(vec![
+            Content::text(format!(
+                "Dashboard '{}' has {} panels:",
+                export.title,
+                export.panels.len()
+            )),
+            Content::json(export)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: find saved searches
+    #[tool(
+        description = "Find Kibana saved searches (Discover sessions), optionally matching words of their title or \
+            description, with their query, filters, columns and index patterns",
+        annotations(title = "Find Kibana saved searches", read_only_hint = true)
+    )]
+    async fn find_saved_searches(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(FindSavedSearchesParams { search, limit }): Parameters<FindSavedSearchesParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let response = self
+            .find(req_ctx.clone(), "search", search.as_deref(), limit.unwrap_or(20))
+            .await?;
+
+        let data_views = response.saved_objects.iter().flat_map(|o| o.data_views());
+        let patterns = self.index_patterns(req_ctx, data_views).await?;
+        let searches = response
+            .saved_objects
+            .iter()
+            .map(|o| SavedSearch::new(o, &patterns))
+            .collect::<Vec<_>>();
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!(
+                "Found {} saved searches, showing {}:",
+                response.total,
+                searches.len()
+            )),
+            Content::json(searches)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: list alerting rules
+    #[tool(
+        description = "List Kibana alerting rules with their type, schedule, execution status, number of active \
+            alerts and muting. Use active_only to triage firing alerts, and get_rule_status for the details of a rule",
+        annotations(title = "List Kibana alerting rules", read_only_hint = true)
+    )]
+    async fn list_alerting_rules(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(ListAlertingRulesParams {
+            search,
+            active_only,
+            limit,
+        }): Parameters<ListAlertingRulesParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let per_page = limit.unwrap_or(20).min(MAX_RESULTS).to_string();
+        let mut query = vec![("per_page", per_page.as_str())];
+        if let Some(search) = &search {
+            query.extend([("search", search.as_str()), ("search_fields", "name")]);
+        }
+        if active_only {
+            query.push(("filter", "alert.attributes.executionStatus.status:active"));
+        }
+        let response = self
+            .send(req_ctx, Method::Get, "/api/alerting/rules/_find", &query, None)
+            .await;
+        let response: FindRulesResponse = read_json(response).await?;
+        let rules = response.data.iter().map(RuleSummary::new).collect::<Vec<_>>();
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("Found {} rules, showing {}:", response.total, rules.len())),
+            Content::json(rules)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get the execution status of a rule
+    #[tool(
+        description = "Get the execution status of a Kibana alerting rule: outcome and duration of its last run, \
+            errors and warnings, number of active, new and recovered alerts, muted alerts and next run",
+        annotations(title = "Get the status of a Kibana rule", read_only_hint = true)
+    )]
+    async fn get_rule_status(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(GetRuleStatusParams { id }): Parameters<GetRuleStatusParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        check_id(&id)?;
+        let response = self
+            .send(req_ctx, Method::Get, &format!("/api/alerting/rule/{id}"), &[], None)
+            .await;
+        if let Ok(response) = &response
+            && response.status_code() == StatusCode::NOT_FOUND
+        {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Rule '{id}' not found. Use list_alerting_rules to find rule ids."
+            ))]));
+        }
+        let rule: Rule = read_json(response).await?;
+        let status = RuleStatus::new(rule);
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("Rule '{}' is {}:", status.summary.name, status.summary.status)),
+            Content::json(status)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: mute a rule
+    #[tool(
+        description = "Mute all the alerts of a Kibana alerting rule, or one of its alerts: the rule keeps running \
+            and tracking alerts, but doesn't notify (run actions) for muted alerts",
+        annotations(
+            title = "Mute a Kibana rule",
+            read_only_hint = false,
+            destructive_hint = false,
+            idempotent_hint = true
+        )
+    )]
+    async fn mute_rule(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<RuleAlertParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        self.set_muted(req_ctx, true, params).await
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: unmute a rule
+    #[tool(
+        description = "Unmute all the alerts of a Kibana alerting rule, or one of its alerts, so that they notify \
+            (run actions) again",
+        annotations(
+            title = "Unmute a Kibana rule",
+            read_only_hint = false,
+            destructive_hint = false,
+            idempotent_hint = true
+        )
+    )]
+    async fn unmute_rule(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<RuleAlertParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        self.set_muted(req_ctx, false, params).await
+    }
+}
+
+/// Check that an id can be used in an API path.
+fn check_id(id: &str) -> Result<(), rmcp::Error> {
+    if id.is_empty() || id.contains(['/', '?', '#', '%']) || id == "." || id == ".." {
+        return Err(rmcp::Error::invalid_params(format!("Invalid id '{id}'"), None));
+    }
+    Ok(())
+}
+
+//------------------------------------------------------------------------------------------------
+// Saved objects
+
+#[derive(Deserialize)]
+struct FindResponse {
+    saved_objects: Vec<SavedObject>,
+    total: u64,
+}
+
+#[derive(Deserialize)]
+struct BulkGetResponse {
+    saved_objects: Vec<SavedObject>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SavedObject {
+    id: String,
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    attributes: Value,
+    #[serde(default)]
+    references: Vec<Reference>,
+    #[serde(default)]
+    updated_at: Option<String>,
+    #[serde(default)]
+    error: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Reference {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    id: String,
+}
+
+impl SavedObject {
+    fn title(&self) -> Option<&str> {
+        self.attributes.get("title").and_then(Value::as_str)
+    }
+
+    fn description(&self) -> Option<&str> {
+        self.attributes
+            .get("description")
+            .and_then(Value::as_str)
+            .filter(|d| !d.is_empty())
+    }
+
+    /// Ids of the data views used by the object.
+    fn data_views(&self) -> impl Iterator<Item = &String> {
+        data_view_ids(&self.references)
+    }
+}
+
+fn data_view_ids(references: &[Reference]) -> impl Iterator<Item = &String> {
+    references.iter().filter(|r| r.kind == "index-pattern").map(|r| &r.id)
+}
+
+/// The query and the enabled filters of saved object attributes: from the search source of dashboards, saved
+/// searches and legacy visualizations, or from the state of Lens visualizations.
+fn query_and_filters(attributes: &Value) -> (Option<Value>, Vec<Value>) {
+    let search_source = attributes
+        .pointer("/kibanaSavedObjectMeta/searchSourceJSON")
+        .and_then(Value::as_str)
+        .and_then(|source| serde_json::from_str::<Value>(source).ok());
+    let (query, filters) = match &search_source {
+        Some(source) => (source.get("query"), source.get("filter")),
+        None => (attributes.pointer("/state/query"), attributes.pointer("/state/filters")),
+    };
+
+    // Empty KQL or Lucene queries match all documents
+    let query = query
+        .filter(|q| {
+            q.get("query")
+                .and_then(Value::as_str)
+                .is_none_or(|q| !q.trim().is_empty())
+        })
+        .cloned();
+    let filters = filters
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(|f| f.pointer("/meta/disabled").and_then(Value::as_bool) != Some(true))
+        .map(|f| {
+            let mut filter = f.clone();
+            if let Some(filter) = filter.as_object_mut() {
+                filter.remove("meta");
+                filter.remove("$state");
+            }
+            filter.get("query").cloned().unwrap_or(filter)
+        })
+        .collect();
+    (query, filters)
+}
+
+#[derive(Serialize)]
+struct SavedObjectSummary {
+    id: String,
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    updated_at: Option<String>,
+}
+
+impl SavedObjectSummary {
+    fn new(object: &SavedObject) -> Self {
+        SavedObjectSummary {
+            id: object.id.clone(),
+            title: object.title().unwrap_or_default().to_string(),
+            description: object.description().map(str::to_string),
+            updated_at: object.updated_at.clone(),
+        }
+    }
+}
+
+/// A panel of a dashboard, before its linked saved object is read.
+struct DashboardPanel {
+    kind: String,
+    title: Option<String>,
+    /// Attributes of a panel whose visualization is stored in the dashboard, and their references
+    by_value: Option<(Value, Vec<Reference>)>,
+    /// Type and id of the saved object shown by a panel
+    reference: Option<(String, String)>,
+}
+
+/// Panels of a dashboard, from its `panelsJSON` attribute.
+fn dashboard_panels(dashboard: &SavedObject) -> Vec<DashboardPanel> {
+    let panels = dashboard
+        .attributes
+        .get("panelsJSON")
+        .and_then(Value::as_str)
+        .and_then(|panels| serde_json::from_str::<Vec<Value>>(panels).ok())
+        .unwrap_or_default();
+
+    panels
+        .iter()
+        .map(|panel| {
+            let str_at = |pointer: &str| panel.pointer(pointer).and_then(Value::as_str);
+            let panel_index = str_at("/panelIndex").unwrap_or_default();
+            // Reference names are `<panelIndex>:<panelRefName>`, or `<panelRefName>` before Kibana 7.13
+            let ref_name = str_at("/panelRefName")
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("panel_{panel_index}"));
+            let reference = dashboard
+                .references
+                .iter()
+                .find(|r| r.name == format!("{panel_index}:{ref_name}") || r.name == ref_name)
+                .map(|r| (r.kind.clone(), r.id.clone()));
+
+            let by_value = panel.pointer("/embeddableConfig/attributes").map(|attributes| {
+                let references = attributes
+                    .get("references")
+                    .cloned()
+                    .and_then(|r| serde_json::from_value(r).ok())
+                    .unwrap_or_default();
+                (attributes.clone(), references)
+            });
+
+            DashboardPanel {
+                kind: str_at("/type").unwrap_or("unknown").to_string(),
+                title: str_at("/embeddableConfig/title")
+                    .or(str_at("/title"))
+                    .map(str::to_string),
+                by_value,
+                reference,
+            }
+        })
+        .collect()
+}
+
+impl DashboardPanel {
+    /// Summarize the panel with its linked saved object, if any.
+    fn resolve(self, object: Option<&SavedObject>) -> PanelSummary {
+        let (attributes, references) = match (&self.by_value, object) {
+            (Some((attributes, references)), _) => (Some(attributes), references.as_slice()),
+            (None, Some(object)) => (Some(&object.attributes), object.references.as_slice()),
+            (None, None) => (None, [].as_slice()),
+        };
+        let (query, filters) = attributes.map(query_and_filters).unwrap_or_default();
+        let title = self
+            .title
+            .or_else(|| attributes.and_then(|a| a.get("title")?.as_str().map(str::to_string)))
+            .filter(|t| !t.is_empty());
+
+        PanelSummary {
+            title,
+            kind: self.kind,
+            saved_object: self.reference.map(|(kind, id)| format!("{kind}/{id}")),
+            query,
+            filters,
+            data_views: data_view_ids(references).cloned().collect(),
+            indices: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct PanelSummary {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(rename = "type")]
+    kind: String,
+    /// `<type>/<id>` of the saved object shown by the panel
+    #[serde(skip_serializing_if = "Option::is_none")]
+    saved_object: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query: Option<Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    filters: Vec<Value>,
+    #[serde(skip)]
+    data_views: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    indices: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct DashboardExport {
+    id: String,
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query: Option<Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    filters: Vec<Value>,
+    /// Time range stored with the dashboard, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time_range: Option<Value>,
+    panels: Vec<PanelSummary>,
+}
+
+impl DashboardExport {
+    fn new(dashboard: &SavedObject, mut panels: Vec<PanelSummary>, patterns: &HashMap<String, String>) -> Self {
+        for panel in &mut panels {
+            panel.indices = panel
+                .data_views
+                .iter()
+                .filter_map(|id| patterns.get(id).cloned())
+                .collect();
+            panel.indices.dedup();
+        }
+        let attributes = &dashboard.attributes;
+        let (query, filters) = query_and_filters(attributes);
+        let time_range = (attributes.get("timeRestore").and_then(Value::as_bool) == Some(true))
+            .then(|| json!({ "from": attributes.get("timeFrom"), "to": attributes.get("timeTo") }));
+
+        DashboardExport {
+            id: dashboard.id.clone(),
+            title: dashboard.title().unwrap_or_default().to_string(),
+            description: dashboard.description().map(str::to_string),
+            query,
+            filters,
+            time_range,
+            panels,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SavedSearch {
+    id: String,
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query: Option<Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    filters: Vec<Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    columns: Vec<Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    indices: Vec<String>,
+}
+
+impl SavedSearch {
+    fn new(object: &SavedObject, patterns: &HashMap<String, String>) -> Self {
+        let (query, filters) = query_and_filters(&object.attributes);
+        SavedSearch {
+            id: object.id.clone(),
+            title: object.title().unwrap_or_default().to_string(),
+            description: object.description().map(str::to_string),
+            query,
+            filters,
+            columns: object
+                .attributes
+                .get("columns")
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default(),
+            indices: object.data_views().filter_map(|id| patterns.get(id).cloned()).collect(),
+        }
+    }
+}
+
+//------------------------------------------------------------------------------------------------
+// Alerting rules
+
+#[derive(Deserialize)]
+struct FindRulesResponse {
+    data: Vec<Rule>,
+    total: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Rule {
+    id: String,
+    name: String,
+    rule_type_id: String,
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    schedule: Option<Schedule>,
+    #[serde(default)]
+    mute_all: bool,
+    #[serde(default)]
+    muted_alert_ids: Vec<String>,
+    #[serde(default)]
+    execution_status: Option<ExecutionStatus>,
+    #[serde(default)]
+    last_run: Option<LastRun>,
+    #[serde(default)]
+    next_run: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Schedule {
+    interval: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExecutionStatus {
+    status: String,
+    #[serde(default)]
+    last_execution_date: Option<String>,
+    #[serde(default)]
+    last_duration: Option<u64>,
+    #[serde(default)]
+    error: Option<RuleMessage>,
+    #[serde(default)]
+    warning: Option<RuleMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RuleMessage {
+    reason: String,
+    message: String,
+}
+
+impl RuleMessage {
+    fn text(&self) -> String {
+        format!("{}: {}", self.reason, self.message)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct LastRun {
+    #[serde(default)]
+    outcome: Option<String>,
+    #[serde(default)]
+    outcome_msg: Option<Vec<String>>,
+    #[serde(default)]
+    alerts_count: Option<AlertsCount>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct AlertsCount {
+    #[serde(default)]
+    active: u64,
+    #[serde(default)]
+    new: u64,
+    #[serde(default)]
+    recovered: u64,
+}
+
+#[derive(Serialize)]
+struct RuleSummary {
+    id: String,
+    name: String,
+    rule_type: String,
+    enabled: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    interval: Option<String>,
+    /// `ok`, `active` (with alerts), `error`, `warning`, `pending` or `unknown`
+    status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_execution: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    active_alerts: Option<u64>,
+    /// All the alerts of the rule are muted
+    muted: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    muted_alerts: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl RuleSummary {
+    fn new(rule: &Rule) -> Self {
+        let status = rule.execution_status.as_ref();
+        RuleSummary {
+            id: rule.id.clone(),
+            name: rule.name.clone(),
+            rule_type: rule.rule_type_id.clone(),
+            enabled: rule.enabled,
+            tags: rule.tags.clone(),
+            interval: rule.schedule.as_ref().map(|s| s.interval.clone()),
+            status: status.map_or("unknown", |s| s.status.as_str()).to_string(),
+            last_execution: status.and_then(|s| s.last_execution_date.clone()),
+            active_alerts: rule
+                .last_run
+                .as_ref()
+                .and_then(|r| r.alerts_count.as_ref())
+                .map(|c| c.active),
+            muted: rule.mute_all,
+            muted_alerts: rule.muted_alert_ids.clone(),
+            error: status.and_then(|s| s.error.as_ref()).map(RuleMessage::text),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RuleStatus {
+    #[serde(flatten)]
+    summary: RuleSummary,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_duration_ms: Option<u64>,
+    /// Outcome of the last run: `succeeded`, `warning` or `failed`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    outcome: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    messages: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    warning: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alerts: Option<AlertsCount>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_run: Option<String>,
+}
+
+impl RuleStatus {
+    fn new(rule: Rule) -> Self {
+        let summary = RuleSummary::new(&rule);
+        let status = rule.execution_status.as_ref();
+        let (outcome, messages, alerts) = match rule.last_run {
+            Some(run) => (run.outcome, run.outcome_msg.unwrap_or_default(), run.alerts_count),
+            None => (None, Vec::new(), None),
+        };
+
+        RuleStatus {
+            summary,
+            last_duration_ms: status.and_then(|s| s.last_duration),
+            outcome,
+            messages,
+            warning: status.and_then(|s| s.warning.as_ref()).map(RuleMessage::text),
+            alerts,
+            next_run: rule.next_run,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_dashboard_panels() -> anyhow::Result<()> {
+        let panels = json!([
+            { "type": "lens", "panelIndex": "p1", "embeddableConfig": {
+                "title": "Error rate",
+                "attributes": {
+                    "title": "Errors",
+                    "state": {
+                        "query": { "query": "log.level: error", "language": "kuery" },
+                        "filters": [
+                            { "meta": { "disabled": false }, "query": { "match_phrase": { "service.name": "api" } } },
+                            { "meta": { "disabled": true }, "query": { "match_phrase": { "service.name": "web" } } }
+                        ]
+                    },
+                    "references": [{ "name": "layer", "type": "index-pattern", "id": "dv-logs" }]
+                }
+            }},
+            { "type": "search", "panelIndex": "p2", "panelRefName": "panel_p2", "embeddableConfig": {} }
+        ]);
+        let dashboard: SavedObject = serde_json::from_value(json!({
+            "id": "d1",
+            "type": "dashboard",
+            "attributes": {
+                "title": "Service health",
+                "description": "",
+                "panelsJSON": panels.to_string(),
+                "timeRestore": true,
+                "timeFrom": "now-24h",
+                "timeTo": "now",
+                "kibanaSavedObjectMeta": {
+                    "searchSourceJSON": json!({ "query": { "query": "", "language": "kuery" }, "filter": [] }).to_string()
+                }
+            },
+            "references": [{ "name": "p2:panel_p2", "type": "search", "id": "s1" }]
+        }))?;
+        let search: SavedObject = serde_json::from_value(json!({
+            "id": "s1",
+            "type": "search",
+            "attributes": {
+                "title": "Slow requests",
+                "kibanaSavedObjectMeta": {
+                    "searchSourceJSON": json!({ "query": { "esql": "FROM traces-* | WHERE duration > 1000" } }).to_string()
+                }
+            },
+            "references": [{ "name": "kibanaSavedObjectMeta.searchSourceJSON.index", "type": "index-pattern", "id": "dv-traces" }]
+        }))?;
+
+        let mut panels = dashboard_panels(&dashboard).into_iter();
+        let (by_value, linked) = (panels.next().unwrap(), panels.next().unwrap());
+        assert_eq!(linked.reference, Some(("search".to_string(), "s1".to_string())));
+        let panels = vec![by_value.resolve(None), linked.resolve(Some(&search))];
+        let patterns = HashMap::from([("dv-logs".to_string(), "logs-*".to_string())]);
+
+        let export = serde_json::to_value(DashboardExport::new(&dashboard, panels, &patterns))?;
+        assert_eq!(
+            export,
+            json!({
+                "id": "d1",
+                "title": "Service health",
+                "time_range": { "from": "now-24h", "to": "now" },
+                "panels": [
+                    {
+                        "title": "Error rate",
+                        "type": "lens",
+                        "query": { "query": "log.level: error", "language": "kuery" },
+                        "filters": [{ "match_phrase": { "service.name": "api" } }],
+                        "indices": ["logs-*"]
+                    },
+                    {
+                        "title": "Slow requests",
+                        "type": "search",
+                        "saved_object": "search/s1",
+                        "query": { "esql": "FROM traces-* | WHERE duration > 1000" }
+                    }
+                ]
+            })
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,478 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Tools to explore the logs of the `logs-*` data streams: recent lines, log rate spikes and log patterns.
+//!
+//! Logs are expected to follow ECS: `message`, `log.level`, `service.name` and `host.name` fields.
+
+use crate::servers::SubServer;
+use crate::servers::analytics::{TimeRange, buckets, round, source_field};
+use crate::servers::elasticsearch::index_policy::IndexPolicy;
+use crate::servers::elasticsearch::{EsClientProvider, read_json, send};
+use elasticsearch::{Elasticsearch, SearchParts};
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::{Parameters, ToolRouter};
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+/// Maximum number of log lines returned by `tail_logs`.
+const MAX_LINES: usize = 500;
+
+/// Log messages are truncated to this number of characters.
+const MAX_MESSAGE_LEN: usize = 1000;
+
+/// Maximum number of log categories.
+const MAX_CATEGORIES: usize = 100;
+
+/// Number of logs per shard that are categorized.
+const CATEGORIZE_SAMPLE_SIZE: usize = 2000;
+
+/// Buckets with fewer logs are never spikes.
+const MIN_SPIKE_COUNT: u64 = 10;
+
+/// Log data streams of a cluster.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LogsConfig {
+    /// Log indices or data streams (default: `logs-*`)
+    #[serde(default = "default_indices")]
+    pub indices: String,
+}
+
+fn default_indices() -> String {
+    "logs-*".to_string()
+}
+
+impl Default for LogsConfig {
+    fn default() -> Self {
+        LogsConfig {
+            indices: default_indices(),
+        }
+    }
+}
+
+impl LogsConfig {
+    /// Check that the log data streams can be accessed by tools.
+    pub fn check(&self, index_policy: &IndexPolicy) -> anyhow::Result<()> {
+        index_policy
+            .check_expression(&self.indices)
+            .map_err(|reason| anyhow::anyhow!("Logs are denied by the index policy: {reason}"))
+    }
+}
+
+#[derive(Clone)]
+pub struct LogsTools {
+    es_client: EsClientProvider,
+    config: LogsConfig,
+    tool_router: ToolRouter<LogsTools>,
+}
+
+impl LogsTools {
+    pub fn new(es_client: Elasticsearch, config: LogsConfig) -> Self {
+        LogsTools {
+            es_client: EsClientProvider::new(es_client),
+            config,
+            tool_router: Self::tool_router(),
+        }
+    }
+
+    async fn search(&self, req_ctx: RequestContext<RoleServer>, body: Value) -> Result<Value, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+        let response = send!(
+            es_client
+                .search(SearchParts::Index(&[self.config.indices.as_str()]))
+                .body(body)
+        )
+        .await;
+        read_json(response).await
+    }
+}
+
+impl SubServer for LogsTools {
+    fn router(&self) -> &ToolRouter<Self> {
+        &self.tool_router
+    }
+}
+
+/// Logs selected by a tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+struct LogFilter {
+    /// Only logs of this service
+    service: Option<String>,
+
+    /// Only logs of this level, e.g. `error` or `warn`. Case is ignored
+    level: Option<String>,
+
+    /// Words that log messages must all contain
+    text: Option<String>,
+}
+
+impl LogFilter {
+    fn query(&self, range: &TimeRange) -> Value {
+        let mut filters = vec![range.filter()];
+        if let Some(service) = &self.service {
+            filters.push(json!({ "term": { "service.name": service } }));
+        }
+        if let Some(level) = &self.level {
+            filters.push(json!({ "term": { "log.level": { "value": level, "case_insensitive": true } } }));
+        }
+        if let Some(text) = &self.text {
+            filters.push(json!({
+                "simple_query_string": { "query": text, "fields": ["message"], "default_operator": "and" }
+            }));
+        }
+        json!({ "bool": { "filter": filters } })
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct TailLogsParams {
+    #[serde(flatten)]
+    filter: LogFilter,
+
+    /// Time range to look at, back from now, like `15m`, `1h` or `7d` (default: `15m`)
+    time_range: Option<String>,
+
+    /// Maximum number of log lines, at most 500 (default: 50)
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct LogRateSpikeParams {
+    #[serde(flatten)]
+    filter: LogFilter,
+
+    /// Time range to look at, back from now, like `15m`, `1h` or `7d` (default: `1h`)
+    time_range: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct CategorizeLogsParams {
+    #[serde(flatten)]
+    filter: LogFilter,
+
+    /// Time range to look at, back from now, like `15m`, `1h` or `7d` (default: `15m`)
+    time_range: Option<String>,
+
+    /// Maximum number of categories, at most 100 (default: 20)
+    limit: Option<usize>,
+}
+
+#[tool_router]
+impl LogsTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: tail logs
+    #[tool(
+        description = "Get the most recent log lines, optionally filtered by service, level and words of the \
+            message, in chronological order",
+        annotations(title = "Tail logs", read_only_hint = true)
+    )]
+    async fn tail_logs(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(TailLogsParams {
+            filter,
+            time_range,
+            limit,
+        }): Parameters<TailLogsParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let range = TimeRange::parse(time_range.as_deref(), "15m")?;
+        let body = json!({
+            "size": limit.unwrap_or(50).min(MAX_LINES),
+            "track_total_hits": true,
+            "query": filter.query(&range),
+            "sort": [{ "@timestamp": "desc" }],
+            "_source": ["@timestamp", "message", "log.level", "service.name", "host.name", "error.message"]
+        });
+        let response = self.search(req_ctx, body).await?;
+
+        let total = response
+            .pointer("/hits/total/value")
+            .and_then(Value::as_u64)
+            .unwrap_or_default();
+        let mut lines = buckets(&response, "/hits/hits")
+            .map(|hit| {
+                let log = &hit["_source"];
+                let message = source_field(log, "message")
+                    .or_else(|| source_field(log, "error.message"))
+                    .and_then(Value::as_str)
+                    .map(truncate);
+                json!({
+                    "time": log["@timestamp"],
+                    "level": source_field(log, "log.level"),
+                    "service": source_field(log, "service.name"),
+                    "host": source_field(log, "host.name"),
+                    "message": message,
+                })
+            })
+            .collect::<Vec<_>>();
+        lines.reverse();
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!(
+                "{total} logs in the last {}, showing the last {}:",
+                range.span,
+                lines.len()
+            )),
+            Content::json(lines)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: log rate spikes
+    #[tool(
+        description = "Detect spikes of the log rate over a time range, optionally for a service, level or words \
+            of the message. Spikes are compared to the median rate, and come with the services and levels that \
+            contribute the most to them",
+        annotations(title = "Detect log rate spikes", read_only_hint = true)
+    )]
+    async fn log_rate_spike(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(LogRateSpikeParams { filter, time_range }): Parameters<LogRateSpikeParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let range = TimeRange::parse(time_range.as_deref(), "1h")?;
+        let contributors = json!({
+            "services": { "terms": { "field": "service.name", "size": 5 } },
+            "levels": { "terms": { "field": "log.level", "size": 5 } }
+        });
+        let body = json!({
+            "size": 0,
+            "track_total_hits": true,
+            "query": filter.query(&range),
+            "aggs": {
+                "services": { "terms": { "field": "service.name", "size": 50 } },
+                "levels": { "terms": { "field": "log.level", "size": 10 } },
+                "timeline": { "date_histogram": range.timeline(), "aggs": contributors }
+            }
+        });
+        let response = self.search(req_ctx, body).await?;
+
+        let total = response
+            .pointer("/hits/total/value")
+            .and_then(Value::as_u64)
+            .unwrap_or_default();
+        let aggs = &response["aggregations"];
+        let timeline = buckets(aggs, "/timeline/buckets").collect::<Vec<_>>();
+        let counts = timeline
+            .iter()
+            .map(|b| b["doc_count"].as_u64().unwrap_or_default())
+            .collect::<Vec<_>>();
+        let (baseline, spikes) = find_spikes(&counts);
+
+        let spikes = spikes
+            .into_iter()
+            .map(|i| {
+                let bucket = timeline[i];
+                let count = counts[i];
+                let mut contributors = Vec::new();
+                for (field, agg) in [("service.name", "services"), ("log.level", "levels")] {
+                    contributors.extend(buckets(bucket, &format!("/{agg}/buckets")).filter_map(|b| {
+                        let share = percent(b["doc_count"].as_u64()?, count);
+                        let overall = buckets(aggs, &format!("/{agg}/buckets"))
+                            .find(|o| o["key"] == b["key"])
+                            .and_then(|o| o["doc_count"].as_u64())
+                            .map_or(0.0, |c| percent(c, total));
+                        // Values that are over-represented in the spike
+                        (share - overall >= 10.0).then(|| {
+                            json!({ "field": field, "value": b["key"], "spike_percent": share, "overall_percent": overall })
+                        })
+                    }));
+                }
+                json!({
+                    "time": bucket["key_as_string"],
+                    "count": count,
+                    "ratio_to_baseline": round(count as f64 / baseline.max(1.0)),
+                    "contributors": contributors,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let interval = format!("{}s", range.interval().as_secs());
+        let msg = if spikes.is_empty() {
+            format!(
+                "No log rate spike in the last {}: {total} logs, a median of {baseline} logs per {interval}.",
+                range.span
+            )
+        } else {
+            format!(
+                "Found {} log rate spikes in the last {} ({total} logs, a median of {baseline} logs per {interval}):",
+                spikes.len(),
+                range.span
+            )
+        };
+        let result = json!({
+            "interval": interval,
+            "baseline": baseline,
+            "spikes": spikes,
+            "timeline": timeline
+                .iter()
+                .zip(&counts)
+                .map(|(b, count)| json!({ "time": b["key_as_string"], "count": count }))
+                .collect::<Vec<_>>(),
+        });
+        Ok(CallToolResult::success(vec![
+            Content::text(msg),
+            Content::json(result)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: categorize logs
+    #[tool(
+        description = "Group log messages into patterns (categories of similar messages with their variable parts \
+            removed), with their count, services, levels and an example. Useful to see what is being logged and \
+            spot unusual messages. Runs on a sample of the logs",
+        annotations(title = "Categorize logs", read_only_hint = true)
+    )]
+    async fn categorize_logs(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(CategorizeLogsParams {
+            filter,
+            time_range,
+            limit,
+        }): Parameters<CategorizeLogsParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let range = TimeRange::parse(time_range.as_deref(), "15m")?;
+        let body = json!({
+            "size": 0,
+            "track_total_hits": true,
+            "query": filter.query(&range),
+            "aggs": {
+                "sample": {
+                    "sampler": { "shard_size": CATEGORIZE_SAMPLE_SIZE },
+                    "aggs": {
+                        "categories": {
+                            "categorize_text": {
+                                "field": "message",
+                                "size": limit.unwrap_or(20).min(MAX_CATEGORIES)
+                            },
+                            "aggs": {
+                                "services": { "terms": { "field": "service.name", "size": 3 } },
+                                "levels": { "terms": { "field": "log.level", "size": 3 } },
+                                "example": { "top_hits": { "size": 1, "_source": ["message"] } }
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        let response = self.search(req_ctx, body).await?;
+
+        let total = response
+            .pointer("/hits/total/value")
+            .and_then(Value::as_u64)
+            .unwrap_or_default();
+        let sample = &response["aggregations"]["sample"];
+        let sampled = sample["doc_count"].as_u64().unwrap_or_default();
+        let keys = |bucket: &Value, agg: &str| {
+            buckets(bucket, &format!("/{agg}/buckets"))
+                .map(|b| b["key"].clone())
+                .collect::<Vec<_>>()
+        };
+        let categories = buckets(sample, "/categories/buckets")
+            .map(|bucket| {
+                let count = bucket["doc_count"].as_u64().unwrap_or_default();
+                json!({
+                    "pattern": bucket["key"],
+                    "count": count,
+                    "percent": percent(count, sampled),
+                    "services": keys(bucket, "services"),
+                    "levels": keys(bucket, "levels"),
+                    "example": bucket
+                        .pointer("/example/hits/hits/0/_source/message")
+                        .and_then(Value::as_str)
+                        .map(truncate),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!(
+                "{total} logs in the last {}, {} categories in a sample of {sampled} logs:",
+                range.span,
+                categories.len()
+            )),
+            Content::json(categories)?,
+        ]))
+    }
+}
+
+fn percent(count: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    round(count as f64 * 100.0 / total as f64)
+}
+
+fn truncate(message: &str) -> String {
+    match message.char_indices().nth(MAX_MESSAGE_LEN) {
+        Some((pos, _)) => format!("{}...", &message[..pos]),
+        None => message.to_string(),
+    }
+}
+
+/// Find the buckets whose count is well above the baseline, the median count: more than twice the baseline, and
+/// more than 4 median absolute deviations above it. Returns the baseline and the indices of spikes.
+fn find_spikes(counts: &[u64]) -> (f64, Vec<usize>) {
+    fn median(values: &mut [f64]) -> f64 {
+        if values.is_empty() {
+            return 0.0;
+        }
+        values.sort_by(f64::total_cmp);
+        let mid = values.len() / 2;
+        if values.len() % 2 == 0 {
+            (values[mid - 1] + values[mid]) / 2.0
+        } else {
+            values[mid]
+        }
+    }
+
+    let baseline = median(&mut counts.iter().map(|c| *c as f64).collect::<Vec<_>>());
+    let deviation = median(&mut counts.iter().map(|c| (*c as f64 - baseline).abs()).collect::<Vec<_>>());
+    let threshold = (2.0 * baseline).max(baseline + 4.0 * deviation.max(1.0));
+
+    let spikes = counts
+        .iter()
+        .enumerate()
+        .filter(|(_, count)| **count >= MIN_SPIKE_COUNT && **count as f64 > threshold)
+        .map(|(i, _)| i)
+        .collect();
+    (baseline, spikes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spikes() {
+        let counts = [100, 110, 95, 105, 480, 100, 98, 250, 102];
+        assert_eq!(find_spikes(&counts), (102.0, vec![4, 7]));
+
+        // Small variations of low rates aren't spikes
+        assert_eq!(find_spikes(&[0, 1, 0, 0, 4, 0]), (0.0, vec![]));
+        assert_eq!(find_spikes(&[]), (0.0, vec![]));
+
+        assert_eq!(truncate("short"), "short");
+        assert_eq!(truncate(&"é".repeat(1200)).chars().count(), MAX_MESSAGE_LEN + 3);
+    }
+}
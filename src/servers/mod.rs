@@ -15,12 +15,52 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use crate::utils::matches_pattern;
+use futures::FutureExt;
+use rmcp::handler::server::router::tool::ToolRoute;
+use rmcp::handler::server::tool::{ToolCallContext, ToolRouter};
+use rmcp::model::CallToolRequestParam;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
+mod analytics;
+pub mod apm;
 pub mod elasticsearch;
+pub mod kibana;
+pub mod logs;
+pub mod security;
+
+/// Former names of renamed tools, as `(old name, new name)`. Deprecated names are still listed and can be
+/// called, and their description tells the LLM to use the new name. Remove them after a few releases.
+pub const DEPRECATED_TOOL_NAMES: &[(&str, &str)] = &[("esql", "query_esql")];
+
+/// The other name of a renamed tool, possibly prefixed with a cluster name: its new name if it's a former name,
+/// and its former name if it's a new name.
+fn other_tool_name(tool: &str) -> Option<String> {
+    let rename = |from: &str, to: &str| {
+        let prefix = tool.strip_suffix(from)?;
+        (prefix.is_empty() || prefix.ends_with('_')).then(|| format!("{prefix}{to}"))
+    };
+    // New names first, as they may end with the former name
+    DEPRECATED_TOOL_NAMES
+        .iter()
+        .find_map(|(old, new)| rename(new, old).or_else(|| rename(old, new)))
+}
+
+/// Does a tool name match a pattern of the configuration? Patterns written for the former name of a renamed tool
+/// also match its new name, and conversely, so that configurations keep applying to renamed tools.
+pub fn matches_tool(pattern: &str, tool: &str) -> bool {
+    matches_pattern(pattern, tool) || other_tool_name(tool).is_some_and(|other| matches_pattern(pattern, &other))
+}
+
+/// Is a tool name of the configuration the name of a tool, or its former or new name?
+fn is_same_tool(configured: &str, tool: &str) -> bool {
+    configured == tool || other_tool_name(tool).is_some_and(|other| configured == other)
+}
 
 /// Inclusion or exclusion list.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum IncludeExclude {
     Include(Vec<String>),
@@ -31,8 +71,8 @@ impl IncludeExclude {
     pub fn is_included(&self, name: &str) -> bool {
         use IncludeExclude::*;
         match self {
-            Include(includes) => includes.iter().map(|s| s.as_str()).any(|s| s == name),
-            Exclude(excludes) => excludes.iter().map(|s| s.as_str()).all(|s| s != name),
+            Include(includes) => includes.iter().any(|s| is_same_tool(s, name)),
+            Exclude(excludes) => excludes.iter().all(|s| !is_same_tool(s, name)),
         }
     }
 
@@ -40,3 +80,50 @@ impl IncludeExclude {
         tools.retain(|t| self.is_included(&t.name))
     }
 }
+
+/// Tools that are added to the tools of a cluster, with their own state.
+pub trait SubServer: Send + Sync + Sized + 'static {
+    fn router(&self) -> &ToolRouter<Self>;
+
+    /// Routes that call the tools of this server, to add them to the tools of another server.
+    fn routes<S: Send + Sync + 'static>(self) -> impl Iterator<Item = ToolRoute<S>> {
+        let server = Arc::new(self);
+        server.router().list_all().into_iter().map(move |attr| {
+            let server = server.clone();
+            ToolRoute::new_dyn(attr, move |context: ToolCallContext<'_, S>| {
+                let server = server.clone();
+                let request = CallToolRequestParam {
+                    name: context.name.clone(),
+                    arguments: context.arguments,
+                };
+                let request_context = context.request_context;
+                async move {
+                    let context = ToolCallContext::new(server.as_ref(), request, request_context);
+                    server.router().call(context).await
+                }
+                .boxed()
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renamed_tools() {
+        assert_eq!(other_tool_name("esql").as_deref(), Some("query_esql"));
+        assert_eq!(other_tool_name("prod_query_esql").as_deref(), Some("prod_esql"));
+        assert_eq!(other_tool_name("search"), None);
+
+        assert!(matches_tool("esql", "query_esql"));
+        assert!(matches_tool("query_esql", "esql"));
+        assert!(matches_tool("prod_*", "prod_query_esql"));
+        assert!(!matches_tool("esql", "search"));
+
+        let include = IncludeExclude::Include(vec!["esql".to_string()]);
+        assert!(include.is_included("query_esql"));
+        assert!(!include.is_included("validate_esql"));
+    }
+}
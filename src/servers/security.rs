@@ -0,0 +1,488 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Tools to triage the alerts of Elastic Security detection rules: list open alerts, fetch the events that
+//! triggered them, and update their workflow status.
+
+use crate::servers::SubServer;
+use crate::servers::analytics::{TimeRange, buckets, source_field};
+use crate::servers::elasticsearch::index_policy::IndexPolicy;
+use crate::servers::elasticsearch::writes::{self, PlannedCall};
+use crate::servers::elasticsearch::{EsClientProvider, read_json, send};
+use elasticsearch::params::Conflicts;
+use elasticsearch::{Elasticsearch, SearchParts, UpdateByQueryParts};
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::{Parameters, ToolRouter};
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+/// Maximum number of alerts or events returned, and of alerts updated at once.
+const MAX_ALERTS: usize = 100;
+
+/// Fields of alerts that are summarized.
+const ALERT_FIELDS: [&str; 10] = [
+    "@timestamp",
+    "kibana.alert.rule.name",
+    "kibana.alert.severity",
+    "kibana.alert.risk_score",
+    "kibana.alert.workflow_status",
+    "kibana.alert.reason",
+    "kibana.alert.rule.threat",
+    "host.name",
+    "user.name",
+    "kibana.alert.ancestors",
+];
+
+/// Detection alerts of a cluster.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SecurityConfig {
+    /// Detection alert indices (default: `.alerts-security.alerts-*`)
+    #[serde(default = "default_alerts")]
+    pub alerts: String,
+}
+
+fn default_alerts() -> String {
+    ".alerts-security.alerts-*".to_string()
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        SecurityConfig {
+            alerts: default_alerts(),
+        }
+    }
+}
+
+impl SecurityConfig {
+    /// Check that the detection alerts can be accessed by tools.
+    pub fn check(&self, index_policy: &IndexPolicy) -> anyhow::Result<()> {
+        index_policy
+            .check_expression(&self.alerts)
+            .map_err(|reason| anyhow::anyhow!("Detection alerts are denied by the index policy: {reason}"))
+    }
+}
+
+#[derive(Clone)]
+pub struct SecurityTools {
+    es_client: EsClientProvider,
+    config: SecurityConfig,
+    /// Indices of the source events that can be read
+    index_policy: IndexPolicy,
+    dry_run: bool,
+    tool_router: ToolRouter<SecurityTools>,
+}
+
+impl SecurityTools {
+    pub fn new(es_client: Elasticsearch, config: SecurityConfig) -> Self {
+        SecurityTools {
+            es_client: EsClientProvider::new(es_client),
+            config,
+            index_policy: IndexPolicy::default(),
+            dry_run: false,
+            tool_router: Self::tool_router(),
+        }
+    }
+
+    /// Set the indices of source events that can be read.
+    pub fn with_index_policy(mut self, index_policy: IndexPolicy) -> Self {
+        self.index_policy = index_policy;
+        self
+    }
+
+    /// Describe the API calls of write tools instead of making them, for all calls.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    async fn search(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        indices: &[&str],
+        body: Value,
+    ) -> Result<Value, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+        let response = send!(es_client.search(SearchParts::Index(indices)).body(body)).await;
+        read_json(response).await
+    }
+}
+
+impl SubServer for SecurityTools {
+    fn router(&self) -> &ToolRouter<Self> {
+        &self.tool_router
+    }
+}
+
+/// Triage state of an alert.
+#[derive(Debug, Default, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum WorkflowStatus {
+    #[default]
+    Open,
+    Acknowledged,
+    Closed,
+}
+
+impl WorkflowStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            WorkflowStatus::Open => "open",
+            WorkflowStatus::Acknowledged => "acknowledged",
+            WorkflowStatus::Closed => "closed",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ListDetectionAlertsParams {
+    /// Workflow status of the alerts (default: `open`)
+    #[serde(default)]
+    status: WorkflowStatus,
+
+    /// Only alerts of this severity: `low`, `medium`, `high` or `critical`
+    severity: Option<String>,
+
+    /// Only alerts of this detection rule, by name
+    rule: Option<String>,
+
+    /// Only alerts on this host
+    host: Option<String>,
+
+    /// Only alerts on this user
+    user: Option<String>,
+
+    /// Time range to look at, back from now, like `1h`, `24h` or `7d` (default: `24h`)
+    time_range: Option<String>,
+
+    /// Maximum number of alerts, at most 100 (default: 20)
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct GetAlertEventsParams {
+    /// Alert id, as returned by list_detection_alerts
+    id: String,
+
+    /// Maximum number of events, at most 100 (default: 10)
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct UpdateAlertStatusParams {
+    /// Ids of the alerts, as returned by list_detection_alerts (at most 100)
+    ids: Vec<String>,
+
+    /// New workflow status
+    status: WorkflowStatus,
+
+    /// Describe the API calls that would be made, without making them (default: false)
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[tool_router]
+impl SecurityTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: list detection alerts
+    #[tool(
+        description = "List the alerts of Elastic Security detection rules, riskiest first, with their rule, \
+            severity, reason, host and user, and the number of alerts per severity and rule. Lists open alerts \
+            by default",
+        annotations(title = "List detection alerts", read_only_hint = true)
+    )]
+    async fn list_detection_alerts(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<ListDetectionAlertsParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let range = TimeRange::parse(params.time_range.as_deref(), "24h")?;
+        let mut filters = vec![
+            range.filter(),
+            json!({ "term": { "kibana.alert.workflow_status": params.status.as_str() } }),
+        ];
+        let terms = [
+            ("kibana.alert.severity", &params.severity),
+            ("kibana.alert.rule.name", &params.rule),
+            ("host.name", &params.host),
+            ("user.name", &params.user),
+        ];
+        for (field, value) in terms {
+            if let Some(value) = value {
+                filters.push(json!({ "term": { field: value } }));
+            }
+        }
+
+        let body = json!({
+            "size": params.limit.unwrap_or(20).min(MAX_ALERTS),
+            "track_total_hits": true,
+            "query": {
+                "bool": {
+                    "filter": filters,
+                    // Building block alerts are only inputs of other rules
+                    "must_not": { "exists": { "field": "kibana.alert.building_block_type" } }
+                }
+            },
+            "sort": [{ "kibana.alert.risk_score": "desc" }, { "@timestamp": "desc" }],
+            "_source": ALERT_FIELDS,
+            "aggs": {
+                "severities": { "terms": { "field": "kibana.alert.severity", "size": 4 } },
+                "rules": { "terms": { "field": "kibana.alert.rule.name", "size": 10 } }
+            }
+        });
+        let response = self.search(req_ctx, &[self.config.alerts.as_str()], body).await?;
+
+        let total = response
+            .pointer("/hits/total/value")
+            .and_then(Value::as_u64)
+            .unwrap_or_default();
+        let counts = |agg: &str| {
+            buckets(&response, &format!("/aggregations/{agg}/buckets"))
+                .map(|b| {
+                    (
+                        b["key"].as_str().unwrap_or_default().to_string(),
+                        b["doc_count"].clone(),
+                    )
+                })
+                .collect::<serde_json::Map<_, _>>()
+        };
+        let alerts = buckets(&response, "/hits/hits").map(alert_summary).collect::<Vec<_>>();
+        let msg = format!(
+            "{total} {} alerts in the last {}, showing {}:",
+            params.status.as_str(),
+            range.span,
+            alerts.len()
+        );
+        let result = json!({
+            "by_severity": counts("severities"),
+            "by_rule": counts("rules"),
+            "alerts": alerts,
+        });
+
+        Ok(CallToolResult::success(vec![
+            Content::text(msg),
+            Content::json(result)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get the source events of an alert
+    #[tool(
+        description = "Get a detection alert and the source events that triggered it, e.g. the process, network \
+            or authentication events, with all their fields",
+        annotations(title = "Get the source events of a detection alert", read_only_hint = true)
+    )]
+    async fn get_alert_events(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(GetAlertEventsParams { id, limit }): Parameters<GetAlertEventsParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let body = json!({ "size": 1, "query": { "ids": { "values": [&id] } }, "_source": ALERT_FIELDS });
+        let response = self
+            .search(req_ctx.clone(), &[self.config.alerts.as_str()], body)
+            .await?;
+        let Some(alert) = buckets(&response, "/hits/hits").next() else {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Alert '{id}' not found. Use list_detection_alerts to find alert ids."
+            ))]));
+        };
+
+        // Alerts on sequences or other alerts have several ancestors: only keep the original events
+        let ancestors = source_field(&alert["_source"], "kibana.alert.ancestors")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter(|a| a["type"] == "event")
+            .filter_map(|a| Some((a["index"].as_str()?, a["id"].as_str()?)))
+            .collect::<Vec<_>>();
+        let (allowed, denied): (Vec<_>, Vec<_>) = ancestors
+            .iter()
+            .partition(|(index, _)| self.index_policy.is_allowed(index));
+
+        let mut indices = allowed.iter().map(|(index, _)| *index).collect::<Vec<_>>();
+        indices.sort();
+        indices.dedup();
+        let events = if indices.is_empty() {
+            Vec::new()
+        } else {
+            let body = json!({
+                "size": limit.unwrap_or(10).min(MAX_ALERTS),
+                "query": { "ids": { "values": allowed.iter().map(|(_, id)| id).collect::<Vec<_>>() } },
+                "sort": [{ "@timestamp": "asc" }]
+            });
+            let response = self.search(req_ctx, &indices, body).await?;
+            buckets(&response, "/hits/hits")
+                .map(|hit| json!({ "index": hit["_index"], "id": hit["_id"], "event": hit["_source"] }))
+                .collect()
+        };
+
+        let mut msg = format!("Alert '{id}' was triggered by {} events", ancestors.len());
+        if !denied.is_empty() {
+            msg.push_str(&format!(
+                ", {} of them in indices denied by the index policy",
+                denied.len()
+            ));
+        }
+        if ancestors.is_empty() {
+            msg.push_str(". Alerts of threshold and machine learning rules have no source events");
+        }
+        msg.push(':');
+
+        Ok(CallToolResult::success(vec![
+            Content::text(msg),
+            Content::json(json!({ "alert": alert_summary(alert), "events": events }))?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: update the workflow status of alerts
+    #[tool(
+        description = "Update the workflow status of detection alerts to open, acknowledged or closed, e.g. to \
+            close false positives or acknowledge alerts under investigation",
+        annotations(
+            title = "Update the status of detection alerts",
+            read_only_hint = false,
+            destructive_hint = false,
+            idempotent_hint = true
+        )
+    )]
+    async fn update_alert_status(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(UpdateAlertStatusParams { ids, status, dry_run }): Parameters<UpdateAlertStatusParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        if ids.is_empty() || ids.len() > MAX_ALERTS {
+            return Err(rmcp::Error::invalid_params(
+                format!("Between 1 and {MAX_ALERTS} alert ids are expected"),
+                None,
+            ));
+        }
+        let status = status.as_str();
+        let body = json!({
+            "query": { "ids": { "values": ids } },
+            "script": {
+                "lang": "painless",
+                "source": "ctx._source['kibana.alert.workflow_status'] = params.status; \
+                    ctx._source['kibana.alert.workflow_status_updated_at'] = params.now;",
+                "params": { "status": status, "now": chrono::Utc::now().to_rfc3339() }
+            }
+        });
+
+        if self.dry_run || dry_run {
+            return writes::dry_run_result(
+                format!("{} alerts would be {status}.", ids.len()),
+                vec![
+                    PlannedCall::new(
+                        "POST",
+                        format!(
+                            "/{}/_update_by_query?conflicts=proceed&refresh=true",
+                            self.config.alerts
+                        ),
+                    )
+                    .with_body(body),
+                ],
+            );
+        }
+
+        let es_client = self.es_client.get(req_ctx);
+        let response = send!(
+            es_client
+                .update_by_query(UpdateByQueryParts::Index(&[self.config.alerts.as_str()]))
+                .conflicts(Conflicts::Proceed)
+                .refresh(true)
+                .body(body)
+        )
+        .await;
+        let response: Value = read_json(response).await?;
+
+        let updated = response["updated"].as_u64().unwrap_or_default();
+        let mut msg = format!("{updated} alerts set to {status}.");
+        if let Some(failures) = response["failures"].as_array().filter(|f| !f.is_empty()) {
+            msg.push_str(&format!(" {} alerts failed to update.", failures.len()));
+        }
+        let missing = ids.len() as u64 - response["total"].as_u64().unwrap_or_default().min(ids.len() as u64);
+        if missing > 0 {
+            msg.push_str(&format!(" {missing} alerts were not found."));
+        }
+        Ok(CallToolResult::success(vec![Content::text(msg)]))
+    }
+}
+
+/// Summary of an alert hit.
+fn alert_summary(hit: &Value) -> Value {
+    let alert = &hit["_source"];
+    let tactics = source_field(alert, "kibana.alert.rule.threat")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|threat| threat.pointer("/tactic/name"))
+        .collect::<Vec<_>>();
+    json!({
+        "id": hit["_id"],
+        "time": alert["@timestamp"],
+        "rule": source_field(alert, "kibana.alert.rule.name"),
+        "severity": source_field(alert, "kibana.alert.severity"),
+        "risk_score": source_field(alert, "kibana.alert.risk_score"),
+        "status": source_field(alert, "kibana.alert.workflow_status"),
+        "reason": source_field(alert, "kibana.alert.reason"),
+        "tactics": tactics,
+        "host": source_field(alert, "host.name"),
+        "user": source_field(alert, "user.name"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarize_alerts() {
+        let hit = json!({
+            "_id": "a1",
+            "_source": {
+                "@timestamp": "2026-10-16T08:00:00.000Z",
+                "kibana.alert.rule.name": "Suspicious PowerShell",
+                "kibana.alert.severity": "high",
+                "kibana.alert.risk_score": 73,
+                "kibana.alert.workflow_status": "open",
+                "kibana.alert.reason": "process event with process powershell.exe",
+                "kibana.alert.rule.threat": [
+                    { "framework": "MITRE ATT&CK", "tactic": { "id": "TA0002", "name": "Execution" } }
+                ],
+                "host": { "name": "ws-12" }
+            }
+        });
+        assert_eq!(
+            alert_summary(&hit),
+            json!({
+                "id": "a1",
+                "time": "2026-10-16T08:00:00.000Z",
+                "rule": "Suspicious PowerShell",
+                "severity": "high",
+                "risk_score": 73,
+                "status": "open",
+                "reason": "process event with process powershell.exe",
+                "tactics": ["Execution"],
+                "host": "ws-12",
+                "user": null
+            })
+        );
+    }
+}
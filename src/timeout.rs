@@ -0,0 +1,161 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Timeout and cancellation of requests.
+
+use crate::servers::matches_tool;
+use indexmap::IndexMap;
+use rmcp::model::{ClientNotification, ClientRequest, ServerInfo, ServerResult};
+use rmcp::service::{NotificationContext, RequestContext};
+use rmcp::{RoleServer, Service};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Tool call timeouts in seconds, keyed by tool name pattern. A pattern is a tool name, possibly with `*`
+/// wildcards. The first pattern matching a tool name defines its timeout.
+pub type ToolTimeouts = IndexMap<String, u64>;
+
+/// A server that stops processing requests when they are cancelled by the client, or when a tool call
+/// exceeds its timeout.
+///
+/// Stopping a request drops the pending Elasticsearch requests, which closes their HTTP connection.
+/// Elasticsearch then cancels the corresponding search and ES|QL tasks.
+#[derive(Clone)]
+pub struct TimeoutServer<S> {
+    inner: S,
+    timeouts: Arc<ToolTimeouts>,
+}
+
+impl<S: Service<RoleServer>> TimeoutServer<S> {
+    pub fn new(inner: S, timeouts: ToolTimeouts) -> Self {
+        TimeoutServer {
+            inner,
+            timeouts: Arc::new(timeouts),
+        }
+    }
+
+    fn timeout(&self, tool: &str) -> Option<Duration> {
+        self.timeouts
+            .iter()
+            .find(|(pattern, _)| matches_tool(pattern, tool))
+            .map(|(_, secs)| Duration::from_secs(*secs))
+    }
+}
+
+impl<S: Service<RoleServer>> Service<RoleServer> for TimeoutServer<S> {
+    async fn handle_request(
+        &self,
+        request: ClientRequest,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ServerResult, rmcp::Error> {
+        let timeout = match &request {
+            ClientRequest::CallToolRequest(call) => {
+                self.timeout(&call.params.name).map(|t| (call.params.name.clone(), t))
+            }
+            _ => None,
+        };
+        let ct = context.ct.clone();
+        let sleep = async {
+            match &timeout {
+                Some((_, duration)) => tokio::time::sleep(*duration).await,
+                None => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            result = self.inner.handle_request(request, context) => result,
+            _ = ct.cancelled() => {
+                Err(rmcp::Error::internal_error("Request cancelled", None))
+            },
+            _ = sleep => {
+                let (tool, duration) = timeout.unwrap_or_default();
+                Err(rmcp::Error::internal_error(
+                    format!("Tool '{tool}' timed out after {} seconds", duration.as_secs()),
+                    None,
+                ))
+            },
+        }
+    }
+
+    async fn handle_notification(
+        &self,
+        notification: ClientNotification,
+        context: NotificationContext<RoleServer>,
+    ) -> Result<(), rmcp::Error> {
+        self.inner.handle_notification(notification, context).await
+    }
+
+    fn get_info(&self) -> ServerInfo {
+        self.inner.get_info()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::ServiceExt;
+    use rmcp::model::{CallToolRequestParam, CallToolResult, ServerCapabilities};
+
+    #[derive(Clone)]
+    struct SlowServer;
+
+    impl rmcp::ServerHandler for SlowServer {
+        async fn call_tool(
+            &self,
+            _request: CallToolRequestParam,
+            _context: RequestContext<RoleServer>,
+        ) -> Result<CallToolResult, rmcp::Error> {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok(CallToolResult::success(vec![]))
+        }
+
+        fn get_info(&self) -> ServerInfo {
+            ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_tools().build(),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn tool_call_timeout() -> anyhow::Result<()> {
+        let timeouts = ToolTimeouts::from([("slow_*".to_string(), 1)]);
+        let server = TimeoutServer::new(SlowServer, timeouts);
+        assert_eq!(server.timeout("slow_search"), Some(Duration::from_secs(1)));
+        assert_eq!(server.timeout("search"), None);
+
+        let (server_io, client_io) = tokio::io::duplex(4096);
+        tokio::spawn(async move {
+            if let Ok(service) = server.serve(server_io).await {
+                let _ = service.waiting().await;
+            }
+        });
+
+        let client = ().serve(client_io).await?;
+        let err = client
+            .call_tool(CallToolRequestParam {
+                name: "slow_search".into(),
+                arguments: None,
+            })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("timed out after 1 seconds"), "{err}");
+        client.cancel().await?;
+
+        Ok(())
+    }
+}
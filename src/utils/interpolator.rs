@@ -15,7 +15,8 @@
 // specific language governing permissions and limitations
 // under the License.
 
-//! Simple string interpolator to inject environment variables in the configuration file.
+//! Simple string interpolator to inject environment variables and secret files in the configuration file.
+use std::path::Path;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -26,8 +27,39 @@ pub struct InterpolationError {
     pub char: usize,
 }
 
-pub fn interpolate_from_env(s: String) -> Result<String, InterpolationError> {
-    interpolate(s, |name| std::env::var(name).ok())
+/// Interpolate environment variables and secret files. Relative paths of files are resolved from `dir`, the
+/// directory of the configuration file, and their content is escaped to be used in JSON strings.
+pub fn interpolate_from_env(s: String, dir: Option<&Path>) -> Result<String, InterpolationError> {
+    interpolate(s, |name| match name.strip_prefix(FILE_PREFIX) {
+        Some(path) => {
+            let path = match dir {
+                Some(dir) => dir.join(path),
+                None => path.into(),
+            };
+            read_secret(&path).map(|secret| json_string_content(&secret))
+        }
+        None => std::env::var(name).ok(),
+    })
+}
+
+/// Prefix of references to files, e.g. `${file:/run/secrets/es_api_key}`
+const FILE_PREFIX: &str = "file:";
+
+/// Read a secret file, such as a Docker or Kubernetes secret mount. The trailing newline is removed.
+fn read_secret(path: &Path) -> Option<String> {
+    match std::fs::read_to_string(path) {
+        Ok(secret) => Some(secret.trim_end_matches(['\n', '\r']).to_string()),
+        Err(err) => {
+            tracing::error!("Cannot read secret file '{}': {err}", path.display());
+            None
+        }
+    }
+}
+
+/// A string escaped as the content of a JSON string, without the surrounding quotes.
+fn json_string_content(s: &str) -> String {
+    let quoted = serde_json::to_string(s).unwrap_or_default();
+    quoted[1..quoted.len() - 1].to_string()
 }
 
 const OPEN: &str = "${";
@@ -35,7 +67,8 @@ const OPEN_LEN: usize = OPEN.len();
 const CLOSE: &str = "}";
 const CLOSE_LEN: usize = CLOSE.len();
 
-/// Simple string interpolation using the `${name}` and `${name:default_value}` syntax.
+/// Simple string interpolation using the `${name}` and `${name:default_value}` syntax. `${file:path}` looks up
+/// `file:path`, and has no default value.
 pub fn interpolate(s: String, lookup: impl Fn(&str) -> Option<String>) -> Result<String, InterpolationError> {
     if !s.contains(OPEN) {
         return Ok(s);
@@ -64,7 +97,9 @@ pub fn interpolate(s: String, lookup: impl Fn(&str) -> Option<String>) -> Result
 
             if let Some(pos) = line.find(CLOSE) {
                 let expr = &line[..pos];
-                let value = if let Some((name, default)) = expr.split_once(':') {
+                let value = if expr.starts_with(FILE_PREFIX) {
+                    lookup(expr).ok_or_else(|| err(char_no, format!("cannot read '{expr}'")))?
+                } else if let Some((name, default)) = expr.split_once(':') {
                     lookup(name).unwrap_or(default.to_string())
                 } else {
                     lookup(expr).ok_or_else(|| err(char_no, format!("env variable '{expr}' not defined")))?
@@ -91,6 +126,7 @@ mod tests {
         let lookup = |s: &str| match s {
             "foo" => Some("foo_value".to_string()),
             "bar" => Some("bar_value".to_string()),
+            "file:/run/secrets/key" => Some("secret".to_string()),
             _ => None,
         };
 
@@ -104,6 +140,8 @@ mod tests {
         assert_eq!("foo_value01234\n1234bar_value", expand("${foo}01234\n1234${bar}")?);
         assert_eq!("foo_value01234bar_value", expand("${foo}01234${bar}")?);
         assert_eq!("_01_foo_value01234bar_value567", expand("_01_${foo}01234${bar}567")?);
+        assert_eq!("default", expand("${baz:default}")?);
+        assert_eq!("key: secret", expand("key: ${file:/run/secrets/key}")?);
         Ok(())
     }
 
@@ -112,5 +150,21 @@ mod tests {
         assert!(expand("${foo01234").is_err());
         assert!(expand("${foo}01234${bar").is_err());
         assert!(expand("${baz}01234").is_err());
+        assert!(expand("${file:/run/secrets/other}").is_err());
+    }
+
+    #[test]
+    fn secret_files() -> anyhow::Result<()> {
+        let name = format!("interpolator-secret-{}", std::process::id());
+        let path = std::env::temp_dir().join(&name);
+        std::fs::write(&path, "s3cr3t\"\\\n")?;
+        let absolute = interpolate_from_env(format!("${{file:{}}}", path.display()), None);
+        // Relative to the directory of the configuration file
+        let relative = interpolate_from_env(format!("${{file:{name}}}"), Some(&std::env::temp_dir()));
+        std::fs::remove_file(&path)?;
+        // Escaped for JSON strings
+        assert_eq!(absolute?, r#"s3cr3t\"\\"#);
+        assert_eq!(relative?, r#"s3cr3t\"\\"#);
+        Ok(())
     }
 }
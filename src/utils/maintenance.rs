@@ -0,0 +1,172 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Background maintenance: periodic tasks such as configuration reloads, idle session cleanup or closing
+//! PITs of ended sessions.
+//!
+//! All tasks are run by a [`Scheduler`] that adds jitter to their interval, so that tasks created at the same
+//! time don't all run at once, stops them on shutdown, and keeps metrics for each kind of task.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::hash::{BuildHasher, RandomState};
+use std::ops::ControlFlow;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// Maximum variation of task intervals, as a fraction of the interval.
+const JITTER: f64 = 0.1;
+
+/// Metrics of the tasks that have the same name.
+#[derive(Debug, Default, Clone, Serialize, PartialEq)]
+pub struct TaskMetrics {
+    /// Number of running tasks
+    pub active: usize,
+    pub runs: u64,
+    pub failures: u64,
+    /// Duration of the last run, in milliseconds
+    pub last_duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// Runs periodic tasks until they stop or the scheduler is shut down.
+#[derive(Clone, Default)]
+pub struct Scheduler {
+    ct: CancellationToken,
+    metrics: Arc<Mutex<BTreeMap<&'static str, TaskMetrics>>>,
+}
+
+impl Scheduler {
+    /// The scheduler of the process.
+    pub fn global() -> &'static Scheduler {
+        static GLOBAL: OnceLock<Scheduler> = OnceLock::new();
+        GLOBAL.get_or_init(Scheduler::default)
+    }
+
+    /// Run `task` every `interval` (with jitter), until it returns `ControlFlow::Break`. Errors are logged and
+    /// counted in the metrics, and don't stop the task.
+    ///
+    /// Several tasks can have the same name, e.g. one per session, and share their metrics.
+    pub fn spawn<F, Fut>(&self, name: &'static str, interval: Duration, mut task: F)
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = anyhow::Result<ControlFlow<()>>> + Send,
+    {
+        let ct = self.ct.clone();
+        let metrics = self.metrics.clone();
+        metrics.lock().unwrap().entry(name).or_default().active += 1;
+
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(jittered(interval)) => {},
+                    _ = ct.cancelled() => break,
+                }
+
+                let start = Instant::now();
+                let result = task().await;
+
+                let mut by_name = metrics.lock().unwrap();
+                let task_metrics = by_name.entry(name).or_default();
+                task_metrics.runs += 1;
+                task_metrics.last_duration_ms = Some(start.elapsed().as_millis() as u64);
+                match result {
+                    Ok(ControlFlow::Continue(())) => {}
+                    Ok(ControlFlow::Break(())) => break,
+                    Err(err) => {
+                        tracing::warn!(task = name, "Maintenance task failed: {err:#}");
+                        task_metrics.failures += 1;
+                        task_metrics.last_error = Some(format!("{err:#}"));
+                    }
+                }
+            }
+            metrics.lock().unwrap().entry(name).or_default().active -= 1;
+        });
+    }
+
+    /// Metrics of the tasks, by name.
+    pub fn metrics(&self) -> BTreeMap<&'static str, TaskMetrics> {
+        self.metrics.lock().unwrap().clone()
+    }
+
+    /// Stop all tasks. Runs in progress are completed.
+    pub fn shutdown(&self) {
+        self.ct.cancel();
+    }
+}
+
+/// A random duration within [`JITTER`] of `interval`.
+fn jittered(interval: Duration) -> Duration {
+    // A new RandomState has random keys, which is good enough to spread tasks over time
+    let random = RandomState::new().hash_one(Instant::now()) as f64 / u64::MAX as f64;
+    interval.mul_f64(1.0 + JITTER * (2.0 * random - 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn jitter_bounds() {
+        let interval = Duration::from_secs(10);
+        for _ in 0..100 {
+            let jittered = jittered(interval);
+            assert!(jittered >= Duration::from_secs(9) && jittered <= Duration::from_secs(11));
+        }
+    }
+
+    #[tokio::test]
+    async fn run_until_done() {
+        let scheduler = Scheduler::default();
+        let count = Arc::new(AtomicU32::new(0));
+        {
+            let count = count.clone();
+            scheduler.spawn("count", Duration::from_millis(5), move || {
+                let n = count.fetch_add(1, Ordering::Relaxed) + 1;
+                async move {
+                    match n {
+                        2 => anyhow::bail!("second run"),
+                        3 => Ok(ControlFlow::Break(())),
+                        _ => Ok(ControlFlow::Continue(())),
+                    }
+                }
+            });
+        }
+        assert_eq!(scheduler.metrics()["count"].active, 1);
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(count.load(Ordering::Relaxed), 3);
+        let metrics = &scheduler.metrics()["count"];
+        assert_eq!((metrics.active, metrics.runs, metrics.failures), (0, 3, 1));
+        assert_eq!(metrics.last_error.as_deref(), Some("second run"));
+    }
+
+    #[tokio::test]
+    async fn stop_on_shutdown() {
+        let scheduler = Scheduler::default();
+        scheduler.spawn("forever", Duration::from_secs(3600), || async {
+            Ok(ControlFlow::Continue(()))
+        });
+        scheduler.shutdown();
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(scheduler.metrics()["forever"], TaskMetrics::default());
+    }
+}
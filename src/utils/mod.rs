@@ -15,10 +15,16 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use schemars::JsonSchema;
+use schemars::r#gen::SchemaGenerator;
+use schemars::schema::Schema;
 use serde::{Deserialize, Deserializer};
 
 pub mod interpolator;
+pub mod maintenance;
+pub mod resource_store;
 pub mod rmcp_ext;
+pub mod systemd;
 
 /// Deserialize a string, and return `None` if it's empty. Useful for configuration fields like
 /// `"foo": "${SOME_ENV_VAR:}"` that uses an env var if present without failing if missing.
@@ -29,3 +35,81 @@ pub fn none_if_empty_string<'de, D: Deserializer<'de>>(deserializer: D) -> Resul
         _ => Ok(s),
     }
 }
+
+/// Deserialize a list of strings, or a comma-separated string like `"${SOME_ENV_VAR:}"`. Empty items are
+/// removed.
+pub fn vec_from_string_or_vec<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrVec {
+        String(String),
+        Vec(Vec<String>),
+    }
+    let items = match StringOrVec::deserialize(deserializer)? {
+        StringOrVec::String(s) => s.split(',').map(|s| s.trim().to_string()).collect(),
+        StringOrVec::Vec(v) => v,
+    };
+    Ok(items.into_iter().filter(|s| !s.is_empty()).collect())
+}
+
+/// JSON schema of fields deserialized with [`vec_from_string_or_vec`].
+pub fn string_or_vec_schema(generator: &mut SchemaGenerator) -> Schema {
+    #[derive(JsonSchema)]
+    #[serde(untagged)]
+    #[allow(dead_code)]
+    enum StringOrVec {
+        String(String),
+        Vec(Vec<String>),
+    }
+    StringOrVec::json_schema(generator)
+}
+
+/// JSON schema of booleans that can also be strings, like `"${SOME_ENV_VAR:false}"`.
+pub fn bool_or_string_schema(generator: &mut SchemaGenerator) -> Schema {
+    #[derive(JsonSchema)]
+    #[serde(untagged)]
+    #[allow(dead_code)]
+    enum BoolOrString {
+        Bool(bool),
+        String(String),
+    }
+    BoolOrString::json_schema(generator)
+}
+
+/// Does a name match a pattern where `*` matches any sequence of characters?
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts = parts.peekable();
+    while let Some(part) = parts.next() {
+        if parts.peek().is_none() {
+            // Last part must be a suffix
+            return rest.len() >= part.len() && rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    // No wildcard: exact match
+    rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_patterns() {
+        assert!(matches_pattern("esql", "esql"));
+        assert!(!matches_pattern("esql", "esql_validate"));
+        assert!(matches_pattern("*", "search"));
+        assert!(matches_pattern("prod_*", "prod_search"));
+        assert!(matches_pattern("*_search", "prod_search"));
+        assert!(matches_pattern("prod_*_x", "prod_search_x"));
+        assert!(!matches_pattern("prod_*_x", "prod_x"));
+    }
+}
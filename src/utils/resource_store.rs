@@ -0,0 +1,211 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Ephemeral MCP resources created by tools.
+
+use crate::authorization::Identity;
+use crate::utils::rmcp_ext::{random_id, session_id};
+use http::request::Parts;
+use indexmap::IndexMap;
+use rmcp::RoleServer;
+use rmcp::model::{AnnotateAble, RawResource, Resource, ResourceContents};
+use rmcp::service::RequestContext;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Scheme of the resource URIs.
+pub const URI_SCHEME: &str = "elasticsearch-mcp";
+
+/// The client that created a resource. Resources can only be listed and read by the client that created them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Owner {
+    /// Is it a client of the http server? Other clients, e.g. with stdio, are the only client of their server.
+    http: bool,
+    /// Transport session, see [`session_id`]
+    session: Option<String>,
+    /// Identity of named http clients
+    identity: Option<String>,
+}
+
+impl Owner {
+    /// The client of a request.
+    pub fn of(context: &RequestContext<RoleServer>) -> Self {
+        Owner {
+            http: context.extensions.get::<Parts>().is_some(),
+            session: session_id(context).map(str::to_string),
+            identity: Identity::of(context).map(str::to_string),
+        }
+    }
+
+    /// Anonymous clients of stateless http servers can't be told apart: they can read the resources whose URI
+    /// they were given, but can't list them.
+    fn can_list(&self) -> bool {
+        !self.http || self.session.is_some() || self.identity.is_some()
+    }
+}
+
+struct StoredResource {
+    owner: Owner,
+    name: String,
+    mime_type: &'static str,
+    text: String,
+    created: Instant,
+}
+
+/// A store of resources that tools create to keep their results short, such as the full details of an
+/// error. Resources expire after a time to live, and the oldest ones are removed when the store is full.
+///
+/// URIs are random, so that they can't be guessed by other clients, and resources are only visible to the client
+/// that created them (see [`Owner`]).
+#[derive(Clone)]
+pub struct ResourceStore {
+    resources: Arc<Mutex<IndexMap<String, StoredResource>>>,
+    ttl: Duration,
+    capacity: usize,
+}
+
+impl ResourceStore {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        ResourceStore {
+            resources: Default::default(),
+            ttl,
+            capacity,
+        }
+    }
+
+    /// Add a resource and return its URI, e.g. `elasticsearch-mcp://errors/4f2a...`.
+    pub fn add(
+        &self,
+        owner: &Owner,
+        kind: &str,
+        name: impl Into<String>,
+        mime_type: &'static str,
+        text: String,
+    ) -> String {
+        let uri = format!("{URI_SCHEME}://{kind}/{}", random_id());
+
+        let mut resources = self.resources.lock().unwrap();
+        self.expire(&mut resources);
+        while resources.len() >= self.capacity {
+            resources.shift_remove_index(0);
+        }
+        resources.insert(
+            uri.clone(),
+            StoredResource {
+                owner: owner.clone(),
+                name: name.into(),
+                mime_type,
+                text,
+                created: Instant::now(),
+            },
+        );
+        uri
+    }
+
+    /// The resources of a client.
+    pub fn list(&self, owner: &Owner) -> Vec<Resource> {
+        if !owner.can_list() {
+            return Vec::new();
+        }
+        let mut resources = self.resources.lock().unwrap();
+        self.expire(&mut resources);
+        resources
+            .iter()
+            .filter(|(_, r)| &r.owner == owner)
+            .map(|(uri, r)| {
+                let mut resource = RawResource::new(uri, &r.name);
+                resource.mime_type = Some(r.mime_type.to_string());
+                resource.size = Some(r.text.len() as u32);
+                resource.no_annotation()
+            })
+            .collect()
+    }
+
+    /// Read a resource, if it was created by the same client.
+    pub fn read(&self, owner: &Owner, uri: &str) -> Option<ResourceContents> {
+        let mut resources = self.resources.lock().unwrap();
+        self.expire(&mut resources);
+        resources
+            .get(uri)
+            .filter(|r| &r.owner == owner)
+            .map(|r| ResourceContents::TextResourceContents {
+                uri: uri.to_string(),
+                mime_type: Some(r.mime_type.to_string()),
+                text: r.text.clone(),
+            })
+    }
+
+    fn expire(&self, resources: &mut IndexMap<String, StoredResource>) {
+        resources.retain(|_, r| r.created.elapsed() < self.ttl);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_and_read() {
+        let owner = Owner::default();
+        let store = ResourceStore::new(Duration::from_secs(60), 2);
+        let uri1 = store.add(&owner, "errors", "Error 1", "application/json", "{}".to_string());
+        let uri2 = store.add(&owner, "errors", "Error 2", "application/json", "[]".to_string());
+        assert!(uri1.starts_with("elasticsearch-mcp://errors/"));
+        assert_ne!(uri1, uri2);
+
+        assert!(matches!(
+            store.read(&owner, &uri1),
+            Some(ResourceContents::TextResourceContents { text, .. }) if text == "{}"
+        ));
+
+        // Oldest resource is removed when full
+        store.add(&owner, "errors", "Error 3", "application/json", "1".to_string());
+        assert!(store.read(&owner, &uri1).is_none());
+        assert_eq!(store.list(&owner).len(), 2);
+
+        let store = ResourceStore::new(Duration::ZERO, 2);
+        let uri = store.add(&owner, "errors", "Error", "application/json", "{}".to_string());
+        assert!(store.read(&owner, &uri).is_none());
+    }
+
+    #[test]
+    fn resources_of_other_clients() {
+        let client = |session: Option<&str>, identity: Option<&str>| Owner {
+            http: true,
+            session: session.map(str::to_string),
+            identity: identity.map(str::to_string),
+        };
+        let alice = client(Some("s1"), Some("alice"));
+        let bob = client(Some("s2"), Some("bob"));
+        let anonymous = client(None, None);
+
+        let store = ResourceStore::new(Duration::from_secs(60), 10);
+        let uri = store.add(&alice, "results", "Result", "text/plain", "secret".to_string());
+        store.add(&anonymous, "results", "Result", "text/plain", "other".to_string());
+
+        assert_eq!(store.list(&alice).len(), 1);
+        assert!(store.read(&alice, &uri).is_some());
+        assert!(store.list(&bob).is_empty());
+        assert!(store.read(&bob, &uri).is_none());
+        // The same client in another session
+        assert!(store.read(&client(Some("s3"), Some("alice")), &uri).is_none());
+
+        // Anonymous stateless clients can't list resources, as they can't be told apart
+        assert!(store.list(&anonymous).is_empty());
+        assert!(store.read(&anonymous, &uri).is_none());
+    }
+}
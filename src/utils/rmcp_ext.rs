@@ -17,8 +17,23 @@
 
 //! Various extensions and utilities for the Rust MCP sdk.
 
+use crate::metrics::Metrics;
+use crate::utils::maintenance::Scheduler;
+use axum::extract::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use futures::Stream;
+use rmcp::model::{ClientJsonRpcMessage, ClientNotification, ClientRequest, ServerInfo, ServerResult};
+use rmcp::service::{NotificationContext, Peer, RequestContext};
+use rmcp::transport::common::http_header::HEADER_SESSION_ID;
+use rmcp::transport::common::server_side_http::ServerSseMessage;
+use rmcp::transport::streamable_http_server::{SessionId, SessionManager};
 use rmcp::{RoleServer, Service};
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
 
 /// A factory to create server (`Service<RoleServer>`) instances.
 pub struct ServerProvider<S: Service<RoleServer>>(pub Arc<dyn Fn() -> S + Send + Sync>);
@@ -34,3 +49,332 @@ impl<S: Service<RoleServer>> From<Arc<dyn Fn() -> S + Send + Sync>> for ServerPr
         ServerProvider(value)
     }
 }
+
+/// The MCP session of a request, added to the request extensions by the http transports that have sessions:
+/// stateful streamable HTTP, SSE and WebSocket. Unlike the `Mcp-Session-Id` header, that stateless servers
+/// don't check, it can't be chosen by clients.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransportSession(pub String);
+
+/// The MCP session of a request, for stateful HTTP, SSE and WebSocket servers.
+pub fn session_id(context: &RequestContext<RoleServer>) -> Option<&str> {
+    context
+        .extensions
+        .get::<http::request::Parts>()
+        .and_then(|p| p.extensions.get::<TransportSession>())
+        .map(|session| session.0.as_str())
+}
+
+/// A random identifier that can't be guessed, e.g. for sessions.
+pub fn random_id() -> String {
+    rmcp::transport::common::server_side_http::session_id().to_string()
+}
+
+/// Axum middleware of stateful streamable HTTP servers, that adds the [`TransportSession`] of requests with a
+/// session id. The server rejects ids of unknown sessions.
+pub async fn streamable_http_session(mut request: Request, next: Next) -> Response {
+    let session = request
+        .headers()
+        .get(HEADER_SESSION_ID)
+        .and_then(|h| h.to_str().ok())
+        .map(|id| TransportSession(id.to_string()));
+    if let Some(session) = session {
+        request.extensions_mut().insert(session);
+    }
+    next.run(request).await
+}
+
+/// Axum middleware of SSE servers, that adds the [`TransportSession`] of messages posted to a session. The
+/// server rejects ids of unknown sessions.
+pub async fn sse_session(mut request: Request, next: Next) -> Response {
+    let session = request
+        .uri()
+        .query()
+        .and_then(|query| query.split('&').find_map(|param| param.strip_prefix("sessionId=")))
+        .map(|id| TransportSession(id.to_string()));
+    if let Some(session) = session {
+        request.extensions_mut().insert(session);
+    }
+    next.run(request).await
+}
+
+/// A server whose implementation can be replaced while it's running. Initialized clients are notified
+/// that the tool list has changed when this happens.
+pub struct ReloadableServer<S> {
+    current: Arc<RwLock<S>>,
+    peers: Arc<Mutex<Vec<Peer<RoleServer>>>>,
+}
+
+impl<S> Clone for ReloadableServer<S> {
+    fn clone(&self) -> Self {
+        ReloadableServer {
+            current: self.current.clone(),
+            peers: self.peers.clone(),
+        }
+    }
+}
+
+impl<S: Service<RoleServer> + Clone> ReloadableServer<S> {
+    pub fn new(server: S) -> Self {
+        ReloadableServer {
+            current: Arc::new(RwLock::new(server)),
+            peers: Default::default(),
+        }
+    }
+
+    /// Replace the server implementation and notify connected clients.
+    pub async fn replace(&self, server: S) {
+        *self.current.write().unwrap() = server;
+
+        let peers = {
+            let mut peers = self.peers.lock().unwrap();
+            peers.retain(|p| !p.is_transport_closed());
+            peers.clone()
+        };
+
+        for peer in peers {
+            if let Err(err) = peer.notify_tool_list_changed().await {
+                tracing::warn!("Failed to send tool list change notification: {err}");
+            }
+        }
+    }
+
+    fn current(&self) -> S {
+        self.current.read().unwrap().clone()
+    }
+}
+
+impl<S: Service<RoleServer> + Clone> Service<RoleServer> for ReloadableServer<S> {
+    async fn handle_request(
+        &self,
+        request: ClientRequest,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ServerResult, rmcp::Error> {
+        if let ClientRequest::InitializeRequest(_) = &request {
+            let mut peers = self.peers.lock().unwrap();
+            peers.retain(|p| !p.is_transport_closed());
+            peers.push(context.peer.clone());
+        }
+        self.current().handle_request(request, context).await
+    }
+
+    async fn handle_notification(
+        &self,
+        notification: ClientNotification,
+        context: NotificationContext<RoleServer>,
+    ) -> Result<(), rmcp::Error> {
+        self.current().handle_notification(notification, context).await
+    }
+
+    fn get_info(&self) -> ServerInfo {
+        let mut info = self.current().get_info();
+        if let Some(tools) = info.capabilities.tools.as_mut() {
+            tools.list_changed = Some(true);
+        }
+        info
+    }
+}
+
+/// A session manager that closes sessions that have been inactive for longer than an idle timeout.
+///
+/// Closing a session stops its MCP service, which cancels the requests it is still processing. Without
+/// a timeout, this is a pass-through to the inner session manager.
+pub struct IdleSessionManager<M: SessionManager> {
+    inner: Arc<M>,
+    idle_timeout: Option<Duration>,
+    last_activity: Mutex<HashMap<SessionId, Instant>>,
+}
+
+impl<M: SessionManager> IdleSessionManager<M> {
+    pub fn new(inner: Arc<M>, idle_timeout: Option<Duration>) -> Self {
+        IdleSessionManager {
+            inner,
+            idle_timeout,
+            last_activity: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn touch(&self, id: &SessionId) {
+        if self.idle_timeout.is_some() {
+            self.last_activity.lock().unwrap().insert(id.clone(), Instant::now());
+        }
+    }
+
+    /// Close sessions that have been idle for longer than the timeout.
+    pub async fn close_idle_sessions(&self) {
+        let Some(idle_timeout) = self.idle_timeout else {
+            return;
+        };
+
+        let expired = {
+            let mut last_activity = self.last_activity.lock().unwrap();
+            let now = Instant::now();
+            let expired = last_activity
+                .iter()
+                .filter(|(_, last)| now.duration_since(**last) >= idle_timeout)
+                .map(|(id, _)| id.clone())
+                .collect::<Vec<_>>();
+            for id in &expired {
+                last_activity.remove(id);
+            }
+            expired
+        };
+
+        for id in expired {
+            if let Err(err) = self.inner.close_session(&id).await {
+                tracing::warn!(session_id = %id, "Failed to close idle session: {err}");
+            }
+            let expired_total = Metrics::global().record_expired_session();
+            tracing::info!(session_id = %id, expired_total, "Closed idle session");
+        }
+    }
+
+    /// Periodically close idle sessions until `ct` is cancelled.
+    pub fn spawn_reaper(self: &Arc<Self>, ct: CancellationToken) {
+        let Some(idle_timeout) = self.idle_timeout else {
+            return;
+        };
+        // Check often enough for sessions to not outlive the timeout by much more than 50%
+        let period = (idle_timeout / 2).max(Duration::from_secs(1));
+        let this = self.clone();
+        Scheduler::global().spawn("idle_sessions", period, move || {
+            let this = this.clone();
+            let cancelled = ct.is_cancelled();
+            async move {
+                if cancelled {
+                    return Ok(ControlFlow::Break(()));
+                }
+                this.close_idle_sessions().await;
+                Ok(ControlFlow::Continue(()))
+            }
+        });
+    }
+}
+
+impl<M: SessionManager> SessionManager for IdleSessionManager<M> {
+    type Error = M::Error;
+    type Transport = M::Transport;
+
+    async fn create_session(&self) -> Result<(SessionId, Self::Transport), Self::Error> {
+        let (id, transport) = self.inner.create_session().await?;
+        self.touch(&id);
+        Ok((id, transport))
+    }
+
+    async fn initialize_session(
+        &self,
+        id: &SessionId,
+        message: ClientJsonRpcMessage,
+    ) -> Result<rmcp::model::ServerJsonRpcMessage, Self::Error> {
+        self.touch(id);
+        self.inner.initialize_session(id, message).await
+    }
+
+    async fn has_session(&self, id: &SessionId) -> Result<bool, Self::Error> {
+        self.inner.has_session(id).await
+    }
+
+    async fn close_session(&self, id: &SessionId) -> Result<(), Self::Error> {
+        self.last_activity.lock().unwrap().remove(id);
+        self.inner.close_session(id).await
+    }
+
+    async fn create_stream(
+        &self,
+        id: &SessionId,
+        message: ClientJsonRpcMessage,
+    ) -> Result<impl Stream<Item = ServerSseMessage> + Send + Sync + 'static, Self::Error> {
+        self.touch(id);
+        self.inner.create_stream(id, message).await
+    }
+
+    async fn accept_message(&self, id: &SessionId, message: ClientJsonRpcMessage) -> Result<(), Self::Error> {
+        self.touch(id);
+        self.inner.accept_message(id, message).await
+    }
+
+    async fn create_standalone_stream(
+        &self,
+        id: &SessionId,
+    ) -> Result<impl Stream<Item = ServerSseMessage> + Send + Sync + 'static, Self::Error> {
+        self.touch(id);
+        self.inner.create_standalone_stream(id).await
+    }
+
+    async fn resume(
+        &self,
+        id: &SessionId,
+        last_event_id: String,
+    ) -> Result<impl Stream<Item = ServerSseMessage> + Send + Sync + 'static, Self::Error> {
+        self.touch(id);
+        self.inner.resume(id, last_event_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::transport::streamable_http_server::session::local::LocalSessionManager;
+
+    #[tokio::test]
+    async fn idle_sessions_are_closed() -> anyhow::Result<()> {
+        let manager = IdleSessionManager::new(
+            Arc::new(LocalSessionManager::default()),
+            Some(Duration::from_millis(100)),
+        );
+        let (id, _transport) = manager.create_session().await?;
+
+        manager.close_idle_sessions().await;
+        assert!(manager.has_session(&id).await?);
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        manager.close_idle_sessions().await;
+        assert!(!manager.has_session(&id).await?);
+        Ok(())
+    }
+
+    #[derive(Clone)]
+    struct EmptyServer;
+
+    impl rmcp::ServerHandler for EmptyServer {
+        fn get_info(&self) -> ServerInfo {
+            ServerInfo {
+                capabilities: rmcp::model::ServerCapabilities::builder().enable_tools().build(),
+                ..Default::default()
+            }
+        }
+    }
+
+    struct ListChangedClient(tokio::sync::mpsc::UnboundedSender<()>);
+
+    impl rmcp::ClientHandler for ListChangedClient {
+        async fn on_tool_list_changed(&self, _context: NotificationContext<rmcp::RoleClient>) {
+            let _ = self.0.send(());
+        }
+    }
+
+    #[tokio::test]
+    async fn replacing_server_notifies_clients() -> anyhow::Result<()> {
+        use rmcp::ServiceExt;
+
+        let server = ReloadableServer::new(EmptyServer);
+        assert_eq!(server.get_info().capabilities.tools.unwrap().list_changed, Some(true));
+
+        let (server_io, client_io) = tokio::io::duplex(4096);
+        let running = server.clone();
+        tokio::spawn(async move {
+            if let Ok(service) = running.serve(server_io).await {
+                let _ = service.waiting().await;
+            }
+        });
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let client = ListChangedClient(tx).serve(client_io).await?;
+
+        server.replace(EmptyServer).await;
+        tokio::time::timeout(Duration::from_secs(5), rx.recv()).await?;
+
+        client.cancel().await?;
+        Ok(())
+    }
+}
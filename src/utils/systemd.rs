@@ -0,0 +1,82 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! systemd integration: socket activation and service state notifications, following the protocols of
+//! `sd_listen_fds(3)` and `sd_notify(3)`. These are no-ops when not started by systemd.
+
+/// First file descriptor passed by systemd (`SD_LISTEN_FDS_START`).
+#[cfg(unix)]
+const LISTEN_FDS_START: std::os::fd::RawFd = 3;
+
+/// The listening socket passed by systemd socket activation, if any. Only the first socket is used.
+#[cfg(unix)]
+pub fn listener() -> anyhow::Result<Option<std::net::TcpListener>> {
+    use std::os::fd::FromRawFd;
+
+    let (Ok(pid), Ok(fds)) = (std::env::var("LISTEN_PID"), std::env::var("LISTEN_FDS")) else {
+        return Ok(None);
+    };
+    // Sockets are for another process, e.g. our parent
+    if pid.parse::<u32>()? != std::process::id() {
+        return Ok(None);
+    }
+    let fds = fds.parse::<u32>()?;
+    if fds == 0 {
+        return Ok(None);
+    }
+    if fds > 1 {
+        tracing::warn!("systemd passed {fds} sockets, only the first one is used");
+    }
+
+    // Safety: systemd passes the ownership of file descriptors starting at LISTEN_FDS_START
+    let listener = unsafe { std::net::TcpListener::from_raw_fd(LISTEN_FDS_START) };
+    listener.set_nonblocking(true)?;
+    Ok(Some(listener))
+}
+
+#[cfg(not(unix))]
+pub fn listener() -> anyhow::Result<Option<std::net::TcpListener>> {
+    Ok(None)
+}
+
+/// Send a state notification, e.g. `READY=1`, to the service manager.
+#[cfg(unix)]
+pub fn notify(state: &str) -> std::io::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+
+    let Some(path) = std::env::var_os("NOTIFY_SOCKET") else {
+        return Ok(());
+    };
+    let socket = UnixDatagram::unbound()?;
+
+    // Abstract socket addresses start with '@'
+    #[cfg(target_os = "linux")]
+    if let Some(name) = path.as_encoded_bytes().strip_prefix(b"@") {
+        use std::os::linux::net::SocketAddrExt;
+        let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+        socket.send_to_addr(state.as_bytes(), &addr)?;
+        return Ok(());
+    }
+
+    socket.send_to(state.as_bytes(), path)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn notify(_state: &str) -> std::io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,299 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Strict validation of configuration files: unlike when starting the server, unknown fields are reported, all
+//! clusters are checked, and they can be pinged.
+
+use crate::cli::{self, Configuration, McpServer};
+use crate::migration;
+use crate::servers::elasticsearch::{ElasticsearchMcp, ElasticsearchMcpConfig};
+use crate::utils::interpolator;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Problems found in a configuration file.
+#[derive(Debug, Default)]
+pub struct Report {
+    /// Problems that prevent the server from starting or working as configured
+    pub errors: Vec<String>,
+    /// Outdated or ignored settings
+    pub warnings: Vec<String>,
+}
+
+/// Validate a configuration file, log the problems found, and fail if there are errors.
+pub async fn validate(path: &Path, container_mode: bool, ping: bool) -> anyhow::Result<()> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| anyhow::anyhow!("Cannot read config file {}: {err}", path.display()))?;
+    let report = check(text, path.parent(), container_mode, ping).await;
+
+    for warning in &report.warnings {
+        tracing::warn!("{warning}");
+    }
+    for error in &report.errors {
+        tracing::error!("{error}");
+    }
+    if !report.errors.is_empty() {
+        anyhow::bail!("Invalid configuration file {}", path.display());
+    }
+    tracing::info!("Configuration file {} is valid", path.display());
+    Ok(())
+}
+
+/// Check a configuration, read from a file in `dir`. Checks stop at the first error that prevents reading the
+/// configuration.
+pub async fn check(text: String, dir: Option<&Path>, container_mode: bool, ping: bool) -> Report {
+    let mut report = Report::default();
+
+    let config = match parse(text, dir, &mut report) {
+        Ok(config) => config,
+        Err(err) => {
+            report.errors.push(format!("{err:#}"));
+            return report;
+        }
+    };
+
+    for rule in config.redactions.iter().filter_map(|rule| rule.mask.as_deref()) {
+        if let Err(err) = regex::Regex::new(rule) {
+            report.errors.push(format!("Invalid redaction mask '{rule}': {err}"));
+        }
+    }
+
+    if let Some(Err(err)) = config.authorization.as_ref().map(|a| a.check()) {
+        report.errors.push(format!("{err:#}"));
+    }
+
+    let mut tenants = config.tenants.into_iter().collect::<Vec<_>>();
+    tenants.sort_by(|a, b| a.0.cmp(&b.0));
+    // The default clusters are optional when there are tenants
+    if config.elasticsearch.is_some() || !config.mcp_servers.is_empty() || tenants.is_empty() {
+        check_clusters(
+            None,
+            config.elasticsearch,
+            config.mcp_servers,
+            container_mode,
+            ping,
+            &mut report,
+        )
+        .await;
+    }
+    for (name, tenant) in tenants {
+        if let Err(err) = cli::check_tenant_name(&name) {
+            report.errors.push(err);
+            continue;
+        }
+        let tenant_name = Some(name.as_str());
+        check_clusters(
+            tenant_name,
+            tenant.elasticsearch,
+            tenant.mcp_servers,
+            container_mode,
+            ping,
+            &mut report,
+        )
+        .await;
+    }
+
+    report
+}
+
+/// Check the clusters of a tenant, or the default clusters if `tenant` is `None`.
+async fn check_clusters(
+    tenant: Option<&str>,
+    elasticsearch: Option<ElasticsearchMcpConfig>,
+    mcp_servers: HashMap<String, McpServer>,
+    container_mode: bool,
+    ping: bool,
+    report: &mut Report,
+) {
+    let prefix = tenant.map(|name| format!("Tenant '{name}': ")).unwrap_or_default();
+    let errors_before = report.errors.len();
+
+    // Elasticsearch clusters, the default one first
+    let mut clusters: Vec<(Option<String>, ElasticsearchMcpConfig)> = Vec::new();
+    if let Some(es_config) = elasticsearch {
+        clusters.push((None, es_config));
+    }
+    let mut servers = mcp_servers.into_iter().collect::<Vec<_>>();
+    servers.sort_by(|a, b| a.0.cmp(&b.0));
+    for (name, server) in servers {
+        match server {
+            McpServer::Elasticsearch(es_config) => clusters.push((Some(name), *es_config)),
+            _ => report.warnings.push(format!(
+                "{prefix}MCP server '{name}': only Elasticsearch servers are supported, it will be ignored"
+            )),
+        }
+    }
+
+    for (name, es_config) in &clusters {
+        let cluster = match name {
+            Some(name) => format!("{prefix}Cluster '{name}'"),
+            None => format!("{prefix}Default cluster"),
+        };
+        let es_client = match ElasticsearchMcp::new_client(es_config, container_mode) {
+            Ok(es_client) => es_client,
+            Err(err) => {
+                report.errors.push(format!("{cluster}: {err:#}"));
+                continue;
+            }
+        };
+        if ping {
+            let response = es_client.info().send().await.and_then(|r| r.error_for_status_code());
+            if let Err(err) = response {
+                report.errors.push(format!("{cluster}: cannot connect: {err}"));
+            }
+        }
+    }
+
+    // Custom tools, tool selection and tool names
+    if report.errors.len() == errors_before {
+        let default = match clusters.first() {
+            Some((None, _)) => Some(clusters.remove(0).1),
+            _ => None,
+        };
+        let named = clusters
+            .into_iter()
+            .filter_map(|(name, config)| name.map(|name| (name, config)))
+            .collect();
+        if let Err(err) = ElasticsearchMcp::new_with_clusters(default, named, container_mode) {
+            report.errors.push(format!("{prefix}{err:#}"));
+        }
+    }
+}
+
+/// Expand variables, migrate and deserialize a configuration, reporting unknown fields.
+fn parse(text: String, dir: Option<&Path>, report: &mut Report) -> anyhow::Result<Configuration> {
+    let text = interpolator::interpolate_from_env(text, dir)?;
+    let mut value: Value = crate::from_json5(&text)?;
+
+    for change in migration::migrate(&mut value)? {
+        report.warnings.push(format!("Outdated configuration: {change}"));
+    }
+
+    let mut unknown = Vec::new();
+    let deserializer = serde_ignored::Deserializer::new(value, |path| unknown.push(path.to_string()));
+    let config = serde_path_to_error::deserialize(deserializer)
+        .map_err(|err| anyhow::anyhow!("Invalid value at '{}': {}", err.path(), err.inner()))?;
+
+    // Editors use `$schema` to find the schema of the file
+    for path in unknown.into_iter().filter(|path| path != "$schema") {
+        report.errors.push(format!("Unknown field '{path}'"));
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn unknown_fields() {
+        let report = check(
+            r#"{
+                version: 1,
+                elasticsearch: { url: "http://localhost:9200", api_ky: "abc" },
+                rateLimit: { "*": "10/min" },
+            }"#
+            .to_string(),
+            None,
+            false,
+            false,
+        )
+        .await;
+        assert_eq!(
+            report.errors,
+            vec!["Unknown field 'elasticsearch.api_ky'", "Unknown field 'rateLimit'"]
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_values() {
+        let report = check(
+            r#"{ version: 1, rateLimits: { "*": "ten/min" } }"#.to_string(),
+            None,
+            false,
+            false,
+        )
+        .await;
+        assert_eq!(report.errors.len(), 1);
+        assert!(
+            report.errors[0].starts_with("Invalid value at 'rateLimits.*'"),
+            "{:?}",
+            report.errors
+        );
+
+        let report = check(
+            r#"{ version: 1, elasticsearch: { url: "not a url" } }"#.to_string(),
+            None,
+            false,
+            false,
+        )
+        .await;
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].starts_with("Default cluster:"), "{:?}", report.errors);
+    }
+
+    #[tokio::test]
+    async fn valid_config() {
+        let report = check(
+            r#"{
+                version: 1,
+                elasticsearch: { url: "http://localhost:9200" },
+                mcpServers: {
+                    prod: { type: "elasticsearch", hosts: ["https://es-1:9200", "https://es-2:9200"] },
+                },
+            }"#
+            .to_string(),
+            None,
+            false,
+            false,
+        )
+        .await;
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+    }
+
+    #[tokio::test]
+    async fn tenants() {
+        let report = check(
+            r#"{
+                version: 1,
+                tenants: {
+                    "team-a": { elasticsearch: { url: "http://localhost:9200" } },
+                    "team-b": { elasticsearch: { url: "not a url" } },
+                    "team/c": { elasticsearch: { url: "http://localhost:9200" } },
+                },
+            }"#
+            .to_string(),
+            None,
+            false,
+            false,
+        )
+        .await;
+        assert_eq!(report.errors.len(), 2, "{:?}", report.errors);
+        assert!(
+            report.errors[0].starts_with("Tenant 'team-b': Default cluster:"),
+            "{:?}",
+            report.errors
+        );
+        assert!(
+            report.errors[1].starts_with("Invalid tenant name 'team/c'"),
+            "{:?}",
+            report.errors
+        );
+    }
+}
@@ -0,0 +1,232 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Golden-file tests for tool outputs.
+//!
+//! Each directory in `tests/golden` is a test case containing:
+//! - `request.json`: the tool to call, its arguments, and optionally the `es_status` of Elasticsearch responses
+//!   and additional `es_config` properties of the Elasticsearch cluster configuration. A `kibana` property
+//!   also targets the mock server,
+//! - `es_response.json`: the canned response returned by the mock Elasticsearch (or Kibana) server,
+//! - `es_responses.json` (optional): canned responses by request, as `"<method> <path>"` or `"<path>"` keys, for
+//!   tools that send several requests. Other requests get `es_response.json`, or a 404 if there's none,
+//! - `expected.json`: the Elasticsearch requests that were sent, with their query parameters and body, and the
+//!   resulting `CallToolResult`. NDJSON bodies are stored as an array of their lines.
+//!
+//! Text contents that hold JSON objects or arrays are stored parsed in a `json` property to make
+//! the golden files readable. Run with `UPDATE_GOLDEN=1` to rewrite the expected files, and review
+//! the diff before committing.
+
+use anyhow::Context;
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::Query;
+use elasticsearch_core_mcp_server::setup_services;
+use http::header::CONTENT_TYPE;
+use http::{Method, StatusCode, Uri};
+use rmcp::ServiceExt;
+use rmcp::model::{CallToolRequestParam, CallToolResult};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+#[tokio::test]
+async fn golden_tool_outputs() -> anyhow::Result<()> {
+    let update = std::env::var("UPDATE_GOLDEN").is_ok();
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+
+    let mut cases = std::fs::read_dir(&root)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    cases.retain(|p| p.is_dir());
+    cases.sort();
+
+    let mut failures = Vec::new();
+    for case in &cases {
+        let name = case.file_name().unwrap().to_string_lossy().to_string();
+        let actual = run_case(case).await?;
+        let expected_path = case.join("expected.json");
+
+        if update {
+            std::fs::write(&expected_path, serde_json::to_string_pretty(&actual)? + "\n")?;
+            continue;
+        }
+
+        let expected: Value = serde_json::from_str(&std::fs::read_to_string(&expected_path)?)?;
+        if expected != actual {
+            failures.push(format!(
+                "{name}:\n--- expected\n{}\n--- actual\n{}",
+                serde_json::to_string_pretty(&expected)?,
+                serde_json::to_string_pretty(&actual)?
+            ));
+        }
+    }
+
+    assert!(!cases.is_empty(), "no golden test cases found in {}", root.display());
+    assert!(failures.is_empty(), "golden files mismatch:\n{}", failures.join("\n\n"));
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct CaseRequest {
+    tool: String,
+    #[serde(default)]
+    arguments: serde_json::Map<String, Value>,
+    #[serde(default)]
+    es_status: Option<u16>,
+    #[serde(default)]
+    es_config: serde_json::Map<String, Value>,
+}
+
+/// Run a test case, and return the requests sent to Elasticsearch along with the normalized tool result.
+async fn run_case(case: &Path) -> anyhow::Result<Value> {
+    let read_json = |name: &str| -> anyhow::Result<Option<Value>> {
+        let path = case.join(name);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&std::fs::read_to_string(path)?)?))
+    };
+    let request = read_json("request.json")?.with_context(|| format!("no request.json in {}", case.display()))?;
+    let request: CaseRequest = serde_json::from_value(request)?;
+    let es_response = read_json("es_response.json")?;
+    let es_responses: BTreeMap<String, Value> =
+        serde_json::from_value(read_json("es_responses.json")?.unwrap_or_else(|| json!({})))?;
+
+    // Start an ES mock that records requests and replies with the canned responses
+    let es_requests = Arc::new(Mutex::new(Vec::<Value>::new()));
+    let status = StatusCode::from_u16(request.es_status.unwrap_or(200))?;
+    let router = Router::new().fallback({
+        let es_requests = es_requests.clone();
+        move |method: Method, uri: Uri, Query(query): Query<Vec<(String, String)>>, body: Bytes| {
+            let mut recorded = json!({ "method": method.as_str(), "path": uri.path() });
+            if !query.is_empty() {
+                recorded["query"] = parse_query(query);
+            }
+            if !body.is_empty() {
+                recorded["body"] = parse_body(&body);
+            }
+            es_requests.lock().unwrap().push(recorded);
+
+            let response = es_responses
+                .get(&format!("{method} {}", uri.path()))
+                .or_else(|| es_responses.get(uri.path()))
+                .or(es_response.as_ref());
+            let (status, body) = match response {
+                Some(response) => (status, response.to_string()),
+                None => (
+                    StatusCode::NOT_FOUND,
+                    json!({ "error": "no canned response" }).to_string(),
+                ),
+            };
+            async move { (status, [(CONTENT_TYPE, "application/json")], body) }
+        }
+    });
+    let listener = tokio::net::TcpListener::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)).await?;
+    let es_port = listener.local_addr()?.port();
+    tokio::spawn(async { axum::serve(listener, router).await });
+
+    // Configuration targeting the mock
+    let config_path = std::env::temp_dir().join(format!("golden-{es_port}.json5"));
+    let mut es_config = request.es_config;
+    es_config.insert("url".to_string(), format!("http://127.0.0.1:{es_port}/").into());
+    if let Some(Value::Object(kibana)) = es_config.get_mut("kibana") {
+        kibana.insert("url".to_string(), format!("http://127.0.0.1:{es_port}/").into());
+    }
+    std::fs::write(&config_path, json!({ "elasticsearch": es_config }).to_string())?;
+    let handler = setup_services(&Some(config_path.clone()), false, false).await?;
+    std::fs::remove_file(&config_path)?;
+
+    // Connect a client to the MCP server in-process
+    let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        if let Ok(service) = handler.serve(server_io).await {
+            let _ = service.waiting().await;
+        }
+    });
+    let client = ().serve(client_io).await?;
+
+    let result = client
+        .call_tool(CallToolRequestParam {
+            name: request.tool.into(),
+            arguments: Some(request.arguments),
+        })
+        .await?;
+    client.cancel().await?;
+
+    let es_requests = es_requests.lock().unwrap().clone();
+    Ok(json!({
+        "es_requests": es_requests,
+        "result": normalize(&result)?,
+    }))
+}
+
+/// Query parameters as an object. Repeated parameters are an array of their values.
+fn parse_query(query: Vec<(String, String)>) -> Value {
+    let mut params = serde_json::Map::new();
+    for (name, value) in query {
+        match params.get_mut(&name) {
+            Some(Value::Array(values)) => values.push(value.into()),
+            Some(previous) => *previous = json!([previous.take(), value]),
+            None => {
+                params.insert(name, value.into());
+            }
+        }
+    }
+    Value::Object(params)
+}
+
+/// A request body as JSON, or the array of its lines for NDJSON bodies.
+fn parse_body(body: &[u8]) -> Value {
+    if let Ok(json) = serde_json::from_slice(body) {
+        return json;
+    }
+    let text = String::from_utf8_lossy(body);
+    let lines = text
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|_| Value::String(line.to_string())))
+        .collect();
+    Value::Array(lines)
+}
+
+/// Serialize a tool result, replacing text contents holding JSON objects or arrays with their parsed value, and
+/// the random part of resource URIs with `<id>`.
+fn normalize(result: &CallToolResult) -> anyhow::Result<Value> {
+    let resource_uri = regex::Regex::new(r"(elasticsearch-mcp://\w+/)[0-9a-f-]+")?;
+    let mut value = serde_json::to_value(result)?;
+    if let Some(contents) = value.get_mut("content").and_then(Value::as_array_mut) {
+        for content in contents.iter_mut().filter_map(Value::as_object_mut) {
+            if let Some(Value::String(text)) = content.get_mut("text") {
+                *text = resource_uri.replace_all(text, "${1}<id>").into_owned();
+            }
+            let parsed = content
+                .get("text")
+                .and_then(Value::as_str)
+                .and_then(|text| serde_json::from_str::<Value>(text).ok())
+                .filter(|json| json.is_object() || json.is_array());
+
+            if let Some(json) = parsed {
+                content.remove("text");
+                content.insert("json".to_string(), json);
+            }
+        }
+    }
+    Ok(value)
+}
@@ -23,7 +23,9 @@ use futures_util::StreamExt;
 use http::HeaderMap;
 use http::header::{ACCEPT, CONTENT_TYPE};
 use reqwest::Client;
+use rmcp::ServiceExt;
 use rmcp::model::ToolAnnotations;
+use rmcp::transport::SseClientTransport;
 use serde::Deserialize;
 use serde::de::DeserializeOwned;
 use serde_json::json;
@@ -39,8 +41,15 @@ async fn http_tool_list() -> anyhow::Result<()> {
         container_mode: false,
         command: cli::Command::Http(cli::HttpCommand {
             config: None,
+            reload: false,
+            check: false,
+            dry_run: false,
             address: Some(addr),
             sse: false,
+            systemd: false,
+            session: Default::default(),
+            tls: Default::default(),
+            auth: Default::default(),
         }),
     };
 
@@ -80,6 +89,344 @@ async fn http_tool_list() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Clients that only support the legacy SSE transport use '/mcp/sse'
+#[tokio::test]
+async fn sse_tool_list() -> anyhow::Result<()> {
+    let addr = find_address()?;
+
+    let cli = cli::Cli {
+        container_mode: false,
+        command: cli::Command::Http(cli::HttpCommand {
+            config: None,
+            reload: false,
+            check: false,
+            dry_run: false,
+            address: Some(addr),
+            sse: false,
+            systemd: false,
+            session: Default::default(),
+            tls: Default::default(),
+            auth: Default::default(),
+        }),
+    };
+
+    tokio::spawn(async move { cli.run().await });
+    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+
+    let transport = SseClientTransport::start(format!("http://127.0.0.1:{}/mcp/sse", addr.port())).await?;
+    let client = ().serve(transport).await?;
+    let tools = client.list_all_tools().await?;
+    assert!(tools.iter().any(|t| t.name == "search"));
+    client.cancel().await?;
+    Ok(())
+}
+
+/// MCP over WebSocket on '/mcp/ws'
+#[tokio::test]
+async fn ws_initialize() -> anyhow::Result<()> {
+    use futures_util::SinkExt;
+    use tokio_tungstenite::tungstenite::Message;
+
+    let addr = find_address()?;
+
+    let cli = cli::Cli {
+        container_mode: false,
+        command: cli::Command::Http(cli::HttpCommand {
+            config: None,
+            reload: false,
+            check: false,
+            dry_run: false,
+            address: Some(addr),
+            sse: false,
+            systemd: false,
+            session: Default::default(),
+            tls: Default::default(),
+            auth: Default::default(),
+        }),
+    };
+
+    tokio::spawn(async move { cli.run().await });
+    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://127.0.0.1:{}/mcp/ws", addr.port())).await?;
+
+    let initialize = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "initialize",
+        "params": {
+            "protocolVersion": "2025-03-26",
+            "capabilities": {},
+            "clientInfo": { "name": "ws-test", "version": "1.0" },
+        },
+    });
+    socket.send(Message::Text(initialize.to_string().into())).await?;
+
+    let Some(Message::Text(text)) = socket.next().await.transpose()? else {
+        bail!("Expected a text message");
+    };
+    let response: serde_json::Value = serde_json::from_str(&text)?;
+    assert_eq!(response["id"], 1);
+    assert!(response["result"]["capabilities"]["tools"].is_object());
+
+    socket.close(None).await?;
+    Ok(())
+}
+
+/// Tenants are selected by path prefix or with a header
+#[tokio::test]
+async fn http_tenants() -> anyhow::Result<()> {
+    let addr = find_address()?;
+    let config = std::env::temp_dir().join(format!("tenants-{}.json5", addr.port()));
+    std::fs::write(
+        &config,
+        json!({
+            "version": 1,
+            "tenants": {
+                "team-a": { "elasticsearch": { "url": "http://localhost:9200", "tools": { "include": ["search"] } } },
+                "team-b": { "elasticsearch": { "url": "http://localhost:9200", "tools": { "include": ["query_esql"] } } },
+            }
+        })
+        .to_string(),
+    )?;
+
+    let cli = cli::Cli {
+        container_mode: false,
+        command: cli::Command::Http(cli::HttpCommand {
+            config: Some(config.clone()),
+            reload: false,
+            check: false,
+            dry_run: false,
+            address: Some(addr),
+            sse: false,
+            systemd: false,
+            session: Default::default(),
+            tls: Default::default(),
+            auth: Default::default(),
+        }),
+    };
+
+    tokio::spawn(async move { cli.run().await });
+    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    std::fs::remove_file(&config)?;
+
+    let client = Client::builder().build()?;
+    let list_tools = |path: &str, tenant: Option<&str>| {
+        let mut request = client
+            .post(format!("http://127.0.0.1:{}{path}", addr.port()))
+            .header(CONTENT_TYPE, "application/json")
+            .header(ACCEPT, "application/json, text/event-stream")
+            .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" }));
+        if let Some(tenant) = tenant {
+            request = request.header("X-Mcp-Tenant", tenant);
+        }
+        request.send()
+    };
+    let tool_names = async |response: reqwest::Response| -> anyhow::Result<Vec<String>> {
+        let response: ListToolsResponse = parse_response(response.error_for_status()?).await?;
+        Ok(response.result.tools.into_iter().map(|t| t.name).collect())
+    };
+
+    assert_eq!(
+        tool_names(list_tools("/tenants/team-a/mcp", None).await?).await?,
+        ["search"]
+    );
+    // With the deprecated name of query_esql
+    assert_eq!(
+        tool_names(list_tools("/mcp", Some("team-b")).await?).await?,
+        ["query_esql", "esql"]
+    );
+
+    // No default cluster, and unknown tenants
+    assert_eq!(list_tools("/mcp", None).await?.status(), 404);
+    assert_eq!(list_tools("/mcp", Some("team-c")).await?.status(), 404);
+    Ok(())
+}
+
+/// Clients must authenticate when API keys are configured
+#[tokio::test]
+async fn http_auth() -> anyhow::Result<()> {
+    let addr = find_address()?;
+
+    let cli = cli::Cli {
+        container_mode: false,
+        command: cli::Command::Http(cli::HttpCommand {
+            config: None,
+            reload: false,
+            check: false,
+            dry_run: false,
+            address: Some(addr),
+            sse: false,
+            systemd: false,
+            session: Default::default(),
+            tls: Default::default(),
+            auth: cli::HttpAuthArgs {
+                api_keys: vec!["test-key".to_string()],
+                bearer_tokens: vec![],
+            },
+        }),
+    };
+
+    tokio::spawn(async move { cli.run().await });
+
+    let client = Client::builder().build()?;
+    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+
+    let request = |key: Option<&str>| {
+        let mut request = client
+            .post(format!("http://127.0.0.1:{}/mcp", addr.port()))
+            .header(CONTENT_TYPE, "application/json")
+            .header(ACCEPT, "application/json, text/event-stream")
+            .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" }));
+        if let Some(key) = key {
+            request = request.header("X-Api-Key", key);
+        }
+        request.send()
+    };
+
+    assert_eq!(request(None).await?.status(), http::StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        request(Some("wrong-key")).await?.status(),
+        http::StatusCode::UNAUTHORIZED
+    );
+    assert!(request(Some("test-key")).await?.status().is_success());
+
+    // Health checks don't need authentication
+    let health = client
+        .get(format!("http://127.0.0.1:{}/_health/live", addr.port()))
+        .send()
+        .await?;
+    assert!(health.status().is_success());
+
+    // Metrics do, as they tell which tools are used
+    let metrics = |path: &str, key: Option<&str>| {
+        let mut request = client.get(format!("http://127.0.0.1:{}{path}", addr.port()));
+        if let Some(key) = key {
+            request = request.header("X-Api-Key", key);
+        }
+        request.send()
+    };
+    assert_eq!(
+        metrics("/metrics", None).await?.status(),
+        http::StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        metrics("/_health/maintenance", None).await?.status(),
+        http::StatusCode::UNAUTHORIZED
+    );
+    assert!(
+        metrics("/_health/maintenance", Some("test-key"))
+            .await?
+            .status()
+            .is_success()
+    );
+
+    let metrics = metrics("/metrics", Some("test-key")).await?.text().await?;
+    assert!(metrics.contains("# TYPE mcp_tool_calls_total counter"), "{metrics}");
+    Ok(())
+}
+
+/// Stateful mode assigns a session id to clients
+#[tokio::test]
+async fn http_stateful_session() -> anyhow::Result<()> {
+    let addr = find_address()?;
+
+    let cli = cli::Cli {
+        container_mode: false,
+        command: cli::Command::Http(cli::HttpCommand {
+            config: None,
+            reload: false,
+            check: false,
+            dry_run: false,
+            address: Some(addr),
+            sse: false,
+            systemd: false,
+            session: cli::HttpSessionArgs {
+                stateful: true,
+                session_keep_alive: None,
+                session_ttl: Some(60),
+            },
+            tls: Default::default(),
+            auth: Default::default(),
+        }),
+    };
+
+    tokio::spawn(async move { cli.run().await });
+
+    let client = Client::builder().build()?;
+    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+
+    let response = client
+        .post(format!("http://127.0.0.1:{}/mcp", addr.port()))
+        .header(CONTENT_TYPE, "application/json")
+        .header(ACCEPT, "application/json, text/event-stream")
+        .json(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": "2025-03-26",
+                "capabilities": {},
+                "clientInfo": { "name": "test", "version": "1.0" }
+            }
+        }))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    assert!(response.headers().contains_key("mcp-session-id"));
+    Ok(())
+}
+
+/// Serve HTTPS with a self-signed certificate
+#[tokio::test]
+async fn https_ping() -> anyhow::Result<()> {
+    let addr = find_address()?;
+    let tls_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/tls");
+
+    let cli = cli::Cli {
+        container_mode: false,
+        command: cli::Command::Http(cli::HttpCommand {
+            config: None,
+            reload: false,
+            check: false,
+            dry_run: false,
+            address: Some(addr),
+            sse: false,
+            systemd: false,
+            session: Default::default(),
+            tls: cli::HttpTlsArgs {
+                tls_cert: Some(tls_dir.join("cert.pem")),
+                tls_key: Some(tls_dir.join("key.pem")),
+            },
+            auth: Default::default(),
+        }),
+    };
+
+    tokio::spawn(async move { cli.run().await });
+
+    let cert = reqwest::Certificate::from_pem(&std::fs::read(tls_dir.join("cert.pem"))?)?;
+    let client = Client::builder().add_root_certificate(cert).build()?;
+    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+
+    let response = client
+        .get(format!("https://localhost:{}/ping", addr.port()))
+        .send()
+        .await?
+        .error_for_status()?;
+    assert_eq!(response.text().await?, "Ready\n");
+
+    // Plain HTTP is rejected
+    assert!(
+        client
+            .get(format!("http://localhost:{}/ping", addr.port()))
+            .send()
+            .await
+            .is_err()
+    );
+    Ok(())
+}
+
 // End-to-end test that spawns a mock ES server and calls the `list_indices` tool via http
 #[tokio::test]
 async fn end_to_end() -> anyhow::Result<()> {
@@ -120,8 +467,15 @@ async fn end_to_end() -> anyhow::Result<()> {
         container_mode: false,
         command: cli::Command::Http(cli::HttpCommand {
             config: None,
+            reload: false,
+            check: false,
+            dry_run: false,
             address: Some(addr),
             sse: false,
+            systemd: false,
+            session: Default::default(),
+            tls: Default::default(),
+            auth: Default::default(),
         }),
     };
 